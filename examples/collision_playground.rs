@@ -0,0 +1,123 @@
+//! Collision playground: a box moved with the arrow keys or a gamepad bumps
+//! into walls and crosses a trigger area, logging the contacts.
+//!
+//! Run with `cargo run --example collision_playground`, the colliders being
+//! drawn in debug builds.
+
+use baobei_needs::{
+    camera::MainCamera,
+    collisions::{
+        BoxCollider, CollisionPlugin, CollisionSystems, ContactEvent, Movement, Position,
+        TriggerArea,
+    },
+    constants::{GameState, WINDOW_HEIGHT, WINDOW_WIDTH},
+    controllers::{ControllerPlugin, ControllerSystems, DirectionEvent},
+    drawing::DrawingPlugin,
+    save::Profile,
+    settings::Settings,
+};
+use bevy::prelude::*;
+
+/// Pixels moved by the box per second.
+const SPEED: f32 = 400.0;
+
+/// Component on the box moved by the player.
+struct Mover;
+
+fn main() {
+    App::build()
+        .insert_resource(WindowDescriptor {
+            title: "Collision playground".to_string(),
+            width: WINDOW_WIDTH,
+            height: WINDOW_HEIGHT,
+            ..WindowDescriptor::default()
+        })
+        .add_plugins(DefaultPlugins)
+        // The collisions only run in game
+        .add_state(GameState::InGame)
+        .init_resource::<Profile>()
+        .init_resource::<Settings>()
+        .add_plugin(ControllerPlugin)
+        .add_plugin(CollisionPlugin)
+        .add_plugin(DrawingPlugin)
+        .add_startup_system(setup.system())
+        .add_system(
+            move_system
+                .system()
+                .after(ControllerSystems)
+                .before(CollisionSystems),
+        )
+        .add_system(log_contacts_system.system().after(CollisionSystems))
+        .run();
+}
+
+/// Spawns the camera, the moved box, the walls and a trigger area.
+fn setup(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    let mut camera = OrthographicCameraBundle::new_2d();
+    camera.transform.translation += Vec3::new(WINDOW_WIDTH / 2.0, WINDOW_HEIGHT / 2.0, 0.0);
+    commands.spawn().insert(MainCamera).insert_bundle(camera);
+
+    let mut spawn_box = |position: Vec3, size: Vec2, color: Color| {
+        commands
+            .spawn()
+            .insert(Position(position))
+            .insert_bundle(SpriteBundle {
+                material: materials.add(color.into()),
+                sprite: Sprite::new(size),
+                ..SpriteBundle::default()
+            })
+            .id()
+    };
+
+    let mover = spawn_box(
+        Vec3::new(300.0, 300.0, 1.0),
+        Vec2::new(60.0, 60.0),
+        Color::ORANGE,
+    );
+    let walls = [
+        (Vec3::new(640.0, 80.0, 0.0), Vec2::new(1000.0, 40.0)),
+        (Vec3::new(640.0, 640.0, 0.0), Vec2::new(1000.0, 40.0)),
+        (Vec3::new(150.0, 360.0, 0.0), Vec2::new(40.0, 600.0)),
+        (Vec3::new(800.0, 300.0, 0.0), Vec2::new(200.0, 200.0)),
+    ]
+    .iter()
+    .map(|(position, size)| (spawn_box(*position, *size, Color::GRAY), *size))
+    .collect::<Vec<_>>();
+    let area = spawn_box(
+        Vec3::new(450.0, 500.0, 0.0),
+        Vec2::new(150.0, 150.0),
+        Color::rgba(0.2, 0.8, 0.3, 0.5),
+    );
+
+    commands
+        .entity(mover)
+        .insert(Mover)
+        .insert(Movement::default())
+        .insert(BoxCollider::new(60.0, 60.0));
+    for (wall, size) in walls {
+        commands
+            .entity(wall)
+            .insert(BoxCollider::new(size.x, size.y));
+    }
+    commands.entity(area).insert(TriggerArea::new(150.0, 150.0));
+}
+
+/// Moves the box in the direction chosen by the player.
+fn move_system(
+    time: Res<Time>,
+    mut direction_events: EventReader<DirectionEvent>,
+    mut movers: Query<&mut Movement, With<Mover>>,
+) {
+    for event in direction_events.iter().filter(|event| event.player == 0) {
+        for mut movement in movers.iter_mut() {
+            movement.0 = event.direction * SPEED * time.delta_seconds();
+        }
+    }
+}
+
+/// Logs the contacts starting and stopping.
+fn log_contacts_system(mut contact_events: EventReader<ContactEvent>) {
+    for event in contact_events.iter() {
+        info!("{:?}", event);
+    }
+}
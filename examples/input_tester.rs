@@ -0,0 +1,91 @@
+//! Input tester: shows the direction and the actions read from the keyboard
+//! and the gamepads, with the bindings of the input map.
+//!
+//! Run with `cargo run --example input_tester`.
+
+use baobei_needs::{
+    controllers::{
+        ActiveDevice, ControllerPlugin, ControllerSystems, DirectionEvent, InputAction, InputMap,
+    },
+    save::Profile,
+    settings::Settings,
+};
+use bevy::prelude::*;
+
+/// Component on the text showing the inputs.
+struct InputText;
+
+fn main() {
+    App::build()
+        .insert_resource(WindowDescriptor {
+            title: "Input tester".to_string(),
+            width: 800.0,
+            height: 400.0,
+            ..WindowDescriptor::default()
+        })
+        .add_plugins(DefaultPlugins)
+        .init_resource::<Profile>()
+        .init_resource::<Settings>()
+        .add_plugin(ControllerPlugin)
+        .add_startup_system(setup.system())
+        .add_system(show_inputs_system.system().after(ControllerSystems))
+        .run();
+}
+
+/// Spawns the text showing the inputs.
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn_bundle(UiCameraBundle::default());
+    commands
+        .spawn_bundle(TextBundle {
+            text: Text::with_section(
+                "Press a key or a button",
+                TextStyle {
+                    font: asset_server.load("FiraSans-Bold.ttf"),
+                    font_size: 30.0,
+                    color: Color::WHITE,
+                },
+                TextAlignment::default(),
+            ),
+            ..TextBundle::default()
+        })
+        .insert(InputText);
+}
+
+/// Shows the directions of the players and the held actions with their
+/// bindings on the active device.
+fn show_inputs_system(
+    keyboard: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    input_map: Res<InputMap>,
+    active_device: Res<ActiveDevice>,
+    mut direction_events: EventReader<DirectionEvent>,
+    mut texts: Query<&mut Text, With<InputText>>,
+) {
+    let mut lines = vec![format!("Device: {:?}", active_device.0)];
+    for event in direction_events.iter() {
+        lines.push(format!(
+            "Player {}: direction {:.2} {:.2}{}",
+            event.player,
+            event.direction.x,
+            event.direction.y,
+            if event.sprint { " (sprint)" } else { "" }
+        ));
+    }
+    for action in InputAction::ALL {
+        let held = input_map.key_pressed(action, &keyboard)
+            || gamepad_buttons
+                .get_pressed()
+                .any(|button| input_map.button_pressed(action, button.0, &gamepad_buttons));
+        if held {
+            lines.push(format!(
+                "{:?} ({})",
+                action,
+                input_map.label(action, active_device.0)
+            ));
+        }
+    }
+
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = lines.join("\n");
+    }
+}
@@ -0,0 +1,35 @@
+//! Level viewer: starts a game right away, without the menu, to look at the
+//! apartment with its furniture and characters and walk around with Didi.
+//!
+//! Run with `cargo run --example level_viewer`, the colliders and the
+//! trigger areas being drawn in debug builds.
+
+use baobei_needs::{
+    constants::{GameState, WINDOW_HEIGHT, WINDOW_WIDTH},
+    GamePlugin, VERSION,
+};
+use bevy::prelude::*;
+
+fn main() {
+    App::build()
+        .insert_resource(WindowDescriptor {
+            title: format!("Level viewer {}", VERSION),
+            width: WINDOW_WIDTH,
+            height: WINDOW_HEIGHT,
+            resizable: false,
+            ..WindowDescriptor::default()
+        })
+        .add_plugins(DefaultPlugins)
+        .add_plugin(GamePlugin)
+        .add_system_set(
+            SystemSet::on_update(GameState::Menu).with_system(skip_menu_system.system()),
+        )
+        .run();
+}
+
+/// Starts the game as soon as the menu shows.
+fn skip_menu_system(mut state: ResMut<State<GameState>>) {
+    if let Err(error) = state.set(GameState::InGame) {
+        warn!("Fail to start the game: {:?}", error);
+    }
+}
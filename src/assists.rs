@@ -0,0 +1,68 @@
+//! Assists easing the game, enabled together by the kid mode toggled in the
+//! menu for very young players.
+
+use bevy::prelude::*;
+
+use crate::{constants::MAX_SIMULTANEOUS_REQUESTS, preferences::StartPreferences};
+
+/// Multiplier of the trigger areas of the producers and Baobei in kid mode.
+const KID_TRIGGER_SCALE: f32 = 1.6;
+
+/// Assists read by the gameplay systems.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Assists {
+    /// Baobei never gets sad over time nor gives up on a request
+    pub no_fail: bool,
+    /// Multiplier of the trigger areas of the producers and Baobei
+    pub trigger_scale: f32,
+    /// Steers Didi toward the producer or the Baobei Didi walks to
+    pub aim_assist: bool,
+    /// Deliveries drop stars to collect instead of giving points
+    pub stars: bool,
+    /// Largest number of items Baobei asks for at the same time
+    pub max_requests: usize,
+}
+
+impl Default for Assists {
+    fn default() -> Self {
+        Self {
+            no_fail: false,
+            trigger_scale: 1.0,
+            aim_assist: false,
+            stars: false,
+            max_requests: MAX_SIMULTANEOUS_REQUESTS,
+        }
+    }
+}
+
+impl FromWorld for Assists {
+    fn from_world(world: &mut World) -> Self {
+        let kid_mode = world
+            .get_resource::<StartPreferences>()
+            .map_or(false, |preferences| preferences.kid_mode);
+        Self::preset(kid_mode)
+    }
+}
+
+impl Assists {
+    /// Returns the assists of the kid mode: no failure, larger trigger areas,
+    /// aim assist, stars instead of the score and one request at a time.
+    pub const fn kid_mode() -> Self {
+        Self {
+            no_fail: true,
+            trigger_scale: KID_TRIGGER_SCALE,
+            aim_assist: true,
+            stars: true,
+            max_requests: 1,
+        }
+    }
+
+    /// Returns the assists of the kid mode if enabled, none otherwise.
+    pub fn preset(kid_mode: bool) -> Self {
+        if kid_mode {
+            Self::kid_mode()
+        } else {
+            Self::default()
+        }
+    }
+}
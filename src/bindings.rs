@@ -0,0 +1,237 @@
+//! Screen binding the movement to exotic devices like dance pads or wheels.
+//!
+//! It shows the raw events sent by the gamepads, so the player sees what the
+//! device reports, and binds the next button pressed or axis moved to the
+//! direction chosen with the arrow keys.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{
+    constants::GameState,
+    controllers::{InputMap, MoveDirection},
+};
+
+/// Plugin managing the bindings screen.
+pub struct BindingsPlugin;
+
+impl Plugin for BindingsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Capture>()
+            .add_system_set(
+                SystemSet::on_update(GameState::Menu).with_system(start_bindings_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Bindings)
+                    .with_system(setup_bindings_screen.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Bindings)
+                    .with_system(choose_direction_system.system().label("choose_direction"))
+                    .with_system(capture_system.system().after("choose_direction"))
+                    .with_system(bindings_text_system.system().after("choose_direction"))
+                    .with_system(stop_bindings_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Bindings)
+                    .with_system(cleanup_bindings_screen.system()),
+            );
+    }
+}
+
+/// Number of raw events shown on the screen.
+const RAW_EVENTS_SHOWN: usize = 8;
+/// Value from which a button or an axis is captured, ignoring the light
+/// touches and the noise of the axes.
+const CAPTURE_THRESHOLD: f32 = 0.5;
+
+/// State of the capture of the controls.
+#[derive(Default)]
+struct Capture {
+    /// Direction bound to the next control triggered
+    direction: Option<MoveDirection>,
+    /// Last raw events of the gamepads, the latest first
+    raw_events: VecDeque<String>,
+}
+
+impl Capture {
+    /// Remembers the raw event, forgetting the oldest ones.
+    fn log(&mut self, event: String) {
+        self.raw_events.push_front(event);
+        self.raw_events.truncate(RAW_EVENTS_SHOWN);
+    }
+}
+
+/// Stores entities of the bindings screen.
+struct BindingsScreenData {
+    /// Entity wrapping all the entities of the screen
+    node_wrapper: Entity,
+}
+
+/// Tag the text listing the bindings of the directions.
+struct BindingsText;
+
+/// Tag the text listing the raw events of the gamepads.
+struct RawEventsText;
+
+/// Opens the bindings screen when the player presses `B` in the menu.
+fn start_bindings_system(keyboard_input: Res<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
+    if keyboard_input.just_pressed(KeyCode::B) {
+        state.set(GameState::Bindings).unwrap();
+    }
+}
+
+/// Goes back to the menu when the player presses `Escape`.
+fn stop_bindings_system(keyboard_input: Res<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        state.set(GameState::Menu).unwrap();
+    }
+}
+
+/// Chooses the direction to bind with the arrow keys.
+fn choose_direction_system(keyboard_input: Res<Input<KeyCode>>, mut capture: ResMut<Capture>) {
+    for direction in MoveDirection::ALL {
+        if keyboard_input.just_pressed(direction.arrow_key()) {
+            capture.direction = Some(direction);
+        }
+    }
+}
+
+/// Logs the raw events of the gamepads and binds the first control
+/// triggered to the chosen direction.
+fn capture_system(
+    mut capture: ResMut<Capture>,
+    mut input_map: ResMut<InputMap>,
+    mut gamepad_events: EventReader<GamepadEvent>,
+) {
+    for GamepadEvent(gamepad, event_type) in gamepad_events.iter() {
+        capture.log(format!("{:?}: {:?}", gamepad, event_type));
+
+        let direction = match capture.direction {
+            Some(direction) => direction,
+            None => continue,
+        };
+        match *event_type {
+            GamepadEventType::ButtonChanged(button, value) if value >= CAPTURE_THRESHOLD => {
+                info!("Bind {:?} to {:?}", direction, button);
+                input_map.bind_move_button(direction, button);
+            }
+            GamepadEventType::AxisChanged(axis, value) if value.abs() >= CAPTURE_THRESHOLD => {
+                info!("Bind {:?} to {:?}", direction, axis);
+                input_map.bind_move_axis(direction, axis, value);
+            }
+            _ => continue,
+        }
+        capture.direction = None;
+    }
+}
+
+/// Shows the bindings of the directions and the raw events.
+fn bindings_text_system(
+    capture: Res<Capture>,
+    input_map: Res<InputMap>,
+    mut bindings_texts: Query<&mut Text, (With<BindingsText>, Without<RawEventsText>)>,
+    mut raw_events_texts: Query<&mut Text, (With<RawEventsText>, Without<BindingsText>)>,
+    added_texts: Query<(), Added<BindingsText>>,
+) {
+    let changed = capture.is_changed() || input_map.is_changed();
+    if !changed && added_texts.iter().next().is_none() {
+        return;
+    }
+    let bindings: Vec<String> = MoveDirection::ALL
+        .iter()
+        .map(|direction| {
+            let marker = if capture.direction == Some(*direction) {
+                "> "
+            } else {
+                ""
+            };
+            format!(
+                "{}{:?}: {}",
+                marker,
+                direction,
+                input_map.move_label(*direction)
+            )
+        })
+        .collect();
+
+    for mut text in bindings_texts.iter_mut() {
+        text.sections[0].value = bindings.join("\n");
+    }
+    for mut text in raw_events_texts.iter_mut() {
+        text.sections[0].value = capture
+            .raw_events
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+}
+
+/// Shows the bindings and the instructions of the screen.
+fn setup_bindings_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut capture: ResMut<Capture>,
+) {
+    *capture = Capture::default();
+
+    let font = asset_server.load("FiraSans-Bold.ttf");
+    let text = |value: &str, font_size: f32| TextBundle {
+        text: Text::with_section(
+            value,
+            TextStyle {
+                font: font.clone(),
+                font_size,
+                color: Color::WHITE,
+            },
+            TextAlignment::default(),
+        ),
+        ..TextBundle::default()
+    };
+
+    let node_wrapper = commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                padding: Rect::all(Val::Px(50.0)),
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Style::default()
+            },
+            material: materials.add(Color::NONE.into()),
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            parent.spawn().insert_bundle(text("Device bindings", 80.0));
+            parent.spawn().insert_bundle(text(
+                "Choose a direction with the arrow keys, then press a button or move an axis",
+                30.0,
+            ));
+            parent
+                .spawn()
+                .insert(BindingsText)
+                .insert_bundle(text("", 40.0));
+            parent.spawn().insert_bundle(text("Raw events:", 30.0));
+            parent
+                .spawn()
+                .insert(RawEventsText)
+                .insert_bundle(text("", 25.0));
+            parent
+                .spawn()
+                .insert_bundle(text("Press Escape to go back", 30.0));
+        })
+        .id();
+
+    commands.insert_resource(BindingsScreenData { node_wrapper });
+}
+
+/// Removes all entities of the bindings screen.
+fn cleanup_bindings_screen(mut commands: Commands, screen_data: Res<BindingsScreenData>) {
+    commands
+        .entity(screen_data.node_wrapper)
+        .despawn_recursive();
+}
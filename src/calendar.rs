@@ -0,0 +1,240 @@
+//! Real-world calendar activating seasonal events.
+
+use std::{fmt, str::FromStr, time::SystemTime};
+
+/// A day of the Gregorian calendar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Date {
+    /// The year, e.g. 2021
+    pub year: i64,
+    /// The month, between 1 and 12
+    pub month: i64,
+    /// The day of the month, between 1 and 31
+    pub day: i64,
+}
+
+impl Date {
+    /// Creates the date of the given day.
+    pub const fn new(year: i64, month: i64, day: i64) -> Self {
+        Self { year, month, day }
+    }
+
+    /// Returns the current date in UTC.
+    pub fn today() -> Self {
+        let seconds = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+
+        // Days since 1970 are far from `i64::MAX`
+        #[allow(clippy::cast_possible_wrap)]
+        let days = (seconds / 86_400) as i64;
+
+        Self::from_days_since_epoch(days)
+    }
+
+    /// Converts the number of days since 1970-01-01 into a date.
+    ///
+    /// See <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+    pub const fn from_days_since_epoch(days: i64) -> Self {
+        let z = days + 719_468;
+        let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+        let day_of_era = z - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153; // March is 0
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        Self { year, month, day }
+    }
+
+    /// Returns the number of days since 1970-01-01.
+    ///
+    /// See <http://howardhinnant.github.io/date_algorithms.html#days_from_civil>
+    pub const fn days_since_epoch(self) -> i64 {
+        let year = if self.month <= 2 {
+            self.year - 1
+        } else {
+            self.year
+        };
+        let era = if year >= 0 { year } else { year - 399 } / 400;
+        let year_of_era = year - era * 400;
+        let shifted_month = if self.month > 2 {
+            self.month - 3
+        } else {
+            self.month + 9
+        };
+        let day_of_year = (153 * shifted_month + 2) / 5 + self.day - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+        era * 146_097 + day_of_era - 719_468
+    }
+}
+
+/// First days of the lunar new year.
+const LUNAR_NEW_YEARS: [Date; 10] = [
+    Date::new(2021, 2, 12),
+    Date::new(2022, 2, 1),
+    Date::new(2023, 1, 22),
+    Date::new(2024, 2, 10),
+    Date::new(2025, 1, 29),
+    Date::new(2026, 2, 17),
+    Date::new(2027, 2, 6),
+    Date::new(2028, 1, 26),
+    Date::new(2029, 2, 13),
+    Date::new(2030, 2, 3),
+];
+
+/// Duration of the lunar new year festival, until the lantern festival.
+const LUNAR_NEW_YEAR_DAYS: i64 = 15;
+
+/// An event of the year bringing themed content in the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeasonalEvent {
+    /// The spring festival
+    LunarNewYear,
+    /// The last week of October
+    Halloween,
+    /// The second half of December
+    Christmas,
+}
+
+impl SeasonalEvent {
+    /// All the seasonal events.
+    pub const ALL: [Self; 3] = [Self::LunarNewYear, Self::Halloween, Self::Christmas];
+
+    /// Returns the event happening on the given date, if any.
+    pub fn on(date: Date) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|event| event.is_happening_on(date))
+    }
+
+    /// Returns true if the event happens on the given date.
+    fn is_happening_on(self, date: Date) -> bool {
+        match self {
+            Self::LunarNewYear => LUNAR_NEW_YEARS.iter().any(|new_year| {
+                let elapsed_days = date.days_since_epoch() - new_year.days_since_epoch();
+                (0..LUNAR_NEW_YEAR_DAYS).contains(&elapsed_days)
+            }),
+            Self::Halloween => date.month == 10 && date.day >= 24,
+            Self::Christmas => date.month == 12 && date.day >= 15,
+        }
+    }
+}
+
+impl fmt::Display for SeasonalEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::LunarNewYear => "lunar_new_year",
+            Self::Halloween => "halloween",
+            Self::Christmas => "christmas",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for SeasonalEvent {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|event| event.to_string() == name)
+            .ok_or_else(|| format!("Unknown seasonal event: {}", name))
+    }
+}
+
+/// How the seasonal event is chosen, can be overridden for testing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeasonSetting {
+    /// The event follows the real date
+    Auto,
+    /// No seasonal event at all
+    Disabled,
+    /// The given event is always active
+    Forced(SeasonalEvent),
+}
+
+impl SeasonSetting {
+    /// Returns the active seasonal event on the given date.
+    pub fn resolve(self, date: Date) -> Option<SeasonalEvent> {
+        match self {
+            Self::Auto => SeasonalEvent::on(date),
+            Self::Disabled => None,
+            Self::Forced(event) => Some(event),
+        }
+    }
+}
+
+impl fmt::Display for SeasonSetting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Auto => write!(f, "auto"),
+            Self::Disabled => write!(f, "none"),
+            Self::Forced(event) => write!(f, "{}", event),
+        }
+    }
+}
+
+impl FromStr for SeasonSetting {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "auto" => Ok(Self::Auto),
+            "none" => Ok(Self::Disabled),
+            event => event.parse().map(Self::Forced),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Date, SeasonSetting, SeasonalEvent};
+
+    #[test]
+    fn test_date_conversion() {
+        assert_eq!(Date::from_days_since_epoch(0), Date::new(1970, 1, 1));
+        assert_eq!(Date::new(2021, 2, 12).days_since_epoch(), 18_670);
+
+        for days in 18_000..20_000 {
+            assert_eq!(Date::from_days_since_epoch(days).days_since_epoch(), days);
+        }
+    }
+
+    #[test]
+    fn test_seasonal_events() {
+        use SeasonalEvent::{Christmas, Halloween, LunarNewYear};
+
+        assert_eq!(SeasonalEvent::on(Date::new(2021, 2, 11)), None);
+        assert_eq!(
+            SeasonalEvent::on(Date::new(2021, 2, 12)),
+            Some(LunarNewYear)
+        );
+        assert_eq!(
+            SeasonalEvent::on(Date::new(2021, 2, 26)),
+            Some(LunarNewYear)
+        );
+        assert_eq!(SeasonalEvent::on(Date::new(2021, 2, 27)), None);
+        assert_eq!(SeasonalEvent::on(Date::new(2021, 10, 31)), Some(Halloween));
+        assert_eq!(SeasonalEvent::on(Date::new(2021, 12, 24)), Some(Christmas));
+
+        let date = Date::new(2021, 7, 14);
+        assert_eq!("auto".parse::<SeasonSetting>().unwrap().resolve(date), None);
+        assert_eq!("none".parse::<SeasonSetting>().unwrap().resolve(date), None);
+        assert_eq!(
+            "christmas".parse::<SeasonSetting>().unwrap().resolve(date),
+            Some(Christmas)
+        );
+        assert!("easter".parse::<SeasonSetting>().is_err());
+    }
+}
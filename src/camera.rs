@@ -0,0 +1,148 @@
+//! Camera of the game, zooming smoothly to frame the characters, or the shot
+//! of a cutscene.
+
+use bevy::prelude::*;
+
+use crate::{
+    collisions::Position,
+    constants::{GameState, WINDOW_HEIGHT, WINDOW_WIDTH},
+    settings::Settings,
+};
+
+/// Plugin managing the camera.
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<CameraShot>()
+            .add_startup_system(setup_camera.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame).with_system(framing_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::InGame).with_system(reset_camera_system.system()),
+            );
+    }
+}
+
+/// Smallest zoom of the camera, the fraction of the room shown.
+const MIN_ZOOM: f32 = 0.7;
+/// Biggest zoom of the camera, showing the whole room.
+const MAX_ZOOM: f32 = 1.0;
+/// Space kept around the framed characters, horizontally and vertically.
+const FRAMING_MARGIN: (f32, f32) = (250.0, 200.0);
+/// How fast the camera catches up with the framing, per second.
+const CAMERA_SMOOTHING: f32 = 3.0;
+
+/// Component on the camera of the game.
+pub struct MainCamera;
+
+/// Component on the characters the camera keeps on screen.
+pub struct CameraTarget;
+
+/// Shot of a cutscene, framing its center with its zoom instead of the
+/// characters while set.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CameraShot(pub Option<(Vec2, f32)>);
+
+/// Spawns the camera showing the whole room.
+fn setup_camera(mut commands: Commands) {
+    let mut camera_2d = OrthographicCameraBundle::new_2d();
+    camera_2d.transform.translation += room_center().extend(0.0);
+
+    commands.spawn().insert(MainCamera).insert_bundle(camera_2d);
+}
+
+/// Returns the center of the room.
+fn room_center() -> Vec2 {
+    Vec2::new(WINDOW_WIDTH / 2.0, WINDOW_HEIGHT / 2.0)
+}
+
+/// Returns the center and the zoom of the camera showing all the targets,
+/// without showing anything outside of the room.
+fn framing(targets: &[Vec2]) -> (Vec2, f32) {
+    let room_size = Vec2::new(WINDOW_WIDTH, WINDOW_HEIGHT);
+    let (min, max) = match targets.split_first() {
+        Some((first, others)) => others.iter().fold((*first, *first), |(min, max), target| {
+            (min.min(*target), max.max(*target))
+        }),
+        None => return (room_center(), MAX_ZOOM),
+    };
+
+    let size = max - min + 2.0 * Vec2::new(FRAMING_MARGIN.0, FRAMING_MARGIN.1);
+    let zoom = (size / room_size).max_element().max(MIN_ZOOM).min(MAX_ZOOM);
+
+    let half_view = room_size * zoom / 2.0;
+    let center = ((min + max) / 2.0)
+        .max(half_view)
+        .min(room_size - half_view);
+
+    (center, zoom)
+}
+
+/// Moves and zooms the camera smoothly to keep the targets on screen, or to
+/// the shot of a cutscene.
+fn framing_system(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    shot: Res<CameraShot>,
+    targets: Query<&Position, With<CameraTarget>>,
+    mut cameras: Query<&mut Transform, With<MainCamera>>,
+) {
+    let (center, zoom) = if let Some(shot) = shot.0 {
+        shot
+    } else if settings.dynamic_camera {
+        let targets: Vec<Vec2> = targets
+            .iter()
+            .map(|position| Vec2::new(position.0.x, position.0.y + position.0.z))
+            .collect();
+        framing(&targets)
+    } else {
+        (room_center(), MAX_ZOOM)
+    };
+    let smoothing = (CAMERA_SMOOTHING * time.delta_seconds()).min(1.0);
+
+    for mut transform in cameras.iter_mut() {
+        let translation = center.extend(transform.translation.z);
+        let scale = Vec3::new(zoom, zoom, 1.0);
+
+        transform.translation = transform.translation.lerp(translation, smoothing);
+        transform.scale = transform.scale.lerp(scale, smoothing);
+    }
+}
+
+/// Shows the whole room again when leaving the game, ending any cutscene.
+fn reset_camera_system(
+    mut shot: ResMut<CameraShot>,
+    mut cameras: Query<&mut Transform, With<MainCamera>>,
+) {
+    shot.0 = None;
+    for mut transform in cameras.iter_mut() {
+        transform.translation = room_center().extend(transform.translation.z);
+        transform.scale = Vec3::ONE;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec2;
+
+    use super::{framing, room_center, MAX_ZOOM, MIN_ZOOM};
+
+    #[test]
+    fn test_framing() {
+        // Close targets are zoomed in as much as possible
+        let (center, zoom) = framing(&[Vec2::new(600.0, 300.0), Vec2::new(700.0, 400.0)]);
+        assert!((zoom - MIN_ZOOM).abs() < f32::EPSILON);
+        assert!(center.abs_diff_eq(Vec2::new(650.0, 350.0), f32::EPSILON));
+
+        // Targets in a corner do not show outside of the room
+        let (center, _) = framing(&[Vec2::new(10.0, 10.0), Vec2::new(20.0, 20.0)]);
+        assert!(center.abs_diff_eq(Vec2::new(448.0, 252.0), 0.01));
+
+        // Spread targets show the whole room
+        let (center, zoom) = framing(&[Vec2::new(0.0, 0.0), Vec2::new(1280.0, 720.0)]);
+        assert!((zoom - MAX_ZOOM).abs() < f32::EPSILON);
+        assert!(center.abs_diff_eq(room_center(), f32::EPSILON));
+    }
+}
@@ -4,9 +4,11 @@ use std::collections::HashMap;
 
 use bevy::prelude::*;
 
-use crate::{constants::GameState, drawing::UiObject};
+use crate::{constants::GameState, drawing::Overlay};
 
-use super::{BoxCollider, CollisionSystems, Position, TriggerArea};
+use super::{
+    collider_shape, Colliders, CollisionSystems, Position, Shape, TriggerArea, WithCollider,
+};
 
 /// Plugin for displaying colliders and trigger areas.
 pub struct DebugCollisionPlugin;
@@ -26,7 +28,7 @@ impl Plugin for DebugCollisionPlugin {
 
 /// Colors of the colliders.
 struct ColliderMaterials {
-    /// Debug color for the `BoxCollider`
+    /// Debug color for the `BoxCollider` and the `CircleCollider`
     collider: Handle<ColorMaterial>,
     /// Debug color for the `TriggerArea`
     trigger_area: Handle<ColorMaterial>,
@@ -60,16 +62,28 @@ fn add_collider_viewer_system(
     mut commands: Commands,
     mut viewers: ResMut<ColliderViewers>,
     materials: ResMut<ColliderMaterials>,
-    non_viewed_colliders: Query<(Entity, &BoxCollider, &Position), Without<ViewedCollider>>,
+    non_viewed_colliders: Query<
+        (Entity, Colliders, &Position),
+        (WithCollider, Without<ViewedCollider>),
+    >,
     non_viewed_trigger_areas: Query<(Entity, &TriggerArea, &Position), Without<ViewedTriggerArea>>,
 ) {
-    for (entity, collider, pos) in non_viewed_colliders.iter() {
+    for (entity, colliders, pos) in non_viewed_colliders.iter() {
+        let (offset, shape) = match collider_shape(colliders) {
+            Some(collider) => collider,
+            None => continue,
+        };
         commands.entity(entity).insert(ViewedCollider);
 
+        // The circles are shown by their bounding box
+        let size = match shape {
+            Shape::Box(size) => size,
+            Shape::Circle(radius) => Vec2::splat(radius * 2.0),
+        };
         let viewer = spawn_viewer(
             &mut commands,
-            forwarded_position(pos.0 + collider.offset),
-            collider.size,
+            forwarded_position(pos.0 + offset),
+            size,
             materials.collider.clone(),
         );
 
@@ -108,7 +122,7 @@ fn spawn_viewer(
         .spawn()
         .insert(DebugViewer)
         .insert(pos)
-        .insert(UiObject)
+        .insert(Overlay)
         .insert_bundle(SpriteBundle {
             material: color,
             sprite: Sprite::new(size),
@@ -128,16 +142,18 @@ type MovedCollider = (
 fn update_collider_viewers_system(
     all_viewers: Res<ColliderViewers>,
     moved_colliders: Query<(Entity, &Position), MovedCollider>,
-    box_colliders: Query<&BoxCollider>,
+    colliders: Query<Colliders, WithCollider>,
     mut viewer_query: Query<&mut Position, With<DebugViewer>>,
 ) {
     for (entity, pos) in moved_colliders.iter() {
         if let Some(viewers) = all_viewers.0.get(&entity) {
             for viewer in viewers {
                 if let Ok(mut viewer_pos) = viewer_query.get_mut(*viewer) {
-                    let offset = box_colliders
+                    let offset = colliders
                         .get(entity)
-                        .map(|col| col.offset)
+                        .ok()
+                        .and_then(collider_shape)
+                        .map(|(offset, _)| offset)
                         .unwrap_or_default();
 
                     *viewer_pos = forwarded_position(pos.0 + offset);
@@ -0,0 +1,532 @@
+//! Data layout of the collision hot path.
+//!
+//! The shapes tested by the collision systems are copied from the ECS into
+//! parallel arrays of centers and shapes, iterated without indirection and
+//! shared read-only between the tasks testing the moving entities.
+//!
+//! The shapes are also indexed by a spatial hash, a uniform grid of square
+//! cells listing the shapes covering each cell, built with the arrays. A
+//! shape is only tested against the shapes of the cells it covers, instead
+//! of all of them.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+/// Width and height of a cell of the spatial hash, bigger than most of the
+/// colliders so that they cover few cells.
+const HASH_CELL_SIZE: f32 = 128.0;
+
+/// A cell of the spatial hash, by its column and row.
+type HashCell = (i32, i32);
+
+/// Shape of a collider or of a trigger area, around its center.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    /// A rectangle of the given width and height
+    Box(Vec2),
+    /// A circle of the given radius
+    Circle(f32),
+}
+
+impl Shape {
+    /// Returns half of the size of the box bounding the shape.
+    fn half_extents(self) -> Vec2 {
+        match self {
+            Self::Box(size) => size / 2.0,
+            Self::Circle(radius) => Vec2::splat(radius),
+        }
+    }
+
+    /// Returns true if the shape at the center overlaps the other shape at
+    /// its center, touching edges not counting as an overlap.
+    #[must_use]
+    pub fn overlaps(self, center: Vec2, other: Self, other_center: Vec2) -> bool {
+        let distance = other_center - center;
+        match (self, other) {
+            (Self::Box(size), Self::Box(other_size)) => {
+                let reach = (size + other_size) / 2.0;
+                distance.x.abs() < reach.x && distance.y.abs() < reach.y
+            }
+            (Self::Circle(radius), Self::Circle(other_radius)) => {
+                distance.length_squared() < (radius + other_radius).powi(2)
+            }
+            (Self::Box(size), Self::Circle(radius)) => circle_overlaps_box(distance, radius, size),
+            (Self::Circle(radius), Self::Box(size)) => circle_overlaps_box(-distance, radius, size),
+        }
+    }
+
+    /// Returns the distance along the ray from the origin, going in the
+    /// normalized direction, to the shape at the center, `None` if the ray
+    /// misses it. A ray starting inside the shape does not hit it.
+    #[must_use]
+    pub fn ray_distance(self, center: Vec2, origin: Vec2, direction: Vec2) -> Option<f32> {
+        let origin = origin - center;
+        match self {
+            Self::Box(size) => {
+                let half_size = size / 2.0;
+                let (mut enter, mut exit) = (f32::NEG_INFINITY, f32::INFINITY);
+                for (origin, direction, half_size) in [
+                    (origin.x, direction.x, half_size.x),
+                    (origin.y, direction.y, half_size.y),
+                ] {
+                    if direction == 0.0 {
+                        if origin.abs() >= half_size {
+                            return None;
+                        }
+                        continue;
+                    }
+                    let near = (-half_size - origin) / direction;
+                    let far = (half_size - origin) / direction;
+                    enter = enter.max(near.min(far));
+                    exit = exit.min(near.max(far));
+                }
+                (enter >= 0.0 && enter < exit).then(|| enter)
+            }
+            Self::Circle(radius) => {
+                let along = origin.dot(direction);
+                let outside = origin.length_squared() - radius * radius;
+                let discriminant = along * along - outside;
+                if outside <= 0.0 || discriminant <= 0.0 {
+                    return None;
+                }
+                let distance = -along - discriminant.sqrt();
+                (distance >= 0.0).then(|| distance)
+            }
+        }
+    }
+
+    /// Returns the point of the edge of the shape at the center where the
+    /// other shape, at its center, is pushed out along the translation.
+    #[must_use]
+    pub fn surface_point(self, center: Vec2, other_center: Vec2, translation: Vec2) -> Vec2 {
+        match self {
+            Self::Box(size) => {
+                let half_size = size / 2.0;
+                let inside = other_center - center;
+                let mut point = inside.max(-half_size).min(half_size);
+                if point == inside {
+                    // The other center is inside, the point is on the side
+                    // the shape is pushed out of
+                    if translation.x.abs() > translation.y.abs() {
+                        point.x = half_size.x * translation.x.signum();
+                    } else {
+                        point.y = half_size.y * translation.y.signum();
+                    }
+                }
+                center + point
+            }
+            Self::Circle(radius) => {
+                let length = translation.length();
+                if length > 0.0 {
+                    center + translation / length * radius
+                } else {
+                    center
+                }
+            }
+        }
+    }
+
+    /// Returns the smallest translation pushing the other shape at its
+    /// center out of the shape at the center, `None` without overlap.
+    #[must_use]
+    pub fn penetration(self, center: Vec2, other: Self, other_center: Vec2) -> Option<Vec2> {
+        let distance = other_center - center;
+        match (self, other) {
+            (Self::Box(size), Self::Box(other_size)) => {
+                let depth = (size + other_size) / 2.0 - distance.abs();
+                if depth.x <= 0.0 || depth.y <= 0.0 {
+                    None
+                } else if depth.x < depth.y {
+                    Some(Vec2::new(depth.x * distance.x.signum(), 0.0))
+                } else {
+                    Some(Vec2::new(0.0, depth.y * distance.y.signum()))
+                }
+            }
+            (Self::Circle(radius), Self::Circle(other_radius)) => {
+                let length = distance.length();
+                let depth = radius + other_radius - length;
+                if depth <= 0.0 {
+                    return None;
+                }
+                let normal = if length > 0.0 {
+                    distance / length
+                } else {
+                    Vec2::X
+                };
+                Some(normal * depth)
+            }
+            (Self::Box(size), Self::Circle(radius)) => circle_out_of_box(distance, radius, size),
+            (Self::Circle(radius), Self::Box(size)) => {
+                circle_out_of_box(-distance, radius, size).map(|push| -push)
+            }
+        }
+    }
+}
+
+/// Returns true if the circle overlaps a box of the given size, the center
+/// of the circle being relative to the center of the box.
+fn circle_overlaps_box(center: Vec2, radius: f32, size: Vec2) -> bool {
+    let half_size = size / 2.0;
+    let closest = center.max(-half_size).min(half_size);
+    center.distance_squared(closest) < radius * radius
+}
+
+/// Returns the smallest translation pushing the circle out of a box of the
+/// given size, the center of the circle being relative to the center of the
+/// box, `None` without overlap.
+fn circle_out_of_box(center: Vec2, radius: f32, size: Vec2) -> Option<Vec2> {
+    let half_size = size / 2.0;
+    let closest = center.max(-half_size).min(half_size);
+
+    if closest == center {
+        // The center is inside the box, pushed out by the closest side
+        let depth = half_size + Vec2::splat(radius) - center.abs();
+        return Some(if depth.x < depth.y {
+            Vec2::new(depth.x * center.x.signum(), 0.0)
+        } else {
+            Vec2::new(0.0, depth.y * center.y.signum())
+        });
+    }
+    let outside = center - closest;
+    let length = outside.length();
+    if length < radius {
+        Some(outside / length * (radius - length))
+    } else {
+        None
+    }
+}
+
+/// Returns the longest of the penetrations with the index of the shape
+/// pushing, `None` if none.
+fn deepest(penetrations: impl Iterator<Item = (usize, Option<Vec2>)>) -> Option<(usize, Vec2)> {
+    penetrations
+        .filter_map(|(index, push)| Some((index, push?)))
+        .fold(None, |deepest, (index, push)| match deepest {
+            Some((_, deepest_push)) if deepest_push.length_squared() >= push.length_squared() => {
+                deepest
+            }
+            _ => Some((index, push)),
+        })
+}
+
+/// A shape pushed out of one of the shapes of the arrays.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Push {
+    /// Entity owning the shape pushing
+    pub entity: Entity,
+    /// Smallest translation pushing the shape out
+    pub translation: Vec2,
+    /// Point of the edge of the shape pushing where the shape is pushed out
+    pub point: Vec2,
+}
+
+/// Returns the first and the last cells of the spatial hash covered by the
+/// box bounding the shape at the center.
+// The room spans a few cells
+#[allow(clippy::cast_possible_truncation)]
+fn cell_range(center: Vec2, shape: Shape) -> (HashCell, HashCell) {
+    let half_extents = shape.half_extents();
+    let first = ((center - half_extents) / HASH_CELL_SIZE).floor();
+    let last = ((center + half_extents) / HASH_CELL_SIZE).floor();
+    (
+        (first.x as i32, first.y as i32),
+        (last.x as i32, last.y as i32),
+    )
+}
+
+/// Shapes stored as parallel arrays, one index per shape, indexed by a
+/// spatial hash.
+#[derive(Default)]
+pub struct BoxArrays {
+    /// Entity owning each shape
+    entities: Vec<Entity>,
+    /// Center of each shape
+    centers: Vec<Vec2>,
+    /// Each shape, a box or a circle
+    shapes: Vec<Shape>,
+    /// First cell of the spatial hash covered by each shape
+    first_cells: Vec<HashCell>,
+    /// Indices of the shapes covering each cell of the spatial hash
+    cells: HashMap<HashCell, Vec<usize>>,
+}
+
+impl BoxArrays {
+    /// Returns the shapes of the given entities, centers and shapes.
+    pub fn collect(shapes: impl Iterator<Item = (Entity, Vec3, Shape)>) -> Self {
+        let mut arrays = Self::default();
+        for (entity, center, shape) in shapes {
+            arrays.push(entity, center, shape);
+        }
+        arrays
+    }
+
+    /// Removes all the shapes.
+    pub fn clear(&mut self) {
+        self.entities.clear();
+        self.centers.clear();
+        self.shapes.clear();
+        self.first_cells.clear();
+        self.cells.clear();
+    }
+
+    /// Adds the shape of the entity.
+    pub fn push(&mut self, entity: Entity, center: Vec3, shape: Shape) {
+        let index = self.entities.len();
+        let center = center.truncate();
+        self.entities.push(entity);
+        self.centers.push(center);
+        self.shapes.push(shape);
+
+        let (first, last) = cell_range(center, shape);
+        self.first_cells.push(first);
+        for column in first.0..=last.0 {
+            for row in first.1..=last.1 {
+                self.cells.entry((column, row)).or_default().push(index);
+            }
+        }
+    }
+
+    /// Returns the indices of the shapes covering the cells covered by the
+    /// shape at the center, each index once.
+    fn candidates(&self, center: Vec2, shape: Shape) -> impl Iterator<Item = usize> + '_ {
+        let (first, last) = cell_range(center, shape);
+        (first.0..=last.0)
+            .flat_map(move |column| (first.1..=last.1).map(move |row| (column, row)))
+            .filter_map(move |cell| Some((cell, self.cells.get(&cell)?)))
+            .flat_map(move |(cell, indices)| {
+                // A shape covering several of the cells is only tested in
+                // the first cell both shapes cover
+                indices.iter().copied().filter(move |&index| {
+                    let shape_first = self.first_cells[index];
+                    cell == (shape_first.0.max(first.0), shape_first.1.max(first.1))
+                })
+            })
+    }
+
+    /// Returns true if the shape at the center overlaps one of the shapes.
+    pub fn overlaps(&self, center: Vec3, shape: Shape) -> bool {
+        let center = center.truncate();
+        self.candidates(center, shape)
+            .any(|index| self.overlaps_at(index, center, shape))
+    }
+
+    /// Returns the entities whose shape overlaps the shape at the center.
+    pub fn overlapping(&self, center: Vec3, shape: Shape) -> impl Iterator<Item = Entity> + '_ {
+        let center = center.truncate();
+        self.candidates(center, shape)
+            .filter(move |&index| self.overlaps_at(index, center, shape))
+            .map(move |index| self.entities[index])
+    }
+
+    /// Returns the push of the shape at the center out of the shape it
+    /// overlaps the most, `None` without overlap.
+    pub fn push_out(&self, center: Vec3, shape: Shape) -> Option<Push> {
+        let center = center.truncate();
+        let (index, translation) = deepest(
+            self.candidates(center, shape)
+                .map(|index| (index, self.penetration_at(index, center, shape))),
+        )?;
+        Some(self.push_at(index, center, translation))
+    }
+
+    /// Returns the push of the shape of the entity, moving from the
+    /// `current` to the `next` center, out of the shape of another entity it
+    /// runs into the most, `None` if none. The shapes it already overlaps do
+    /// not push it, so that two entities pushed into each other can move
+    /// apart.
+    pub fn push_out_moving(
+        &self,
+        entity: Entity,
+        current: Vec3,
+        next: Vec3,
+        shape: Shape,
+    ) -> Option<Push> {
+        let (current, next) = (current.truncate(), next.truncate());
+        let (index, translation) = deepest(
+            self.candidates(next, shape)
+                .filter(|&index| {
+                    self.entities[index] != entity && !self.overlaps_at(index, current, shape)
+                })
+                .map(|index| (index, self.penetration_at(index, next, shape))),
+        )?;
+        Some(self.push_at(index, next, translation))
+    }
+
+    /// Returns the push out of the shape at the index of a shape at the
+    /// center along the translation.
+    fn push_at(&self, index: usize, center: Vec2, translation: Vec2) -> Push {
+        Push {
+            entity: self.entities[index],
+            translation,
+            point: self.shapes[index].surface_point(self.centers[index], center, translation),
+        }
+    }
+
+    /// Returns the translation pushing the given shape out of the shape at
+    /// the index, `None` without overlap.
+    fn penetration_at(&self, index: usize, center: Vec2, shape: Shape) -> Option<Vec2> {
+        self.shapes[index].penetration(self.centers[index], shape, center)
+    }
+
+    /// Returns true if the shape at the index overlaps the given shape.
+    fn overlaps_at(&self, index: usize, center: Vec2, shape: Shape) -> bool {
+        self.shapes[index].overlaps(self.centers[index], shape, center)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlapping_boxes_are_found() {
+        let wall = Entity::new(0);
+        let couch = Entity::new(1);
+        let boxes = BoxArrays::collect(
+            vec![
+                (
+                    wall,
+                    Vec3::new(0.0, 0.0, 5.0),
+                    Shape::Box(Vec2::new(100.0, 10.0)),
+                ),
+                (
+                    couch,
+                    Vec3::new(100.0, 50.0, 0.0),
+                    Shape::Box(Vec2::new(40.0, 40.0)),
+                ),
+            ]
+            .into_iter(),
+        );
+        let small_box = Shape::Box(Vec2::new(10.0, 10.0));
+
+        assert!(boxes.overlaps(Vec3::new(40.0, 8.0, 0.0), small_box));
+        // Touching edges do not overlap
+        assert!(!boxes.overlaps(Vec3::new(0.0, 10.0, 0.0), small_box));
+
+        let found: Vec<Entity> = boxes
+            .overlapping(
+                Vec3::new(75.0, 40.0, 0.0),
+                Shape::Box(Vec2::new(60.0, 60.0)),
+            )
+            .collect();
+        assert_eq!(found, vec![couch]);
+    }
+
+    #[test]
+    fn shapes_covering_several_cells_are_found_once() {
+        let wall = Entity::new(0);
+        let lamp = Entity::new(1);
+        let boxes = BoxArrays::collect(
+            vec![
+                (
+                    wall,
+                    Vec3::new(300.0, 200.0, 0.0),
+                    Shape::Box(Vec2::new(600.0, 20.0)),
+                ),
+                (lamp, Vec3::new(-500.0, -500.0, 0.0), Shape::Circle(10.0)),
+            ]
+            .into_iter(),
+        );
+        let rug = Shape::Box(Vec2::new(400.0, 300.0));
+
+        let found: Vec<Entity> = boxes
+            .overlapping(Vec3::new(250.0, 180.0, 0.0), rug)
+            .collect();
+        assert_eq!(found, vec![wall]);
+        let found: Vec<Entity> = boxes
+            .overlapping(Vec3::new(-400.0, -400.0, 0.0), rug)
+            .collect();
+        assert_eq!(found, vec![lamp]);
+        assert!(!boxes.overlaps(Vec3::new(300.0, 400.0, 0.0), rug));
+    }
+
+    #[test]
+    fn circles_round_the_corners() {
+        let square = Shape::Box(Vec2::new(20.0, 20.0));
+        let circle = Shape::Circle(5.0);
+
+        // Next to a corner, the circle misses the box where a box would not
+        let corner = Vec2::new(14.0, 14.0);
+        assert!(!square.overlaps(Vec2::ZERO, circle, corner));
+        assert!(square.overlaps(Vec2::ZERO, Shape::Box(Vec2::new(10.0, 10.0)), corner));
+        // Along a side and inside, the circle overlaps the box
+        assert!(circle.overlaps(Vec2::new(0.0, 14.0), square, Vec2::ZERO));
+        assert!(square.overlaps(Vec2::ZERO, circle, Vec2::new(3.0, -2.0)));
+
+        assert!(circle.overlaps(Vec2::ZERO, Shape::Circle(4.0), Vec2::new(6.0, 6.0)));
+        assert!(!circle.overlaps(Vec2::ZERO, Shape::Circle(4.0), Vec2::new(9.0, 0.0)));
+    }
+
+    #[test]
+    fn moving_boxes_run_into_the_others() {
+        let didi = Entity::new(0);
+        let cat = Entity::new(1);
+        let size = Shape::Box(Vec2::new(10.0, 10.0));
+        let boxes = BoxArrays::collect(
+            vec![
+                (didi, Vec3::ZERO, size),
+                (cat, Vec3::new(20.0, 0.0, 0.0), size),
+            ]
+            .into_iter(),
+        );
+
+        assert_eq!(
+            boxes.push_out_moving(didi, Vec3::ZERO, Vec3::new(12.0, 0.0, 0.0), size),
+            Some(Push {
+                entity: cat,
+                translation: Vec2::new(-2.0, 0.0),
+                point: Vec2::new(15.0, 0.0),
+            })
+        );
+        // Its own box does not push it
+        assert_eq!(
+            boxes.push_out_moving(didi, Vec3::ZERO, Vec3::new(-5.0, 0.0, 0.0), size),
+            None
+        );
+        // Overlapping boxes can move apart
+        assert_eq!(
+            boxes.push_out_moving(
+                cat,
+                Vec3::new(5.0, 0.0, 0.0),
+                Vec3::new(8.0, 0.0, 0.0),
+                size
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn overlapping_shapes_are_pushed_out_the_shortest_way() {
+        let wall = Shape::Box(Vec2::new(100.0, 20.0));
+        let circle = Shape::Circle(5.0);
+
+        // Pushed back above the wall, not along it
+        let push = wall.penetration(
+            Vec2::ZERO,
+            Shape::Box(Vec2::new(10.0, 10.0)),
+            Vec2::new(30.0, 12.0),
+        );
+        assert_eq!(push, Some(Vec2::new(0.0, 3.0)));
+        let push = wall.penetration(Vec2::ZERO, circle, Vec2::new(-20.0, -12.0));
+        assert_eq!(push, Some(Vec2::new(0.0, -3.0)));
+        // Around the corner, along the diagonal
+        let push = wall
+            .penetration(Vec2::ZERO, circle, Vec2::new(52.4, 13.2))
+            .unwrap();
+        assert!((push - Vec2::new(0.6, 0.8)).length() < 1e-4);
+        // From inside, out of the closest side
+        assert_eq!(
+            wall.penetration(Vec2::ZERO, circle, Vec2::new(45.0, 0.0)),
+            Some(Vec2::new(10.0, 0.0))
+        );
+        assert_eq!(
+            circle.penetration(Vec2::ZERO, Shape::Circle(3.0), Vec2::new(0.0, 6.0)),
+            Some(Vec2::new(0.0, 2.0))
+        );
+        assert_eq!(
+            wall.penetration(Vec2::ZERO, circle, Vec2::new(0.0, 15.0)),
+            None
+        );
+    }
+}
@@ -5,14 +5,35 @@ use std::{
     cmp::min,
     collections::{HashMap, HashSet},
     hash::{Hash, Hasher},
+    sync::Mutex,
 };
 
-use bevy::{prelude::*, sprite::collide_aabb::collide};
+use bevy::{
+    prelude::*,
+    sprite::collide_aabb::collide,
+    tasks::{ComputeTaskPool, ParallelSlice},
+};
 use debug_collisions::DebugCollisionPlugin;
+pub use layout::Shape;
+use layout::{BoxArrays, Push};
+pub use nav_grid::NavGrid;
+pub use raycast::{RayHit, Raycast};
 
-use crate::constants::GameState;
+use crate::{constants::GameState, pool::Pool};
 
 mod debug_collisions;
+mod layout;
+mod nav_grid;
+mod raycast;
+
+/// Number of moving entities tested by each task of the collision systems.
+const MOVING_BATCH_SIZE: usize = 16;
+/// Longest step of a moving collider between two resolutions, shorter than
+/// the smallest colliders so that the fast colliders are not pushed out on
+/// the far side of the thin furniture.
+const MAX_STEP: f32 = 4.0;
+/// Number of times a step is pushed out of the colliders it runs into.
+const RESOLUTION_ITERATIONS: usize = 4;
 
 /// Label for collision systems
 #[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
@@ -24,12 +45,23 @@ pub struct CollisionPlugin;
 impl Plugin for CollisionPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app.add_event::<ContactEvent>()
+            .add_event::<CollisionEvent>()
+            .init_resource::<Pool<Contact>>()
+            .init_resource::<StaticColliders>()
+            .init_resource::<NavGrid>()
             .register_type::<Position>()
             .register_type::<BoxCollider>()
+            .register_type::<CircleCollider>()
             .add_system_set(
                 SystemSet::on_update(GameState::InGame)
                     .label(CollisionSystems)
-                    .with_system(collision_system.system())
+                    .with_system(
+                        refresh_static_colliders_system
+                            .system()
+                            .label("static_colliders"),
+                    )
+                    .with_system(collision_system.system().after("static_colliders"))
+                    .with_system(free_movement_system.system())
                     .with_system(trigger_area_system.system()),
             );
 
@@ -61,12 +93,61 @@ pub struct BoxCollider {
 
 impl BoxCollider {
     /// Creates a box collider with the given size and no offset.
+    #[must_use]
     pub fn new(width: f32, height: f32) -> Self {
         Self {
             size: Vec2::new(width, height),
             offset: Vec3::new(0.0, 0.0, 0.0),
         }
     }
+
+    /// Returns the shape of the collider.
+    #[must_use]
+    pub const fn shape(&self) -> Shape {
+        Shape::Box(self.size)
+    }
+}
+
+/// Collider in a shape of a circle, for the round characters sliding around
+/// the corners of the furniture.
+#[derive(Clone, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct CircleCollider {
+    /// The radius of the circle.
+    pub radius: f32,
+    /// Offset of the collider with the position.
+    pub offset: Vec3,
+}
+
+impl CircleCollider {
+    /// Creates a circle collider with the given radius and no offset.
+    #[must_use]
+    pub fn new(radius: f32) -> Self {
+        Self {
+            radius,
+            offset: Vec3::ZERO,
+        }
+    }
+
+    /// Returns the shape of the collider.
+    #[must_use]
+    pub const fn shape(&self) -> Shape {
+        Shape::Circle(self.radius)
+    }
+}
+
+/// Colliders an entity may have, a box or a circle.
+type Colliders<'a> = (Option<&'a BoxCollider>, Option<&'a CircleCollider>);
+
+/// Query filter for entities with a collider, a box or a circle.
+type WithCollider = Or<(With<BoxCollider>, With<CircleCollider>)>;
+
+/// Returns the offset and the shape of the collider of an entity, its box if
+/// it has both.
+fn collider_shape((box_collider, circle_collider): Colliders) -> Option<(Vec3, Shape)> {
+    box_collider
+        .map(|collider| (collider.offset, collider.shape()))
+        .or_else(|| circle_collider.map(|collider| (collider.offset, collider.shape())))
 }
 
 /// A rectangle area that can be contacted without collision.
@@ -79,6 +160,7 @@ pub struct TriggerArea {
 
 impl TriggerArea {
     /// Creates a box collider with the given size.
+    #[must_use]
     pub fn new(width: f32, height: f32) -> Self {
         Self {
             size: Vec2::new(width, height),
@@ -86,6 +168,13 @@ impl TriggerArea {
     }
 }
 
+/// A trigger area slowing down the moving colliders in contact with it.
+#[derive(Clone, Copy, Debug)]
+pub struct SlowZone {
+    /// Multiplier of the movement of the colliders in the zone.
+    pub speed_multiplier: f32,
+}
+
 /// Represents a contact between two entities
 #[derive(Clone, Copy, Debug, Eq)]
 pub struct Contact(pub Entity, pub Entity);
@@ -110,75 +199,405 @@ impl Hash for Contact {
     }
 }
 
+/// Geometry of a contact when it starts, the first entity of the contact
+/// being pushed out of the second one.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ContactGeometry {
+    /// Direction the first entity is pushed out of the second one.
+    pub normal: Vec2,
+    /// Length of the overlap along the normal.
+    pub depth: f32,
+    /// Point of the edge of the second entity where the first one is pushed
+    /// out, at the height of the first one.
+    pub point: Vec3,
+}
+
+impl ContactGeometry {
+    /// Returns the geometry of the push out of a shape, at the height of the
+    /// shape pushed.
+    fn new(push: Push, height: f32) -> Self {
+        let depth = push.translation.length();
+        Self {
+            normal: if depth > 0.0 {
+                push.translation / depth
+            } else {
+                Vec2::ZERO
+            },
+            depth,
+            point: push.point.extend(height),
+        }
+    }
+}
+
 /// Event appearing when entities collides.
 #[derive(Clone, Debug, PartialEq)]
 pub enum ContactEvent {
-    /// A contact is happening.
-    Started(Contact),
+    /// A contact is happening, the collider entering the area.
+    Started(Contact, ContactGeometry),
     /// A contact is finished.
     Stopped(Contact),
 }
 
-/// Moves the position of moving entities depending on their movement.
-/// If the entity collides with another collider, then the movement will not be made.
+/// Event appearing when a moving collider is blocked by another collider,
+/// the first entity of the contact being the moving one.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CollisionEvent {
+    /// The collider starts being blocked.
+    Started(Contact, ContactGeometry),
+    /// The collider is not blocked anymore.
+    Stopped(Contact),
+}
+
+/// Colliders that do not move, copied from the ECS when one of them is added,
+/// moved, resized or removed.
+#[derive(Default)]
+struct StaticColliders(BoxArrays);
+
+/// Returns true if a box of the given size at the position overlaps one of
+/// the colliders.
+#[must_use]
+pub fn overlaps_colliders<'a>(
+    position: Vec3,
+    size: Vec2,
+    colliders: impl IntoIterator<Item = (&'a Position, &'a BoxCollider)>,
+) -> bool {
+    colliders.into_iter().any(|(collider_position, collider)| {
+        collide(
+            position,
+            size,
+            collider_position.0 + collider.offset,
+            collider.size,
+        )
+        .is_some()
+    })
+}
+
+/// Static colliders added, moved or resized since the last frame.
+type ChangedStaticCollider = (
+    Without<Movement>,
+    With<Position>,
+    WithCollider,
+    Or<(
+        Changed<Position>,
+        Changed<BoxCollider>,
+        Changed<CircleCollider>,
+    )>,
+);
+
+/// Copies the static colliders in their arrays when one of them changed, or
+/// when a collider started or stopped moving, and rebuilds the grid of the
+/// walkable floor around them.
+fn refresh_static_colliders_system(
+    mut refreshed_once: Local<bool>,
+    mut static_colliders: ResMut<StaticColliders>,
+    mut nav_grid: ResMut<NavGrid>,
+    colliders: Query<(Entity, &Position, Colliders), (Without<Movement>, WithCollider)>,
+    changed_colliders: Query<Entity, ChangedStaticCollider>,
+    started_moving: Query<Entity, (WithCollider, Added<Movement>)>,
+    removed: (
+        RemovedComponents<Position>,
+        RemovedComponents<BoxCollider>,
+        RemovedComponents<CircleCollider>,
+        RemovedComponents<Movement>,
+    ),
+) {
+    let outdated = changed_colliders.iter().next().is_some()
+        || started_moving.iter().next().is_some()
+        || removed.0.iter().next().is_some()
+        || removed.1.iter().next().is_some()
+        || removed.2.iter().next().is_some()
+        || removed.3.iter().next().is_some();
+    if *refreshed_once && !outdated {
+        return;
+    }
+    *refreshed_once = true;
+
+    static_colliders.0.clear();
+    for (entity, position, colliders) in colliders.iter() {
+        if let Some((offset, shape)) = collider_shape(colliders) {
+            static_colliders.0.push(entity, position.0 + offset, shape);
+        }
+    }
+    nav_grid.rebuild(&static_colliders.0);
+}
+
+/// Returns the center of the collider of the entity after its movement from
+/// the current center, and the colliders blocking it with the geometry of
+/// their first push. The movement is cut in short steps, and each step is
+/// pushed out of the colliders it runs into along the shortest way, so that
+/// the entity slides along the walls instead of stopping.
+// The movement of a frame is a few steps long
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn resolve_move(
+    static_colliders: &BoxArrays,
+    movers: &BoxArrays,
+    entity: Entity,
+    current: Vec3,
+    movement: Vec2,
+    shape: Shape,
+) -> (Vec3, Vec<(Entity, ContactGeometry)>) {
+    let steps = (movement.length() / MAX_STEP).ceil().max(1.0);
+    let step = (movement / steps).extend(0.0);
+    let mut next = current;
+    let mut blockers = Vec::new();
+
+    for _ in 0..steps as usize {
+        next += step;
+        for _ in 0..RESOLUTION_ITERATIONS {
+            let push = match static_colliders
+                .push_out(next, shape)
+                .or_else(|| movers.push_out_moving(entity, current, next, shape))
+            {
+                Some(push) => push,
+                None => break,
+            };
+            if blockers.iter().all(|&(blocker, _)| blocker != push.entity) {
+                blockers.push((push.entity, ContactGeometry::new(push, next.z)));
+            }
+            next += push.translation.extend(0.0);
+        }
+    }
+    (next, blockers)
+}
+
+/// Moves the position of moving entities depending on their movement,
+/// sliding along the colliders they run into.
 ///
-/// The collision is checked for both the X and Y axises, and in case of
-/// diagonal movement, one axis can still be moved.
-pub fn collision_system(
-    mut moving_colliders: Query<(&mut Position, &BoxCollider, &mut Movement)>,
-    other_colliders: Query<(&Position, &BoxCollider), Without<Movement>>,
+/// The moving entities are tested in parallel against the arrays of static
+/// colliders and against the other moving entities where they stand at the
+/// start of the frame. The movement is slowed down by the slowest zone the
+/// entity is in contact with. The collision events are sent when an entity
+/// starts or stops being blocked by another collider.
+fn collision_system(
+    task_pool: Res<ComputeTaskPool>,
+    static_colliders: Res<StaticColliders>,
+    mut collision_events: EventWriter<CollisionEvent>,
+    mut prev_blocked: Local<HashSet<Contact>>,
+    contacts: Query<&Contact>,
+    slow_zones: Query<&SlowZone>,
+    mut moving_colliders: Query<(Entity, &mut Position, Colliders, &mut Movement), WithCollider>,
 ) {
-    for (mut pos_a, col_a, mut mov_a) in moving_colliders.iter_mut() {
-        let will_not_collide = |next_pos_a: Vec3| {
-            other_colliders.iter().all(|(pos_b, col_b)| {
-                collide(
-                    next_pos_a + col_a.offset,
-                    col_a.size,
-                    pos_b.0 + col_b.offset,
-                    col_b.size,
-                )
-                .is_none()
-            })
-        };
+    let mut slowdowns: HashMap<Entity, f32> = HashMap::new();
+    for Contact(collider, area) in contacts.iter() {
+        if let Ok(zone) = slow_zones.get(*area) {
+            let multiplier = slowdowns.entry(*collider).or_insert(1.0);
+            *multiplier = multiplier.min(zone.speed_multiplier);
+        }
+    }
+    let slowdowns = &slowdowns;
+    let static_colliders = &static_colliders.0;
+    let movers = BoxArrays::collect(moving_colliders.iter_mut().filter_map(
+        |(entity, position, colliders, _)| {
+            let (offset, shape) = collider_shape(colliders)?;
+            Some((entity, position.0 + offset, shape))
+        },
+    ));
+    let movers = &movers;
+    let blocked = Mutex::new(HashMap::new());
+    let blocked_ref = &blocked;
+
+    moving_colliders.par_for_each_mut(
+        &task_pool,
+        MOVING_BATCH_SIZE,
+        |(entity, mut pos_a, colliders, mut mov_a)| {
+            let (offset, shape) = match collider_shape(colliders) {
+                Some(collider) => collider,
+                None => return,
+            };
+            let current = pos_a.0 + offset;
+            let movement = mov_a.0 * slowdowns.get(&entity).copied().unwrap_or(1.0);
+            let (next, blockers) = resolve_move(
+                static_colliders,
+                movers,
+                entity,
+                current,
+                movement.truncate(),
+                shape,
+            );
 
-        if will_not_collide(pos_a.0 + mov_a.0 * Vec3::X) {
-            pos_a.0.x += mov_a.0.x;
+            if next != current {
+                pos_a.0 = next - offset;
+            }
+            *mov_a = Movement::default();
+            if !blockers.is_empty() {
+                let mut blocked_contacts = blocked_ref.lock().unwrap();
+                for (blocker, geometry) in blockers {
+                    // Two colliders running into each other keep the first
+                    blocked_contacts
+                        .entry(Contact(entity, blocker))
+                        .or_insert(geometry);
+                }
+            }
+        },
+    );
+
+    let next_blocked: HashMap<Contact, ContactGeometry> = blocked.into_inner().unwrap();
+    for (&started_collision, &geometry) in &next_blocked {
+        if !prev_blocked.contains(&started_collision) {
+            debug!("Started collision: {:?} {:?}", started_collision, geometry);
+            collision_events.send(CollisionEvent::Started(started_collision, geometry));
         }
-        if will_not_collide(pos_a.0 + mov_a.0 * Vec3::Y) {
-            pos_a.0.y += mov_a.0.y;
+    }
+    for &stopped_collision in &*prev_blocked {
+        if !next_blocked.contains_key(&stopped_collision) {
+            debug!("Stopped collision: {:?}", stopped_collision);
+            collision_events.send(CollisionEvent::Stopped(stopped_collision));
         }
+    }
+    *prev_blocked = next_blocked.keys().copied().collect();
+}
 
-        *mov_a = Movement::default();
+/// Moving entities without collider.
+type FreeMover = (Without<BoxCollider>, Without<CircleCollider>);
+
+/// Moves the entities without collider, walking over the furniture and
+/// through the other entities.
+fn free_movement_system(mut movers: Query<(&mut Position, &mut Movement), FreeMover>) {
+    for (mut position, mut movement) in movers.iter_mut() {
+        if movement.0 != Vec3::ZERO {
+            position.0 += movement.0;
+            *movement = Movement::default();
+        }
     }
 }
 
+/// Moving colliders whose position or size changed since the last frame.
+type ChangedCollider = (
+    With<Movement>,
+    With<Position>,
+    WithCollider,
+    Or<(
+        Changed<Position>,
+        Changed<BoxCollider>,
+        Changed<CircleCollider>,
+    )>,
+);
+
+/// Trigger areas whose position or size changed since the last frame.
+type ChangedTriggerArea = (
+    With<Position>,
+    With<TriggerArea>,
+    Or<(Changed<Position>, Changed<TriggerArea>)>,
+);
+
 /// Compares positions of box colliders with trigger areas and emit trigger
-/// events.
-pub fn trigger_area_system(
+/// events. Only the pairs with an entity that moved, resized or lost its
+/// collider since the last frame are tested again, in parallel, the other
+/// contacts are kept as they are. An entity having both a collider and a
+/// trigger area never contacts itself.
+#[allow(clippy::too_many_arguments)]
+fn trigger_area_system(
     mut commands: Commands,
+    task_pool: Res<ComputeTaskPool>,
+    mut pool: ResMut<Pool<Contact>>,
     mut contact_events: EventWriter<ContactEvent>,
-    moving_colliders: Query<(Entity, &Position, &BoxCollider), With<Movement>>,
+    moving_colliders: Query<(Entity, &Position, Colliders), (With<Movement>, WithCollider)>,
     trigger_areas: Query<(Entity, &Position, &TriggerArea)>,
+    changed_colliders: Query<Entity, ChangedCollider>,
+    changed_areas: Query<Entity, ChangedTriggerArea>,
+    removed: (
+        RemovedComponents<Position>,
+        RemovedComponents<BoxCollider>,
+        RemovedComponents<CircleCollider>,
+        RemovedComponents<TriggerArea>,
+        RemovedComponents<Movement>,
+    ),
     contacts: Query<(&Contact, Entity)>,
 ) {
-    let mut next_contacts: HashSet<Contact> = HashSet::new();
-
-    for (entity_a, pos_a, col_a) in moving_colliders.iter() {
-        for (entity_b, pos_b, area_b) in trigger_areas.iter() {
-            if collide(pos_a.0, col_a.size, pos_b.0, area_b.size).is_some() {
-                next_contacts.insert(Contact(entity_a, entity_b));
-            }
-        }
-    }
+    // The contacts are tested at the position, without the offset of the
+    // colliders
+    let moving_shape = |(entity, position, colliders): (Entity, &Position, Colliders)| {
+        let (_, shape) = collider_shape(colliders)?;
+        Some((entity, position.0, shape))
+    };
+    let moved_colliders: Vec<(Entity, Vec3, Shape)> = changed_colliders
+        .iter()
+        .filter_map(|entity| moving_colliders.get(entity).ok())
+        .filter_map(moving_shape)
+        .collect();
+    let moved_areas: Vec<(Entity, Vec3, Shape)> = changed_areas
+        .iter()
+        .filter_map(|entity| trigger_areas.get(entity).ok())
+        .map(|(entity, position, area)| (entity, position.0, Shape::Box(area.size)))
+        .collect();
+    let outdated: HashSet<Entity> = removed
+        .0
+        .iter()
+        .chain(removed.1.iter())
+        .chain(removed.2.iter())
+        .chain(removed.3.iter())
+        .chain(removed.4.iter())
+        .chain(changed_colliders.iter())
+        .chain(changed_areas.iter())
+        .collect();
 
     let prev_entities: HashMap<_, _> = contacts.iter().map(|(&c, e)| (c, e)).collect();
     let prev_contacts: HashSet<_> = prev_entities.keys().copied().collect();
 
+    let mut next_contacts: HashSet<Contact> = prev_contacts
+        .iter()
+        .filter(|Contact(a, b)| !outdated.contains(a) && !outdated.contains(b))
+        .copied()
+        .collect();
+
+    if !moved_colliders.is_empty() {
+        let areas = BoxArrays::collect(
+            trigger_areas
+                .iter()
+                .map(|(entity, position, area)| (entity, position.0, Shape::Box(area.size))),
+        );
+        let started = moved_colliders.par_chunk_map(&task_pool, MOVING_BATCH_SIZE, |chunk| {
+            chunk
+                .iter()
+                .flat_map(|&(collider, position, shape)| {
+                    areas
+                        .overlapping(position, shape)
+                        .filter(move |&area| area != collider)
+                        .map(move |area| Contact(collider, area))
+                })
+                .collect::<Vec<_>>()
+        });
+        next_contacts.extend(started.into_iter().flatten());
+    }
+    if !moved_areas.is_empty() {
+        let colliders = BoxArrays::collect(moving_colliders.iter().filter_map(moving_shape));
+        let started = moved_areas.par_chunk_map(&task_pool, MOVING_BATCH_SIZE, |chunk| {
+            chunk
+                .iter()
+                .flat_map(|&(area, position, shape)| {
+                    colliders
+                        .overlapping(position, shape)
+                        .filter(move |&collider| collider != area)
+                        .map(move |collider| Contact(collider, area))
+                })
+                .collect::<Vec<_>>()
+        });
+        next_contacts.extend(started.into_iter().flatten());
+    }
+
+    // The collider is pushed out of the area along the shortest way
+    let contact_geometry = |Contact(collider, area): Contact| {
+        let (_, position, colliders) = moving_colliders.get(collider).ok()?;
+        let (_, shape) = collider_shape(colliders)?;
+        let (_, area_position, trigger_area) = trigger_areas.get(area).ok()?;
+        let area_shape = Shape::Box(trigger_area.size);
+        let (center, area_center) = (position.0.truncate(), area_position.0.truncate());
+        let translation = area_shape.penetration(area_center, shape, center)?;
+        let push = Push {
+            entity: area,
+            translation,
+            point: area_shape.surface_point(area_center, center, translation),
+        };
+        Some(ContactGeometry::new(push, position.0.z))
+    };
+
     for &started_contact in next_contacts.difference(&prev_contacts) {
-        debug!("Started contact: {:?}", started_contact);
+        let geometry = contact_geometry(started_contact).unwrap_or_default();
+        debug!("Started contact: {:?} {:?}", started_contact, geometry);
 
-        contact_events.send(ContactEvent::Started(started_contact));
-        commands.spawn().insert(started_contact);
+        contact_events.send(ContactEvent::Started(started_contact, geometry));
+        let (entity, _) = pool.acquire(&mut commands);
+        commands.entity(entity).insert(started_contact);
     }
 
     for stopped_contact in prev_contacts.difference(&next_contacts) {
@@ -186,7 +605,248 @@ pub fn trigger_area_system(
 
         contact_events.send(ContactEvent::Stopped(*stopped_contact));
         if let Some(&entity) = prev_entities.get(stopped_contact) {
-            commands.entity(entity).despawn();
+            pool.release(&mut commands, entity);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::{app::Events, tasks::TaskPool};
+
+    use super::*;
+
+    /// Returns the contacts currently in the world.
+    fn contacts(world: &mut World) -> Vec<Contact> {
+        world.query::<&Contact>().iter(world).copied().collect()
+    }
+
+    #[test]
+    fn contacts_are_updated_with_the_moves() {
+        let mut world = World::default();
+        world.insert_resource(Events::<ContactEvent>::default());
+        world.insert_resource(Pool::<Contact>::default());
+        world.insert_resource(ComputeTaskPool(TaskPool::new()));
+        let mut stage = SystemStage::single(trigger_area_system.system());
+
+        let didi = world
+            .spawn()
+            .insert_bundle((Position(Vec3::ZERO), BoxCollider::new(10.0, 10.0)))
+            .insert(Movement::default())
+            .id();
+        let item = world
+            .spawn()
+            .insert_bundle((Position(Vec3::ZERO), TriggerArea::new(10.0, 10.0)))
+            .id();
+
+        stage.run(&mut world);
+        assert_eq!(contacts(&mut world), vec![Contact(didi, item)]);
+
+        // Nothing moves, the contact stays
+        world.clear_trackers();
+        stage.run(&mut world);
+        assert_eq!(contacts(&mut world), vec![Contact(didi, item)]);
+
+        world.get_mut::<Position>(didi).unwrap().0 = Vec3::new(100.0, 0.0, 0.0);
+        world.clear_trackers();
+        stage.run(&mut world);
+        assert!(contacts(&mut world).is_empty());
+
+        world.get_mut::<Position>(item).unwrap().0 = Vec3::new(100.0, 0.0, 0.0);
+        world.clear_trackers();
+        stage.run(&mut world);
+        assert_eq!(contacts(&mut world), vec![Contact(didi, item)]);
+
+        world.entity_mut(item).remove::<TriggerArea>();
+        stage.run(&mut world);
+        assert!(contacts(&mut world).is_empty());
+    }
+
+    #[test]
+    fn static_colliders_block_the_moves() {
+        let mut world = World::default();
+        world.insert_resource(StaticColliders::default());
+        world.insert_resource(Events::<CollisionEvent>::default());
+        world.insert_resource(NavGrid::default());
+        world.insert_resource(ComputeTaskPool(TaskPool::new()));
+        let mut refresh = SystemStage::single(refresh_static_colliders_system.system());
+        let mut collide = SystemStage::single(collision_system.system());
+
+        let wall = world
+            .spawn()
+            .insert_bundle((
+                Position(Vec3::new(20.0, 0.0, 0.0)),
+                BoxCollider::new(10.0, 100.0),
+            ))
+            .id();
+        let didi = world
+            .spawn()
+            .insert_bundle((Position(Vec3::ZERO), BoxCollider::new(10.0, 10.0)))
+            .insert(Movement(Vec3::new(12.0, 5.0, 0.0)))
+            .id();
+
+        refresh.run(&mut world);
+        collide.run(&mut world);
+
+        // Didi slides along the wall
+        assert_eq!(
+            world.get::<Position>(didi).unwrap().0,
+            Vec3::new(10.0, 5.0, 0.0)
+        );
+        assert_eq!(world.get::<Movement>(didi).unwrap().0, Vec3::ZERO);
+
+        // Didi is blocked until moving away from the wall
+        world.get_mut::<Movement>(didi).unwrap().0 = Vec3::new(-5.0, 0.0, 0.0);
+        collide.run(&mut world);
+        let events = world.get_resource::<Events<CollisionEvent>>().unwrap();
+        let mut reader = events.get_reader();
+        assert_eq!(
+            reader.iter(events).cloned().collect::<Vec<_>>(),
+            vec![
+                CollisionEvent::Started(
+                    Contact(didi, wall),
+                    ContactGeometry {
+                        normal: Vec2::new(-1.0, 0.0),
+                        depth: 2.0,
+                        point: Vec3::new(15.0, 5.0, 0.0),
+                    }
+                ),
+                CollisionEvent::Stopped(Contact(didi, wall)),
+            ]
+        );
+    }
+
+    #[test]
+    fn circles_move_past_the_corners() {
+        let mut world = World::default();
+        world.insert_resource(StaticColliders::default());
+        world.insert_resource(Events::<CollisionEvent>::default());
+        world.insert_resource(NavGrid::default());
+        world.insert_resource(ComputeTaskPool(TaskPool::new()));
+        let mut refresh = SystemStage::single(refresh_static_colliders_system.system());
+        let mut collide = SystemStage::single(collision_system.system());
+
+        world
+            .spawn()
+            .insert_bundle((Position(Vec3::ZERO), BoxCollider::new(20.0, 20.0)));
+        let start = Vec3::new(-15.5, -14.0, 0.0);
+        let round = world
+            .spawn()
+            .insert_bundle((Position(start), CircleCollider::new(5.0)))
+            .insert(Movement(Vec3::new(1.0, 0.0, 0.0)))
+            .id();
+        let square = world
+            .spawn()
+            .insert_bundle((Position(start), BoxCollider::new(10.0, 10.0)))
+            .insert(Movement(Vec3::new(1.0, 0.0, 0.0)))
+            .id();
+
+        refresh.run(&mut world);
+        collide.run(&mut world);
+
+        // The circle passes by the corner where the box stops against it
+        assert_eq!(
+            world.get::<Position>(round).unwrap().0,
+            Vec3::new(-14.5, -14.0, 0.0)
+        );
+        assert_eq!(
+            world.get::<Position>(square).unwrap().0,
+            Vec3::new(-15.0, -14.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn fast_moves_do_not_go_through_thin_walls() {
+        let wall = BoxArrays::collect(
+            vec![(
+                Entity::new(0),
+                Vec3::new(20.0, 0.0, 0.0),
+                Shape::Box(Vec2::new(4.0, 100.0)),
+            )]
+            .into_iter(),
+        );
+        let shape = Shape::Circle(5.0);
+
+        let (next, blockers) = resolve_move(
+            &wall,
+            &BoxArrays::default(),
+            Entity::new(1),
+            Vec3::ZERO,
+            Vec2::new(60.0, 0.0),
+            shape,
+        );
+        assert!((next.x - 13.0).abs() < 1e-4);
+        assert_eq!(blockers.len(), 1);
+        let (blocker, geometry) = blockers[0];
+        assert_eq!(blocker, Entity::new(0));
+        assert_eq!(geometry.normal, Vec2::new(-1.0, 0.0));
+        assert_eq!(geometry.point, Vec3::new(18.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn slow_zones_slow_down_the_moves() {
+        let mut world = World::default();
+        world.insert_resource(StaticColliders::default());
+        world.insert_resource(Events::<CollisionEvent>::default());
+        world.insert_resource(ComputeTaskPool(TaskPool::new()));
+        let mut collide = SystemStage::single(collision_system.system());
+
+        let didi = world
+            .spawn()
+            .insert_bundle((Position(Vec3::ZERO), BoxCollider::new(10.0, 10.0)))
+            .insert(Movement(Vec3::new(12.0, 0.0, 0.0)))
+            .id();
+        let mess = world
+            .spawn()
+            .insert_bundle((Position(Vec3::ZERO), TriggerArea::new(50.0, 50.0)))
+            .insert(SlowZone {
+                speed_multiplier: 0.5,
+            })
+            .id();
+        world.spawn().insert(Contact(didi, mess));
+
+        collide.run(&mut world);
+        assert_eq!(
+            world.get::<Position>(didi).unwrap().0,
+            Vec3::new(6.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn walking_areas_are_contacted() {
+        let mut world = World::default();
+        world.insert_resource(Events::<ContactEvent>::default());
+        world.insert_resource(Pool::<Contact>::default());
+        world.insert_resource(ComputeTaskPool(TaskPool::new()));
+        let mut walk = SystemStage::single(free_movement_system.system());
+        let mut trigger = SystemStage::single(trigger_area_system.system());
+
+        let didi = world
+            .spawn()
+            .insert_bundle((Position(Vec3::ZERO), BoxCollider::new(10.0, 10.0)))
+            .insert(TriggerArea::new(10.0, 10.0))
+            .insert(Movement::default())
+            .id();
+        let baobei = world
+            .spawn()
+            .insert_bundle((
+                Position(Vec3::new(100.0, 0.0, 0.0)),
+                TriggerArea::new(50.0, 50.0),
+            ))
+            .insert(Movement(Vec3::new(-80.0, 0.0, 0.0)))
+            .id();
+
+        // Didi does not contact its own area
+        trigger.run(&mut world);
+        assert!(contacts(&mut world).is_empty());
+
+        world.clear_trackers();
+        walk.run(&mut world);
+        trigger.run(&mut world);
+        assert_eq!(
+            world.get::<Position>(baobei).unwrap().0,
+            Vec3::new(20.0, 0.0, 0.0)
+        );
+        assert_eq!(contacts(&mut world), vec![Contact(didi, baobei)]);
+    }
+}
@@ -0,0 +1,238 @@
+//! Grid of the walkable floor, for the characters finding their way around
+//! the furniture.
+//!
+//! The room is cut in square cells, a cell being blocked when a character
+//! standing at its center would overlap a static collider. The paths are
+//! found with A* over the eight neighbours of each cell. Walking through a
+//! blocked cell is not forbidden but costs a lot, so that a character
+//! sitting on the furniture can still leave it, and a diagonal step next to
+//! a blocked cell costs as much so that the corners of the furniture are not
+//! cut.
+
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use bevy::{math::const_vec2, prelude::*};
+
+use crate::constants::{WINDOW_HEIGHT, WINDOW_WIDTH};
+
+use super::layout::{BoxArrays, Shape};
+
+/// Width and height of a cell.
+const CELL_SIZE: f32 = 20.0;
+/// Size of the box of a walking character tested against the furniture.
+const CLEARANCE: Vec2 = const_vec2!([40.0, 20.0]);
+/// Cost of a straight step to a neighbour.
+const STRAIGHT_COST: u32 = 10;
+/// Cost of a diagonal step to a neighbour, about `STRAIGHT_COST * √2`.
+const DIAGONAL_COST: u32 = 14;
+/// Extra cost of a step through a blocked cell.
+const BLOCKED_COST: u32 = 1000;
+/// Steps from a cell to its eight neighbours.
+const NEIGHBOUR_STEPS: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// A cell of the grid, by its column and row.
+type Cell = (usize, usize);
+
+/// Walkable cells of the room, rebuilt when the static colliders change.
+#[derive(Debug, Clone)]
+pub struct NavGrid {
+    /// Number of cells along the width of the room
+    columns: usize,
+    /// Number of cells along the height of the room
+    rows: usize,
+    /// Whether each cell is blocked, row by row from the bottom
+    blocked: Vec<bool>,
+}
+
+impl Default for NavGrid {
+    // The room is a few dozens of cells wide
+    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+    fn default() -> Self {
+        let columns = (WINDOW_WIDTH / CELL_SIZE).ceil() as usize;
+        let rows = (WINDOW_HEIGHT / CELL_SIZE).ceil() as usize;
+
+        Self {
+            columns,
+            rows,
+            blocked: vec![false; columns * rows],
+        }
+    }
+}
+
+impl NavGrid {
+    /// Blocks the cells where a character would overlap one of the boxes.
+    pub fn rebuild(&mut self, boxes: &BoxArrays) {
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                let center = self.center((column, row)).extend(0.0);
+                self.blocked[row * self.columns + column] =
+                    boxes.overlaps(center, Shape::Box(CLEARANCE));
+            }
+        }
+    }
+
+    /// Returns the cell containing the position, the closest one outside of
+    /// the room.
+    // The position is clamped to the cells of the room
+    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+    fn cell_of(&self, position: Vec3) -> Cell {
+        let column = (position.x / CELL_SIZE).max(0.0) as usize;
+        let row = (position.y / CELL_SIZE).max(0.0) as usize;
+        (column.min(self.columns - 1), row.min(self.rows - 1))
+    }
+
+    /// Returns the center of the cell in the room.
+    // The grid has a few thousands of cells
+    #[allow(clippy::cast_precision_loss)]
+    fn center(&self, (column, row): Cell) -> Vec2 {
+        Vec2::new(column as f32 + 0.5, row as f32 + 0.5) * CELL_SIZE
+    }
+
+    /// Returns true if the cell is blocked by the furniture.
+    fn is_blocked(&self, (column, row): Cell) -> bool {
+        self.blocked[row * self.columns + column]
+    }
+
+    /// Returns the neighbours of the cell with the cost of the step to each.
+    fn neighbours(&self, (column, row): Cell) -> impl Iterator<Item = (Cell, u32)> + '_ {
+        NEIGHBOUR_STEPS.iter().filter_map(move |&(dx, dy)| {
+            let next = (
+                offset(column, dx).filter(|&column| column < self.columns)?,
+                offset(row, dy).filter(|&row| row < self.rows)?,
+            );
+            let (mut cost, crossed) = if dx != 0 && dy != 0 {
+                let corners = [(next.0, row), (column, next.1)];
+                (
+                    DIAGONAL_COST,
+                    corners.iter().any(|&cell| self.is_blocked(cell)),
+                )
+            } else {
+                (STRAIGHT_COST, false)
+            };
+            if crossed || self.is_blocked(next) {
+                cost += BLOCKED_COST;
+            }
+            Some((next, cost))
+        })
+    }
+
+    /// Returns the waypoints from the start to the goal around the furniture,
+    /// ending at the goal. Only the turns of the path are kept, the
+    /// waypoints being at the height of the goal.
+    #[must_use]
+    pub fn find_path(&self, start: Vec3, goal: Vec3) -> Vec<Vec3> {
+        let index = |(column, row): Cell| row * self.columns + column;
+        let (start_cell, goal_cell) = (self.cell_of(start), self.cell_of(goal));
+        let heuristic = |(column, row): Cell| {
+            let dx = column.max(goal_cell.0) - column.min(goal_cell.0);
+            let dy = row.max(goal_cell.1) - row.min(goal_cell.1);
+            // Few cells, the distances fit in the costs
+            #[allow(clippy::cast_possible_truncation)]
+            let (long, short) = (dx.max(dy) as u32, dx.min(dy) as u32);
+            STRAIGHT_COST * long + (DIAGONAL_COST - STRAIGHT_COST) * short
+        };
+
+        let mut costs = vec![u32::MAX; self.blocked.len()];
+        let mut previous: Vec<Option<Cell>> = vec![None; self.blocked.len()];
+        let mut open = BinaryHeap::new();
+        costs[index(start_cell)] = 0;
+        open.push(Reverse((heuristic(start_cell), 0, start_cell)));
+
+        while let Some(Reverse((_, cost, cell))) = open.pop() {
+            if cell == goal_cell {
+                break;
+            }
+            if cost > costs[index(cell)] {
+                continue; // Already reached more cheaply
+            }
+            for (next, step_cost) in self.neighbours(cell) {
+                let next_cost = cost + step_cost;
+                if next_cost < costs[index(next)] {
+                    costs[index(next)] = next_cost;
+                    previous[index(next)] = Some(cell);
+                    open.push(Reverse((next_cost + heuristic(next), next_cost, next)));
+                }
+            }
+        }
+
+        let mut cell = goal_cell;
+        let mut cells = vec![cell];
+        while let Some(previous_cell) = previous[index(cell)] {
+            cells.push(previous_cell);
+            cell = previous_cell;
+        }
+        cells.reverse();
+
+        let mut waypoints: Vec<Vec3> = cells
+            .windows(3)
+            .filter(|cells| direction(cells[0], cells[1]) != direction(cells[1], cells[2]))
+            .map(|cells| self.center(cells[1]).extend(goal.z))
+            .collect();
+        waypoints.push(goal);
+        waypoints
+    }
+}
+
+/// Returns the index moved by the step, if not negative.
+// The steps are of one cell
+#[allow(clippy::cast_sign_loss)]
+const fn offset(index: usize, step: isize) -> Option<usize> {
+    if step < 0 {
+        index.checked_sub(-step as usize)
+    } else {
+        Some(index + step as usize)
+    }
+}
+
+/// Returns the step between two neighbour cells.
+// The cells are neighbours
+#[allow(clippy::cast_possible_wrap)]
+const fn direction(from: Cell, to: Cell) -> (isize, isize) {
+    (
+        to.0 as isize - from.0 as isize,
+        to.1 as isize - from.1 as isize,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_go_around_the_furniture() {
+        let mut grid = NavGrid::default();
+        // A wall across the middle of the room, open at the top
+        let wall = BoxArrays::collect(
+            vec![(
+                Entity::new(0),
+                Vec3::new(640.0, 250.0, 0.0),
+                Shape::Box(Vec2::new(40.0, 500.0)),
+            )]
+            .into_iter(),
+        );
+        grid.rebuild(&wall);
+
+        let start = Vec3::new(400.0, 100.0, 0.0);
+        let goal = Vec3::new(900.0, 100.0, 0.0);
+        let path = grid.find_path(start, goal);
+
+        assert_eq!(path.last(), Some(&goal));
+        assert!(path.len() > 2);
+        assert!(path.iter().any(|waypoint| waypoint.y > 500.0));
+        assert!(path
+            .iter()
+            .all(|waypoint| !grid.is_blocked(grid.cell_of(*waypoint))));
+
+        // Without furniture, the path goes straight to the goal
+        assert_eq!(NavGrid::default().find_path(start, goal), vec![goal]);
+    }
+}
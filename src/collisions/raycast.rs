@@ -0,0 +1,120 @@
+//! Rays cast through the room, stopped by the box colliders: the line of
+//! sight between two characters, the path of a thrown item or the straight
+//! walk to a clicked point.
+//!
+//! The rays go over the floor, along the `x` and `y` axes, the height of the
+//! origin being kept at the hit point.
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use super::{BoxCollider, Position};
+
+/// First box collider hit by a ray.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    /// Entity owning the collider
+    pub entity: Entity,
+    /// Point of the edge of the collider where the ray enters it
+    pub point: Vec3,
+    /// Distance from the origin of the ray to the point
+    pub distance: f32,
+}
+
+/// System parameter casting rays against the box colliders.
+#[derive(SystemParam)]
+pub struct Raycast<'a> {
+    /// The box colliders stopping the rays
+    colliders: Query<'a, (Entity, &'static Position, &'static BoxCollider)>,
+}
+
+impl Raycast<'_> {
+    /// Returns the first box collider hit by the ray from the origin in the
+    /// direction, within the distance. The colliders containing the origin,
+    /// like the one of the entity casting the ray, are not hit.
+    #[must_use]
+    pub fn cast(&self, origin: Vec3, direction: Vec2, max_distance: f32) -> Option<RayHit> {
+        first_hit(
+            self.colliders.iter().map(|(entity, position, collider)| {
+                (entity, position.0 + collider.offset, collider)
+            }),
+            origin,
+            direction,
+            max_distance,
+        )
+    }
+
+    /// Returns the first box collider between the origin and the target, if
+    /// any, for the line of sight.
+    #[must_use]
+    pub fn between(&self, origin: Vec3, target: Vec3) -> Option<RayHit> {
+        let direction = (target - origin).truncate();
+        self.cast(origin, direction, direction.length())
+    }
+}
+
+/// Returns the closest of the colliders at their center hit by the ray.
+fn first_hit<'a>(
+    colliders: impl Iterator<Item = (Entity, Vec3, &'a BoxCollider)>,
+    origin: Vec3,
+    direction: Vec2,
+    max_distance: f32,
+) -> Option<RayHit> {
+    if direction == Vec2::ZERO {
+        return None;
+    }
+    let direction = direction.normalize();
+
+    colliders
+        .filter_map(|(entity, center, collider)| {
+            let distance =
+                collider
+                    .shape()
+                    .ray_distance(center.truncate(), origin.truncate(), direction)?;
+            (distance <= max_distance).then(|| RayHit {
+                entity,
+                point: origin + (direction * distance).extend(0.0),
+                distance,
+            })
+        })
+        .min_by(|a, b| {
+            a.distance
+                .partial_cmp(&b.distance)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rays_stop_at_the_closest_collider() {
+        let couch = BoxCollider::new(100.0, 40.0);
+        let table = BoxCollider::new(20.0, 20.0);
+        let colliders = || {
+            vec![
+                (Entity::new(0), Vec3::new(200.0, 0.0, 0.0), &couch),
+                (Entity::new(1), Vec3::new(100.0, 5.0, 0.0), &table),
+            ]
+            .into_iter()
+        };
+        let origin = Vec3::new(0.0, 0.0, 3.0);
+
+        let hit = first_hit(colliders(), origin, Vec2::new(2.0, 0.0), 500.0);
+        assert_eq!(
+            hit,
+            Some(RayHit {
+                entity: Entity::new(1),
+                point: Vec3::new(90.0, 0.0, 3.0),
+                distance: 90.0,
+            })
+        );
+        // Too short, going the other way or starting inside
+        assert_eq!(first_hit(colliders(), origin, Vec2::X, 50.0), None);
+        assert_eq!(first_hit(colliders(), origin, -Vec2::X, 500.0), None);
+        let inside = Vec3::new(100.0, 0.0, 0.0);
+        let hit = first_hit(colliders(), inside, Vec2::X, 500.0).unwrap();
+        assert_eq!(hit.entity, Entity::new(0));
+        assert!((hit.distance - 50.0).abs() < 1e-4);
+    }
+}
@@ -1,5 +1,7 @@
 //! Constants of the application
 
+use std::{fmt, str::FromStr};
+
 /// Width of the window
 pub const WINDOW_WIDTH: f32 = 1280.0;
 /// Height of the window
@@ -8,8 +10,224 @@ pub const WINDOW_HEIGHT: f32 = 720.0;
 /// Movement speed of the player
 pub const SPEED: f32 = 750.0;
 
-/// Happiness decrease per second
-pub const HAPPINESS_DECREASE: f32 = 0.05; // 5%
+/// Points earned when Baobei receives the asked item
+pub const DELIVERY_POINTS: u32 = 10;
+/// Points lost when Baobei receives another item
+pub const WRONG_DELIVERY_PENALTY: u32 = 5;
+/// Points earned for each step of a chore
+pub const CHORE_POINTS: u32 = 3;
+/// Points lost when a carried item spoils
+pub const SPOILED_ITEM_PENALTY: u32 = 3;
+/// Points lost when an item is thrown in the trash can
+pub const DISCARDED_ITEM_PENALTY: u32 = 1;
+/// Points lost when a plant dies of thirst
+pub const DEAD_PLANT_PENALTY: u32 = 8;
+/// Points earned for each note hit in a bonus round
+pub const BONUS_NOTE_POINTS: u32 = 2;
+/// Bonus points earned when a forgotten item is found back right away
+pub const RETRIEVAL_POINTS: u32 = 20;
+/// Bonus points earned when Baobei stays happy during the in-laws visit
+pub const IN_LAWS_POINTS: u32 = 50;
+/// Seconds before a carried ice cream melts
+pub const ICE_CREAM_MELT_DURATION: f32 = 20.0;
+
+/// Seconds to deliver the next item to keep the combo going
+pub const COMBO_WINDOW: f32 = 6.0;
+/// Increase of the combo multiplier per consecutive delivery
+pub const COMBO_MULTIPLIER_STEP: f32 = 0.25;
+/// Highest combo multiplier
+pub const MAX_COMBO_MULTIPLIER: f32 = 2.0;
+
+/// Deliveries needed to reach the next level
+pub const DELIVERIES_PER_LEVEL: u32 = 5;
+/// Increase of the happiness decay each new level
+pub const LEVEL_DECAY_INCREASE: f32 = 0.15; // 15%
+/// Seconds of patience lost each new level
+pub const LEVEL_PATIENCE_DECREASE: f32 = 2.0;
+/// Happiness lost when Baobei waits too long for a request
+pub const EXPIRED_REQUEST_PENALTY: f32 = 0.25; // 25%
+/// Fraction of the patience left under which a delivery is perfect
+pub const PERFECT_DELIVERY_PATIENCE: f32 = 0.75; // 75%
+/// Duration in seconds of the freeze on key moments
+pub const HIT_STOP_DURATION: f32 = 0.08;
+/// Maximum number of items asked at the same time by Baobei
+pub const MAX_SIMULTANEOUS_REQUESTS: usize = 3;
+
+/// Weight of the last request in the rating of the recent performance
+pub const ADAPTIVE_SMOOTHING: f32 = 0.2; // 20%
+/// Change of the patience between the worst and the best rating
+pub const ADAPTIVE_PATIENCE_RANGE: f32 = 0.6; // 60%
+/// Rating above which Baobei asks for one more item at the same time
+pub const ADAPTIVE_PRESSURE_RATING: f32 = 0.85; // 85%
+/// Rating under which Baobei asks for one less item at the same time
+pub const ADAPTIVE_SLACK_RATING: f32 = 0.3; // 30%
+
+/// Duration in seconds of a phase where Baobei asks for items
+pub const REQUESTS_PHASE_DURATION: f32 = 90.0;
+/// Duration in seconds of a breather between two phases
+pub const BREATHER_DURATION: f32 = 15.0;
+/// Increase of the happiness decay each new phase
+pub const PHASE_DECAY_INCREASE: f32 = 0.2; // 20%
+
+/// Energy of Didi lost per second
+pub const ENERGY_DRAIN: f32 = 0.01; // 1%
+/// Energy of Didi restored by drinking a coffee
+pub const COFFEE_ENERGY: f32 = 0.5; // 50%
+/// Energy of Didi restored each time Didi sits down on the couch
+pub const SIT_ENERGY: f32 = 0.02; // 2%
+
+/// Crying meter of the baby filled per second
+pub const BABY_CRYING_RATE: f32 = 0.02; // 2%
+/// Crying meter of the baby emptied per second of rocking
+pub const BABY_ROCKING_RATE: f32 = 0.25; // 25%
+/// Crying meter above which the baby cries
+pub const BABY_CRYING_THRESHOLD: f32 = 0.7; // 70%
+/// Decay of the needs of Baobei while the baby cries
+pub const BABY_CRYING_DECAY_MULTIPLIER: f32 = 1.5; // 150%
+
+/// Multiplier of the speed of Didi while sprinting
+pub const SPRINT_SPEED_MULTIPLIER: f32 = 1.6;
+/// Stamina of Didi lost per second of sprint
+pub const STAMINA_DRAIN: f32 = 0.25; // 25%
+/// Stamina of Didi restored per second when not sprinting
+pub const STAMINA_REGEN: f32 = 0.1; // 10%
+
+/// Seconds between two piles of dirty clothes
+pub const CLOTHES_PILE_INTERVAL: f32 = 20.0;
+/// Maximum number of piles of dirty clothes on the floor
+pub const MAX_CLOTHES_PILES: usize = 5;
+/// Happiness decrease per second and per pile of dirty clothes on the floor
+pub const CLOTHES_PILE_PENALTY: f32 = 0.005; // 0.5%
+/// Duration in seconds of the washing machine cycle
+pub const WASHING_DURATION: f32 = 15.0;
+
+/// Seconds between two messes on the floor
+pub const MESS_INTERVAL: f32 = 25.0;
+/// Maximum number of messes on the floor
+pub const MAX_MESSES: usize = 3;
+/// Speed of Didi walking through a mess
+pub const MESS_SPEED_MULTIPLIER: f32 = 0.5; // 50%
+
+/// Water of a plant drying out per second
+pub const PLANT_DRYING_RATE: f32 = 0.008; // 0.8%
+
+/// Average seconds between two interruptions, like the doorbell ringing
+pub const INTERRUPTION_INTERVAL: f32 = 40.0;
+/// Seconds Didi has to answer an interruption
+pub const INTERRUPTION_DURATION: f32 = 8.0;
+/// Happiness lost when an interruption is not answered in time
+pub const INTERRUPTION_PENALTY: f32 = 0.15; // 15%
+
+/// Average seconds between two visitors knocking at the door
+pub const VISITOR_INTERVAL: f32 = 70.0;
+/// Seconds a visitor waits at the door for Didi to greet them
+pub const VISITOR_PATIENCE: f32 = 10.0;
+/// Seconds a greeted visitor stays on the couch
+pub const VISIT_DURATION: f32 = 30.0;
+/// Happiness lost when a visitor leaves without being greeted
+pub const VISITOR_SNUB_PENALTY: f32 = 0.1; // 10%
+/// Items the Baobeis ask for at the same time in addition while a visitor
+/// sits on the couch
+pub const VISITOR_EXTRA_REQUESTS: usize = 1;
+
+/// Seconds between ordering an item with the phone and its delivery
+pub const ORDER_DELAY: f32 = 20.0;
+/// Most items ordered with the phone waiting for their delivery
+pub const MAX_PENDING_ORDERS: usize = 2;
+
+/// Coins earned when Baobei receives the asked item
+pub const COINS_PER_DELIVERY: u32 = 5;
+/// Speed of Didi wearing the faster shoes of the shop
+pub const SHOES_SPEED_MULTIPLIER: f32 = 1.15; // 115%
+/// Decay of the needs with the slower decay of the shop
+pub const SLOW_DECAY_MULTIPLIER: f32 = 0.8; // 80%
+
+/// Seconds between two requests for an item Didi dropped earlier
+pub const MEMORY_REQUEST_INTERVAL: f32 = 50.0;
+/// Seconds to find a forgotten item back before the bonus is lost
+pub const MEMORY_RETRIEVAL_WINDOW: f32 = 30.0;
+
+/// Seconds between two power-ups appearing on the floor
+pub const POWER_UP_INTERVAL: f32 = 30.0;
+/// Seconds a power-up stays on the floor before vanishing
+pub const POWER_UP_LIFETIME: f32 = 12.0;
+
+/// Seconds of game between two bonus rounds
+pub const BONUS_ROUND_INTERVAL: f32 = 150.0;
+/// Seconds between two beats of a bonus round
+pub const BONUS_ROUND_BEAT: f32 = 0.5;
+/// Number of notes to play in a bonus round
+pub const BONUS_ROUND_NOTES: usize = 16;
+
+/// Number of items in stock in a producer
+pub const PRODUCER_STOCK: u32 = 3;
+/// Seconds Didi waits at the kettle to craft an item
+pub const CRAFTING_DURATION: f32 = 2.0;
+/// Seconds after a delivery before Baobei leaves the dirty dish
+pub const DIRTY_DISH_DELAY: f32 = 4.0;
+
+/// Number of items Didi carries, one in hand and the others in the backpack
+pub const INVENTORY_SLOTS: usize = 2;
+/// Number of items Didi carries with the bigger backpack of the shop
+pub const MAX_INVENTORY_SLOTS: usize = 3;
+
+/// Number of foods carried on a tray
+pub const TRAY_CAPACITY: usize = 3;
+/// Speed of Didi carrying a tray
+pub const TRAY_SPEED_MULTIPLIER: f32 = 0.85;
+/// Chance to spill a food of the tray when bumping into something
+pub const TRAY_SPILL_CHANCE: f64 = 0.5;
+
+/// Radius of the light around Didi during the night with the hard mode mutator
+pub const LIGHT_RADIUS: f32 = 170.0;
+/// Radius of the round collider of Didi and of the helper of the co-op
+pub const DIDI_RADIUS: f32 = 28.0;
+
+/// The in-laws visit every few phases
+pub const IN_LAWS_INTERVAL: u32 = 3;
+/// Seconds an in-law waits for an item before complaining
+pub const IN_LAW_PATIENCE: f32 = 8.0;
+/// Happiness decrease of Baobei when an in-law complains
+pub const IN_LAW_COMPLAINT: f32 = 0.1; // 10%
+
+/// Minimum mean happiness of a run for Baobei to gain affection.
+pub const GOOD_RUN_HAPPINESS: f32 = 0.6; // 60%
+/// Seconds of a fully happy run needed to gain one affection point.
+pub const AFFECTION_PERIOD: f32 = 30.0;
+/// Affection points gained when Baobei stays happy during the in-laws visit.
+pub const IN_LAWS_AFFECTION_BONUS: u32 = 5;
+
+/// Affection points needed to see the second upcoming request on the order
+/// ticket bought in the shop, the first one being shown once bought.
+pub const PRECOGNITION_UNLOCK: u32 = 45;
+
+/// Distance under which dropped items are pulled toward Didi with the pickup assist
+pub const MAGNET_RADIUS: f32 = 150.0;
+/// Distance under which dropped items are pulled toward Didi with the magnet of the shop
+pub const UPGRADE_MAGNET_RADIUS: f32 = 220.0;
+/// Speed of the dropped items pulled toward Didi
+pub const MAGNET_SPEED: f32 = 250.0;
+
+/// Duration in seconds of a moment of the day in the story mode
+pub const ROUTINE_PERIOD_DURATION: f32 = 60.0;
+/// Duration in seconds of a whole day and night outside of the story mode
+pub const DAY_CYCLE_DURATION: f32 = 240.0;
+
+/// Size of a cell of the grid where furnishings are placed
+pub const FURNISHING_GRID: f32 = 40.0;
+
+/// Duration in seconds of an attempt in a seed race
+pub const RACE_DURATION: f32 = 60.0;
+
+/// Walking speed of the pet cat
+pub const PET_SPEED: f32 = 180.0;
+/// Chance that the pet cat knocks a dropped item it walks into
+pub const PET_KNOCK_CHANCE: f64 = 0.35;
+
+/// Walking speed of Baobei moving to another place
+pub const BAOBEI_SPEED: f32 = 120.0;
+/// Seconds between two moves of Baobei to another place
+pub const BAOBEI_RELOCATION_INTERVAL: f32 = 45.0;
 
 /// States of the game
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
@@ -18,4 +236,98 @@ pub enum GameState {
     Menu,
     /// The game phase
     InGame,
+    /// The results of a seed race between two attempts
+    RaceResults,
+    /// The game is suspended, pushed on top of the game phase
+    Paused,
+    /// Between runs, the player furnishes the apartment with the earned points
+    Decorate,
+    /// The victory or defeat screen of a survival run
+    SurvivalResults,
+    /// Screen binding the controls of exotic devices, reached from the menu
+    Bindings,
+    /// A rhythm minigame pushed on top of the game phase
+    BonusRound,
+    /// The interactive tutorial, the game phase being pushed on top of it
+    Tutorial,
+    /// Free play without decay nor timers, the game phase being pushed on
+    /// top of it
+    Sandbox,
+    /// Screen choosing the difficulty, reached from the menu
+    Difficulty,
+    /// First-run flow choosing the language, the controls and the
+    /// accessibility options before the tutorial
+    Onboarding,
+    /// The statistics of the session, shown when the player leaves the game
+    SessionSummary,
+    /// The shop spending the coins in upgrades between two levels, after the
+    /// summary of the level
+    Shop,
+    /// The statistics and the grade of the level, at its end or at the game
+    /// over, pushed on top of the game phase
+    LevelSummary,
+    /// The game is frozen while the player frames and takes photos, pushed
+    /// on top of the game phase
+    Photo,
+}
+
+/// Modes of the game, chosen in the menu
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameMode {
+    /// Baobei asks for items until the player goes back to the menu or
+    /// Baobei has no happiness left
+    Endless,
+    /// The player keeps Baobei happy during a countdown
+    Survival,
+    /// Days following a routine from the morning to the evening
+    Story,
+}
+
+impl Default for GameMode {
+    fn default() -> Self {
+        Self::Endless
+    }
+}
+
+impl GameMode {
+    /// All the modes.
+    pub const ALL: [Self; 3] = [Self::Endless, Self::Survival, Self::Story];
+
+    /// Returns the mode following this one in the menu.
+    #[must_use]
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Endless => Self::Survival,
+            Self::Survival => Self::Story,
+            Self::Story => Self::Endless,
+        }
+    }
+
+    /// Returns the name of the mode shown in the menu.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Endless => "Endless",
+            Self::Survival => "Survival",
+            Self::Story => "Story",
+        }
+    }
+}
+
+impl fmt::Display for GameMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name().to_lowercase())
+    }
+}
+
+impl FromStr for GameMode {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|mode| mode.to_string() == name)
+            .ok_or_else(|| format!("Unknown game mode: {}", name))
+    }
 }
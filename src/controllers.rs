@@ -2,6 +2,8 @@
 
 use bevy::{prelude::*, utils::HashSet};
 
+use crate::settings::Settings;
+
 /// Label for controller systems
 #[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
 pub struct ControllerSystems;
@@ -13,13 +15,17 @@ impl Plugin for ControllerPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app.add_event::<DirectionEvent>()
             .init_resource::<GamepadLobby>()
+            .init_resource::<InputMap>()
+            .init_resource::<ActiveDevice>()
             .add_system_set(
                 SystemSet::new()
                     .label(ControllerSystems)
                     .with_system(connection_system.system())
                     .with_system(keyboard_system.system())
-                    .with_system(gamepad_system.system()),
-            );
+                    .with_system(gamepad_system.system())
+                    .with_system(active_device_system.system()),
+            )
+            .add_system(binding_text_system.system().after(ControllerSystems));
     }
 }
 
@@ -27,41 +33,83 @@ impl Plugin for ControllerPlugin {
 pub struct DirectionEvent {
     /// Direction vector normalized to length 1.
     pub direction: Vec3,
+    /// True while the sprint control is held.
+    pub sprint: bool,
+    /// Number of the player choosing the direction, 0 for Didi.
+    pub player: usize,
 }
 
-/// Generates direction events when arrow keys are pressed.
+/// Keys moving the second player in local co-op, in the order up, down,
+/// left and right.
+const COOP_KEYS: [KeyCode; 4] = [KeyCode::W, KeyCode::S, KeyCode::A, KeyCode::D];
+
+/// Generates direction events when arrow keys are pressed, and for the
+/// second player when the `WASD` keys are pressed in local co-op.
 fn keyboard_system(
     keyboard_input: Res<Input<KeyCode>>,
+    input_map: Res<InputMap>,
+    settings: Res<Settings>,
     mut direction_events: EventWriter<DirectionEvent>,
 ) {
+    let arrow_keys = [KeyCode::Up, KeyCode::Down, KeyCode::Left, KeyCode::Right];
+    let direction = keys_direction(&keyboard_input, arrow_keys);
+    if direction != Vec3::ZERO {
+        direction_events.send(DirectionEvent {
+            direction: direction.normalize(),
+            sprint: input_map.key_pressed(InputAction::Sprint, &keyboard_input),
+            player: 0,
+        })
+    }
+
+    if !settings.coop {
+        return;
+    }
+    let direction = keys_direction(&keyboard_input, COOP_KEYS);
+    if direction != Vec3::ZERO {
+        direction_events.send(DirectionEvent {
+            direction: direction.normalize(),
+            sprint: false,
+            player: 1,
+        })
+    }
+}
+
+/// Returns the direction of the pressed keys, given in the order up, down,
+/// left and right, not normalized.
+fn keys_direction(keyboard_input: &Input<KeyCode>, keys: [KeyCode; 4]) -> Vec3 {
+    let [up, down, left, right] = keys;
     let mut direction = Vec3::ZERO;
 
-    if keyboard_input.pressed(KeyCode::Up) {
+    if keyboard_input.pressed(up) {
         direction += Vec3::new(0.0, 1.0, 0.0)
     }
-    if keyboard_input.pressed(KeyCode::Down) {
+    if keyboard_input.pressed(down) {
         direction += Vec3::new(0.0, -1.0, 0.0)
     }
-    if keyboard_input.pressed(KeyCode::Left) {
+    if keyboard_input.pressed(left) {
         direction += Vec3::new(-1.0, 0.0, 0.0)
     }
-    if keyboard_input.pressed(KeyCode::Right) {
+    if keyboard_input.pressed(right) {
         direction += Vec3::new(1.0, 0.0, 0.0)
     }
-
-    if direction != Vec3::ZERO {
-        let direction = direction.normalize();
-        direction_events.send(DirectionEvent { direction })
-    }
+    direction
 }
 
 /// Lobby containing connected gamepads.
 #[derive(Default)]
-struct GamepadLobby {
+pub struct GamepadLobby {
     /// Connected gamepads
     gamepads: HashSet<Gamepad>,
 }
 
+impl GamepadLobby {
+    /// Returns true if a gamepad is connected.
+    #[must_use]
+    pub fn has_gamepad(&self) -> bool {
+        !self.gamepads.is_empty()
+    }
+}
+
 /// Adds or removes gamepads to/from the lobby when they are connected or disconnected.
 fn connection_system(
     mut lobby: ResMut<GamepadLobby>,
@@ -82,25 +130,473 @@ fn connection_system(
     }
 }
 
-/// Generates direction events when a gamepad left stick is triggered.
+/// Generates direction events when the axes or the buttons bound to the
+/// movement are triggered, the left stick and the D-pad by default.
+///
+/// In local co-op, the second gamepad moves the second player.
 fn gamepad_system(
     lobby: Res<GamepadLobby>,
+    input_map: Res<InputMap>,
+    settings: Res<Settings>,
     axes: Res<Axis<GamepadAxis>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
     mut direction_events: EventWriter<DirectionEvent>,
 ) {
     for gamepad in lobby.gamepads.iter().cloned() {
-        let left_stick_x = axes
-            .get(GamepadAxis(gamepad, GamepadAxisType::LeftStickX))
-            .unwrap_or(0.0);
+        let direction = input_map.move_direction(gamepad, &axes, &gamepad_buttons);
 
-        let left_stick_y = axes
-            .get(GamepadAxis(gamepad, GamepadAxisType::LeftStickY))
-            .unwrap_or(0.0);
-
-        if left_stick_x != 0.0 && left_stick_y != 0.0 {
+        if direction != Vec3::ZERO {
             direction_events.send(DirectionEvent {
-                direction: Vec3::new(left_stick_x, left_stick_y, 0.0).normalize(),
+                direction: direction.normalize(),
+                sprint: input_map.button_pressed(InputAction::Sprint, gamepad, &gamepad_buttons),
+                player: if settings.coop {
+                    usize::from(gamepad.0 > 0)
+                } else {
+                    0
+                },
+            })
+        }
+    }
+}
+
+/// A direction of the movement, bound to a gamepad axis or to a button for
+/// devices without sticks like dance pads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveDirection {
+    /// Towards the top of the screen
+    Up,
+    /// Towards the bottom of the screen
+    Down,
+    /// Towards the left of the screen
+    Left,
+    /// Towards the right of the screen
+    Right,
+}
+
+impl MoveDirection {
+    /// All the directions.
+    pub const ALL: [Self; 4] = [Self::Up, Self::Down, Self::Left, Self::Right];
+
+    /// Returns the arrow key of the direction.
+    #[must_use]
+    pub const fn arrow_key(self) -> KeyCode {
+        match self {
+            Self::Up => KeyCode::Up,
+            Self::Down => KeyCode::Down,
+            Self::Left => KeyCode::Left,
+            Self::Right => KeyCode::Right,
+        }
+    }
+
+    /// Returns true for the directions of the horizontal axis.
+    #[must_use]
+    pub const fn is_horizontal(self) -> bool {
+        matches!(self, Self::Left | Self::Right)
+    }
+
+    /// Returns the sign of the direction on its axis.
+    #[must_use]
+    pub const fn sign(self) -> f32 {
+        match self {
+            Self::Up | Self::Right => 1.0,
+            Self::Down | Self::Left => -1.0,
+        }
+    }
+
+    /// Returns the unit vector of the direction.
+    fn vector(self) -> Vec3 {
+        if self.is_horizontal() {
+            Vec3::new(self.sign(), 0.0, 0.0)
+        } else {
+            Vec3::new(0.0, self.sign(), 0.0)
+        }
+    }
+}
+
+/// A gamepad axis moving Didi, inverted when its values go the other way.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MoveAxis {
+    /// The bound axis
+    pub axis: GamepadAxisType,
+    /// Multiplier of the values, -1 to invert the axis
+    pub factor: f32,
+}
+
+impl MoveAxis {
+    /// Returns the axis with its values unchanged.
+    const fn new(axis: GamepadAxisType) -> Self {
+        Self { axis, factor: 1.0 }
+    }
+}
+
+/// An action of the player bound to a key and to a gamepad button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputAction {
+    /// Starts the game or validates a screen
+    Confirm,
+    /// Leaves a screen
+    Back,
+    /// Pauses or resumes the game
+    Pause,
+    /// Swaps the item in hand with the one in the backpack
+    SwapItems,
+    /// Makes Didi run while held
+    Sprint,
+}
+
+impl InputAction {
+    /// All the actions.
+    pub const ALL: [Self; 5] = [
+        Self::Confirm,
+        Self::Back,
+        Self::Pause,
+        Self::SwapItems,
+        Self::Sprint,
+    ];
+
+    /// Returns the token replaced by the binding of the action in texts.
+    const fn token(self) -> &'static str {
+        match self {
+            Self::Confirm => "{confirm}",
+            Self::Back => "{back}",
+            Self::Pause => "{pause}",
+            Self::SwapItems => "{swap}",
+            Self::Sprint => "{sprint}",
+        }
+    }
+}
+
+/// Device last used by the player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputDevice {
+    /// The keyboard
+    Keyboard,
+    /// A gamepad
+    Gamepad,
+}
+
+/// Device last used by the player, whose bindings are shown in the texts.
+pub struct ActiveDevice(pub InputDevice);
+
+impl Default for ActiveDevice {
+    fn default() -> Self {
+        Self(InputDevice::Keyboard)
+    }
+}
+
+/// Key and gamepad button bound to each action, and gamepad controls bound
+/// to the movement.
+pub struct InputMap {
+    /// Bindings of the actions
+    bindings: Vec<(InputAction, KeyCode, GamepadButtonType)>,
+    /// Horizontal and vertical axes of the movement
+    move_axes: [MoveAxis; 2],
+    /// Buttons moving Didi in a direction
+    move_buttons: Vec<(MoveDirection, GamepadButtonType)>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        Self {
+            bindings: vec![
+                (
+                    InputAction::Confirm,
+                    KeyCode::Space,
+                    GamepadButtonType::South,
+                ),
+                (InputAction::Back, KeyCode::Escape, GamepadButtonType::East),
+                (InputAction::Pause, KeyCode::P, GamepadButtonType::Start),
+                (
+                    InputAction::SwapItems,
+                    KeyCode::Tab,
+                    GamepadButtonType::North,
+                ),
+                (
+                    InputAction::Sprint,
+                    KeyCode::LShift,
+                    GamepadButtonType::RightTrigger2,
+                ),
+            ],
+            move_axes: [
+                MoveAxis::new(GamepadAxisType::LeftStickX),
+                MoveAxis::new(GamepadAxisType::LeftStickY),
+            ],
+            move_buttons: vec![
+                (MoveDirection::Up, GamepadButtonType::DPadUp),
+                (MoveDirection::Down, GamepadButtonType::DPadDown),
+                (MoveDirection::Left, GamepadButtonType::DPadLeft),
+                (MoveDirection::Right, GamepadButtonType::DPadRight),
+            ],
+        }
+    }
+}
+
+impl InputMap {
+    /// Returns true if the key or the button of the action has just been
+    /// pressed.
+    #[must_use]
+    pub fn just_pressed(
+        &self,
+        action: InputAction,
+        keyboard: &Input<KeyCode>,
+        gamepad_buttons: &Input<GamepadButton>,
+    ) -> bool {
+        self.bindings
+            .iter()
+            .filter(|(bound_action, _, _)| *bound_action == action)
+            .any(|(_, key, button)| {
+                keyboard.just_pressed(*key)
+                    || gamepad_buttons
+                        .get_just_pressed()
+                        .any(|pressed| pressed.1 == *button)
             })
+    }
+
+    /// Returns true while the key of the action is held.
+    #[must_use]
+    pub fn key_pressed(&self, action: InputAction, keyboard: &Input<KeyCode>) -> bool {
+        self.bindings
+            .iter()
+            .any(|(bound_action, key, _)| *bound_action == action && keyboard.pressed(*key))
+    }
+
+    /// Returns true while the button of the action is held on the gamepad.
+    #[must_use]
+    pub fn button_pressed(
+        &self,
+        action: InputAction,
+        gamepad: Gamepad,
+        gamepad_buttons: &Input<GamepadButton>,
+    ) -> bool {
+        self.bindings.iter().any(|(bound_action, _, button)| {
+            *bound_action == action && gamepad_buttons.pressed(GamepadButton(gamepad, *button))
+        })
+    }
+
+    /// Binds the action to the key.
+    pub fn bind_key(&mut self, action: InputAction, key: KeyCode) {
+        for (_, bound_key, _) in self.bindings_mut(action) {
+            *bound_key = key;
+        }
+    }
+
+    /// Binds the action to the gamepad button.
+    pub fn bind_button(&mut self, action: InputAction, button: GamepadButtonType) {
+        for (_, _, bound_button) in self.bindings_mut(action) {
+            *bound_button = button;
         }
     }
+
+    /// Binds the axis of the direction, inverted if moving towards the
+    /// direction gives values of the opposite sign, like the `value` captured.
+    pub fn bind_move_axis(&mut self, direction: MoveDirection, axis: GamepadAxisType, value: f32) {
+        let index = if direction.is_horizontal() { 0 } else { 1 };
+        self.move_axes[index] = MoveAxis {
+            axis,
+            factor: value.signum() * direction.sign(),
+        };
+    }
+
+    /// Binds the direction to the gamepad button.
+    pub fn bind_move_button(&mut self, direction: MoveDirection, button: GamepadButtonType) {
+        for (_, bound_button) in self
+            .move_buttons
+            .iter_mut()
+            .filter(|(bound_direction, _)| *bound_direction == direction)
+        {
+            *bound_button = button;
+        }
+    }
+
+    /// Returns the movement asked on the gamepad with the bound axes and
+    /// buttons, not normalized.
+    #[must_use]
+    pub fn move_direction(
+        &self,
+        gamepad: Gamepad,
+        axes: &Axis<GamepadAxis>,
+        gamepad_buttons: &Input<GamepadButton>,
+    ) -> Vec3 {
+        let axis_value = |move_axis: MoveAxis| {
+            axes.get(GamepadAxis(gamepad, move_axis.axis))
+                .unwrap_or(0.0)
+                * move_axis.factor
+        };
+        let from_axes = Vec3::new(
+            axis_value(self.move_axes[0]),
+            axis_value(self.move_axes[1]),
+            0.0,
+        );
+
+        self.move_buttons
+            .iter()
+            .filter(|(_, button)| gamepad_buttons.pressed(GamepadButton(gamepad, *button)))
+            .fold(from_axes, |direction, (bound_direction, _)| {
+                direction + bound_direction.vector()
+            })
+    }
+
+    /// Returns the names of the axis and of the button bound to the direction.
+    #[must_use]
+    pub fn move_label(&self, direction: MoveDirection) -> String {
+        let move_axis = self.move_axes[if direction.is_horizontal() { 0 } else { 1 }];
+        let sign = if move_axis.factor * direction.sign() > 0.0 {
+            '+'
+        } else {
+            '-'
+        };
+        let buttons: Vec<String> = self
+            .move_buttons
+            .iter()
+            .filter(|(bound_direction, _)| *bound_direction == direction)
+            .map(|(_, button)| button_label(*button))
+            .collect();
+
+        format!("{:?} {} / {}", move_axis.axis, sign, buttons.join(", "))
+    }
+
+    /// Returns the name of the control bound to the action on the device.
+    #[must_use]
+    pub fn label(&self, action: InputAction, device: InputDevice) -> String {
+        self.bindings
+            .iter()
+            .find(|(bound_action, _, _)| *bound_action == action)
+            .map_or_else(String::new, |(_, key, button)| match device {
+                InputDevice::Keyboard => format!("{:?}", key),
+                InputDevice::Gamepad => button_label(*button),
+            })
+    }
+
+    /// Returns the bindings of the action.
+    fn bindings_mut(
+        &mut self,
+        action: InputAction,
+    ) -> impl Iterator<Item = &mut (InputAction, KeyCode, GamepadButtonType)> {
+        self.bindings
+            .iter_mut()
+            .filter(move |(bound_action, _, _)| *bound_action == action)
+    }
+}
+
+/// Returns the name of the gamepad button printed on common gamepads.
+fn button_label(button: GamepadButtonType) -> String {
+    match button {
+        GamepadButtonType::South => "A".to_string(),
+        GamepadButtonType::East => "B".to_string(),
+        GamepadButtonType::West => "X".to_string(),
+        GamepadButtonType::North => "Y".to_string(),
+        GamepadButtonType::LeftTrigger => "LB".to_string(),
+        GamepadButtonType::RightTrigger => "RB".to_string(),
+        GamepadButtonType::LeftTrigger2 => "LT".to_string(),
+        GamepadButtonType::RightTrigger2 => "RT".to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+/// Replaces the tokens of the actions, like `{confirm}`, by the controls
+/// bound on the device.
+#[must_use]
+pub fn substitute_bindings(template: &str, input_map: &InputMap, device: InputDevice) -> String {
+    InputAction::ALL
+        .iter()
+        .fold(template.to_string(), |text, action| {
+            text.replace(action.token(), &input_map.label(*action, device))
+        })
+}
+
+/// Component on a text whose action tokens are replaced by the bound
+/// controls, see `substitute_bindings`.
+pub struct BindingText(pub String);
+
+/// Switches the active device to the last one used.
+fn active_device_system(
+    keyboard: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    mut active_device: ResMut<ActiveDevice>,
+) {
+    let device = if gamepad_buttons.get_just_pressed().next().is_some() {
+        InputDevice::Gamepad
+    } else if keyboard.get_just_pressed().next().is_some() {
+        InputDevice::Keyboard
+    } else {
+        return;
+    };
+    if active_device.0 != device {
+        active_device.0 = device;
+    }
+}
+
+/// Shows the controls of the active device in the binding texts.
+fn binding_text_system(
+    input_map: Res<InputMap>,
+    active_device: Res<ActiveDevice>,
+    mut texts: Query<(&BindingText, &mut Text)>,
+    added_texts: Query<(), Added<BindingText>>,
+) {
+    let bindings_changed = input_map.is_changed() || active_device.is_changed();
+    if !bindings_changed && added_texts.iter().next().is_none() {
+        return;
+    }
+    for (binding_text, mut text) in texts.iter_mut() {
+        text.sections[0].value = substitute_bindings(&binding_text.0, &input_map, active_device.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_substitute_bindings() {
+        let mut input_map = InputMap::default();
+        let template = "Press {confirm} to play, {back} to leave";
+
+        assert_eq!(
+            substitute_bindings(template, &input_map, InputDevice::Keyboard),
+            "Press Space to play, Escape to leave"
+        );
+        assert_eq!(
+            substitute_bindings(template, &input_map, InputDevice::Gamepad),
+            "Press A to play, B to leave"
+        );
+
+        input_map.bind_key(InputAction::Confirm, KeyCode::Return);
+        input_map.bind_button(InputAction::Back, GamepadButtonType::Select);
+        assert_eq!(
+            substitute_bindings(template, &input_map, InputDevice::Keyboard),
+            "Press Return to play, Escape to leave"
+        );
+        assert_eq!(
+            substitute_bindings(template, &input_map, InputDevice::Gamepad),
+            "Press A to play, Select to leave"
+        );
+    }
+
+    #[test]
+    fn test_move_bindings() {
+        let mut input_map = InputMap::default();
+        let gamepad = Gamepad(0);
+        let mut axes = Axis::<GamepadAxis>::default();
+        let mut gamepad_buttons = Input::<GamepadButton>::default();
+
+        // A wheel turned to the left gives positive values
+        input_map.bind_move_axis(MoveDirection::Left, GamepadAxisType::RightZ, 0.8);
+        axes.set(GamepadAxis(gamepad, GamepadAxisType::RightZ), 0.5);
+        assert_eq!(
+            input_map.move_direction(gamepad, &axes, &gamepad_buttons),
+            Vec3::new(-0.5, 0.0, 0.0)
+        );
+
+        // A dance pad sends face buttons
+        input_map.bind_move_button(MoveDirection::Up, GamepadButtonType::North);
+        gamepad_buttons.press(GamepadButton(gamepad, GamepadButtonType::North));
+        assert_eq!(
+            input_map.move_direction(gamepad, &axes, &gamepad_buttons),
+            Vec3::new(-0.5, 1.0, 0.0)
+        );
+        assert_eq!(
+            input_map.move_label(MoveDirection::Right),
+            "RightZ - / DPadRight"
+        );
+    }
 }
@@ -37,6 +37,15 @@ impl Cooldown {
         self.available
     }
 
+    /// Returns the fraction of the duration remaining, 0 when available.
+    pub fn remaining_fraction(&self) -> f32 {
+        if self.available || self.duration <= 0.0 {
+            0.0
+        } else {
+            self.remaining / self.duration
+        }
+    }
+
     /// Advances the cooldown by `delta` seconds.
     pub fn tick(&mut self, delta: f32) -> &Self {
         if self.available {
@@ -0,0 +1,299 @@
+//! Difficulty of the game, chosen on a screen reached from the menu.
+//!
+//! Each difficulty has a profile of tuning values, read by the gameplay
+//! systems: how fast Baobei gets sad, how long Baobei waits for a request
+//! and how long the producers take to restock.
+
+use std::{fmt, str::FromStr};
+
+use bevy::prelude::*;
+
+use crate::{constants::GameState, preferences::StartPreferences, save::Profile};
+
+/// Plugin managing the difficulty screen.
+pub struct DifficultyPlugin;
+
+impl Plugin for DifficultyPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Difficulty>()
+            .add_system_set(
+                SystemSet::on_update(GameState::Menu).with_system(start_difficulty_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Difficulty)
+                    .with_system(setup_difficulty_screen.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Difficulty)
+                    .with_system(choose_difficulty_system.system().label("choose_difficulty"))
+                    .with_system(difficulty_text_system.system().after("choose_difficulty"))
+                    .with_system(stop_difficulty_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Difficulty)
+                    .with_system(cleanup_difficulty_screen.system()),
+            );
+    }
+}
+
+/// How hard Baobei is to keep happy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    /// Baobei is patient and the producers restock quickly
+    Chill,
+    /// The intended balance
+    Normal,
+    /// Baobei gets sad quickly and waits less
+    Hectic,
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Self::Normal
+    }
+}
+
+impl FromWorld for Difficulty {
+    fn from_world(world: &mut World) -> Self {
+        world
+            .get_resource::<StartPreferences>()
+            .map_or_else(Self::default, |preferences| preferences.difficulty)
+    }
+}
+
+/// Tuning values of a difficulty.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DifficultyProfile {
+    /// Happiness decrease per second
+    pub happiness_decrease: f32,
+    /// Seconds Baobei waits for a request at the first level before getting upset
+    pub patience: f32,
+    /// Shortest patience of Baobei, whatever the level
+    pub min_patience: f32,
+    /// Seconds to restock an item in a producer
+    pub restock_duration: f32,
+}
+
+impl Difficulty {
+    /// All the difficulties, from the easiest.
+    pub const ALL: [Self; 3] = [Self::Chill, Self::Normal, Self::Hectic];
+
+    /// Returns the tuning values of the difficulty.
+    pub const fn profile(self) -> DifficultyProfile {
+        match self {
+            Self::Chill => DifficultyProfile {
+                happiness_decrease: 0.03, // 3%
+                patience: 30.0,
+                min_patience: 14.0,
+                restock_duration: 5.0,
+            },
+            Self::Normal => DifficultyProfile {
+                happiness_decrease: 0.05, // 5%
+                patience: 20.0,
+                min_patience: 8.0,
+                restock_duration: 8.0,
+            },
+            Self::Hectic => DifficultyProfile {
+                happiness_decrease: 0.08, // 8%
+                patience: 15.0,
+                min_patience: 6.0,
+                restock_duration: 12.0,
+            },
+        }
+    }
+
+    /// Returns the name of the difficulty shown on the screen.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Chill => "Chill",
+            Self::Normal => "Normal",
+            Self::Hectic => "Hectic",
+        }
+    }
+
+    /// Returns the harder difficulty, or the same if already the hardest.
+    const fn harder(self) -> Self {
+        match self {
+            Self::Chill => Self::Normal,
+            Self::Normal | Self::Hectic => Self::Hectic,
+        }
+    }
+
+    /// Returns the easier difficulty, or the same if already the easiest.
+    const fn easier(self) -> Self {
+        match self {
+            Self::Hectic => Self::Normal,
+            Self::Normal | Self::Chill => Self::Chill,
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name().to_lowercase())
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|difficulty| difficulty.to_string() == name)
+            .ok_or_else(|| format!("Unknown difficulty: {}", name))
+    }
+}
+
+/// Stores entities of the difficulty screen.
+struct DifficultyScreenData {
+    /// Entity wrapping all the entities of the screen
+    node_wrapper: Entity,
+}
+
+/// Tag the text listing the difficulties.
+struct DifficultyText;
+
+/// Opens the difficulty screen when the player presses `H` in the menu.
+fn start_difficulty_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::H) {
+        state.set(GameState::Difficulty).unwrap();
+    }
+}
+
+/// Remembers the difficulty and goes back to the menu when the player
+/// presses `Escape`.
+fn stop_difficulty_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    difficulty: Res<Difficulty>,
+    profile: Res<Profile>,
+    mut preferences: ResMut<StartPreferences>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::Escape) {
+        return;
+    }
+    if preferences.difficulty != *difficulty {
+        preferences.difficulty = *difficulty;
+        preferences.store(&profile);
+    }
+    state.set(GameState::Menu).unwrap();
+}
+
+/// Chooses the difficulty with the arrow keys.
+fn choose_difficulty_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut difficulty: ResMut<Difficulty>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Up) {
+        *difficulty = difficulty.easier();
+    }
+    if keyboard_input.just_pressed(KeyCode::Down) {
+        *difficulty = difficulty.harder();
+    }
+}
+
+/// Lists the difficulties, marking the chosen one.
+fn difficulty_text_system(
+    difficulty: Res<Difficulty>,
+    mut texts: Query<&mut Text, With<DifficultyText>>,
+    added_texts: Query<(), Added<DifficultyText>>,
+) {
+    if !difficulty.is_changed() && added_texts.iter().next().is_none() {
+        return;
+    }
+    let lines: Vec<String> = Difficulty::ALL
+        .iter()
+        .map(|choice| {
+            let marker = if choice == &*difficulty { "> " } else { "" };
+            format!("{}{}", marker, choice.name())
+        })
+        .collect();
+
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = lines.join("\n");
+    }
+}
+
+/// Shows the difficulties and the instructions of the screen.
+fn setup_difficulty_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let font = asset_server.load("FiraSans-Bold.ttf");
+    let text = |value: &str, font_size: f32| TextBundle {
+        text: Text::with_section(
+            value,
+            TextStyle {
+                font: font.clone(),
+                font_size,
+                color: Color::WHITE,
+            },
+            TextAlignment::default(),
+        ),
+        ..TextBundle::default()
+    };
+
+    let node_wrapper = commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                padding: Rect::all(Val::Px(50.0)),
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Style::default()
+            },
+            material: materials.add(Color::NONE.into()),
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            parent.spawn().insert_bundle(text("Difficulty", 80.0));
+            parent
+                .spawn()
+                .insert(DifficultyText)
+                .insert_bundle(text("", 50.0));
+            parent.spawn().insert_bundle(text(
+                "Choose with the arrow keys, then press Escape to go back",
+                30.0,
+            ));
+        })
+        .id();
+
+    commands.insert_resource(DifficultyScreenData { node_wrapper });
+}
+
+/// Removes all entities of the difficulty screen.
+fn cleanup_difficulty_screen(mut commands: Commands, screen_data: Res<DifficultyScreenData>) {
+    commands
+        .entity(screen_data.node_wrapper)
+        .despawn_recursive();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn harder_difficulties_are_less_forgiving() {
+        let chill = Difficulty::Chill.profile();
+        let normal = Difficulty::Normal.profile();
+        let hectic = Difficulty::Hectic.profile();
+
+        assert!(chill.happiness_decrease < normal.happiness_decrease);
+        assert!(normal.happiness_decrease < hectic.happiness_decrease);
+        assert!(chill.patience > normal.patience && normal.patience > hectic.patience);
+        assert!(chill.restock_duration < hectic.restock_duration);
+
+        for difficulty in Difficulty::ALL {
+            assert_eq!(difficulty.to_string().parse(), Ok(difficulty));
+        }
+        assert_eq!(Difficulty::Hectic.harder(), Difficulty::Hectic);
+        assert_eq!(Difficulty::Chill.easier(), Difficulty::Chill);
+    }
+}
@@ -3,6 +3,7 @@
 use bevy::prelude::*;
 
 use crate::{
+    camera::MainCamera,
     collisions::{CollisionSystems, Position},
     constants::{WINDOW_HEIGHT, WINDOW_WIDTH},
 };
@@ -12,24 +13,42 @@ pub struct DrawingPlugin;
 
 impl Plugin for DrawingPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_system_set(
-            SystemSet::new()
-                .with_system(update_game_object_position_system.system())
-                .with_system(update_ui_objects_position_system.system())
-                .after(CollisionSystems),
-        );
+        app.init_resource::<Fog>()
+            .init_resource::<Tint>()
+            .add_startup_system(spawn_fog_masks.system())
+            .add_startup_system(spawn_tint_overlay.system())
+            .add_system(attach_ui_objects_system.system())
+            .add_system_set(
+                SystemSet::new()
+                    .with_system(update_game_object_position_system.system())
+                    .with_system(update_ui_objects_position_system.system())
+                    .with_system(update_overlays_position_system.system())
+                    .with_system(update_fog_system.system())
+                    .with_system(update_tint_system.system())
+                    .after(CollisionSystems),
+            );
     }
 }
 
 /// Component meaning that the entity will be drawn in the foreground as a UI object.
+/// It stays at the same place of the screen when the camera moves.
 pub struct UiObject;
 
+/// Component meaning that the entity will be drawn in the foreground at its
+/// game position, like a speech bubble.
+pub struct Overlay;
+
 /// Limit value in which the displayed sprite is visible.
 /// z = 0 => background, z = 1000 => foreground
 const Z_LIMIT: f32 = 1000.0;
 
 /// Query filter for game entities that are moved
-type MovedGameObject = (Without<(Parent, UiObject)>, Changed<Position>);
+type MovedGameObject = (
+    Without<Parent>,
+    Without<UiObject>,
+    Without<Overlay>,
+    Changed<Position>,
+);
 
 /// Updates transform of game objects following their game position.
 ///
@@ -59,16 +78,59 @@ fn update_game_object_position_system(
 /// Query filter for UI entities that are moved
 type MovedUiObject = (With<UiObject>, Changed<Position>);
 
-/// Updates transform of UI objects following their position.
+/// Makes the UI objects follow the camera.
+fn attach_ui_objects_system(
+    mut commands: Commands,
+    cameras: Query<Entity, With<MainCamera>>,
+    ui_objects: Query<Entity, Added<UiObject>>,
+) {
+    let ui_objects: Vec<Entity> = ui_objects.iter().collect();
+
+    if let Some(camera) = cameras.iter().next() {
+        if !ui_objects.is_empty() {
+            commands.entity(camera).push_children(&ui_objects);
+        }
+    }
+}
+
+/// Updates transform of UI objects following their position on the screen,
+/// relatively to the camera.
 fn update_ui_objects_position_system(
-    mut ui_objects: Query<(&Position, &mut Transform), MovedUiObject>,
+    cameras: Query<&Transform, With<MainCamera>>,
+    mut ui_objects: Query<(&Position, &mut Transform), (MovedUiObject, Without<MainCamera>)>,
 ) {
+    let camera_z = match cameras.iter().next() {
+        Some(camera) => camera.translation.z,
+        None => return,
+    };
+
     for (position, mut transform) in ui_objects.iter_mut() {
+        let screen_center = Vec3::new(WINDOW_WIDTH / 2.0, WINDOW_HEIGHT / 2.0, 0.0);
+
+        transform.translation = position.0 - screen_center;
+        transform.translation.z = Z_LIMIT - 1.0 - camera_z;
+    }
+}
+
+/// Query filter for overlay entities that are moved
+type MovedOverlay = (With<Overlay>, Changed<Position>);
+
+/// Updates transform of overlays following their game position.
+fn update_overlays_position_system(mut overlays: Query<(&Position, &mut Transform), MovedOverlay>) {
+    for (position, mut transform) in overlays.iter_mut() {
         transform.translation = position.0;
         transform.translation.z = Z_LIMIT - 1.0;
     }
 }
 
+/// Returns the translation, relative to the center of a UI rectangle of the
+/// given size, of the game position, the whole room fitting the rectangle.
+#[must_use]
+pub fn world_to_ui(position: Vec3, size: Vec2) -> Vec2 {
+    let room = Vec2::new(WINDOW_WIDTH, WINDOW_HEIGHT);
+    (position.truncate() / room - Vec2::splat(0.5)) * size
+}
+
 /// Returns the ratio between the current size and the initial one.
 fn _window_size_ratio(window: Window) -> Vec3 {
     Vec3::new(
@@ -77,3 +139,133 @@ fn _window_size_ratio(window: Window) -> Vec3 {
         1.0,
     )
 }
+
+/// Darkness covering the screen except around the light source.
+#[derive(Default)]
+pub struct Fog {
+    /// Whether the fog is displayed.
+    pub enabled: bool,
+}
+
+/// Color tinting the whole scene, like the darkness of the night.
+pub struct Tint(pub Color);
+
+impl Default for Tint {
+    fn default() -> Self {
+        Self(Color::NONE)
+    }
+}
+
+/// Component on the rectangle covering the screen with the tint.
+struct TintOverlay;
+
+/// Spawns the transparent rectangle of the tint.
+fn spawn_tint_overlay(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    commands
+        .spawn()
+        .insert(TintOverlay)
+        .insert_bundle(SpriteBundle {
+            material: materials.add(Color::NONE.into()),
+            sprite: Sprite::new(Vec2::new(WINDOW_WIDTH, WINDOW_HEIGHT)),
+            // Above game objects but under the fog and UI objects
+            transform: Transform::from_xyz(WINDOW_WIDTH / 2.0, WINDOW_HEIGHT / 2.0, Z_LIMIT - 3.0),
+            ..SpriteBundle::default()
+        });
+}
+
+/// Colors the tint rectangle when the tint changes.
+fn update_tint_system(
+    tint: Res<Tint>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    overlays: Query<&Handle<ColorMaterial>, With<TintOverlay>>,
+) {
+    if !tint.is_changed() {
+        return;
+    }
+    for handle in overlays.iter() {
+        if let Some(material) = materials.get_mut(handle) {
+            material.color = tint.0;
+        }
+    }
+}
+
+/// Component on the entity lighting around it when there is fog.
+pub struct LightSource {
+    /// Half of the width of the lighted square.
+    pub radius: f32,
+}
+
+/// Component on one of the dark rectangles masking the screen around the
+/// light source.
+enum FogMask {
+    /// Above the light source
+    Top,
+    /// Under the light source
+    Bottom,
+    /// At the left of the light source, on the whole height
+    Left,
+    /// At the right of the light source, on the whole height
+    Right,
+}
+
+/// Spawns the hidden rectangles of the fog.
+fn spawn_fog_masks(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    let darkness = materials.add(Color::rgba(0.0, 0.0, 0.05, 0.95).into());
+
+    for mask in [FogMask::Top, FogMask::Bottom, FogMask::Left, FogMask::Right] {
+        commands.spawn().insert(mask).insert_bundle(SpriteBundle {
+            material: darkness.clone(),
+            sprite: Sprite::new(Vec2::ZERO),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..SpriteBundle::default()
+        });
+    }
+}
+
+/// Places the fog rectangles around the light source, leaving it visible.
+fn update_fog_system(
+    fog: Res<Fog>,
+    light_sources: Query<(&Position, &LightSource)>,
+    mut masks: Query<(&FogMask, &mut Sprite, &mut Transform, &mut Visible)>,
+) {
+    let light = light_sources.iter().next();
+
+    for (mask, mut sprite, mut transform, mut visible) in masks.iter_mut() {
+        let (position, light_source) = match light {
+            Some(light) if fog.enabled => light,
+            _ => {
+                visible.is_visible = false;
+                continue;
+            }
+        };
+        let (x, y) = (position.0.x, position.0.y + position.0.z);
+        let radius = light_source.radius;
+
+        let (center, size) = match mask {
+            FogMask::Top => (
+                Vec2::new(x, (y + radius + WINDOW_HEIGHT) / 2.0),
+                Vec2::new(2.0 * radius, WINDOW_HEIGHT - y - radius),
+            ),
+            FogMask::Bottom => (
+                Vec2::new(x, (y - radius) / 2.0),
+                Vec2::new(2.0 * radius, y - radius),
+            ),
+            FogMask::Left => (
+                Vec2::new((x - radius) / 2.0, WINDOW_HEIGHT / 2.0),
+                Vec2::new(x - radius, WINDOW_HEIGHT),
+            ),
+            FogMask::Right => (
+                Vec2::new((x + radius + WINDOW_WIDTH) / 2.0, WINDOW_HEIGHT / 2.0),
+                Vec2::new(WINDOW_WIDTH - x - radius, WINDOW_HEIGHT),
+            ),
+        };
+
+        visible.is_visible = true;
+        sprite.size = size.max(Vec2::ZERO);
+        // Above game objects but under UI objects
+        transform.translation = center.extend(Z_LIMIT - 2.0);
+    }
+}
@@ -0,0 +1,134 @@
+//! Vsync and frame rate cap chosen in the settings, so the game does not
+//! render hundreds of useless frames when the present mode does not wait for
+//! the screen. The frame rate is lowered further by the battery saver.
+
+use std::{
+    fmt,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    power::{PowerSaver, SAVER_FPS},
+    settings::Settings,
+};
+
+/// Plugin applying the vsync and limiting the frame rate.
+pub struct FrameLimiterPlugin;
+
+impl Plugin for FrameLimiterPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(vsync_system.system())
+            .add_system_to_stage(CoreStage::Last, frame_limiter_system.system());
+    }
+}
+
+/// Largest number of frames rendered per second.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FpsCap {
+    /// 30 frames per second
+    Thirty,
+    /// 60 frames per second
+    Sixty,
+    /// 120 frames per second
+    OneHundredTwenty,
+    /// As many frames as the present mode allows
+    Unlimited,
+}
+
+impl FpsCap {
+    /// Returns the shortest duration of a frame, if capped.
+    pub fn frame_duration(self) -> Option<Duration> {
+        let fps = match self {
+            Self::Thirty => 30.0,
+            Self::Sixty => 60.0,
+            Self::OneHundredTwenty => 120.0,
+            Self::Unlimited => return None,
+        };
+        Some(Duration::from_secs_f64(1.0 / fps))
+    }
+}
+
+impl fmt::Display for FpsCap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Thirty => "30",
+            Self::Sixty => "60",
+            Self::OneHundredTwenty => "120",
+            Self::Unlimited => "unlimited",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for FpsCap {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "30" => Ok(Self::Thirty),
+            "60" => Ok(Self::Sixty),
+            "120" => Ok(Self::OneHundredTwenty),
+            "unlimited" => Ok(Self::Unlimited),
+            _ => Err(format!("Unknown frame rate cap: {}", name)),
+        }
+    }
+}
+
+/// Applies the vsync of the settings to the window, at launch and when the
+/// settings change.
+fn vsync_system(settings: Res<Settings>, mut windows: ResMut<Windows>) {
+    if !settings.is_changed() {
+        return;
+    }
+    if let Some(window) = windows.get_primary_mut() {
+        if window.vsync() != settings.vsync {
+            window.set_vsync(settings.vsync);
+        }
+    }
+}
+
+/// Sleeps at the end of the frames shorter than the frame rate cap, or than
+/// the frame rate of the battery saver while saving.
+fn frame_limiter_system(
+    settings: Res<Settings>,
+    saver: Res<PowerSaver>,
+    mut frame_start: Local<Option<Instant>>,
+) {
+    let frame_duration = if saver.is_saving(&settings) {
+        Some(Duration::from_secs_f64(1.0 / SAVER_FPS))
+    } else {
+        settings.fps_cap.frame_duration()
+    };
+    if let (Some(duration), Some(start)) = (frame_duration, *frame_start) {
+        let elapsed = start.elapsed();
+        if elapsed < duration {
+            std::thread::sleep(duration - elapsed);
+        }
+    }
+    *frame_start = Some(Instant::now());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caps_are_saved_by_name() {
+        for cap in [
+            FpsCap::Thirty,
+            FpsCap::Sixty,
+            FpsCap::OneHundredTwenty,
+            FpsCap::Unlimited,
+        ] {
+            assert_eq!(cap.to_string().parse(), Ok(cap));
+        }
+        assert_eq!(
+            FpsCap::Sixty.frame_duration(),
+            Some(Duration::from_secs_f64(1.0 / 60.0))
+        );
+        assert_eq!(FpsCap::Unlimited.frame_duration(), None);
+    }
+}
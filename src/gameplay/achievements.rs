@@ -0,0 +1,326 @@
+//! Achievements unlocked by the actions of the player, stored in the profile
+//! and announced by a toast at the top of the screen.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{
+    constants::GameState,
+    save::{Profile, SaveData},
+    time_scale::TimeScale,
+};
+
+use super::{
+    happiness::Happiness,
+    in_laws::BossRoundEvent,
+    items::{ActionEvent, DeliveryEvent, ItemSystems},
+    Baobei, NewRunAppExt,
+};
+
+/// Plugin managing the achievements.
+pub struct AchievementsPlugin;
+
+impl Plugin for AchievementsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<UnlockEvent>()
+            .init_resource::<Achievements>()
+            .init_resource::<AchievementProgress>()
+            .init_resource::<ToastQueue>()
+            .add_system(unlock_system.system().label("unlock"))
+            .add_system(toast_system.system().after("unlock"))
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(
+                        delivery_achievements_system
+                            .system()
+                            .after(ItemSystems)
+                            .before("unlock"),
+                    )
+                    .with_system(survivor_achievement_system.system().before("unlock"))
+                    .with_system(in_laws_achievement_system.system().before("unlock")),
+            )
+            .add_new_run_system(reset_progress_system);
+    }
+}
+
+/// Save file storing the unlocked achievements.
+const ACHIEVEMENTS_FILE: &str = "achievements.sav";
+/// Deliveries in a row without a mistake for the flawless achievement.
+const FLAWLESS_STREAK: u32 = 10;
+/// Seconds of game without Baobei running out of happiness for the survivor
+/// achievement.
+const SURVIVOR_DURATION: f32 = 300.0;
+/// Happiness of Baobei under which a delivery is a close call.
+const CLOSE_CALL_HAPPINESS: f32 = 0.1; // 10%
+/// Seconds a toast stays on the screen.
+const TOAST_DURATION: f32 = 3.0;
+
+/// An achievement to unlock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Achievement {
+    /// Baobei receives a first item
+    FirstDelivery,
+    /// Deliveries in a row without giving a wrong item
+    Flawless,
+    /// Baobei never runs out of happiness for minutes
+    Survivor,
+    /// Baobei receives an item when almost out of happiness
+    CloseCall,
+    /// Baobei stays happy during the whole visit of the in-laws
+    InLaws,
+}
+
+impl Achievement {
+    /// All the achievements.
+    const ALL: [Self; 5] = [
+        Self::FirstDelivery,
+        Self::Flawless,
+        Self::Survivor,
+        Self::CloseCall,
+        Self::InLaws,
+    ];
+
+    /// Returns the identifier of the achievement in the save file.
+    const fn id(self) -> &'static str {
+        match self {
+            Self::FirstDelivery => "first_delivery",
+            Self::Flawless => "flawless",
+            Self::Survivor => "survivor",
+            Self::CloseCall => "close_call",
+            Self::InLaws => "in_laws",
+        }
+    }
+
+    /// Returns the title shown in the toast.
+    const fn title(self) -> &'static str {
+        match self {
+            Self::FirstDelivery => "First delivery",
+            Self::Flawless => "10 deliveries without a mistake",
+            Self::Survivor => "Survive 5 minutes",
+            Self::CloseCall => "Close call",
+            Self::InLaws => "The in-laws approve",
+        }
+    }
+}
+
+/// Event sent when the conditions of an achievement are met.
+pub struct UnlockEvent(pub Achievement);
+
+/// Achievements unlocked by the profile.
+#[derive(Debug, Default, PartialEq)]
+pub struct Achievements {
+    /// The unlocked achievements, in the order of the unlocks
+    unlocked: Vec<Achievement>,
+}
+
+impl FromWorld for Achievements {
+    fn from_world(world: &mut World) -> Self {
+        let profile = world.get_resource::<Profile>().unwrap();
+        Self::from_save_data(&profile.load(ACHIEVEMENTS_FILE))
+    }
+}
+
+impl Achievements {
+    /// Returns true if the achievement is unlocked.
+    pub fn is_unlocked(&self, achievement: Achievement) -> bool {
+        self.unlocked.contains(&achievement)
+    }
+
+    /// Unlocks the achievement, returns false if it already was.
+    fn unlock(&mut self, achievement: Achievement) -> bool {
+        if self.is_unlocked(achievement) {
+            return false;
+        }
+        self.unlocked.push(achievement);
+        true
+    }
+
+    /// Reads the unlocked achievements from the save file, ignoring the
+    /// unknown ones.
+    fn from_save_data(data: &SaveData) -> Self {
+        Self {
+            unlocked: Achievement::ALL
+                .iter()
+                .copied()
+                .filter(|achievement| data.get(achievement.id()).unwrap_or(false))
+                .collect(),
+        }
+    }
+
+    /// Writes the unlocked achievements as entries of the save file.
+    fn to_save_data(&self) -> SaveData {
+        let mut data = SaveData::default();
+        for achievement in &self.unlocked {
+            data.set(achievement.id(), true);
+        }
+        data
+    }
+}
+
+/// Progress of the game toward the achievements.
+#[derive(Default)]
+struct AchievementProgress {
+    /// Deliveries in a row without a mistake
+    streak: u32,
+    /// Seconds of game since Baobei last ran out of happiness
+    survived: f32,
+}
+
+/// Unlocked achievements waiting for their toast.
+#[derive(Default)]
+struct ToastQueue(VecDeque<Achievement>);
+
+/// Component on the toast shown, removed when the timer finishes.
+struct Toast(Timer);
+
+/// Counts the deliveries in a row, a gift that is not delivered being a
+/// mistake, and rewards the close calls.
+fn delivery_achievements_system(
+    mut progress: ResMut<AchievementProgress>,
+    mut action_events: EventReader<ActionEvent>,
+    mut delivery_events: EventReader<DeliveryEvent>,
+    mut unlock_events: EventWriter<UnlockEvent>,
+) {
+    let gifts = action_events
+        .iter()
+        .filter(|action| matches!(action, ActionEvent::Give(_, _, _)))
+        .count();
+    let deliveries: Vec<&DeliveryEvent> = delivery_events.iter().collect();
+
+    if gifts > deliveries.len() {
+        progress.streak = 0;
+        return;
+    }
+    for delivery in deliveries {
+        progress.streak += 1;
+        unlock_events.send(UnlockEvent(Achievement::FirstDelivery));
+        if progress.streak >= FLAWLESS_STREAK {
+            unlock_events.send(UnlockEvent(Achievement::Flawless));
+        }
+        if delivery.happiness < CLOSE_CALL_HAPPINESS {
+            unlock_events.send(UnlockEvent(Achievement::CloseCall));
+        }
+    }
+}
+
+/// Counts the game time while no Baobei is out of happiness.
+fn survivor_achievement_system(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut progress: ResMut<AchievementProgress>,
+    mut unlock_events: EventWriter<UnlockEvent>,
+    baobei: Query<&Happiness, With<Baobei>>,
+) {
+    if baobei.iter().any(|happiness| happiness.value() <= 0.0) {
+        progress.survived = 0.0;
+        return;
+    }
+    progress.survived += time_scale.scale(time.delta()).as_secs_f32();
+    if progress.survived >= SURVIVOR_DURATION {
+        unlock_events.send(UnlockEvent(Achievement::Survivor));
+    }
+}
+
+/// Rewards the visits of the in-laws Baobei stayed happy through.
+fn in_laws_achievement_system(
+    mut boss_round_events: EventReader<BossRoundEvent>,
+    mut unlock_events: EventWriter<UnlockEvent>,
+) {
+    if boss_round_events.iter().any(|event| event.survived) {
+        unlock_events.send(UnlockEvent(Achievement::InLaws));
+    }
+}
+
+/// Stores the newly unlocked achievements and queues their toast.
+fn unlock_system(
+    profile: Res<Profile>,
+    mut achievements: ResMut<Achievements>,
+    mut toasts: ResMut<ToastQueue>,
+    mut unlock_events: EventReader<UnlockEvent>,
+) {
+    let mut unlocked = false;
+    for UnlockEvent(achievement) in unlock_events.iter() {
+        if achievements.unlock(*achievement) {
+            info!("Achievement unlocked: {:?}", achievement);
+            toasts.0.push_back(*achievement);
+            unlocked = true;
+        }
+    }
+    if unlocked {
+        profile.store(ACHIEVEMENTS_FILE, &achievements.to_save_data());
+    }
+}
+
+/// Shows the queued toasts one after the other at the top of the screen.
+fn toast_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    mut queue: ResMut<ToastQueue>,
+    mut toasts: Query<(Entity, &mut Toast)>,
+) {
+    let mut shown = false;
+    for (entity, mut toast) in toasts.iter_mut() {
+        if toast.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        } else {
+            shown = true;
+        }
+    }
+    if shown {
+        return;
+    }
+    let achievement = match queue.0.pop_front() {
+        Some(achievement) => achievement,
+        None => return,
+    };
+
+    commands
+        .spawn()
+        .insert(Toast(Timer::from_seconds(TOAST_DURATION, false)))
+        .insert_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(20.0),
+                    left: Val::Px(440.0),
+                    ..Rect::default()
+                },
+                ..Style::default()
+            },
+            text: Text::with_section(
+                format!("Achievement unlocked: {}", achievement.title()),
+                TextStyle {
+                    font: asset_server.load("FiraSans-Bold.ttf"),
+                    font_size: 30.0,
+                    color: Color::GOLD,
+                },
+                TextAlignment::default(),
+            ),
+            ..TextBundle::default()
+        });
+}
+
+/// Restarts the progress when a new game starts.
+fn reset_progress_system(mut progress: ResMut<AchievementProgress>) {
+    *progress = AchievementProgress::default();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn achievements_are_unlocked_once_and_saved() {
+        let mut achievements = Achievements::default();
+        assert!(achievements.unlock(Achievement::Flawless));
+        assert!(!achievements.unlock(Achievement::Flawless));
+        assert!(achievements.unlock(Achievement::CloseCall));
+
+        let loaded = Achievements::from_save_data(&achievements.to_save_data());
+        assert!(loaded.is_unlocked(Achievement::Flawless));
+        assert!(loaded.is_unlocked(Achievement::CloseCall));
+        assert!(!loaded.is_unlocked(Achievement::Survivor));
+    }
+}
@@ -0,0 +1,119 @@
+//! Adaptive difficulty: the recent performance of the player is rated from
+//! the requests served, given wrong or left to expire. A struggling player
+//! gets a more patient Baobei asking for fewer items at once, and a player
+//! doing well gets the opposite.
+
+use bevy::prelude::*;
+
+use crate::constants::{
+    GameState, ADAPTIVE_PATIENCE_RANGE, ADAPTIVE_PRESSURE_RATING, ADAPTIVE_SLACK_RATING,
+    ADAPTIVE_SMOOTHING,
+};
+
+use super::NewRunAppExt;
+
+use super::items::{DeliveryEvent, ItemSystems, WrongDeliveryEvent};
+
+/// Plugin rating the recent performance of the player.
+pub struct AdaptiveDifficultyPlugin;
+
+impl Plugin for AdaptiveDifficultyPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<AdaptiveDifficulty>()
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame).with_system(
+                    rate_deliveries_system
+                        .system()
+                        .label("adaptive_difficulty")
+                        .after(ItemSystems),
+                ),
+            )
+            .add_new_run_system(reset_adaptive_difficulty_system);
+    }
+}
+
+/// Rating of the recent performance of the player, adapting the patience of
+/// Baobei and the number of items asked at the same time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveDifficulty {
+    /// Moving average of the outcomes of the last requests, from 0 (all
+    /// failed) to 1 (all served)
+    rating: f32,
+}
+
+impl Default for AdaptiveDifficulty {
+    fn default() -> Self {
+        Self { rating: 0.5 }
+    }
+}
+
+impl AdaptiveDifficulty {
+    /// Counts a request served, or failed by a wrong item or by waiting too
+    /// long.
+    pub fn record(&mut self, served: bool) {
+        let outcome = if served { 1.0 } else { 0.0 };
+        self.rating = ADAPTIVE_SMOOTHING.mul_add(outcome - self.rating, self.rating);
+    }
+
+    /// Returns the multiplier of the patience of Baobei, above 1 for a
+    /// struggling player and under 1 for a player doing well.
+    pub fn patience_multiplier(&self) -> f32 {
+        ADAPTIVE_PATIENCE_RANGE.mul_add(0.5 - self.rating, 1.0)
+    }
+
+    /// Returns the number of items asked at the same time, one more or one
+    /// less than the given number depending on the rating, at least one.
+    pub fn simultaneous_requests(&self, requests: usize) -> usize {
+        if self.rating > ADAPTIVE_PRESSURE_RATING {
+            requests + 1
+        } else if self.rating < ADAPTIVE_SLACK_RATING {
+            requests.saturating_sub(1).max(1)
+        } else {
+            requests
+        }
+    }
+}
+
+/// Rates the items delivered to the askers, right or wrong.
+fn rate_deliveries_system(
+    mut adaptive: ResMut<AdaptiveDifficulty>,
+    mut delivery_events: EventReader<DeliveryEvent>,
+    mut wrong_delivery_events: EventReader<WrongDeliveryEvent>,
+) {
+    for _ in delivery_events.iter() {
+        adaptive.record(true);
+    }
+    for _ in wrong_delivery_events.iter() {
+        adaptive.record(false);
+    }
+}
+
+/// Forgets the performance of the last game for the new game.
+fn reset_adaptive_difficulty_system(mut adaptive: ResMut<AdaptiveDifficulty>) {
+    *adaptive = AdaptiveDifficulty::default();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn difficulty_adapts_to_the_performance() {
+        let mut adaptive = AdaptiveDifficulty::default();
+        assert!((adaptive.patience_multiplier() - 1.0).abs() < f32::EPSILON);
+        assert_eq!(adaptive.simultaneous_requests(2), 2);
+
+        for _ in 0..10 {
+            adaptive.record(true);
+        }
+        assert!(adaptive.patience_multiplier() < 1.0);
+        assert_eq!(adaptive.simultaneous_requests(2), 3);
+
+        for _ in 0..10 {
+            adaptive.record(false);
+        }
+        assert!(adaptive.patience_multiplier() > 1.0);
+        assert_eq!(adaptive.simultaneous_requests(2), 1);
+        assert_eq!(adaptive.simultaneous_requests(1), 1);
+    }
+}
@@ -0,0 +1,230 @@
+//! Long-term affection of Baobei, growing with good runs and unlocking
+//! decorations in the apartment.
+
+use bevy::prelude::*;
+
+use crate::{
+    collisions::Position,
+    constants::{GameState, AFFECTION_PERIOD, GOOD_RUN_HAPPINESS, IN_LAWS_AFFECTION_BONUS},
+    save::Profile,
+};
+
+use super::{happiness::Happiness, in_laws::BossRoundEvent, Baobei};
+
+/// Plugin managing the affection meta progression.
+pub struct AffectionPlugin;
+
+impl Plugin for AffectionPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Affection>()
+            .init_resource::<DecorationMaterials>()
+            .init_resource::<RunHappiness>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::InGame)
+                    .with_system(start_run_system.system())
+                    .with_system(spawn_decorations_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(track_run_happiness_system.system())
+                    .with_system(in_laws_bonus_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::InGame).with_system(end_run_system.system()),
+            );
+    }
+}
+
+/// Save file storing the progression of the profile.
+const PROGRESSION_FILE: &str = "progression.sav";
+
+/// Long-term affection of Baobei for Didi, kept across runs.
+pub struct Affection {
+    /// Affection points earned during all the runs of the profile.
+    pub points: u32,
+}
+
+impl FromWorld for Affection {
+    fn from_world(world: &mut World) -> Self {
+        let profile = world.get_resource::<Profile>().unwrap();
+
+        Self {
+            points: profile.load(PROGRESSION_FILE).get("affection").unwrap_or(0),
+        }
+    }
+}
+
+impl Affection {
+    /// Adds affection points and saves them in the profile.
+    pub fn gain(&mut self, points: u32, profile: &Profile) {
+        self.points += points;
+        info!("Affection +{} => {}", points, self.points);
+
+        let mut progression = profile.load(PROGRESSION_FILE);
+        progression.set("affection", self.points);
+        profile.store(PROGRESSION_FILE, &progression);
+    }
+
+    /// Returns the decorations unlocked with the current affection.
+    pub fn unlocked_decorations(&self) -> impl Iterator<Item = Decoration> + '_ {
+        DECORATION_UNLOCKS
+            .iter()
+            .filter(move |(required_points, _)| self.points >= *required_points)
+            .map(|(_, decoration)| *decoration)
+    }
+}
+
+/// Cosmetic decoration of the apartment unlocked with affection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decoration {
+    /// Cushions on the couch
+    CouchCushions,
+    /// Photo of the couple on the wall
+    CouplePhoto,
+    /// Photo of a travel on the wall
+    TravelPhoto,
+    /// Photo of the wedding on the wall
+    WeddingPhoto,
+}
+
+/// Affection points needed to unlock each decoration.
+const DECORATION_UNLOCKS: [(u32, Decoration); 4] = [
+    (5, Decoration::CouchCushions),
+    (15, Decoration::CouplePhoto),
+    (30, Decoration::TravelPhoto),
+    (60, Decoration::WeddingPhoto),
+];
+
+impl Decoration {
+    /// Returns the position and the size of the decoration in the apartment.
+    fn placement(self) -> (Vec3, Vec2) {
+        match self {
+            Self::CouchCushions => (Vec3::new(1000.0, 140.0, 30.0), Vec2::new(180.0, 30.0)),
+            Self::CouplePhoto => (Vec3::new(520.0, 640.0, 0.0), Vec2::new(50.0, 60.0)),
+            Self::TravelPhoto => (Vec3::new(880.0, 650.0, 0.0), Vec2::new(70.0, 50.0)),
+            Self::WeddingPhoto => (Vec3::new(1150.0, 640.0, 0.0), Vec2::new(60.0, 80.0)),
+        }
+    }
+}
+
+/// Colors of the decorations.
+struct DecorationMaterials {
+    /// Color of the couch cushions
+    cushions: Handle<ColorMaterial>,
+    /// Color of the photo frames
+    photo_frame: Handle<ColorMaterial>,
+}
+
+impl FromWorld for DecorationMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            cushions: materials.add(Color::rgb(0.85, 0.45, 0.5).into()),
+            photo_frame: materials.add(Color::rgb(0.55, 0.35, 0.2).into()),
+        }
+    }
+}
+
+impl DecorationMaterials {
+    /// Returns the material of the given decoration.
+    fn material_for(&self, decoration: Decoration) -> Handle<ColorMaterial> {
+        match decoration {
+            Decoration::CouchCushions => self.cushions.clone(),
+            Decoration::CouplePhoto | Decoration::TravelPhoto | Decoration::WeddingPhoto => {
+                self.photo_frame.clone()
+            }
+        }
+    }
+}
+
+/// Spawns the unlocked decorations that are not in the apartment yet.
+fn spawn_decorations_system(
+    mut commands: Commands,
+    affection: Res<Affection>,
+    materials: Res<DecorationMaterials>,
+    spawned_decorations: Query<&Decoration>,
+) {
+    let spawned: Vec<Decoration> = spawned_decorations.iter().copied().collect();
+
+    for decoration in affection
+        .unlocked_decorations()
+        .filter(|decoration| !spawned.contains(decoration))
+    {
+        let (position, size) = decoration.placement();
+
+        commands
+            .spawn()
+            .insert(decoration)
+            .insert(Position(position))
+            .insert_bundle(SpriteBundle {
+                material: materials.material_for(decoration),
+                sprite: Sprite::new(size),
+                ..SpriteBundle::default()
+            });
+    }
+}
+
+/// Happiness of Baobei accumulated during the current run.
+#[derive(Default)]
+struct RunHappiness {
+    /// Sum of the happiness weighted by the elapsed time.
+    accumulated: f32,
+    /// Duration of the run in seconds.
+    duration: f32,
+}
+
+impl RunHappiness {
+    /// Returns the affection points earned by the run, only good runs count.
+    // The accumulated happiness is positive and a run is far from `u32::MAX` periods
+    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+    fn affection_gained(&self) -> u32 {
+        if self.duration <= 0.0 || self.accumulated / self.duration < GOOD_RUN_HAPPINESS {
+            return 0;
+        }
+        (self.accumulated / AFFECTION_PERIOD) as u32
+    }
+}
+
+/// Resets the happiness of the run when the game starts.
+fn start_run_system(mut run: ResMut<RunHappiness>) {
+    *run = RunHappiness::default();
+}
+
+/// Accumulates the mean happiness of Baobei during the run.
+fn track_run_happiness_system(
+    time: Res<Time>,
+    mut run: ResMut<RunHappiness>,
+    happiness_values: Query<&Happiness, With<Baobei>>,
+) {
+    let (sum, count) = happiness_values
+        .iter()
+        .fold((0.0, 0), |(sum, count), happiness| {
+            (sum + happiness.value(), count + 1)
+        });
+
+    if count > 0 {
+        run.accumulated += sum / count as f32 * time.delta_seconds();
+    }
+    run.duration += time.delta_seconds();
+}
+
+/// Adds the affection gained by the run and saves it in the profile.
+fn end_run_system(profile: Res<Profile>, run: Res<RunHappiness>, mut affection: ResMut<Affection>) {
+    let gained = run.affection_gained();
+    if gained > 0 {
+        affection.gain(gained, &profile);
+    }
+}
+
+/// Gives a big affection bonus when Baobei stayed happy during the visit of
+/// the in-laws.
+fn in_laws_bonus_system(
+    profile: Res<Profile>,
+    mut boss_round_events: EventReader<BossRoundEvent>,
+    mut affection: ResMut<Affection>,
+) {
+    for _ in boss_round_events.iter().filter(|event| event.survived) {
+        affection.gain(IN_LAWS_AFFECTION_BONUS, &profile);
+    }
+}
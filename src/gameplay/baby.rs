@@ -0,0 +1,246 @@
+//! Baby: a second dependent sleeping in the crib, whose crying meter fills
+//! over time. Didi rocks the crib by holding the action key next to it, and
+//! while the baby cries the needs of Baobei decay faster.
+
+use bevy::{math::const_vec3, prelude::*};
+
+use crate::{
+    collisions::{BoxCollider, Contact, Position, TriggerArea},
+    constants::{
+        GameState, BABY_CRYING_DECAY_MULTIPLIER, BABY_CRYING_RATE, BABY_CRYING_THRESHOLD,
+        BABY_ROCKING_RATE,
+    },
+    locale::Language,
+    settings::Settings,
+    time_scale::TimeScale,
+    widgets::{entity_timer_system, spawn_timer_bar, EntityTimer, Progress, WidgetMaterials},
+};
+
+use super::{
+    bubbles::SayEvent,
+    entities::GameData,
+    interactables::{InteractAction, Interactable},
+    items::PickAndDropCooldown,
+    phases::PhaseController,
+    NewRunAppExt,
+};
+
+/// Plugin managing the baby and the crib.
+pub struct BabyPlugin;
+
+impl Plugin for BabyPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<BabyMaterials>()
+            .add_startup_system(spawn_crib.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(rock_system.system().label("rock").before("item_actions"))
+                    .with_system(crying_system.system().label("crying").after("rock"))
+                    .with_system(entity_timer_system::<Baby>.system().after("crying")),
+            )
+            .add_new_run_system(reset_baby_system);
+    }
+}
+
+/// Where the crib stands, next to the table.
+const CRIB_POSITION: Vec3 = const_vec3!([560.0, 175.0, 0.0]);
+
+/// Component on the baby lying in the crib.
+#[derive(Debug, Default)]
+pub struct Baby {
+    /// Crying meter, from 0 (asleep) to 1
+    crying: f32,
+}
+
+impl Baby {
+    /// Returns true while the baby cries.
+    pub fn is_crying(&self) -> bool {
+        self.crying >= BABY_CRYING_THRESHOLD
+    }
+
+    /// Returns the multiplier of the decay of the needs of Baobei.
+    pub fn decay_multiplier(&self) -> f32 {
+        if self.is_crying() {
+            BABY_CRYING_DECAY_MULTIPLIER
+        } else {
+            1.0
+        }
+    }
+
+    /// Fills the crying meter over the seconds, or empties it while rocked.
+    /// Returns true if the baby starts crying.
+    fn advance(&mut self, seconds: f32, rocked: bool) -> bool {
+        let was_crying = self.is_crying();
+        let change = if rocked {
+            -BABY_ROCKING_RATE
+        } else {
+            BABY_CRYING_RATE
+        };
+        self.crying = change.mul_add(seconds, self.crying).clamp(0.0, 1.0);
+        !was_crying && self.is_crying()
+    }
+}
+
+impl Progress for Baby {
+    fn remaining(&self) -> f32 {
+        1.0 - self.crying
+    }
+}
+
+/// Component on the crib, rocked while Didi holds the action key next to it.
+#[derive(Debug, Default)]
+struct Crib {
+    /// Whether Didi rocks the crib
+    rocked: bool,
+}
+
+/// Colors of the crib and the baby.
+struct BabyMaterials {
+    /// Wooden crib
+    crib: Handle<ColorMaterial>,
+    /// Baby in its blanket
+    baby: Handle<ColorMaterial>,
+}
+
+impl FromWorld for BabyMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            crib: materials.add(Color::rgb(0.75, 0.6, 0.45).into()),
+            baby: materials.add(Color::rgb(0.7, 0.85, 1.0).into()),
+        }
+    }
+}
+
+/// Returns what the baby says when starting to cry.
+const fn crying_line(language: Language) -> &'static str {
+    match language {
+        Language::English => "Waaah!",
+        Language::French => "Ouin !",
+    }
+}
+
+/// Spawns the crib with the baby and the bar of its crying meter.
+fn spawn_crib(
+    mut commands: Commands,
+    materials: Res<BabyMaterials>,
+    widget_materials: Res<WidgetMaterials>,
+) {
+    let size = Vec2::new(100.0, 60.0);
+
+    let baby = commands
+        .spawn()
+        .insert(Baby::default())
+        .insert_bundle(SpriteBundle {
+            material: materials.baby.clone(),
+            sprite: Sprite::new(Vec2::new(40.0, 30.0)),
+            transform: Transform::from_xyz(0.0, 0.0, 0.1),
+            ..SpriteBundle::default()
+        })
+        .id();
+
+    let crying_bar = spawn_timer_bar(
+        &mut commands,
+        &widget_materials,
+        Vec3::new(0.0, 50.0, 0.1),
+        Vec2::new(90.0, 10.0),
+    );
+    commands
+        .entity(crying_bar)
+        .insert(EntityTimer::<Baby>::new(baby));
+
+    commands
+        .spawn()
+        .insert(Crib::default())
+        .insert(Interactable(InteractAction::Rock))
+        .insert(Position(CRIB_POSITION))
+        .insert(BoxCollider::new(size.x, size.y))
+        .insert(TriggerArea::new(size.x + 60.0, size.y + 60.0))
+        .insert_bundle(SpriteBundle {
+            material: materials.crib.clone(),
+            sprite: Sprite::new(size),
+            ..SpriteBundle::default()
+        })
+        .push_children(&[baby, crying_bar]);
+}
+
+/// Rocks the crib while Didi holds `Space` next to it, keeping the items
+/// from being dropped meanwhile.
+fn rock_system(
+    keyboard: Res<Input<KeyCode>>,
+    game_data: Res<GameData>,
+    mut cooldown: ResMut<PickAndDropCooldown>,
+    contacts: Query<&Contact>,
+    mut cribs: Query<(Entity, &mut Crib)>,
+) {
+    let holding = keyboard.pressed(KeyCode::Space);
+
+    for (entity, mut crib) in cribs.iter_mut() {
+        let rocked = holding
+            && contacts
+                .iter()
+                .any(|contact| contact.0 == game_data.didi_entity && contact.1 == entity);
+        if crib.rocked != rocked {
+            crib.rocked = rocked;
+        }
+        if rocked {
+            cooldown.0.start();
+        }
+    }
+}
+
+/// Fills the crying meter of the baby, except when Baobei naps, and empties
+/// it while the crib is rocked. The baby calls out when starting to cry.
+fn crying_system(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    settings: Res<Settings>,
+    phases: Res<PhaseController>,
+    mut say_events: EventWriter<SayEvent>,
+    cribs: Query<&Crib>,
+    mut babies: Query<(&mut Baby, &Parent)>,
+) {
+    let seconds = time_scale.scale(time.delta()).as_secs_f32();
+
+    for (mut baby, parent) in babies.iter_mut() {
+        let rocked = cribs.get(parent.0).map_or(false, |crib| crib.rocked);
+        if phases.is_breather() && !rocked {
+            continue;
+        }
+        if baby.advance(seconds, rocked) {
+            info!("The baby cries");
+            say_events.send(SayEvent {
+                speaker: parent.0,
+                line: crying_line(settings.language),
+            });
+        }
+    }
+}
+
+/// Puts the baby back to sleep for the new game.
+fn reset_baby_system(mut babies: Query<&mut Baby>) {
+    for mut baby in babies.iter_mut() {
+        *baby = Baby::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn baby_cries_until_rocked() {
+        let mut baby = Baby::default();
+        let seconds_to_cry = BABY_CRYING_THRESHOLD / BABY_CRYING_RATE;
+
+        assert!(!baby.advance(seconds_to_cry * 0.9, false));
+        assert!((baby.decay_multiplier() - 1.0).abs() < f32::EPSILON);
+        assert!(baby.advance(seconds_to_cry * 0.2, false));
+        assert!(baby.is_crying());
+
+        baby.advance(10.0, true);
+        assert!(!baby.is_crying());
+        assert!((baby.remaining() - 1.0).abs() < f32::EPSILON);
+    }
+}
@@ -0,0 +1,404 @@
+//! Bonus round: from time to time the game stops for a rhythm minigame,
+//! where the notes falling on the beat are played with the arrow keys when
+//! they reach the line. Each note hit earns bonus points.
+//!
+//! The round is pushed on top of the game state, so the game is suspended
+//! until the last note falls.
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    constants::{GameState, BONUS_ROUND_BEAT, BONUS_ROUND_INTERVAL, BONUS_ROUND_NOTES},
+    controllers::MoveDirection,
+    rng::GameRng,
+    scheduler::{ScheduledEvent, Scheduler, SchedulerSystems},
+};
+
+use super::{score::Score, NewRunAppExt};
+
+/// Plugin managing the bonus rounds.
+pub struct BonusRoundPlugin;
+
+impl Plugin for BonusRoundPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<BonusRoundMaterials>()
+            .add_startup_system(schedule_bonus_rounds.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(start_bonus_round_system.system().after(SchedulerSystems)),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::BonusRound).with_system(setup_bonus_round.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::BonusRound)
+                    .with_system(play_notes_system.system().label("play_notes"))
+                    .with_system(update_notes_system.system().after("play_notes"))
+                    .with_system(end_bonus_round_system.system().after("play_notes")),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::BonusRound).with_system(cleanup_bonus_round.system()),
+            )
+            .add_new_run_system(schedule_bonus_rounds);
+    }
+}
+
+/// Scheduled task starting the next bonus round.
+const BONUS_ROUND_TASK: &str = "bonus_round";
+/// Beats before the first note reaches the line.
+const LEAD_IN_BEATS: usize = 4;
+/// Seconds before or after its time a note can still be hit.
+const HIT_WINDOW: f32 = 0.15;
+/// Speed of the falling notes, in pixels per second.
+const FALL_SPEED: f32 = 300.0;
+/// Distance of the line from the top of the screen, in pixels.
+const LINE_TOP: f32 = 560.0;
+/// Size of a note, in pixels.
+const NOTE_SIZE: f32 = 60.0;
+
+/// A note of the round, to play when it reaches the line.
+struct Note {
+    /// Seconds from the start of the round when the note reaches the line
+    time: f32,
+    /// Arrow playing the note
+    direction: MoveDirection,
+    /// Whether the note was hit, once played or missed
+    hit: Option<bool>,
+}
+
+/// Notes of the bonus round being played.
+pub struct RhythmRound {
+    /// Notes in the order they fall
+    notes: Vec<Note>,
+    /// Seconds since the start of the round
+    elapsed: f32,
+}
+
+impl RhythmRound {
+    /// Rolls the directions of the notes, one on each beat.
+    pub fn new<R: Rng + ?Sized>(rng: &mut R, note_count: usize) -> Self {
+        let notes = (0..note_count)
+            .map(|index| Note {
+                time: (LEAD_IN_BEATS + index) as f32 * BONUS_ROUND_BEAT,
+                direction: MoveDirection::ALL[rng.gen_range(0..MoveDirection::ALL.len())],
+                hit: None,
+            })
+            .collect();
+
+        Self {
+            notes,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Advances the round, the notes too far below the line being missed.
+    pub fn advance(&mut self, seconds: f32) {
+        self.elapsed += seconds;
+        let elapsed = self.elapsed;
+
+        for note in &mut self.notes {
+            if note.hit.is_none() && note.time + HIT_WINDOW < elapsed {
+                note.hit = Some(false);
+            }
+        }
+    }
+
+    /// Plays the arrow, hitting the first note of the direction on the line.
+    /// Returns true if a note is hit.
+    pub fn play(&mut self, direction: MoveDirection) -> bool {
+        let elapsed = self.elapsed;
+        let note = self.notes.iter_mut().find(|note| {
+            note.hit.is_none()
+                && note.direction == direction
+                && (note.time - elapsed).abs() <= HIT_WINDOW
+        });
+
+        match note {
+            Some(note) => {
+                note.hit = Some(true);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the number of notes hit.
+    // A round has a few notes
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn hits(&self) -> u32 {
+        self.notes
+            .iter()
+            .filter(|note| note.hit == Some(true))
+            .count() as u32
+    }
+
+    /// Returns true once all the notes are played or missed.
+    pub fn is_over(&self) -> bool {
+        self.notes.iter().all(|note| note.hit.is_some())
+    }
+}
+
+/// Colors of the bonus round.
+struct BonusRoundMaterials {
+    /// Darkened game behind the round
+    overlay: Handle<ColorMaterial>,
+    /// Line where the notes are played
+    line: Handle<ColorMaterial>,
+    /// Notes of each direction
+    notes: Vec<(MoveDirection, Handle<ColorMaterial>)>,
+}
+
+impl FromWorld for BonusRoundMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+        let colors = [Color::GOLD, Color::CYAN, Color::PINK, Color::LIME_GREEN];
+
+        Self {
+            overlay: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.7).into()),
+            line: materials.add(Color::WHITE.into()),
+            notes: MoveDirection::ALL
+                .iter()
+                .zip(colors.iter())
+                .map(|(direction, color)| (*direction, materials.add((*color).into())))
+                .collect(),
+        }
+    }
+}
+
+/// Stores entities of the bonus round.
+struct BonusRoundData {
+    /// Entity wrapping all the entities of the round
+    node_wrapper: Entity,
+}
+
+/// Component on the node of a note, with its index in the round.
+struct NoteNode(usize);
+
+/// Tag the text counting the notes hit.
+struct HitsText;
+
+/// Returns the distance from the left of the screen of the column of the
+/// direction, in pixels.
+fn column_left(direction: MoveDirection) -> f32 {
+    let column = MoveDirection::ALL
+        .iter()
+        .position(|other| *other == direction)
+        .unwrap_or_default();
+    (column as f32).mul_add(100.0, 460.0)
+}
+
+/// Schedules the bonus rounds.
+fn schedule_bonus_rounds(mut scheduler: ResMut<Scheduler>) {
+    scheduler.every(BONUS_ROUND_TASK, BONUS_ROUND_INTERVAL);
+}
+
+/// Suspends the game for a bonus round when it is due.
+fn start_bonus_round_system(
+    mut scheduled_events: EventReader<ScheduledEvent>,
+    mut state: ResMut<State<GameState>>,
+) {
+    let due = scheduled_events
+        .iter()
+        .any(|ScheduledEvent(task)| *task == BONUS_ROUND_TASK);
+
+    if due {
+        state.push(GameState::BonusRound).unwrap();
+    }
+}
+
+/// Rolls the notes and shows the round over the game.
+fn setup_bonus_round(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    materials: Res<BonusRoundMaterials>,
+    mut rng: ResMut<GameRng>,
+) {
+    let round = RhythmRound::new(&mut rng.rng, BONUS_ROUND_NOTES);
+    let font = asset_server.load("FiraSans-Bold.ttf");
+    let text = |value: &str, font_size: f32, top: f32, left: f32| TextBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect {
+                top: Val::Px(top),
+                left: Val::Px(left),
+                ..Rect::default()
+            },
+            ..Style::default()
+        },
+        text: Text::with_section(
+            value,
+            TextStyle {
+                font: font.clone(),
+                font_size,
+                color: Color::WHITE,
+            },
+            TextAlignment::default(),
+        ),
+        ..TextBundle::default()
+    };
+    let absolute = |top: f32, left: f32, width: f32, height: f32| Style {
+        position_type: PositionType::Absolute,
+        position: Rect {
+            top: Val::Px(top),
+            left: Val::Px(left),
+            ..Rect::default()
+        },
+        size: Size::new(Val::Px(width), Val::Px(height)),
+        ..Style::default()
+    };
+
+    let node_wrapper = commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                ..Style::default()
+            },
+            material: materials.overlay.clone(),
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            parent.spawn().insert_bundle(NodeBundle {
+                style: absolute(LINE_TOP + NOTE_SIZE / 2.0, 440.0, 400.0, 4.0),
+                material: materials.line.clone(),
+                ..NodeBundle::default()
+            });
+            for (index, note) in round.notes.iter().enumerate() {
+                let material = materials
+                    .notes
+                    .iter()
+                    .find(|(direction, _)| *direction == note.direction)
+                    .map(|(_, material)| material.clone())
+                    .unwrap_or_default();
+
+                parent
+                    .spawn()
+                    .insert(NoteNode(index))
+                    .insert_bundle(NodeBundle {
+                        style: absolute(
+                            -NOTE_SIZE,
+                            column_left(note.direction),
+                            NOTE_SIZE,
+                            NOTE_SIZE,
+                        ),
+                        material,
+                        visible: Visible {
+                            is_visible: false,
+                            is_transparent: true,
+                        },
+                        ..NodeBundle::default()
+                    });
+            }
+            for direction in MoveDirection::ALL {
+                parent.spawn().insert_bundle(text(
+                    &format!("{:?}", direction),
+                    25.0,
+                    LINE_TOP + NOTE_SIZE + 20.0,
+                    column_left(direction) + 5.0,
+                ));
+            }
+            parent.spawn().insert_bundle(text(
+                "Bonus round! Press the arrows on the beat",
+                40.0,
+                40.0,
+                320.0,
+            ));
+            parent
+                .spawn()
+                .insert(HitsText)
+                .insert_bundle(text("", 30.0, 100.0, 560.0));
+        })
+        .id();
+
+    commands.insert_resource(round);
+    commands.insert_resource(BonusRoundData { node_wrapper });
+}
+
+/// Advances the round and plays the arrows pressed.
+fn play_notes_system(
+    time: Res<Time>,
+    keyboard: Res<Input<KeyCode>>,
+    mut round: ResMut<RhythmRound>,
+) {
+    round.advance(time.delta_seconds());
+
+    for direction in MoveDirection::ALL {
+        if keyboard.just_pressed(direction.arrow_key()) {
+            round.play(direction);
+        }
+    }
+}
+
+/// Makes the notes fall towards the line and hides the played ones.
+fn update_notes_system(
+    round: Res<RhythmRound>,
+    mut notes: Query<(&NoteNode, &mut Style, &mut Visible)>,
+    mut texts: Query<&mut Text, With<HitsText>>,
+) {
+    for (node, mut style, mut visible) in notes.iter_mut() {
+        let note = &round.notes[node.0];
+        let top = (note.time - round.elapsed).mul_add(-FALL_SPEED, LINE_TOP);
+        let shown = note.hit != Some(true) && top > -NOTE_SIZE && top < LINE_TOP + NOTE_SIZE;
+
+        if visible.is_visible != shown {
+            visible.is_visible = shown;
+        }
+        if shown {
+            style.position.top = Val::Px(top);
+        }
+    }
+
+    let hits = format!("Hits: {} / {}", round.hits(), round.notes.len());
+    for mut text in texts.iter_mut() {
+        if text.sections[0].value != hits {
+            text.sections[0].value = hits.clone();
+        }
+    }
+}
+
+/// Gives the bonus points and goes back to the game after the last note.
+fn end_bonus_round_system(
+    round: Res<RhythmRound>,
+    mut score: ResMut<Score>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if !round.is_over() {
+        return;
+    }
+    info!("Bonus round over: {} notes hit", round.hits());
+    score.reward_bonus_notes(round.hits());
+    state.pop().unwrap();
+}
+
+/// Removes all entities of the bonus round.
+fn cleanup_bonus_round(mut commands: Commands, round_data: Res<BonusRoundData>) {
+    commands.entity(round_data.node_wrapper).despawn_recursive();
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn notes_are_hit_on_the_line() {
+        let mut round = RhythmRound::new(&mut StdRng::seed_from_u64(42), 2);
+        let first = round.notes[0].direction;
+        let time = round.notes[0].time;
+
+        // Too early
+        round.advance(time - 2.0 * HIT_WINDOW);
+        assert!(!round.play(first));
+
+        round.advance(HIT_WINDOW * 1.5);
+        assert!(round.play(first));
+        assert!(!round.is_over());
+
+        // The second note falls without being played
+        round.advance(BONUS_ROUND_BEAT + 2.0 * HIT_WINDOW);
+        assert!(round.is_over());
+        assert_eq!(round.hits(), 1);
+    }
+}
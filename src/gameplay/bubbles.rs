@@ -0,0 +1,302 @@
+//! Speech bubbles where Baobei complains when getting impatient or says the
+//! lines of other systems, the text being revealed like a typewriter.
+
+use bevy::prelude::*;
+
+use crate::{
+    collisions::Position,
+    constants::GameState,
+    drawing::Overlay,
+    locale::{Language, Verbosity},
+    pool::Pool,
+    settings::Settings,
+};
+
+use super::{
+    happiness::Happiness,
+    items::{Item, ItemRequestQueue},
+    Baobei,
+};
+
+/// Plugin managing the speech bubbles.
+pub struct BubblesPlugin;
+
+impl Plugin for BubblesPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<SayEvent>()
+            .init_resource::<BubbleAssets>()
+            .init_resource::<SilenceDuration>()
+            .init_resource::<Pool<SpeechBubble>>()
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(speak_system.system())
+                    .with_system(say_system.system())
+                    .with_system(speech_bubble_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::InGame).with_system(remove_bubbles_system.system()),
+            );
+    }
+}
+
+/// Happiness under which Baobei complains.
+const IMPATIENT_HAPPINESS: f32 = 0.35; // 35%
+/// Seconds between two revealed characters.
+const TYPEWRITER_DELAY: f32 = 0.05;
+/// Seconds a bubble stays after its text is fully revealed.
+const READING_DURATION: f32 = 2.0;
+/// Height of the bubble above the head of the speaker.
+const BUBBLE_HEIGHT: f32 = 150.0;
+
+/// Returns what Baobei says to ask for the item.
+const fn complaint(language: Language, verbosity: Verbosity, item: Item) -> Option<&'static str> {
+    let line = match (language, verbosity, item) {
+        (_, Verbosity::Silent, _)
+        | (
+            _,
+            _,
+            Item::LaundryBasket
+            | Item::Tray
+            | Item::TeaLeaves
+            | Item::DirtyDish
+            | Item::Mop
+            | Item::Custom(_),
+        ) => return None,
+        (Language::English, Verbosity::Short, Item::IceCream) => "Ice cream…",
+        (Language::English, Verbosity::Short, Item::WaterGlass) => "Thirsty…",
+        (Language::English, Verbosity::Short, Item::Chips) => "Hungry…",
+        (Language::English, Verbosity::Full, Item::IceCream) => "I'm craving an ice cream…",
+        (Language::English, Verbosity::Full, Item::WaterGlass) => "I'm thirsty… some water?",
+        (Language::English, Verbosity::Full, Item::Chips) => "I'm hungry… where are my chips?",
+        (Language::English, Verbosity::Short, Item::Coffee) => "Coffee…",
+        (Language::English, Verbosity::Full, Item::Coffee) => "I could use a coffee…",
+        (Language::English, Verbosity::Short, Item::HotTea) => "Tea…",
+        (Language::English, Verbosity::Full, Item::HotTea) => "A hot tea would be perfect…",
+        (Language::French, Verbosity::Short, Item::IceCream) => "Une glace…",
+        (Language::French, Verbosity::Short, Item::WaterGlass) => "Soif…",
+        (Language::French, Verbosity::Short, Item::Chips) => "Faim…",
+        (Language::French, Verbosity::Full, Item::IceCream) => "J'ai envie d'une glace…",
+        (Language::French, Verbosity::Full, Item::WaterGlass) => "J'ai soif… un verre d'eau ?",
+        (Language::French, Verbosity::Full, Item::Chips) => "J'ai faim… où sont mes chips ?",
+        (Language::French, Verbosity::Short, Item::Coffee) => "Un café…",
+        (Language::French, Verbosity::Full, Item::Coffee) => "J'ai besoin d'un café…",
+        (Language::French, Verbosity::Short, Item::HotTea) => "Un thé…",
+        (Language::French, Verbosity::Full, Item::HotTea) => {
+            "Un thé bien chaud, ce serait parfait…"
+        }
+    };
+    Some(line)
+}
+
+/// Event making a character say a line in a speech bubble.
+pub struct SayEvent {
+    /// Entity talking
+    pub speaker: Entity,
+    /// Line said
+    pub line: &'static str,
+}
+
+/// Font and colors of the bubbles.
+struct BubbleAssets {
+    /// Font of the text
+    font: Handle<Font>,
+    /// Color of the bubble
+    paper: Handle<ColorMaterial>,
+}
+
+impl FromWorld for BubbleAssets {
+    fn from_world(world: &mut World) -> Self {
+        let font = world
+            .get_resource::<AssetServer>()
+            .unwrap()
+            .load("FiraSans-Bold.ttf");
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            font,
+            paper: materials.add(Color::rgba(1.0, 1.0, 1.0, 0.9).into()),
+        }
+    }
+}
+
+/// Seconds since the last speech bubble.
+#[derive(Default)]
+struct SilenceDuration(f32);
+
+/// Component on a speech bubble.
+struct SpeechBubble {
+    /// Entity talking
+    speaker: Entity,
+    /// Whole text of the bubble
+    line: &'static str,
+    /// Timer revealing the characters one by one
+    typewriter: Timer,
+    /// Number of characters revealed
+    revealed: usize,
+    /// Timer until the bubble disappears
+    lifetime: Timer,
+}
+
+/// Makes Baobei talk when getting impatient, not too often.
+fn speak_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    assets: Res<BubbleAssets>,
+    mut silence: ResMut<SilenceDuration>,
+    mut pool: ResMut<Pool<SpeechBubble>>,
+    speakers: Query<(Entity, &Happiness, &ItemRequestQueue), With<Baobei>>,
+    bubbles: Query<(), With<SpeechBubble>>,
+) {
+    silence.0 += time.delta_seconds();
+    if silence.0 < settings.bubble_interval || bubbles.iter().next().is_some() {
+        return;
+    }
+
+    let impatient_speaker = speakers
+        .iter()
+        .find(|(_, happiness, _)| happiness.value() < IMPATIENT_HAPPINESS);
+    let (speaker, line) = match impatient_speaker {
+        Some((speaker, _, requests)) => {
+            let line = requests
+                .front()
+                .and_then(|item| complaint(settings.language, settings.bubble_verbosity, item));
+            match line {
+                Some(line) => (speaker, line),
+                None => return,
+            }
+        }
+        None => return,
+    };
+    silence.0 = 0.0;
+    spawn_bubble(&mut commands, &assets, &mut pool, speaker, line);
+}
+
+/// Shows the lines said by other systems, replacing the current bubble of
+/// the speaker.
+fn say_system(
+    mut commands: Commands,
+    assets: Res<BubbleAssets>,
+    mut silence: ResMut<SilenceDuration>,
+    mut pool: ResMut<Pool<SpeechBubble>>,
+    mut say_events: EventReader<SayEvent>,
+    bubbles: Query<(Entity, &SpeechBubble)>,
+) {
+    for event in say_events.iter() {
+        for (entity, bubble) in bubbles.iter() {
+            if bubble.speaker == event.speaker {
+                pool.release(&mut commands, entity);
+            }
+        }
+        silence.0 = 0.0;
+        spawn_bubble(&mut commands, &assets, &mut pool, event.speaker, event.line);
+    }
+}
+
+/// Spawns a bubble above the speaker revealing the line, reusing a bubble of
+/// the pool if possible.
+fn spawn_bubble(
+    commands: &mut Commands,
+    assets: &BubbleAssets,
+    pool: &mut Pool<SpeechBubble>,
+    speaker: Entity,
+    line: &'static str,
+) {
+    let char_count = line.chars().count() as f32;
+    let lifetime = char_count.mul_add(TYPEWRITER_DELAY, READING_DURATION);
+    let (entity, recycled) = pool.acquire(commands);
+
+    let mut bubble = commands.entity(entity);
+    bubble
+        .insert(SpeechBubble {
+            speaker,
+            line,
+            typewriter: Timer::from_seconds(TYPEWRITER_DELAY, true),
+            revealed: 0,
+            lifetime: Timer::from_seconds(lifetime, false),
+        })
+        .insert(Overlay)
+        .insert(Position::default())
+        .insert_bundle(SpriteBundle {
+            material: assets.paper.clone(),
+            sprite: Sprite::new(Vec2::new(char_count.mul_add(11.0, 30.0), 40.0)),
+            ..SpriteBundle::default()
+        });
+
+    if recycled {
+        return; // The text is already a child of the bubble
+    }
+    bubble.with_children(|parent| {
+        parent.spawn().insert_bundle(Text2dBundle {
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: assets.font.clone(),
+                    font_size: 20.0,
+                    color: Color::BLACK,
+                },
+                TextAlignment {
+                    vertical: VerticalAlign::Center,
+                    horizontal: HorizontalAlign::Center,
+                },
+            ),
+            transform: Transform::from_xyz(0.0, 0.0, 0.1),
+            ..Text2dBundle::default()
+        });
+    });
+}
+
+/// Follows the speaker, reveals the text and removes the bubble once read.
+fn speech_bubble_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut pool: ResMut<Pool<SpeechBubble>>,
+    mut bubbles: Query<(Entity, &mut SpeechBubble, &mut Position, &Children)>,
+    speakers: Query<&Position, Without<SpeechBubble>>,
+    mut texts: Query<&mut Text>,
+) {
+    for (entity, mut bubble, mut position, children) in bubbles.iter_mut() {
+        let read = bubble.lifetime.tick(time.delta()).finished();
+        let speaker_position = match speakers.get(bubble.speaker) {
+            Ok(speaker_position) if !read => speaker_position.0,
+            _ => {
+                pool.release(&mut commands, entity);
+                continue;
+            }
+        };
+
+        let bubble_position = Vec3::new(
+            speaker_position.x,
+            speaker_position.y + speaker_position.z + BUBBLE_HEIGHT,
+            0.0,
+        );
+        if position.0 != bubble_position {
+            position.0 = bubble_position;
+        }
+
+        if bubble.typewriter.tick(time.delta()).just_finished() {
+            bubble.revealed += bubble.typewriter.times_finished() as usize;
+        }
+
+        // A recycled bubble still shows the previous line until replaced
+        let shown: String = bubble.line.chars().take(bubble.revealed).collect();
+        for &child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(child) {
+                if text.sections[0].value != shown {
+                    text.sections[0].value = shown.clone();
+                }
+            }
+        }
+    }
+}
+
+/// Removes the bubbles when leaving the game.
+fn remove_bubbles_system(
+    mut commands: Commands,
+    mut pool: ResMut<Pool<SpeechBubble>>,
+    bubbles: Query<Entity, With<SpeechBubble>>,
+) {
+    for bubble in bubbles.iter() {
+        pool.release(&mut commands, bubble);
+    }
+}
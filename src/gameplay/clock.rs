@@ -0,0 +1,152 @@
+//! Day and night cycle: the apartment darkens in the evening, Baobei asks for
+//! fewer items during the night and gets sleepy.
+
+use bevy::prelude::*;
+
+use crate::{
+    constants::{GameState, DAY_CYCLE_DURATION},
+    drawing::Tint,
+    scheduler::{Scheduler, SchedulerSystems},
+    time_scale::TimeScale,
+};
+
+use super::{
+    routine::Routine,
+    status_effects::{StatusEffectKind, StatusEffects},
+    Baobei, NewRunAppExt,
+};
+
+/// Plugin managing the day and night cycle.
+pub struct ClockPlugin;
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<GameClock>()
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(clock_system.system().label("clock").after(SchedulerSystems))
+                    .with_system(night_tint_system.system().after("clock"))
+                    .with_system(
+                        sleepy_system
+                            .system()
+                            .after("clock")
+                            .after("status_effects"),
+                    ),
+            )
+            .add_new_run_system(reset_clock_system)
+            .add_system_set(
+                SystemSet::on_exit(GameState::InGame).with_system(clear_tint_system.system()),
+            );
+    }
+}
+
+/// Hour at which the games start outside of the story mode.
+const START_HOUR: f32 = 8.0;
+/// Hours of the dusk, from the start to the end of the sunset.
+const DUSK: (f32, f32) = (18.0, 21.0);
+/// Hours of the dawn, from the start to the end of the sunrise.
+const DAWN: (f32, f32) = (5.0, 7.0);
+/// Color of the scene in the middle of the night.
+const NIGHT_TINT: (f32, f32, f32, f32) = (0.05, 0.05, 0.25, 0.45);
+
+/// Hour of the day in the apartment, following the routine in the story mode.
+pub struct GameClock {
+    /// Hour of the day, from 0 to 24.
+    pub hour: f32,
+}
+
+impl Default for GameClock {
+    fn default() -> Self {
+        Self { hour: START_HOUR }
+    }
+}
+
+impl GameClock {
+    /// Advances the clock by the number of hours, going back to 0 at
+    /// midnight.
+    fn advance(&mut self, hours: f32) {
+        self.hour = (self.hour + hours).rem_euclid(24.0);
+    }
+
+    /// Returns how dark it is, from 0 during the day to 1 during the night.
+    pub fn darkness(&self) -> f32 {
+        let ramp = |(start, end): (f32, f32)| ((self.hour - start) / (end - start)).clamp(0.0, 1.0);
+        if self.hour >= DUSK.0 {
+            ramp(DUSK)
+        } else {
+            1.0 - ramp(DAWN)
+        }
+    }
+
+    /// Returns true between the end of the dusk and the start of the dawn.
+    pub fn is_night(&self) -> bool {
+        self.hour >= DUSK.1 || self.hour < DAWN.0
+    }
+}
+
+/// Advances the clock, or follows the one of the routine in the story mode.
+fn clock_system(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    routine: Res<Routine>,
+    scheduler: Res<Scheduler>,
+    mut clock: ResMut<GameClock>,
+) {
+    match routine.hour(&scheduler) {
+        Some(hour) => clock.hour = hour,
+        None => {
+            let seconds = time_scale.scale(time.delta()).as_secs_f32();
+            clock.advance(seconds * 24.0 / DAY_CYCLE_DURATION);
+        }
+    }
+}
+
+/// Darkens the scene with the night.
+fn night_tint_system(clock: Res<GameClock>, mut tint: ResMut<Tint>) {
+    let (red, green, blue, alpha) = NIGHT_TINT;
+    let color = Color::rgba(red, green, blue, alpha * clock.darkness());
+    if tint.0 != color {
+        tint.0 = color;
+    }
+}
+
+/// Makes Baobei sleepy during the night.
+fn sleepy_system(clock: Res<GameClock>, mut baobei: Query<&mut StatusEffects, With<Baobei>>) {
+    if !clock.is_night() {
+        return;
+    }
+    for mut status_effects in baobei.iter_mut() {
+        status_effects.apply(StatusEffectKind::Sleepy);
+    }
+}
+
+/// Sets the clock back to the morning when a new game starts.
+fn reset_clock_system(mut clock: ResMut<GameClock>) {
+    *clock = GameClock::default();
+}
+
+/// Lights the scene up again when leaving the game.
+fn clear_tint_system(mut tint: ResMut<Tint>) {
+    *tint = Tint::default();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn night_falls_after_the_dusk() {
+        let mut clock = GameClock::default();
+        assert!(clock.darkness() < f32::EPSILON);
+        assert!(!clock.is_night());
+
+        clock.advance(11.5); // 19:30
+        assert!((clock.darkness() - 0.5).abs() < f32::EPSILON);
+        assert!(!clock.is_night());
+
+        clock.advance(6.5); // 02:00
+        assert!((clock.hour - 2.0).abs() < f32::EPSILON);
+        assert!((clock.darkness() - 1.0).abs() < f32::EPSILON);
+        assert!(clock.is_night());
+    }
+}
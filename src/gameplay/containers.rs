@@ -0,0 +1,237 @@
+//! Containers carrying several items at once, like the tray bringing a
+//! whole meal to Baobei.
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    collisions::{BoxCollider, CircleCollider, CollisionSystems, Movement, Position},
+    constants::{GameState, TRAY_CAPACITY, TRAY_SPILL_CHANCE},
+    rng::GameRng,
+};
+
+use super::{
+    entities::GameData,
+    items::{spawn_item_on_ground, CarriedItem, Item},
+    materials::GameplayMaterials,
+    storage::Storage,
+    Didi,
+};
+
+/// Plugin managing the containers.
+pub struct ContainersPlugin;
+
+impl Plugin for ContainersPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::InGame)
+                .with_system(ensure_tray_system.system())
+                .with_system(add_containers_system.system())
+                .with_system(update_container_slots_system.system())
+                .with_system(
+                    spill_system
+                        .system()
+                        .after("movement")
+                        .before(CollisionSystems),
+                ),
+        );
+    }
+}
+
+/// Where the tray is put back when it got lost.
+const TRAY_HOME: (f32, f32) = (500.0, 470.0);
+/// Height between two items stacked on a container.
+const SLOT_HEIGHT: f32 = 90.0;
+
+/// Component on an item holding other items.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Container {
+    /// Items held, from the bottom to the top of the stack.
+    items: Vec<Item>,
+    /// Maximum number of items held.
+    capacity: usize,
+}
+
+impl Container {
+    /// Creates an empty container holding up to `capacity` items.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            items: Vec::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Returns true if the item can be put in the container.
+    pub fn accepts(&self, item: Item) -> bool {
+        item.is_food() && self.items.len() < self.capacity
+    }
+
+    /// Puts the item on top of the others, if accepted.
+    pub fn put(&mut self, item: Item) {
+        if self.accepts(item) {
+            self.items.push(item);
+        }
+    }
+
+    /// Takes the item out of the container, returns false if it is not held.
+    pub fn remove(&mut self, item: Item) -> bool {
+        match self.items.iter().rposition(|held| *held == item) {
+            Some(index) => {
+                self.items.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Takes the item on top of the stack.
+    pub fn pop(&mut self) -> Option<Item> {
+        self.items.pop()
+    }
+}
+
+/// Component on a mini-sprite showing an item held in the parent container.
+struct ContainerSlot(usize);
+
+/// Puts the tray back in the kitchen if it got lost, for instance with a new
+/// attempt of a seed race.
+fn ensure_tray_system(
+    mut commands: Commands,
+    materials: Res<GameplayMaterials>,
+    items: Query<&Item>,
+    storages: Query<&Storage>,
+) {
+    let tray_exists = items.iter().any(|item| *item == Item::Tray)
+        || storages.iter().any(|storage| storage.contains(Item::Tray));
+
+    if !tray_exists {
+        spawn_item_on_ground(
+            &mut commands,
+            &materials,
+            Item::Tray,
+            Vec3::new(TRAY_HOME.0, TRAY_HOME.1, 0.0),
+        );
+    }
+}
+
+/// Makes the new container items hold items, with a hidden mini-sprite per
+/// place.
+fn add_containers_system(
+    mut commands: Commands,
+    materials: Res<GameplayMaterials>,
+    items: Query<(Entity, &Item), Added<Item>>,
+) {
+    for (entity, item) in items.iter() {
+        if !item.is_container() {
+            continue;
+        }
+        commands
+            .entity(entity)
+            .insert(Container::new(TRAY_CAPACITY))
+            .with_children(|parent| {
+                for index in 0..TRAY_CAPACITY {
+                    let height = SLOT_HEIGHT * (index + 1) as f32;
+
+                    parent
+                        .spawn()
+                        .insert(ContainerSlot(index))
+                        .insert_bundle(SpriteBundle {
+                            material: materials.none.clone(),
+                            transform: Transform {
+                                translation: Vec3::new(0.0, height, 0.1 * (index + 1) as f32),
+                                scale: Vec3::new(0.5, 0.5, 1.0),
+                                ..Transform::default()
+                            },
+                            ..SpriteBundle::default()
+                        });
+                }
+            });
+    }
+}
+
+/// Shows the items held by the containers as stacked mini-sprites.
+fn update_container_slots_system(
+    materials: Res<GameplayMaterials>,
+    containers: Query<(&Container, &Children), Changed<Container>>,
+    mut slots: Query<(&ContainerSlot, &mut Handle<ColorMaterial>)>,
+) {
+    for (container, children) in containers.iter() {
+        for child in children.iter() {
+            if let Ok((slot, mut material)) = slots.get_mut(*child) {
+                *material = container
+                    .items
+                    .get(slot.0)
+                    .map_or(materials.none.clone(), |item| {
+                        materials.item_sprite_for(*item)
+                    });
+            }
+        }
+    }
+}
+
+/// Spills the item on top of the carried container on the floor when Didi
+/// bumps into something.
+fn spill_system(
+    mut commands: Commands,
+    game_data: Res<GameData>,
+    materials: Res<GameplayMaterials>,
+    mut rng: ResMut<GameRng>,
+    mut was_bumping: Local<bool>,
+    didi: Query<(&Position, &Movement, &CircleCollider), With<Didi>>,
+    colliders: Query<(&Position, &BoxCollider), Without<Didi>>,
+    mut carried_containers: Query<(&Parent, &mut Container), With<CarriedItem>>,
+) {
+    let (position, movement, collider) = match didi.get(game_data.didi_entity) {
+        Ok(didi) => didi,
+        Err(_) => return,
+    };
+    let next_position = (position.0 + collider.offset + movement.0).truncate();
+    let bumping = movement.0 != Vec3::ZERO
+        && colliders.iter().any(|(obstacle_position, obstacle)| {
+            let obstacle_center = (obstacle_position.0 + obstacle.offset).truncate();
+            collider
+                .shape()
+                .overlaps(next_position, obstacle.shape(), obstacle_center)
+        });
+
+    // Only the first contact with an obstacle is a bump
+    let bumped = bumping && !*was_bumping;
+    *was_bumping = bumping;
+
+    if !bumped || !rng.rng.gen_bool(TRAY_SPILL_CHANCE) {
+        return;
+    }
+    for (parent, mut container) in carried_containers.iter_mut() {
+        if parent.0 != game_data.didi_entity {
+            continue; // Carried by the helper of the co-op
+        }
+        if let Some(item) = container.pop() {
+            info!("Spill {:?} on the floor", item);
+            let floor_position = Vec3::new(position.0.x, position.0.y - 40.0, 0.0);
+            spawn_item_on_ground(&mut commands, &materials, item, floor_position);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn container_holds_foods_up_to_its_capacity() {
+        let mut container = Container::new(2);
+
+        container.put(Item::Chips);
+        container.put(Item::LaundryBasket);
+        container.put(Item::IceCream);
+        container.put(Item::Coffee);
+
+        assert_eq!(container.items, vec![Item::Chips, Item::IceCream]);
+        assert!(!container.accepts(Item::WaterGlass));
+
+        assert!(container.remove(Item::Chips));
+        assert!(!container.remove(Item::Chips));
+        assert_eq!(container.pop(), Some(Item::IceCream));
+        assert_eq!(container.pop(), None);
+    }
+}
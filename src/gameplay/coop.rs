@@ -0,0 +1,138 @@
+//! Local co-op where a second player controls a helper of Didi, moving with
+//! `WASD` or a second gamepad and carrying items with their own hands.
+
+use bevy::prelude::*;
+
+use crate::{
+    camera::CameraTarget,
+    collisions::{CircleCollider, Movement, Position},
+    constants::{GameState, DIDI_RADIUS},
+    controllers::{InputAction, InputMap},
+    cooldown::Cooldown,
+    settings::Settings,
+};
+
+use super::{items::Inventory, materials::GameplayMaterials, Didi};
+
+/// Plugin managing the helper of the local co-op.
+pub struct CoopPlugin;
+
+impl Plugin for CoopPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_to_stage(CoreStage::PreUpdate, tag_didi_system.system())
+            .add_system_set(
+                SystemSet::on_exit(GameState::Menu).with_system(spawn_helper_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Menu).with_system(despawn_helper_system.system()),
+            );
+    }
+}
+
+/// Component on the entities controlled by a player, with the number of the
+/// player: 0 for Didi and 1 for the helper.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Player(pub usize);
+
+/// Cooldown of the action of the helper, apart from the one of Didi.
+pub struct HelperCooldown(pub Cooldown);
+
+/// Key of the action of the helper on the keyboard.
+const HELPER_ACTION_KEY: KeyCode = KeyCode::F;
+
+/// Returns true while the second player holds the action control, the `F`
+/// key or the confirm button of the second gamepad.
+pub fn helper_action_pressed(
+    keyboard: &Input<KeyCode>,
+    gamepad_buttons: &Input<GamepadButton>,
+    input_map: &InputMap,
+) -> bool {
+    keyboard.pressed(HELPER_ACTION_KEY)
+        || input_map.button_pressed(InputAction::Confirm, Gamepad(1), gamepad_buttons)
+}
+
+/// Makes Didi the first player, also when the entity is replaced like after
+/// reloading the scene.
+fn tag_didi_system(mut commands: Commands, didis: Query<Entity, (With<Didi>, Without<Player>)>) {
+    for didi in didis.iter() {
+        commands.entity(didi).insert(Player(0));
+    }
+}
+
+/// Spawns the helper next to Didi when a game starts in local co-op.
+fn spawn_helper_system(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    materials: Res<GameplayMaterials>,
+    players: Query<&Player>,
+) {
+    if !settings.coop || players.iter().any(|player| player.0 > 0) {
+        return;
+    }
+    commands
+        .spawn()
+        .insert(Player(1))
+        .insert(CameraTarget)
+        .insert(Position(Vec3::new(540.0, 260.0, 0.0)))
+        .insert(CircleCollider {
+            radius: DIDI_RADIUS,
+            offset: Vec3::new(0.0, -10.0, 0.0),
+        })
+        .insert(Movement::default())
+        .insert(Inventory::default())
+        .insert(HelperCooldown(Cooldown::from_seconds(0.2)))
+        .insert_bundle(SpriteBundle {
+            material: materials.helper_sprite.clone(),
+            transform: Transform::from_scale(Vec3::new(0.3, 0.3, 0.0)),
+            ..SpriteBundle::default()
+        });
+}
+
+/// Removes the helper with the carried items when back to the menu.
+fn despawn_helper_system(mut commands: Commands, players: Query<(Entity, &Player)>) {
+    for (entity, player) in players.iter() {
+        if player.0 > 0 {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn helper_acts_with_its_own_controls() {
+        let input_map = InputMap::default();
+        let mut keyboard = Input::<KeyCode>::default();
+        let mut gamepad_buttons = Input::<GamepadButton>::default();
+        assert!(!helper_action_pressed(
+            &keyboard,
+            &gamepad_buttons,
+            &input_map
+        ));
+
+        keyboard.press(KeyCode::Space);
+        gamepad_buttons.press(GamepadButton(Gamepad(0), GamepadButtonType::South));
+        assert!(!helper_action_pressed(
+            &keyboard,
+            &gamepad_buttons,
+            &input_map
+        ));
+
+        keyboard.press(HELPER_ACTION_KEY);
+        assert!(helper_action_pressed(
+            &keyboard,
+            &gamepad_buttons,
+            &input_map
+        ));
+
+        keyboard.release(HELPER_ACTION_KEY);
+        gamepad_buttons.press(GamepadButton(Gamepad(1), GamepadButtonType::South));
+        assert!(helper_action_pressed(
+            &keyboard,
+            &gamepad_buttons,
+            &input_map
+        ));
+    }
+}
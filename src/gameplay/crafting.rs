@@ -0,0 +1,248 @@
+//! Crafting at the kitchen: standing at the kettle with the ingredients of a
+//! recipe in the inventory, Didi combines them into a new item that no
+//! producer gives, like a hot tea brewed from water and tea leaves.
+
+use bevy::prelude::*;
+
+use crate::{
+    collisions::{BoxCollider, Contact, Position, TriggerArea},
+    constants::{GameState, CRAFTING_DURATION},
+    locale::Language,
+    settings::Settings,
+    time_scale::TimeScale,
+    widgets::{entity_timer_system, spawn_timer_bar, EntityTimer, Progress, WidgetMaterials},
+};
+
+use super::{
+    bubbles::SayEvent,
+    entities::GameData,
+    items::{ActionEvent, CraftEvent, Inventory, Item, ItemSystems},
+    Furniture,
+};
+
+/// Plugin managing the crafting of items.
+pub struct CraftingPlugin;
+
+impl Plugin for CraftingPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<CraftingMaterials>()
+            .add_startup_system(spawn_kettle.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(
+                        crafting_system
+                            .system()
+                            .label("crafting")
+                            .before("item_actions"),
+                    )
+                    .with_system(entity_timer_system::<Kettle>.system().after("crafting"))
+                    .with_system(crafted_system.system().after(ItemSystems)),
+            );
+    }
+}
+
+/// Combination of carried ingredients into a new item.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Recipe {
+    /// Items consumed by the recipe
+    pub ingredients: [Item; 2],
+    /// Item crafted
+    pub result: Item,
+}
+
+impl Recipe {
+    /// Returns true if the inventory holds all the ingredients.
+    fn is_ready(&self, inventory: &Inventory) -> bool {
+        let mut slots = inventory.slots;
+        self.ingredients.iter().all(|ingredient| {
+            match slots.iter().position(|slot| *slot == Some(*ingredient)) {
+                Some(index) => {
+                    slots[index] = None;
+                    true
+                }
+                None => false,
+            }
+        })
+    }
+}
+
+/// Recipes known at the kitchen.
+const RECIPES: &[Recipe] = &[Recipe {
+    ingredients: [Item::WaterGlass, Item::TeaLeaves],
+    result: Item::HotTea,
+}];
+
+/// Returns the recipe whose ingredients are in the inventory.
+fn find_recipe(inventory: &Inventory) -> Option<Recipe> {
+    RECIPES
+        .iter()
+        .find(|recipe| recipe.is_ready(inventory))
+        .copied()
+}
+
+/// Component on the kettle, brewing the recipe while Didi stands next to it
+/// with the ingredients.
+struct Kettle {
+    /// Recipe being crafted
+    recipe: Option<Recipe>,
+    /// Timer until the recipe is crafted
+    timer: Timer,
+}
+
+impl Progress for Kettle {
+    fn remaining(&self) -> f32 {
+        match self.recipe {
+            Some(_) => self.timer.percent_left(),
+            None => 0.0,
+        }
+    }
+}
+
+/// Colors of the crafting station.
+struct CraftingMaterials {
+    /// Color of the kettle
+    kettle: Handle<ColorMaterial>,
+}
+
+impl FromWorld for CraftingMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            kettle: materials.add(Color::rgb(0.7, 0.3, 0.25).into()),
+        }
+    }
+}
+
+/// Spawns the kettle on the kitchen counter with the timer bar of the
+/// crafting.
+fn spawn_kettle(
+    mut commands: Commands,
+    materials: Res<CraftingMaterials>,
+    widget_materials: Res<WidgetMaterials>,
+) {
+    let size = Vec2::new(40.0, 30.0);
+
+    let kettle = commands
+        .spawn()
+        .insert(Furniture)
+        .insert(Kettle {
+            recipe: None,
+            timer: Timer::from_seconds(CRAFTING_DURATION, false),
+        })
+        .insert(Position(Vec3::new(300.0, 480.0, 0.0)))
+        .insert(BoxCollider::new(size.x, size.y))
+        .insert(TriggerArea::new(size.x + 40.0, size.y + 60.0))
+        .insert_bundle(SpriteBundle {
+            material: materials.kettle.clone(),
+            sprite: Sprite::new(size),
+            ..SpriteBundle::default()
+        })
+        .id();
+
+    let crafting_bar = spawn_timer_bar(
+        &mut commands,
+        &widget_materials,
+        Vec3::new(0.0, 35.0, 0.1),
+        Vec2::new(50.0, 8.0),
+    );
+    commands
+        .entity(crafting_bar)
+        .insert(EntityTimer::<Kettle>::new(kettle));
+    commands.entity(kettle).push_children(&[crafting_bar]);
+}
+
+/// Brews the recipe whose ingredients Didi carries while standing at the
+/// kettle, starting over when Didi leaves or drops an ingredient.
+fn crafting_system(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    game_data: Res<GameData>,
+    mut action_events: EventWriter<ActionEvent>,
+    contacts: Query<&Contact>,
+    inventories: Query<&Inventory>,
+    mut kettles: Query<(Entity, &mut Kettle)>,
+) {
+    let didi = game_data.didi_entity;
+    let inventory = match inventories.get(didi) {
+        Ok(inventory) => inventory,
+        Err(_) => return,
+    };
+
+    for (entity, mut kettle) in kettles.iter_mut() {
+        let at_kettle = contacts
+            .iter()
+            .any(|contact| contact.0 == didi && contact.1 == entity);
+        let recipe = if at_kettle {
+            find_recipe(inventory)
+        } else {
+            None
+        };
+
+        if recipe != kettle.recipe {
+            kettle.recipe = recipe;
+            kettle.timer.reset();
+        }
+        let recipe = match recipe {
+            Some(recipe) => recipe,
+            None => continue,
+        };
+
+        if kettle
+            .timer
+            .tick(time_scale.scale(time.delta()))
+            .just_finished()
+        {
+            action_events.send(ActionEvent::Craft(recipe));
+            kettle.recipe = None;
+            kettle.timer.reset();
+        }
+    }
+}
+
+/// Returns what Didi says when the item is crafted.
+const fn crafted_line(language: Language, item: Item) -> Option<&'static str> {
+    match (language, item) {
+        (Language::English, Item::HotTea) => Some("Tea is ready!"),
+        (Language::French, Item::HotTea) => Some("Le thé est prêt !"),
+        _ => None,
+    }
+}
+
+/// Makes Didi announce the crafted items.
+fn crafted_system(
+    settings: Res<Settings>,
+    game_data: Res<GameData>,
+    mut craft_events: EventReader<CraftEvent>,
+    mut say_events: EventWriter<SayEvent>,
+) {
+    for CraftEvent(item) in craft_events.iter() {
+        if let Some(line) = crafted_line(settings.language, *item) {
+            say_events.send(SayEvent {
+                speaker: game_data.didi_entity,
+                line,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recipes_need_all_ingredients() {
+        let mut inventory = Inventory::default();
+        inventory.slots[0] = Some(Item::WaterGlass);
+        assert_eq!(find_recipe(&inventory), None);
+
+        inventory.slots[1] = Some(Item::Chips);
+        assert_eq!(find_recipe(&inventory), None);
+
+        inventory.slots[1] = Some(Item::TeaLeaves);
+        assert_eq!(
+            find_recipe(&inventory).map(|recipe| recipe.result),
+            Some(Item::HotTea)
+        );
+    }
+}
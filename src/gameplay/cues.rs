@@ -0,0 +1,121 @@
+//! Cues of the key moments of the game, such as a request expiring soon or a
+//! producer restocked, for the players who cannot hear them.
+//!
+//! The gameplay systems send a `CueEvent`, which is turned into a flash on
+//! the element of the moment when the visual cues are enabled in the
+//! settings.
+
+use bevy::prelude::*;
+
+use crate::{constants::GameState, settings::Settings};
+
+/// Plugin turning the cues into visual flashes.
+pub struct CuesPlugin;
+
+impl Plugin for CuesPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<CueEvent>()
+            .init_resource::<CueMaterials>()
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(spawn_flash_system.system().label("spawn_flash"))
+                    .with_system(blink_flash_system.system().after("spawn_flash")),
+            );
+    }
+}
+
+/// Seconds a flash lasts.
+const FLASH_DURATION: f32 = 1.2;
+/// Seconds a flash stays shown or hidden while blinking.
+const BLINK_PERIOD: f32 = 0.15;
+/// Fraction of the patience under which a request expires soon.
+pub const EXPIRING_PATIENCE: f32 = 0.25; // 25%
+
+/// A key moment of the game for the entity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CueEvent {
+    /// The patience of the Baobei for the front request is running out
+    RequestExpiring(Entity),
+    /// The empty producer has an item again
+    Restocked(Entity),
+}
+
+impl CueEvent {
+    /// Returns the entity of the moment, with the position and the size of
+    /// its element to flash.
+    const fn flash_target(self) -> (Entity, Vec3, Vec2) {
+        match self {
+            // The patience bar of the Baobei
+            Self::RequestExpiring(entity) => {
+                (entity, Vec3::new(0.0, 350.0, -0.1), Vec2::new(320.0, 50.0))
+            }
+            // The stock icon of the producer
+            Self::Restocked(entity) => (entity, Vec3::new(0.0, 40.0, 0.9), Vec2::new(70.0, 70.0)),
+        }
+    }
+}
+
+/// Colors of the flashes.
+struct CueMaterials {
+    /// A flash behind an element
+    flash: Handle<ColorMaterial>,
+}
+
+impl FromWorld for CueMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            flash: materials.add(Color::rgba(1.0, 0.9, 0.2, 0.8).into()),
+        }
+    }
+}
+
+/// Component on a flash, despawned when the timer finishes.
+struct Flash(Timer);
+
+/// Flashes the element of the cues, if enabled in the settings.
+fn spawn_flash_system(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    materials: Res<CueMaterials>,
+    mut cue_events: EventReader<CueEvent>,
+) {
+    for cue in cue_events.iter() {
+        if !settings.visual_cues {
+            continue;
+        }
+        let (entity, translation, size) = cue.flash_target();
+        let flash = commands
+            .spawn()
+            .insert(Flash(Timer::from_seconds(FLASH_DURATION, false)))
+            .insert_bundle(SpriteBundle {
+                material: materials.flash.clone(),
+                sprite: Sprite::new(size),
+                transform: Transform::from_translation(translation),
+                ..SpriteBundle::default()
+            })
+            .id();
+        commands.entity(entity).push_children(&[flash]);
+    }
+}
+
+/// Blinks the flashes and removes the finished ones.
+// The blinks of a flash are few
+#[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+fn blink_flash_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut flashes: Query<(Entity, &mut Flash, &mut Visible)>,
+) {
+    for (entity, mut flash, mut visible) in flashes.iter_mut() {
+        if flash.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let shown = (flash.0.elapsed_secs() / BLINK_PERIOD) as u32 % 2 == 0;
+        if visible.is_visible != shown {
+            visible.is_visible = shown;
+        }
+    }
+}
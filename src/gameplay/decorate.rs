@@ -0,0 +1,522 @@
+//! Decorate mode: between runs, the player spends the points earned during
+//! the runs to furnish the apartment, placing furnishings on a grid.
+
+use bevy::{prelude::*, sprite::collide_aabb::collide};
+
+use crate::{
+    collisions::{overlaps_colliders, BoxCollider, Position},
+    constants::{GameState, FURNISHING_GRID, WINDOW_HEIGHT, WINDOW_WIDTH},
+    save::{Profile, SaveData},
+};
+
+use super::score::Score;
+
+/// Plugin managing the decorate mode.
+pub struct DecoratePlugin;
+
+impl Plugin for DecoratePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Furnishings>()
+            .init_resource::<FurnishingMaterials>()
+            .init_resource::<DecorateCursor>()
+            .add_startup_system(spawn_furnishings.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::Menu).with_system(start_decorate_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::InGame).with_system(save_earnings_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Decorate).with_system(setup_decorate_mode.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Decorate)
+                    .with_system(move_cursor_system.system().label("move_cursor"))
+                    .with_system(
+                        update_cursor_system
+                            .system()
+                            .label("update_cursor")
+                            .after("move_cursor"),
+                    )
+                    .with_system(furnish_system.system().after("update_cursor"))
+                    .with_system(decorate_hud_system.system().after("update_cursor"))
+                    .with_system(stop_decorate_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Decorate).with_system(cleanup_decorate_mode.system()),
+            );
+    }
+}
+
+/// Save file storing the furnishings placed by the player and the points
+/// left to spend.
+const FURNISHINGS_FILE: &str = "furnishings.sav";
+
+/// Furnishing the player can buy and place in the apartment.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Furnishing {
+    /// A plant in a pot
+    Plant,
+    /// A standing lamp
+    Lamp,
+    /// A rug Didi can walk on
+    Rug,
+    /// A shelf full of books
+    Bookshelf,
+}
+
+impl Furnishing {
+    /// All the furnishings of the catalog.
+    pub const ALL: [Self; 4] = [Self::Plant, Self::Lamp, Self::Rug, Self::Bookshelf];
+
+    /// Returns the name of the furnishing, used in the save file.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Plant => "Plant",
+            Self::Lamp => "Lamp",
+            Self::Rug => "Rug",
+            Self::Bookshelf => "Bookshelf",
+        }
+    }
+
+    /// Returns the furnishing with the given name.
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|furnishing| furnishing.name() == name)
+    }
+
+    /// Returns the points needed to buy the furnishing.
+    const fn price(self) -> u32 {
+        match self {
+            Self::Plant => 30,
+            Self::Lamp => 50,
+            Self::Rug => 40,
+            Self::Bookshelf => 80,
+        }
+    }
+
+    /// Returns the size of the furnishing on the floor.
+    pub fn size(self) -> Vec2 {
+        match self {
+            Self::Plant | Self::Lamp => Vec2::new(40.0, 40.0),
+            Self::Rug => Vec2::new(160.0, 80.0),
+            Self::Bookshelf => Vec2::new(120.0, 40.0),
+        }
+    }
+
+    /// Returns true if Didi cannot walk through the furnishing.
+    pub const fn is_obstacle(self) -> bool {
+        !matches!(self, Self::Rug)
+    }
+}
+
+/// Furnishings of the profile, loaded over the base apartment.
+pub struct Furnishings {
+    /// Points earned during the runs and not spent yet.
+    savings: u32,
+    /// Placed furnishings, with their position on the floor.
+    placed: Vec<(Furnishing, Vec2)>,
+}
+
+impl FromWorld for Furnishings {
+    fn from_world(world: &mut World) -> Self {
+        let profile = world.get_resource::<Profile>().unwrap();
+        Self::from_save_data(&profile.load(FURNISHINGS_FILE))
+    }
+}
+
+impl Furnishings {
+    /// Reads the furnishings from the save file, ignoring the invalid ones.
+    fn from_save_data(data: &SaveData) -> Self {
+        let count: usize = data.get("count").unwrap_or(0);
+        let placed = (0..count)
+            .filter_map(|index| data.get::<String>(&format!("furnishing.{}", index)))
+            .filter_map(|entry| {
+                let mut fields = entry.split_whitespace();
+                let furnishing = Furnishing::from_name(fields.next()?)?;
+                let x = fields.next()?.parse().ok()?;
+                let y = fields.next()?.parse().ok()?;
+                Some((furnishing, Vec2::new(x, y)))
+            })
+            .collect();
+
+        Self {
+            savings: data.get("savings").unwrap_or(0),
+            placed,
+        }
+    }
+
+    /// Writes the furnishings as entries of the save file.
+    fn to_save_data(&self) -> SaveData {
+        let mut data = SaveData::default();
+        data.set("savings", self.savings);
+        data.set("count", self.placed.len());
+
+        for (index, (furnishing, position)) in self.placed.iter().enumerate() {
+            data.set(
+                &format!("furnishing.{}", index),
+                format!("{} {} {}", furnishing.name(), position.x, position.y),
+            );
+        }
+        data
+    }
+
+    /// Pays for the furnishing and places it, returns false if the savings
+    /// are not enough.
+    fn buy(&mut self, furnishing: Furnishing, position: Vec2) -> bool {
+        if self.savings < furnishing.price() {
+            return false;
+        }
+        self.savings -= furnishing.price();
+        self.placed.push((furnishing, position));
+        true
+    }
+
+    /// Removes the furnishing placed at the position, without refund.
+    fn remove(&mut self, furnishing: Furnishing, position: Vec2) {
+        self.placed
+            .retain(|placed| *placed != (furnishing, position));
+    }
+}
+
+/// Colors of the furnishings and of the cursor.
+pub struct FurnishingMaterials {
+    /// Plant
+    plant: Handle<ColorMaterial>,
+    /// Lamp
+    lamp: Handle<ColorMaterial>,
+    /// Rug
+    rug: Handle<ColorMaterial>,
+    /// Bookshelf
+    bookshelf: Handle<ColorMaterial>,
+    /// Cursor on a free place
+    free_cursor: Handle<ColorMaterial>,
+    /// Cursor on an occupied place
+    blocked_cursor: Handle<ColorMaterial>,
+}
+
+impl FromWorld for FurnishingMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            plant: materials.add(Color::rgb(0.3, 0.6, 0.3).into()),
+            lamp: materials.add(Color::rgb(0.95, 0.85, 0.5).into()),
+            rug: materials.add(Color::rgb(0.6, 0.25, 0.3).into()),
+            bookshelf: materials.add(Color::rgb(0.45, 0.3, 0.2).into()),
+            free_cursor: materials.add(Color::rgba(0.3, 0.9, 0.3, 0.5).into()),
+            blocked_cursor: materials.add(Color::rgba(0.9, 0.2, 0.2, 0.5).into()),
+        }
+    }
+}
+
+impl FurnishingMaterials {
+    /// Returns the material of the given furnishing.
+    pub fn material_for(&self, furnishing: Furnishing) -> Handle<ColorMaterial> {
+        match furnishing {
+            Furnishing::Plant => self.plant.clone(),
+            Furnishing::Lamp => self.lamp.clone(),
+            Furnishing::Rug => self.rug.clone(),
+            Furnishing::Bookshelf => self.bookshelf.clone(),
+        }
+    }
+}
+
+/// Component on a furnishing placed in the apartment.
+pub struct PlacedFurnishing(pub Furnishing);
+
+/// Component on the preview of the furnishing to place.
+struct FurnishingCursor;
+
+/// Placement being prepared by the player.
+struct DecorateCursor {
+    /// Position on the grid.
+    position: Vec2,
+    /// Index of the selected furnishing in the catalog.
+    selected: usize,
+    /// Whether the selected furnishing fits at the position.
+    free: bool,
+}
+
+impl Default for DecorateCursor {
+    fn default() -> Self {
+        Self {
+            position: snap_to_grid(Vec2::new(WINDOW_WIDTH / 2.0, WINDOW_HEIGHT / 3.0)),
+            selected: 0,
+            free: false,
+        }
+    }
+}
+
+impl DecorateCursor {
+    /// Returns the selected furnishing.
+    fn furnishing(&self) -> Furnishing {
+        Furnishing::ALL[self.selected]
+    }
+}
+
+/// Stores entities of the decorate mode
+struct DecorateData {
+    /// Preview of the furnishing to place
+    cursor: Entity,
+    /// Text showing the savings and the controls
+    hud: Entity,
+}
+
+/// Returns the nearest position on the grid of the furnishings.
+fn snap_to_grid(position: Vec2) -> Vec2 {
+    (position / FURNISHING_GRID).round() * FURNISHING_GRID
+}
+
+/// Spawns a placed furnishing in the apartment.
+pub fn spawn_furnishing(
+    commands: &mut Commands,
+    materials: &FurnishingMaterials,
+    furnishing: Furnishing,
+    position: Vec2,
+) -> Entity {
+    let size = furnishing.size();
+    let mut entity = commands.spawn();
+
+    entity
+        .insert(PlacedFurnishing(furnishing))
+        .insert(Position(position.extend(0.0)))
+        .insert_bundle(SpriteBundle {
+            material: materials.material_for(furnishing),
+            sprite: Sprite::new(size),
+            ..SpriteBundle::default()
+        });
+
+    if furnishing.is_obstacle() {
+        entity.insert(BoxCollider::new(size.x, size.y));
+    }
+    entity.id()
+}
+
+/// Spawns the furnishings of the profile over the base apartment.
+fn spawn_furnishings(
+    mut commands: Commands,
+    furnishings: Res<Furnishings>,
+    materials: Res<FurnishingMaterials>,
+) {
+    for (furnishing, position) in &furnishings.placed {
+        spawn_furnishing(&mut commands, &materials, *furnishing, *position);
+    }
+}
+
+/// Saves the points earned by the run, to spend them in the decorate mode.
+fn save_earnings_system(
+    profile: Res<Profile>,
+    score: Res<Score>,
+    mut furnishings: ResMut<Furnishings>,
+) {
+    if score.points() == 0 {
+        return;
+    }
+    furnishings.savings += score.points();
+    profile.store(FURNISHINGS_FILE, &furnishings.to_save_data());
+}
+
+/// Enters the decorate mode when the player presses `D` in the menu.
+fn start_decorate_system(keyboard_input: Res<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
+    if keyboard_input.just_pressed(KeyCode::D) {
+        state.set(GameState::Decorate).unwrap();
+    }
+}
+
+/// Goes back to the menu when the player presses `Escape`.
+fn stop_decorate_system(keyboard_input: Res<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        state.set(GameState::Menu).unwrap();
+    }
+}
+
+/// Spawns the cursor and the text of the decorate mode.
+fn setup_decorate_mode(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    materials: Res<FurnishingMaterials>,
+    cursor: Res<DecorateCursor>,
+) {
+    let cursor = commands
+        .spawn()
+        .insert(FurnishingCursor)
+        .insert_bundle(SpriteBundle {
+            material: materials.free_cursor.clone(),
+            sprite: Sprite::new(cursor.furnishing().size()),
+            ..SpriteBundle::default()
+        })
+        .id();
+
+    let hud = commands
+        .spawn()
+        .insert_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(20.0),
+                    left: Val::Px(40.0),
+                    ..Rect::default()
+                },
+                ..Style::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("FiraSans-Bold.ttf"),
+                    font_size: 30.0,
+                    color: Color::WHITE,
+                },
+                TextAlignment::default(),
+            ),
+            ..TextBundle::default()
+        })
+        .id();
+
+    commands.insert_resource(DecorateData { cursor, hud });
+}
+
+/// Moves the cursor cell by cell with the arrows and changes the selected
+/// furnishing with `Tab`.
+fn move_cursor_system(keyboard_input: Res<Input<KeyCode>>, mut cursor: ResMut<DecorateCursor>) {
+    let moves = [
+        (KeyCode::Left, Vec2::new(-FURNISHING_GRID, 0.0)),
+        (KeyCode::Right, Vec2::new(FURNISHING_GRID, 0.0)),
+        (KeyCode::Up, Vec2::new(0.0, FURNISHING_GRID)),
+        (KeyCode::Down, Vec2::new(0.0, -FURNISHING_GRID)),
+    ];
+    for (key, offset) in moves {
+        if keyboard_input.just_pressed(key) {
+            let position = (cursor.position + offset)
+                .max(Vec2::splat(FURNISHING_GRID))
+                .min(Vec2::new(WINDOW_WIDTH, WINDOW_HEIGHT) - Vec2::splat(FURNISHING_GRID));
+            cursor.position = snap_to_grid(position);
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Tab) {
+        cursor.selected = (cursor.selected + 1) % Furnishing::ALL.len();
+    }
+}
+
+/// Shows the selected furnishing at the cursor, colored whether it fits
+/// between the colliders and the other furnishings.
+fn update_cursor_system(
+    materials: Res<FurnishingMaterials>,
+    mut cursor: ResMut<DecorateCursor>,
+    colliders: Query<(&Position, &BoxCollider)>,
+    placed: Query<(&Position, &PlacedFurnishing)>,
+    mut previews: Query<
+        (&mut Transform, &mut Sprite, &mut Handle<ColorMaterial>),
+        With<FurnishingCursor>,
+    >,
+) {
+    let size = cursor.furnishing().size();
+    let position = cursor.position.extend(0.0);
+
+    let free = !overlaps_colliders(position, size, colliders.iter())
+        && !placed.iter().any(|(placed_position, furnishing)| {
+            collide(position, size, placed_position.0, furnishing.0.size()).is_some()
+        });
+    if cursor.free != free {
+        cursor.free = free;
+    }
+
+    for (mut transform, mut sprite, mut material) in previews.iter_mut() {
+        // Drawn above everything
+        transform.translation = cursor.position.extend(999.0);
+        sprite.size = size;
+        *material = if free {
+            materials.free_cursor.clone()
+        } else {
+            materials.blocked_cursor.clone()
+        };
+    }
+}
+
+/// Buys and places the selected furnishing with `Space`, or removes the
+/// furnishing under the cursor with `X`.
+fn furnish_system(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    profile: Res<Profile>,
+    materials: Res<FurnishingMaterials>,
+    cursor: Res<DecorateCursor>,
+    mut furnishings: ResMut<Furnishings>,
+    placed: Query<(Entity, &Position, &PlacedFurnishing)>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Space) && cursor.free {
+        let furnishing = cursor.furnishing();
+
+        if furnishings.buy(furnishing, cursor.position) {
+            info!("Place {:?} at {}", furnishing, cursor.position);
+            spawn_furnishing(&mut commands, &materials, furnishing, cursor.position);
+            profile.store(FURNISHINGS_FILE, &furnishings.to_save_data());
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::X) {
+        let under_cursor = placed.iter().find(|(_, position, furnishing)| {
+            collide(
+                cursor.position.extend(0.0),
+                Vec2::splat(1.0),
+                position.0,
+                furnishing.0.size(),
+            )
+            .is_some()
+        });
+        if let Some((entity, position, furnishing)) = under_cursor {
+            info!("Remove {:?} at {}", furnishing.0, position.0);
+            furnishings.remove(furnishing.0, position.0.truncate());
+            commands.entity(entity).despawn();
+            profile.store(FURNISHINGS_FILE, &furnishings.to_save_data());
+        }
+    }
+}
+
+/// Shows the savings, the selected furnishing and the controls.
+fn decorate_hud_system(
+    furnishings: Res<Furnishings>,
+    cursor: Res<DecorateCursor>,
+    data: Res<DecorateData>,
+    mut texts: Query<&mut Text>,
+) {
+    if !furnishings.is_changed() && !cursor.is_changed() {
+        return;
+    }
+    if let Ok(mut text) = texts.get_mut(data.hud) {
+        let furnishing = cursor.furnishing();
+        text.sections[0].value = format!(
+            "Savings: {} | {} ({} points)\n\
+             Arrows: move, Tab: change, Space: buy, X: remove, Escape: back",
+            furnishings.savings,
+            furnishing.name(),
+            furnishing.price(),
+        );
+    }
+}
+
+/// Removes the cursor and the text of the decorate mode.
+fn cleanup_decorate_mode(mut commands: Commands, data: Res<DecorateData>) {
+    commands.entity(data.cursor).despawn();
+    commands.entity(data.hud).despawn();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn furnishings_are_bought_and_saved() {
+        let mut furnishings = Furnishings::from_save_data(&SaveData::parse("savings = 150"));
+
+        assert!(furnishings.buy(Furnishing::Bookshelf, Vec2::new(120.0, 80.0)));
+        assert!(furnishings.buy(Furnishing::Rug, Vec2::new(400.0, 80.0)));
+        assert!(!furnishings.buy(Furnishing::Lamp, Vec2::new(600.0, 80.0)));
+
+        let loaded = Furnishings::from_save_data(&furnishings.to_save_data());
+        assert_eq!(loaded.savings, 150 - 80 - 40);
+        assert_eq!(loaded.placed, furnishings.placed);
+        assert_eq!(snap_to_grid(Vec2::new(61.0, 19.0)), Vec2::new(80.0, 0.0));
+    }
+}
@@ -0,0 +1,162 @@
+//! Dirty dishes: after a glass of water or an ice cream, Baobei leaves the
+//! dirty dish next to her. Didi brings it back to the sink to wash it, or
+//! Baobei gets sad faster because of the mess.
+
+use bevy::prelude::*;
+
+use crate::{
+    collisions::{Contact, Position},
+    constants::{GameState, DIRTY_DISH_DELAY},
+    time_scale::TimeScale,
+};
+
+use super::{
+    entities::GameData,
+    items::{
+        spawn_item_on_ground, ActionEvent, CarriedItem, DeliveryEvent, Inventory, Item,
+        ItemSystems, PickAndDropCooldown,
+    },
+    materials::GameplayMaterials,
+    status_effects::{StatusEffectKind, StatusEffects},
+    Baobei, NewRunAppExt,
+};
+
+/// Plugin managing the dirty dishes.
+pub struct DishesPlugin;
+
+impl Plugin for DishesPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<PendingDishes>()
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(add_washable_system.system())
+                    .with_system(used_dishes_system.system().after(ItemSystems))
+                    .with_system(wash_system.system().before("item_actions"))
+                    .with_system(mess_effects_system.system().after("status_effects")),
+            )
+            .add_new_run_system(clear_dishes_system);
+    }
+}
+
+/// Where Baobei leaves the dirty dishes, from where she sits.
+const DISH_OFFSET: (f32, f32) = (-130.0, -40.0);
+
+/// Component on the dirty items to wash at the sink.
+pub struct Washable;
+
+/// Component on the sink where the dirty dishes are washed.
+pub struct DishSink;
+
+/// Dishes Baobei is still using, left on the floor when the timer finishes.
+#[derive(Default)]
+struct PendingDishes(Vec<(Timer, Vec3)>);
+
+/// Returns true if the delivered item leaves a dirty dish.
+const fn leaves_dish(item: Item) -> bool {
+    matches!(item, Item::WaterGlass | Item::IceCream)
+}
+
+/// Makes the new dirty dishes washable.
+fn add_washable_system(
+    mut commands: Commands,
+    items: Query<(Entity, &Item), (Added<Item>, Without<Washable>)>,
+) {
+    for (entity, item) in items.iter() {
+        if *item == Item::DirtyDish {
+            commands.entity(entity).insert(Washable);
+        }
+    }
+}
+
+/// Leaves the dish of the delivered glasses of water and ice creams next to
+/// the asker after a while.
+fn used_dishes_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    materials: Res<GameplayMaterials>,
+    mut pending: ResMut<PendingDishes>,
+    mut delivery_events: EventReader<DeliveryEvent>,
+    positions: Query<&Position>,
+) {
+    for event in delivery_events.iter() {
+        if !leaves_dish(event.item) {
+            continue;
+        }
+        if let Ok(position) = positions.get(event.asker) {
+            let dish_position = Vec3::new(
+                position.0.x + DISH_OFFSET.0,
+                position.0.y + DISH_OFFSET.1,
+                0.0,
+            );
+            pending
+                .0
+                .push((Timer::from_seconds(DIRTY_DISH_DELAY, false), dish_position));
+        }
+    }
+
+    let delta = time_scale.scale(time.delta());
+    pending.0.retain(|(timer, position)| {
+        if !timer.finished() {
+            return true;
+        }
+        spawn_item_on_ground(&mut commands, &materials, Item::DirtyDish, *position);
+        false
+    });
+    for (timer, _) in &mut pending.0 {
+        timer.tick(delta);
+    }
+}
+
+/// Washes the dirty dish in hand when Didi uses the sink.
+fn wash_system(
+    keyboard: Res<Input<KeyCode>>,
+    game_data: Res<GameData>,
+    mut cooldown: ResMut<PickAndDropCooldown>,
+    mut action_events: EventWriter<ActionEvent>,
+    contacts: Query<&Contact>,
+    inventories: Query<&Inventory>,
+    sinks: Query<(), With<DishSink>>,
+) {
+    let didi = game_data.didi_entity;
+    if !cooldown.0.available() || !keyboard.pressed(KeyCode::Space) {
+        return;
+    }
+    let item = match inventories.get(didi).ok().and_then(Inventory::active_item) {
+        Some(item @ Item::DirtyDish) => item,
+        _ => return,
+    };
+    let touches_sink = contacts
+        .iter()
+        .any(|contact| contact.0 == didi && sinks.get(contact.1).is_ok());
+
+    if touches_sink {
+        action_events.send(ActionEvent::Wash(item));
+        cooldown.0.start();
+    }
+}
+
+/// Disgusts Baobei while dirty dishes lie on the floor.
+fn mess_effects_system(
+    dishes: Query<(), (With<Washable>, Without<CarriedItem>)>,
+    mut baobei: Query<&mut StatusEffects, With<Baobei>>,
+) {
+    if dishes.iter().next().is_none() {
+        return;
+    }
+    for mut status_effects in baobei.iter_mut() {
+        status_effects.apply(StatusEffectKind::Disgusted);
+    }
+}
+
+/// Cleans the room of the dishes when a new game starts.
+fn clear_dishes_system(
+    mut commands: Commands,
+    mut pending: ResMut<PendingDishes>,
+    dishes: Query<Entity, (With<Washable>, Without<CarriedItem>)>,
+) {
+    pending.0.clear();
+    for dish in dishes.iter() {
+        commands.entity(dish).despawn_recursive();
+    }
+}
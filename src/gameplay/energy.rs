@@ -0,0 +1,149 @@
+//! Energy of Didi, slowly draining and restored by drinking coffee.
+
+use bevy::prelude::*;
+
+use crate::{
+    constants::{GameState, COFFEE_ENERGY, ENERGY_DRAIN},
+    time_scale::TimeScale,
+    widgets::{entity_timer_system, spawn_timer_bar, EntityTimer, Progress, WidgetMaterials},
+};
+
+use super::{
+    items::{ActionEvent, Item},
+    NewRunAppExt,
+};
+
+/// Plugin managing the energy of Didi.
+pub struct EnergyPlugin;
+
+impl Plugin for EnergyPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(spawn_energy_bars_system.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(drain_energy_system.system().label("drain_energy"))
+                    .with_system(drink_coffee_system.system())
+                    .with_system(entity_timer_system::<Energy>.system().after("drain_energy")),
+            )
+            .add_new_run_system(reset_energy_system);
+    }
+}
+
+/// Energy under which Didi is tired.
+const LOW_ENERGY: f32 = 0.3; // 30%
+
+/// Component on a character getting tired over time, between 0 and 1.
+pub struct Energy(f32);
+
+impl Energy {
+    /// Returns a full energy.
+    pub const fn full() -> Self {
+        Self(1.0)
+    }
+
+    /// Returns true if the character is tired.
+    pub fn is_tired(&self) -> bool {
+        self.0 < LOW_ENERGY
+    }
+
+    /// Returns the multiplier of the speed, slower when tired.
+    pub fn speed_multiplier(&self) -> f32 {
+        if self.is_tired() {
+            0.7
+        } else {
+            1.0
+        }
+    }
+
+    /// Returns how fast the action cooldown ends, slower when tired.
+    pub fn cooldown_rate(&self) -> f32 {
+        if self.is_tired() {
+            0.6
+        } else {
+            1.0
+        }
+    }
+
+    /// Adds the given value and clamps the result between 0 and 1.
+    pub fn add(&mut self, value: f32) {
+        self.0 = (self.0 + value).max(0.0).min(1.0);
+    }
+}
+
+impl Progress for Energy {
+    fn remaining(&self) -> f32 {
+        self.0
+    }
+}
+
+/// Spawns an energy bar above the characters having an energy.
+fn spawn_energy_bars_system(
+    mut commands: Commands,
+    widget_materials: Res<WidgetMaterials>,
+    characters: Query<Entity, Added<Energy>>,
+) {
+    for character in characters.iter() {
+        let energy_bar = spawn_timer_bar(
+            &mut commands,
+            &widget_materials,
+            Vec3::new(0.0, 300.0, 0.0),
+            Vec2::new(250.0, 25.0),
+        );
+        commands
+            .entity(energy_bar)
+            .insert(EntityTimer::<Energy>::new(character));
+        commands.entity(character).push_children(&[energy_bar]);
+    }
+}
+
+/// Drains the energy over time.
+fn drain_energy_system(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut energies: Query<&mut Energy>,
+) {
+    let seconds = time_scale.scale(time.delta()).as_secs_f32();
+    for mut energy in energies.iter_mut() {
+        energy.add(-ENERGY_DRAIN * seconds);
+    }
+}
+
+/// Restores the energy of Didi when drinking a coffee.
+fn drink_coffee_system(
+    mut action_events: EventReader<ActionEvent>,
+    mut energies: Query<&mut Energy>,
+) {
+    for action in action_events.iter() {
+        if let ActionEvent::Consume(consumer, Item::Coffee) = action {
+            if let Ok(mut energy) = energies.get_mut(*consumer) {
+                energy.add(COFFEE_ENERGY);
+            }
+        }
+    }
+}
+
+/// Restores the energy when a new game starts.
+fn reset_energy_system(mut energies: Query<&mut Energy>) {
+    for mut energy in energies.iter_mut() {
+        *energy = Energy::full();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Energy;
+
+    #[test]
+    fn test_tired_when_low_energy() {
+        let mut energy = Energy::full();
+        assert!((energy.speed_multiplier() - 1.0).abs() < f32::EPSILON);
+
+        energy.add(-0.9);
+        assert!(energy.is_tired());
+        assert!(energy.speed_multiplier() < 1.0);
+
+        energy.add(2.0);
+        assert!(!energy.is_tired());
+        assert!((energy.0 - 1.0).abs() < f32::EPSILON);
+    }
+}
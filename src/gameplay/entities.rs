@@ -1,17 +1,29 @@
 //! Systems spawning entities of the game.
 
 use bevy::prelude::*;
-use rand::random;
 
 use crate::{
-    collisions::{BoxCollider, Movement, Position, TriggerArea},
-    constants::{WINDOW_HEIGHT, WINDOW_WIDTH},
+    camera::CameraTarget,
+    collisions::{BoxCollider, CircleCollider, Movement, Position, TriggerArea},
+    constants::{DIDI_RADIUS, LIGHT_RADIUS, WINDOW_HEIGHT, WINDOW_WIDTH},
+    drawing::LightSource,
+    rng::GameRng,
+    settings::Settings,
 };
 
 use super::{
+    coop::Player,
+    dishes::DishSink,
+    energy::Energy,
     happiness::Happiness,
-    items::{AskedItem, AskingItem, Item, ItemProducer},
+    interactables::{InteractAction, Interactable, Selection},
+    items::{spawn_asked_items, Inventory, Item, ItemProducer, ItemRequestQueue},
     materials::GameplayMaterials,
+    registry::ItemRegistry,
+    requests::RequestQueue,
+    roaming::Roamer,
+    stamina::Stamina,
+    status_effects::StatusEffects,
     Baobei, Didi,
 };
 
@@ -20,12 +32,13 @@ pub struct SpawnEntitiesPlugin;
 
 impl Plugin for SpawnEntitiesPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_startup_system(setup_camera.system())
+        app.add_event::<MissingEntityEvent>()
             .add_startup_system(spawn_background.system())
             .add_startup_system(spawn_furniture.system())
             .add_startup_system(spawn_didi_and_baobei.system())
             .add_startup_system(spawn_item_producers.system())
-            .add_startup_system(spawn_boarders.system());
+            .add_startup_system(spawn_boarders.system())
+            .add_system_to_stage(CoreStage::PreUpdate, track_didi_system.system());
     }
 }
 
@@ -33,18 +46,45 @@ impl Plugin for SpawnEntitiesPlugin {
 pub struct GameData {
     /// Entity of didi
     pub didi_entity: Entity,
-    /// Entity of baobei
-    pub baobei_entity: Entity,
 }
 
-/// Spawn the camera.
-fn setup_camera(mut commands: Commands) {
-    let mut camera_2d = OrthographicCameraBundle::new_2d();
-    camera_2d.transform.translation += Vec3::new(WINDOW_WIDTH / 2.0, WINDOW_HEIGHT / 2.0, 0.0);
+/// Event sent when an entity stored in the `GameData` no longer exists, the
+/// systems using it doing nothing until it is back.
+pub struct MissingEntityEvent {
+    /// Name of the missing entity
+    pub name: &'static str,
+    /// The despawned entity
+    pub entity: Entity,
+}
 
-    commands.spawn().insert_bundle(camera_2d);
+/// Follows Didi when the entity is replaced, like after reloading the
+/// scene, and reports once when no Didi exists anymore.
+fn track_didi_system(
+    mut game_data: ResMut<GameData>,
+    mut reported: Local<bool>,
+    mut missing_events: EventWriter<MissingEntityEvent>,
+    didis: Query<Entity, With<Didi>>,
+) {
+    if didis.get(game_data.didi_entity).is_ok() {
+        *reported = false;
+        return;
+    }
+    if let Some(didi) = didis.iter().next() {
+        info!("Didi is now the entity {:?}", didi);
+        game_data.didi_entity = didi;
+        *reported = false;
+    } else if !*reported {
+        missing_events.send(MissingEntityEvent {
+            name: "Didi",
+            entity: game_data.didi_entity,
+        });
+        *reported = true;
+    }
 }
 
+/// Where the Baobeis sit, the first one on the couch.
+const BAOBEI_SEATS: [(f32, f32, f32); 2] = [(1050.0, 150.0, 85.0), (300.0, 200.0, 85.0)];
+
 /// Spawn the background of the screen.
 fn spawn_background(mut commands: Commands, materials: Res<GameplayMaterials>) {
     commands.spawn().insert_bundle(SpriteBundle {
@@ -54,19 +94,34 @@ fn spawn_background(mut commands: Commands, materials: Res<GameplayMaterials>) {
     });
 }
 
-/// Spawn the entity for Didi, the player and Baobei.
-fn spawn_didi_and_baobei(mut commands: Commands, materials: Res<GameplayMaterials>) {
+/// Spawn the entity for Didi, the player and the Baobeis.
+fn spawn_didi_and_baobei(
+    mut commands: Commands,
+    materials: Res<GameplayMaterials>,
+    settings: Res<Settings>,
+    registry: Res<ItemRegistry>,
+    mut rng: ResMut<GameRng>,
+) {
     let transform = Transform::from_scale(Vec3::new(0.3, 0.3, 0.0));
 
     let didi_entity = commands
         .spawn()
         .insert(Didi)
+        .insert(Player(0))
+        .insert(CameraTarget)
         .insert(Position(Vec3::new(640.0, 260.0, 0.0)))
-        .insert(BoxCollider {
-            size: Vec2::new(75.0, 50.0),
+        .insert(CircleCollider {
+            radius: DIDI_RADIUS,
             offset: Vec3::new(0.0, -10.0, 0.0),
         })
         .insert(Movement::default())
+        .insert(StatusEffects::default())
+        .insert(Energy::full())
+        .insert(Stamina::full())
+        .insert(Inventory::default())
+        .insert(LightSource {
+            radius: LIGHT_RADIUS,
+        })
         .insert_bundle(SpriteBundle {
             material: materials.didi_sprite.clone(),
             transform,
@@ -74,40 +129,51 @@ fn spawn_didi_and_baobei(mut commands: Commands, materials: Res<GameplayMaterial
         })
         .id();
 
-    let asked_item = random::<Item>();
+    for (x, y, z) in BAOBEI_SEATS.iter().take(settings.baobei_count.max(1)) {
+        spawn_baobei(
+            &mut commands,
+            &materials,
+            &registry,
+            &mut rng,
+            Vec3::new(*x, *y, *z),
+        );
+    }
+
+    commands.insert_resource(GameData { didi_entity });
+}
+
+/// Spawns a Baobei asking for items at the given position.
+fn spawn_baobei(
+    commands: &mut Commands,
+    materials: &GameplayMaterials,
+    registry: &ItemRegistry,
+    rng: &mut GameRng,
+    position: Vec3,
+) -> Entity {
+    let transform = Transform::from_scale(Vec3::new(0.3, 0.3, 0.0));
+    let asked_item = registry.random_request(&mut rng.rng);
+    let request_queue = RequestQueue::new(registry, &mut rng.rng, asked_item);
+    let asked_items = ItemRequestQueue::new(asked_item);
 
-    let baobei_entity = commands
+    commands
         .spawn()
         .insert(Baobei)
-        .insert(Position(Vec3::new(1050.0, 150.0, 85.0)))
+        .insert(CameraTarget)
+        .insert(Position(position))
         .insert(TriggerArea::new(150.0, 150.0))
-        .insert(AskingItem(asked_item))
+        .insert(Roamer::new(position))
+        .insert(Movement::default())
+        .insert(request_queue)
         .insert(Happiness::happy())
+        .insert(StatusEffects::default())
         .insert_bundle(SpriteBundle {
             material: materials.baobei_sprite.clone(),
             transform,
             ..SpriteBundle::default()
         })
-        .with_children(|parent| {
-            parent
-                .spawn()
-                .insert(AskedItem)
-                .insert_bundle(SpriteBundle {
-                    material: materials.item_sprite_for(asked_item),
-                    transform: Transform {
-                        translation: Vec3::new(0.0, 475.0, 0.0),
-                        scale: Vec3::new(1.5, 1.5, 0.0),
-                        ..Transform::default()
-                    },
-                    ..SpriteBundle::default()
-                });
-        })
-        .id();
-
-    commands.insert_resource(GameData {
-        didi_entity,
-        baobei_entity,
-    });
+        .with_children(|parent| spawn_asked_items(parent, materials, &asked_items))
+        .insert(asked_items)
+        .id()
 }
 
 /// Spawn furniture in the.
@@ -120,11 +186,25 @@ fn spawn_furniture(mut commands: Commands, materials: Res<GameplayMaterials>) {
             size: Vec2::new(220.0, 40.0),
             offset: Vec3::new(0.0, 10.0, 0.0),
         })
+        .insert(TriggerArea::new(230.0, 50.0))
+        .insert(DishSink)
         .insert_bundle(SpriteBundle {
             material: materials.sink_sprite.clone(),
             transform: Transform::from_scale(Vec3::new(0.3, 0.3, 0.0)),
             ..SpriteBundle::default()
         });
+    // Washbasin, next to the sink
+    commands
+        .spawn()
+        .insert(Position(Vec3::new(1220.0, 500.0, 0.0)))
+        .insert(BoxCollider::new(60.0, 30.0))
+        .insert(TriggerArea::new(80.0, 50.0))
+        .insert(Interactable(InteractAction::WashHands))
+        .insert_bundle(SpriteBundle {
+            material: materials.sink_sprite.clone(),
+            transform: Transform::from_scale(Vec3::new(0.1, 0.1, 0.0)),
+            ..SpriteBundle::default()
+        });
     // Kitchen
     commands
         .spawn()
@@ -153,6 +233,8 @@ fn spawn_furniture(mut commands: Commands, materials: Res<GameplayMaterials>) {
             size: Vec2::new(300.0, 40.0),
             offset: Vec3::new(10.0, 15.0, 0.0),
         })
+        .insert(Interactable(InteractAction::SitOn))
+        .insert(TriggerArea::new(320.0, 90.0))
         .insert_bundle(SpriteBundle {
             material: materials.couch_sprite.clone(),
             transform: Transform::from_scale(Vec3::new(0.4, 0.4, 0.0)),
@@ -173,23 +255,58 @@ fn spawn_furniture(mut commands: Commands, materials: Res<GameplayMaterials>) {
         });
 }
 
-/// Spawn item producers.
-fn spawn_item_producers(mut commands: Commands) {
+/// Spawn item producers, the items only defined in the registry being
+/// produced along the bottom wall.
+fn spawn_item_producers(mut commands: Commands, registry: Res<ItemRegistry>) {
+    let take = Interactable(InteractAction::Take);
     commands
         .spawn()
-        .insert(ItemProducer(Item::WaterGlass))
+        .insert(ItemProducer::new(Item::WaterGlass))
+        .insert(take)
         .insert(Position(Vec3::new(1050.0, 500.0, 0.0)))
         .insert(TriggerArea::new(230.0, 50.0));
     commands
         .spawn()
-        .insert(ItemProducer(Item::Chips))
+        .insert(ItemProducer::new(Item::Chips))
+        .insert(take)
         .insert(Position(Vec3::new(210.0, 480.0, 0.0)))
         .insert(TriggerArea::new(75.0, 75.0));
+    // The fridge, opened to choose a cold item
     commands
         .spawn()
-        .insert(ItemProducer(Item::IceCream))
+        .insert(ItemProducer::new(Item::IceCream))
+        .insert(Interactable(InteractAction::Open))
+        .insert(Selection(vec![Item::IceCream, Item::WaterGlass]))
         .insert(Position(Vec3::new(720.0, 540.0, 0.0)))
         .insert(TriggerArea::new(175.0, 175.0));
+    commands
+        .spawn()
+        .insert(ItemProducer::new(Item::Coffee))
+        .insert(take)
+        .insert(Position(Vec3::new(390.0, 480.0, 0.0)))
+        .insert(TriggerArea::new(75.0, 75.0));
+    commands
+        .spawn()
+        .insert(ItemProducer::new(Item::TeaLeaves))
+        .insert(take)
+        .insert(Position(Vec3::new(130.0, 480.0, 0.0)))
+        .insert(TriggerArea::new(75.0, 75.0));
+
+    let custom_items = registry
+        .definitions()
+        .filter(|def| matches!(def.item, Item::Custom(_)));
+    for (index, definition) in custom_items.enumerate() {
+        commands
+            .spawn()
+            .insert(ItemProducer::new(definition.item))
+            .insert(take)
+            .insert(Position(Vec3::new(
+                80.0f32.mul_add(index as f32, 460.0),
+                90.0,
+                0.0,
+            )))
+            .insert(TriggerArea::new(75.0, 75.0));
+    }
 }
 
 /// Spawn boarders of the room, avoiding the user to go out of the screen.
@@ -222,3 +339,35 @@ fn spawn_boarders(mut commands: Commands) {
         )))
         .insert(BoxCollider::new(GAP, WINDOW_HEIGHT));
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::app::Events;
+
+    use super::*;
+
+    #[test]
+    fn game_data_follows_didi() {
+        let mut world = World::default();
+        world.insert_resource(Events::<MissingEntityEvent>::default());
+        let mut stage = SystemStage::single(track_didi_system.system());
+
+        let first = world.spawn().insert(Didi).id();
+        world.insert_resource(GameData { didi_entity: first });
+        world.despawn(first);
+        let second = world.spawn().insert(Didi).id();
+
+        stage.run(&mut world);
+        assert_eq!(
+            world.get_resource::<GameData>().unwrap().didi_entity,
+            second
+        );
+
+        world.despawn(second);
+        stage.run(&mut world);
+        stage.run(&mut world);
+        let events = world.get_resource::<Events<MissingEntityEvent>>().unwrap();
+        let mut reader = events.get_reader();
+        assert_eq!(reader.iter(events).count(), 1);
+    }
+}
@@ -0,0 +1,128 @@
+//! Feedback of the wrong deliveries: when Baobei receives another item than
+//! the one asked for, an angry emote pops above Baobei with a short red
+//! flash.
+//!
+//! The feedback follows the `WrongDeliveryEvent` of the items, which is also
+//! the hook of the negative sound.
+
+use bevy::{math::const_vec3, prelude::*};
+
+use crate::{collisions::TriggerArea, constants::GameState};
+
+use super::{
+    items::{ItemSystems, WrongDeliveryEvent},
+    materials::GameplayMaterials,
+};
+
+/// Plugin showing the feedback of the wrong deliveries.
+pub struct FeedbackPlugin;
+
+impl Plugin for FeedbackPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<FeedbackMaterials>().add_system_set(
+            SystemSet::on_update(GameState::InGame)
+                .with_system(spawn_feedback_system.system().after(ItemSystems))
+                .with_system(despawn_feedback_system.system()),
+        );
+    }
+}
+
+/// Seconds the angry emote stays above Baobei.
+const EMOTE_DURATION: f32 = 1.5;
+/// Seconds the red flash lasts.
+const FLASH_DURATION: f32 = 0.25;
+/// Index of the angry emotion in the emotion atlas, the saddest one.
+const ANGRY_EMOTION: u32 = 0;
+/// Position of the emote relative to Baobei, above the patience bar.
+const EMOTE_TRANSLATION: Vec3 = const_vec3!([0.0, 470.0, 1.0]);
+
+/// Colors of the feedback.
+struct FeedbackMaterials {
+    /// A red flash over Baobei
+    flash: Handle<ColorMaterial>,
+}
+
+impl FromWorld for FeedbackMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            flash: materials.add(Color::rgba(1.0, 0.1, 0.1, 0.45).into()),
+        }
+    }
+}
+
+/// Component on the emote and the flash, children of Baobei despawned when
+/// the timer finishes.
+struct Feedback(Timer);
+
+/// Pops the angry emote and flashes the asker receiving a wrong item, in
+/// place of its previous feedback.
+fn spawn_feedback_system(
+    mut commands: Commands,
+    materials: Res<GameplayMaterials>,
+    feedback_materials: Res<FeedbackMaterials>,
+    mut wrong_delivery_events: EventReader<WrongDeliveryEvent>,
+    askers: Query<(&TriggerArea, &Transform)>,
+    feedbacks: Query<(Entity, &Parent), With<Feedback>>,
+) {
+    for event in wrong_delivery_events.iter() {
+        info!(
+            "Wrong delivery of {:?} instead of {:?}",
+            event.item, event.asked
+        );
+        let (area, transform) = match askers.get(event.asker) {
+            Ok(asker) => asker,
+            Err(_) => continue,
+        };
+        for (feedback, _) in feedbacks
+            .iter()
+            .filter(|(_, parent)| parent.0 == event.asker)
+        {
+            commands.entity(feedback).despawn_recursive();
+        }
+
+        let emote = commands
+            .spawn()
+            .insert(Feedback(Timer::from_seconds(EMOTE_DURATION, false)))
+            .insert_bundle(SpriteSheetBundle {
+                texture_atlas: materials.emotion_atlas.clone(),
+                sprite: TextureAtlasSprite::new(ANGRY_EMOTION),
+                transform: Transform {
+                    translation: EMOTE_TRANSLATION,
+                    scale: Vec3::new(1.5, 1.5, 1.0),
+                    ..Transform::default()
+                },
+                ..SpriteSheetBundle::default()
+            })
+            .id();
+
+        // The children of the asker are scaled with it
+        let scale = transform.scale.truncate().max(Vec2::splat(f32::EPSILON));
+        let flash = commands
+            .spawn()
+            .insert(Feedback(Timer::from_seconds(FLASH_DURATION, false)))
+            .insert_bundle(SpriteBundle {
+                material: feedback_materials.flash.clone(),
+                sprite: Sprite::new(area.size / scale),
+                transform: Transform::from_xyz(0.0, 0.0, 0.5),
+                ..SpriteBundle::default()
+            })
+            .id();
+
+        commands.entity(event.asker).push_children(&[emote, flash]);
+    }
+}
+
+/// Removes the finished emotes and flashes.
+fn despawn_feedback_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut feedbacks: Query<(Entity, &mut Feedback)>,
+) {
+    for (entity, mut feedback) in feedbacks.iter_mut() {
+        if feedback.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
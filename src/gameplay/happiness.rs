@@ -1,55 +1,210 @@
 //! Systems and components managing the happiness of Baobei.
+//!
+//! The happiness is split in needs: hunger, thirst and fun, each decaying at
+//! its own rate and satisfied only by the items of its category. The mood of
+//! Baobei is the aggregate of the needs.
+
+use std::{fmt, str::FromStr};
+
 use bevy::prelude::*;
 
 use crate::{
+    assists::Assists,
     collisions::Position,
-    constants::{GameState, HAPPINESS_DECREASE},
+    constants::GameState,
+    difficulty::Difficulty,
     drawing::UiObject,
+    scheduler::{ScheduledEvent, Scheduler, SchedulerSystems},
 };
 
-use super::{items::ItemSystems, materials::GameplayMaterials};
+use super::{
+    baby::Baby, items::ItemSystems, levels::Level, materials::GameplayMaterials,
+    phases::PhaseController, shop::UpgradeRegistry, status_effects::StatusEffects, Baobei,
+    NewRunAppExt,
+};
 
 /// Plugin managing the happiness value.
 pub struct HappinessPlugin;
 
 impl Plugin for HappinessPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.insert_resource(HappinessTimer::default())
+        app.add_startup_system(schedule_decay.system())
             .add_startup_system(spawn_happiness_smiley.system())
             .add_startup_system(spawn_debug_text.system())
             .add_system_set(
                 SystemSet::on_update(GameState::InGame)
-                    .with_system(decrease_happiness_system.system())
+                    .with_system(decrease_happiness_system.system().after(SchedulerSystems))
                     .with_system(text_update_system.system())
                     .with_system(update_happiness_sprite_system.system().after(ItemSystems)),
-            );
+            )
+            .add_new_run_system(reset_happiness_system);
+    }
+}
+
+/// A need of Baobei, satisfied by a category of items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Need {
+    /// Satisfied by the foods
+    Hunger,
+    /// Satisfied by the drinks
+    Thirst,
+    /// Satisfied by the other items, like a blanket or a phone
+    Fun,
+}
+
+impl Need {
+    /// All the needs, in the order of the meters.
+    pub const ALL: [Self; 3] = [Self::Hunger, Self::Thirst, Self::Fun];
+
+    /// Returns the multiplier of the decay of the need.
+    const fn decay_rate(self) -> f32 {
+        match self {
+            Self::Hunger => 1.0,
+            Self::Thirst => 1.3,
+            Self::Fun => 0.7,
+        }
+    }
+
+    /// Returns the index of the meter of the need.
+    const fn index(self) -> usize {
+        match self {
+            Self::Hunger => 0,
+            Self::Thirst => 1,
+            Self::Fun => 2,
+        }
+    }
+
+    /// Returns the name of the need displayed in the HUD.
+    const fn label(self) -> &'static str {
+        match self {
+            Self::Hunger => "Hunger",
+            Self::Thirst => "Thirst",
+            Self::Fun => "Fun",
+        }
+    }
+}
+
+impl fmt::Display for Need {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Hunger => "hunger",
+            Self::Thirst => "thirst",
+            Self::Fun => "fun",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Need {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "hunger" => Ok(Self::Hunger),
+            "thirst" => Ok(Self::Thirst),
+            "fun" => Ok(Self::Fun),
+            _ => Err(format!("Unknown need: {}", name)),
+        }
+    }
+}
+
+/// Meter of a need decaying over time, between 0 (unsatisfied) and 1
+/// (satisfied), shared by Baobei and the plants.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NeedMeter(f32);
+
+impl NeedMeter {
+    /// A need fully satisfied.
+    pub const FULL: Self = Self(1.0);
+
+    /// Returns the meter, between 0 and 1.
+    pub const fn value(self) -> f32 {
+        self.0
+    }
+
+    /// Adds the given value to the meter and clamps the result between 0
+    /// and 1.
+    pub fn satisfy(&mut self, value: f32) {
+        self.0 = (self.0 + value).clamp(0.0, 1.0);
+    }
+
+    /// Subtracts the given amount from the meter, without going under 0.
+    pub fn decay(&mut self, amount: f32) {
+        self.satisfy(-amount);
+    }
+
+    /// Returns true once the meter is down to 0.
+    pub fn is_empty(self) -> bool {
+        self.0 <= 0.0
+    }
+}
+
+impl Default for NeedMeter {
+    fn default() -> Self {
+        Self::FULL
     }
 }
 
-/// Component representing the  for the happiness of the entity (Baobei).
-/// Between 0 and 1.
-pub struct Happiness(f32);
+/// Component holding the meters of the needs of the entity (Baobei).
+pub struct Happiness {
+    /// Meters of the needs, in the order of `Need::ALL`
+    meters: [NeedMeter; 3],
+}
 
 impl Happiness {
-    /// Returns a happiness of 100%.
+    /// Returns a happiness of 100%, every need being satisfied.
     pub const fn happy() -> Self {
-        Self(1.0)
+        Self {
+            meters: [NeedMeter::FULL; 3],
+        }
     }
 
-    /// Adds the given value and clamps the result between 0 and 1
-    pub fn add(&mut self, value: f32) {
-        self.0 += value;
+    /// Returns the mood, the mean of the needs between 0 and 1.
+    // There are only a few needs
+    #[allow(clippy::cast_precision_loss)]
+    pub fn value(&self) -> f32 {
+        self.meters
+            .iter()
+            .copied()
+            .map(NeedMeter::value)
+            .sum::<f32>()
+            / self.meters.len() as f32
+    }
 
-        // TODO: Use `clamp` when stable
-        if self.0 > 1.0 {
-            self.0 = 1.0;
-        }
-        if self.0 < 0.0 {
-            self.0 = 0.0;
+    /// Returns the meter of the need, between 0 and 1.
+    pub const fn meter(&self, need: Need) -> f32 {
+        self.meters[need.index()].value()
+    }
+
+    /// Returns the least satisfied need.
+    pub fn lowest_need(&self) -> Need {
+        Need::ALL
+            .iter()
+            .copied()
+            .fold(Need::Hunger, |lowest, need| {
+                if self.meter(need) < self.meter(lowest) {
+                    need
+                } else {
+                    lowest
+                }
+            })
+    }
+
+    /// Adds the given value to the need and clamps the result between 0 and 1.
+    pub fn satisfy(&mut self, need: Need, value: f32) {
+        self.meters[need.index()].satisfy(value);
+    }
+
+    /// Adds the given value to every need, changing the mood as much, and
+    /// clamps the results between 0 and 1.
+    pub fn add(&mut self, value: f32) {
+        for need in Need::ALL {
+            self.satisfy(need, value);
         }
     }
 
-    /// Subtracts the given value and clamps the result between 0 and 1
+    /// Subtracts the given value from every need and clamps the results
+    /// between 0 and 1.
     pub fn sub(&mut self, value: f32) {
         self.add(-value)
     }
@@ -72,46 +227,94 @@ fn spawn_happiness_smiley(mut commands: Commands, materials: Res<GameplayMateria
         });
 }
 
-/// Timer of the decrease of the happiness over time.
-struct HappinessTimer(Timer);
+/// Scheduled task decreasing the needs every second.
+const DECAY_TASK: &str = "happiness_decay";
+/// Meter under which a need makes Baobei sad whatever the other needs.
+const UNSATISFIED_NEED: f32 = 0.2; // 20%
 
-impl Default for HappinessTimer {
-    fn default() -> Self {
-        Self(Timer::from_seconds(1.0, true))
+/// Schedules the decrease of the needs over time.
+fn schedule_decay(mut scheduler: ResMut<Scheduler>) {
+    scheduler.every(DECAY_TASK, 1.0);
+}
+
+/// Returns the happiness of the saddest entity, if any happiness changed.
+fn lowest_changed_happiness<'a>(
+    changed: &Query<(), Changed<Happiness>>,
+    happiness_values: &'a Query<&Happiness>,
+) -> Option<&'a Happiness> {
+    changed.iter().next()?;
+    happiness_values.iter().reduce(|lowest, happiness| {
+        if happiness.value() < lowest.value() {
+            happiness
+        } else {
+            lowest
+        }
+    })
+}
+
+/// Returns the emotion of the atlas, from the saddest to the happiest, shown
+/// for the happiness: the mood, or the saddest emotion when a need is left
+/// unsatisfied.
+// The mood is between 0 and 1 and the emotions are a few
+#[allow(
+    clippy::cast_sign_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss
+)]
+fn emotion_index(happiness: &Happiness, emotion_count: usize) -> u32 {
+    if emotion_count == 0 || happiness.meter(happiness.lowest_need()) < UNSATISFIED_NEED {
+        return 0;
     }
+    let index = (happiness.value() * emotion_count as f32) as usize;
+    index.min(emotion_count - 1) as u32
 }
 
-/// Update the Happiness smiley image depending on the new happiness value.
+/// Update the Happiness smiley image depending on the saddest Baobei.
 fn update_happiness_sprite_system(
     texture_atlases: Res<Assets<TextureAtlas>>,
-    mut sprites: Query<(&mut TextureAtlasSprite, &Handle<TextureAtlas>)>,
-    happiness_values: Query<&Happiness, Changed<Happiness>>,
+    mut sprites: Query<(&mut TextureAtlasSprite, &Handle<TextureAtlas>), With<UiObject>>,
+    changed: Query<(), Changed<Happiness>>,
+    happiness_values: Query<&Happiness>,
 ) {
-    for happiness_value in happiness_values.iter() {
+    if let Some(happiness) = lowest_changed_happiness(&changed, &happiness_values) {
         for (mut sprite, texture_atlas_handle) in sprites.iter_mut() {
             let texture_atlas = texture_atlases.get(texture_atlas_handle).unwrap();
-            let nb_sprites = texture_atlas.textures.len() as f32;
-
-            // Happiness is between 0 and 1 and the result index is a small number
-            #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
-            let sprite_index = (happiness_value.0 * nb_sprites) as u32;
-
-            sprite.index = sprite_index;
+            sprite.index = emotion_index(happiness, texture_atlas.textures.len());
         }
     }
 }
 
-/// Update the value of the happiness text.
+/// Decreases the needs over time at their own rate, except when Baobei naps
+/// or in kid mode, slower with the upgrade of the shop and faster while the
+/// baby cries.
 fn decrease_happiness_system(
-    time: Res<Time>,
-    mut timer: ResMut<HappinessTimer>,
-    mut happiness_values: Query<&mut Happiness>,
+    assists: Res<Assists>,
+    difficulty: Res<Difficulty>,
+    phases: Res<PhaseController>,
+    level: Res<Level>,
+    upgrades: Res<UpgradeRegistry>,
+    mut scheduled_events: EventReader<ScheduledEvent>,
+    babies: Query<&Baby>,
+    mut happiness_values: Query<(&mut Happiness, Option<&StatusEffects>)>,
 ) {
-    if !timer.0.tick(time.delta()).just_finished() {
+    let due = scheduled_events
+        .iter()
+        .any(|ScheduledEvent(task)| *task == DECAY_TASK);
+
+    if !due || phases.is_breather() || assists.no_fail {
         return;
     }
-    for mut happiness in happiness_values.iter_mut() {
-        happiness.sub(HAPPINESS_DECREASE);
+    for (mut happiness, status_effects) in happiness_values.iter_mut() {
+        let effects_multiplier = status_effects.map_or(1.0, StatusEffects::decay_multiplier);
+        let decrease = difficulty.profile().happiness_decrease
+            * phases.decay_multiplier()
+            * level.decay_multiplier()
+            * upgrades.decay_multiplier()
+            * babies.iter().map(Baby::decay_multiplier).product::<f32>()
+            * effects_multiplier;
+        for need in Need::ALL {
+            happiness.satisfy(need, -decrease * need.decay_rate());
+        }
     }
 }
 
@@ -141,14 +344,61 @@ pub fn spawn_debug_text(mut commands: Commands, asset_server: Res<AssetServer>)
         });
 }
 
-/// Update the value of the happiness text.
+/// Returns the text showing the mood and the needs of the happiness.
+fn happiness_label(happiness: &Happiness) -> String {
+    Need::ALL.iter().fold(
+        format!("Happiness: {:.2}", happiness.value()),
+        |label, need| {
+            format!(
+                "{} | {}: {:.2}",
+                label,
+                need.label(),
+                happiness.meter(*need)
+            )
+        },
+    )
+}
+
+/// Update the value of the happiness text with the saddest Baobei.
 fn text_update_system(
     mut happiness_text: Query<&mut Text, With<HappinessText>>,
-    happiness_value: Query<&Happiness, Changed<Happiness>>,
+    changed: Query<(), Changed<Happiness>>,
+    happiness_values: Query<&Happiness>,
 ) {
     for mut text in happiness_text.iter_mut() {
-        if let Some(value) = happiness_value.iter().next() {
-            text.sections[0].value = format!("Happiness: {:.2}", value.0);
+        if let Some(happiness) = lowest_changed_happiness(&changed, &happiness_values) {
+            text.sections[0].value = happiness_label(happiness);
         }
     }
 }
+
+/// Satisfies all the needs of Baobei when a new game starts.
+pub fn reset_happiness_system(mut baobei: Query<&mut Happiness, With<Baobei>>) {
+    for mut happiness in baobei.iter_mut() {
+        *happiness = Happiness::happy();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn needs_make_the_mood() {
+        let mut happiness = Happiness::happy();
+        assert_eq!(emotion_index(&happiness, 5), 4);
+
+        happiness.satisfy(Need::Thirst, -0.9);
+        assert_eq!(happiness.lowest_need(), Need::Thirst);
+        assert!((happiness.value() - 0.7).abs() < 1e-5);
+        // A need left unsatisfied shows the saddest emotion
+        assert_eq!(emotion_index(&happiness, 5), 0);
+
+        happiness.satisfy(Need::Thirst, 0.5);
+        happiness.sub(0.2);
+        assert!((happiness.meter(Need::Hunger) - 0.8).abs() < 1e-5);
+        assert!((happiness.meter(Need::Thirst) - 0.4).abs() < 1e-5);
+        assert_eq!(emotion_index(&happiness, 5), 3);
+        assert_eq!("fun".parse(), Ok(Need::Fun));
+    }
+}
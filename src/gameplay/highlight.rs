@@ -0,0 +1,258 @@
+//! Highlight of the interaction target: the entity Didi acts on when the
+//! player presses `Space`, resolved from the contacts of Didi with the same
+//! priorities as the actions.
+//!
+//! The resolver sends a `TargetEvent` when the target changes, which toggles
+//! a pulsing outline behind the targeted entity.
+
+use bevy::prelude::*;
+
+use crate::{
+    collisions::{Contact, TriggerArea},
+    constants::GameState,
+};
+
+use super::{
+    entities::GameData,
+    interactables::Interactable,
+    items::{CarriedItem, Inventory, Item, ItemRequestQueue},
+    storage::Storage,
+};
+
+/// Plugin managing the interaction target and its highlight.
+pub struct HighlightPlugin;
+
+impl Plugin for HighlightPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<TargetEvent>()
+            .init_resource::<InteractionTarget>()
+            .init_resource::<HighlightMaterials>()
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(select_target_system.system().label("select_target"))
+                    .with_system(toggle_highlight_system.system().after("select_target"))
+                    .with_system(pulse_highlight_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Menu).with_system(clear_target_system.system()),
+            );
+    }
+}
+
+/// Seconds of a pulse of the highlight.
+const PULSE_PERIOD: f32 = 1.0;
+/// Growth of the highlight at the top of a pulse.
+const PULSE_AMPLITUDE: f32 = 0.08; // 8%
+/// Size of the highlight compared to the trigger area of the target.
+const HIGHLIGHT_MARGIN: f32 = 1.1;
+
+/// Event sent when the interaction target changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetEvent {
+    /// The entity becomes the target
+    Selected(Entity),
+    /// The entity is no longer the target
+    Deselected(Entity),
+}
+
+/// The entity Didi acts on when the player presses `Space`.
+#[derive(Debug, Default)]
+pub struct InteractionTarget(Option<Entity>);
+
+impl InteractionTarget {
+    /// Returns the targeted entity, if any.
+    pub const fn entity(&self) -> Option<Entity> {
+        self.0
+    }
+}
+
+/// Kinds of interaction targets, in the order the actions try them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum TargetKind {
+    /// A furniture to interact with, like a producer to take an item from
+    Interactable,
+    /// A Baobei or a guest to give the carried item to
+    Asker,
+    /// A furniture to store the carried item in or retrieve one
+    Storage,
+    /// An item on the ground to pick up
+    GroundItem,
+}
+
+/// Returns the entity in contact the action applies to, among the entities
+/// with their kind. The askers need a carried item, the ground items need
+/// empty hands.
+fn resolve_target(
+    candidates: impl IntoIterator<Item = (Entity, TargetKind)>,
+    carrying: bool,
+) -> Option<Entity> {
+    candidates
+        .into_iter()
+        .filter(|(_, kind)| match kind {
+            TargetKind::Asker => carrying,
+            TargetKind::GroundItem => !carrying,
+            TargetKind::Interactable | TargetKind::Storage => true,
+        })
+        .min_by_key(|(_, kind)| *kind)
+        .map(|(entity, _)| entity)
+}
+
+/// Color of the highlights.
+struct HighlightMaterials {
+    /// A soft glow behind the target
+    outline: Handle<ColorMaterial>,
+}
+
+impl FromWorld for HighlightMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            outline: materials.add(Color::rgba(1.0, 1.0, 0.8, 0.35).into()),
+        }
+    }
+}
+
+/// Component on the highlight, a child of the target.
+struct Highlight(Timer);
+
+/// Resolves the interaction target of Didi and sends the events when it
+/// changes.
+#[allow(clippy::too_many_arguments)]
+fn select_target_system(
+    game_data: Res<GameData>,
+    mut target: ResMut<InteractionTarget>,
+    mut target_events: EventWriter<TargetEvent>,
+    contacts: Query<&Contact>,
+    inventories: Query<&Inventory>,
+    interactables: Query<(), With<Interactable>>,
+    askers: Query<(), With<ItemRequestQueue>>,
+    storages: Query<(), With<Storage>>,
+    ground_items: Query<(), (With<Item>, Without<CarriedItem>)>,
+) {
+    let didi = game_data.didi_entity;
+    let carrying = inventories
+        .get(didi)
+        .map_or(false, |inventory| inventory.active_item().is_some());
+
+    let candidates = contacts
+        .iter()
+        .filter(|contact| contact.0 == didi)
+        .filter_map(|Contact(_, entity)| {
+            let kind = if interactables.get(*entity).is_ok() {
+                TargetKind::Interactable
+            } else if askers.get(*entity).is_ok() {
+                TargetKind::Asker
+            } else if storages.get(*entity).is_ok() {
+                TargetKind::Storage
+            } else if ground_items.get(*entity).is_ok() {
+                TargetKind::GroundItem
+            } else {
+                return None;
+            };
+            Some((*entity, kind))
+        });
+
+    let selected = resolve_target(candidates, carrying);
+    if selected == target.0 {
+        return;
+    }
+    if let Some(previous) = target.0 {
+        target_events.send(TargetEvent::Deselected(previous));
+    }
+    if let Some(entity) = selected {
+        target_events.send(TargetEvent::Selected(entity));
+    }
+    target.0 = selected;
+}
+
+/// Adds the highlight behind the selected target and removes it from the
+/// deselected one.
+fn toggle_highlight_system(
+    mut commands: Commands,
+    materials: Res<HighlightMaterials>,
+    mut target_events: EventReader<TargetEvent>,
+    targets: Query<(&TriggerArea, &Transform)>,
+    highlights: Query<(Entity, &Parent), With<Highlight>>,
+) {
+    for event in target_events.iter() {
+        match *event {
+            TargetEvent::Selected(entity) => {
+                let (area, transform) = match targets.get(entity) {
+                    Ok(target) => target,
+                    Err(_) => continue,
+                };
+                // The children of the target are scaled with it
+                let scale = transform.scale.truncate().max(Vec2::splat(f32::EPSILON));
+                let size = area.size * HIGHLIGHT_MARGIN / scale;
+
+                let highlight = commands
+                    .spawn()
+                    .insert(Highlight(Timer::from_seconds(PULSE_PERIOD, true)))
+                    .insert_bundle(SpriteBundle {
+                        material: materials.outline.clone(),
+                        sprite: Sprite::new(size),
+                        transform: Transform::from_xyz(0.0, 0.0, -0.1),
+                        ..SpriteBundle::default()
+                    })
+                    .id();
+                commands.entity(entity).push_children(&[highlight]);
+            }
+            TargetEvent::Deselected(entity) => {
+                for (highlight, _) in highlights.iter().filter(|(_, parent)| parent.0 == entity) {
+                    commands.entity(highlight).despawn_recursive();
+                }
+            }
+        }
+    }
+}
+
+/// Makes the highlights grow and shrink softly.
+fn pulse_highlight_system(
+    time: Res<Time>,
+    mut highlights: Query<(&mut Highlight, &mut Transform)>,
+) {
+    for (mut highlight, mut transform) in highlights.iter_mut() {
+        highlight.0.tick(time.delta());
+        let phase = highlight.0.percent() * std::f32::consts::TAU;
+        let scale = PULSE_AMPLITUDE.mul_add(phase.sin().mul_add(0.5, 0.5), 1.0);
+        transform.scale = Vec3::new(scale, scale, 1.0);
+    }
+}
+
+/// Removes the highlight when the game ends.
+fn clear_target_system(
+    mut commands: Commands,
+    mut target: ResMut<InteractionTarget>,
+    highlights: Query<Entity, With<Highlight>>,
+) {
+    for highlight in highlights.iter() {
+        commands.entity(highlight).despawn_recursive();
+    }
+    target.0 = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_follows_the_priorities_of_the_actions() {
+        let producer = Entity::new(1);
+        let baobei = Entity::new(2);
+        let ground_item = Entity::new(3);
+        let candidates = [
+            (ground_item, TargetKind::GroundItem),
+            (baobei, TargetKind::Asker),
+            (producer, TargetKind::Interactable),
+        ];
+
+        assert_eq!(resolve_target(candidates, false), Some(producer));
+        assert_eq!(
+            resolve_target(candidates[..2].to_vec(), false),
+            Some(ground_item)
+        );
+        assert_eq!(resolve_target(candidates[..2].to_vec(), true), Some(baobei));
+        assert_eq!(resolve_target([], true), None);
+    }
+}
@@ -0,0 +1,177 @@
+//! Heads-up display showing the score, or the stars in kid mode, the combo and
+//! the survival countdown during the game.
+
+use bevy::prelude::*;
+
+use crate::{assists::Assists, constants::GameState};
+
+use super::{
+    items::ComboState,
+    kid_mode::Stars,
+    score::Score,
+    survival::{format_countdown, Survival},
+};
+
+/// Plugin managing the heads-up display.
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_startup_system(spawn_score_text.system())
+            .add_startup_system(spawn_countdown_text.system())
+            .add_startup_system(spawn_combo_text.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(update_score_text_system.system())
+                    .with_system(update_combo_text_system.system())
+                    .with_system(update_countdown_text_system.system()),
+            );
+    }
+}
+
+/// Tag the text displaying the score.
+struct ScoreText;
+/// Tag the text displaying the countdown of the survival mode.
+struct CountdownText;
+/// Tag the text displaying the combo counter.
+struct ComboText;
+
+/// Spawns the text showing the score in the top right corner.
+fn spawn_score_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn()
+        .insert(ScoreText)
+        .insert_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(20.0),
+                    right: Val::Px(40.0),
+                    ..Rect::default()
+                },
+                ..Style::default()
+            },
+            text: Text::with_section(
+                "Score: 0",
+                TextStyle {
+                    font: asset_server.load("FiraSans-Bold.ttf"),
+                    font_size: 40.0,
+                    color: Color::WHITE,
+                },
+                TextAlignment::default(),
+            ),
+            ..TextBundle::default()
+        });
+}
+
+/// Updates the score text when the score changes, showing the collected
+/// stars instead in kid mode.
+fn update_score_text_system(
+    assists: Res<Assists>,
+    score: Res<Score>,
+    stars: Res<Stars>,
+    mut texts: Query<&mut Text, With<ScoreText>>,
+) {
+    if !assists.is_changed() && !score.is_changed() && !stars.is_changed() {
+        return;
+    }
+    let value = if assists.stars {
+        format!("Stars: {}", stars.0)
+    } else {
+        format!("Score: {}", score.points())
+    };
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
+
+/// Spawns the text showing the combo counter below the countdown.
+fn spawn_combo_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn()
+        .insert(ComboText)
+        .insert_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(120.0),
+                    right: Val::Px(40.0),
+                    ..Rect::default()
+                },
+                ..Style::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("FiraSans-Bold.ttf"),
+                    font_size: 40.0,
+                    color: Color::GOLD,
+                },
+                TextAlignment::default(),
+            ),
+            ..TextBundle::default()
+        });
+}
+
+/// Updates the combo counter, empty until two deliveries are chained.
+fn update_combo_text_system(combo: Res<ComboState>, mut texts: Query<&mut Text, With<ComboText>>) {
+    if !combo.is_changed() {
+        return;
+    }
+    let counter = if combo.count() > 1 {
+        format!("Combo x{}", combo.count())
+    } else {
+        String::new()
+    };
+
+    for mut text in texts.iter_mut() {
+        if text.sections[0].value != counter {
+            text.sections[0].value = counter.clone();
+        }
+    }
+}
+
+/// Spawns the text showing the survival countdown below the score.
+fn spawn_countdown_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn()
+        .insert(CountdownText)
+        .insert_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(70.0),
+                    right: Val::Px(40.0),
+                    ..Rect::default()
+                },
+                ..Style::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("FiraSans-Bold.ttf"),
+                    font_size: 40.0,
+                    color: Color::WHITE,
+                },
+                TextAlignment::default(),
+            ),
+            ..TextBundle::default()
+        });
+}
+
+/// Updates the countdown text, empty outside of the survival mode.
+fn update_countdown_text_system(
+    survival: Res<Survival>,
+    mut texts: Query<&mut Text, With<CountdownText>>,
+) {
+    let countdown = survival
+        .remaining_secs()
+        .map(|seconds| format!("Time left: {}", format_countdown(seconds)))
+        .unwrap_or_default();
+
+    for mut text in texts.iter_mut() {
+        if text.sections[0].value != countdown {
+            text.sections[0].value = countdown.clone();
+        }
+    }
+}
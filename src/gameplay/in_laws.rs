@@ -0,0 +1,226 @@
+//! Boss round where the in-laws visit: they sit at the table and ask for
+//! items in rapid fire, complaining to Baobei when they wait too long.
+//! Keeping Baobei happy during the whole visit earns a big score bonus, an
+//! affection bonus and a unique achievement.
+
+use bevy::prelude::*;
+
+use crate::{
+    collisions::{Position, TriggerArea},
+    constants::{GameState, IN_LAWS_INTERVAL, IN_LAW_COMPLAINT, IN_LAW_PATIENCE},
+    rng::GameRng,
+    time_scale::TimeScale,
+    widgets::{entity_timer_system, spawn_timer_bar, EntityTimer, Progress, WidgetMaterials},
+};
+
+use super::{
+    happiness::Happiness,
+    items::{spawn_asked_items, Item, ItemRequestQueue},
+    materials::GameplayMaterials,
+    phases::{PhaseEvent, PhaseKind},
+    registry::ItemRegistry,
+    score::Score,
+    Baobei, NewRunAppExt,
+};
+
+/// Plugin managing the visit of the in-laws.
+pub struct InLawsPlugin;
+
+impl Plugin for InLawsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<BossRoundEvent>()
+            .init_resource::<BossRound>()
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(boss_round_system.system().after("phase_controller"))
+                    .with_system(in_law_patience_system.system().label("in_law_patience"))
+                    .with_system(
+                        entity_timer_system::<InLaw>
+                            .system()
+                            .after("in_law_patience"),
+                    )
+                    .with_system(survival_system.system())
+                    .with_system(in_laws_score_system.system()),
+            )
+            .add_new_run_system(reset_boss_round_system);
+    }
+}
+
+/// Event sent when the in-laws leave.
+pub struct BossRoundEvent {
+    /// Whether Baobei stayed happy during the whole visit.
+    pub survived: bool,
+}
+
+/// State of the visit of the in-laws.
+#[derive(Default)]
+struct BossRound {
+    /// Whether the in-laws are visiting.
+    active: bool,
+    /// Whether Baobei stayed happy since the beginning of the visit.
+    survived: bool,
+}
+
+/// Component on an in-law, asking items with a strict patience.
+struct InLaw {
+    /// Timer until the in-law complains about the wait.
+    patience: Timer,
+}
+
+impl Progress for InLaw {
+    fn remaining(&self) -> f32 {
+        self.patience.remaining()
+    }
+}
+
+/// Starts the visit every few phases and ends it at the next breather.
+fn boss_round_system(
+    mut commands: Commands,
+    materials: Res<GameplayMaterials>,
+    widget_materials: Res<WidgetMaterials>,
+    registry: Res<ItemRegistry>,
+    mut rng: ResMut<GameRng>,
+    mut boss_round: ResMut<BossRound>,
+    mut phase_events: EventReader<PhaseEvent>,
+    mut boss_round_events: EventWriter<BossRoundEvent>,
+    in_laws: Query<Entity, With<InLaw>>,
+) {
+    for event in phase_events.iter() {
+        match event.kind {
+            PhaseKind::Requests if event.number % IN_LAWS_INTERVAL == 0 => {
+                info!("The in-laws visit!");
+                *boss_round = BossRound {
+                    active: true,
+                    survived: true,
+                };
+                for &position in &[Vec3::new(180.0, 230.0, 60.0), Vec3::new(420.0, 230.0, 60.0)] {
+                    let asked_item = registry.random_request(&mut rng.rng);
+                    spawn_in_law(
+                        &mut commands,
+                        &materials,
+                        &widget_materials,
+                        position,
+                        asked_item,
+                    );
+                }
+            }
+            PhaseKind::Breather if boss_round.active => {
+                info!("The in-laws leave, survived: {}", boss_round.survived);
+                boss_round.active = false;
+                boss_round_events.send(BossRoundEvent {
+                    survived: boss_round.survived,
+                });
+
+                for in_law in in_laws.iter() {
+                    commands.entity(in_law).despawn_recursive();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Spawns an in-law sitting at the given position and asking for the item.
+fn spawn_in_law(
+    commands: &mut Commands,
+    materials: &GameplayMaterials,
+    widget_materials: &WidgetMaterials,
+    position: Vec3,
+    asked_item: Item,
+) {
+    let asked_items = ItemRequestQueue::new(asked_item);
+    let in_law = commands
+        .spawn()
+        .insert(InLaw {
+            patience: Timer::from_seconds(IN_LAW_PATIENCE, true),
+        })
+        .insert(Position(position))
+        .insert(TriggerArea::new(150.0, 150.0))
+        .insert_bundle(SpriteBundle {
+            material: materials.in_law_sprite.clone(),
+            transform: Transform::from_scale(Vec3::new(0.3, 0.3, 0.0)),
+            ..SpriteBundle::default()
+        })
+        .with_children(|parent| spawn_asked_items(parent, materials, &asked_items))
+        .insert(asked_items)
+        .id();
+
+    let patience_bar = spawn_timer_bar(
+        commands,
+        widget_materials,
+        Vec3::new(0.0, 330.0, 0.0),
+        Vec2::new(300.0, 30.0),
+    );
+    commands
+        .entity(patience_bar)
+        .insert(EntityTimer::<InLaw>::new(in_law));
+    commands.entity(in_law).push_children(&[patience_bar]);
+}
+
+/// Makes the in-laws complain to Baobei and ask for something else when
+/// they wait too long, their patience restarts when they are served.
+fn in_law_patience_system(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    registry: Res<ItemRegistry>,
+    mut rng: ResMut<GameRng>,
+    mut in_laws: Query<(&mut InLaw, &mut ItemRequestQueue)>,
+    mut baobei_happiness: Query<&mut Happiness, With<Baobei>>,
+) {
+    let delta = time_scale.scale(time.delta());
+
+    for (mut in_law, mut requests) in in_laws.iter_mut() {
+        if requests.is_changed() {
+            in_law.patience.reset();
+        }
+        if !in_law.patience.tick(delta).just_finished() {
+            continue;
+        }
+
+        for mut happiness in baobei_happiness.iter_mut() {
+            happiness.sub(IN_LAW_COMPLAINT);
+        }
+
+        if let Some(asked_item) = requests.front() {
+            *requests = ItemRequestQueue::new(registry.random_different(&mut rng.rng, asked_item));
+        }
+    }
+}
+
+/// Fails the visit when Baobei becomes completely unhappy.
+fn survival_system(
+    mut boss_round: ResMut<BossRound>,
+    happiness_values: Query<&Happiness, (With<Baobei>, Changed<Happiness>)>,
+) {
+    if !boss_round.active {
+        return;
+    }
+    if happiness_values
+        .iter()
+        .any(|happiness| happiness.value() <= 0.0)
+    {
+        boss_round.survived = false;
+    }
+}
+
+/// Gives a big score bonus when Baobei stayed happy during the visit.
+fn in_laws_score_system(
+    mut score: ResMut<Score>,
+    mut boss_round_events: EventReader<BossRoundEvent>,
+) {
+    for _ in boss_round_events.iter().filter(|event| event.survived) {
+        score.reward_in_laws();
+    }
+}
+
+/// Sends the in-laws home when a new game starts during their visit.
+fn reset_boss_round_system(
+    mut commands: Commands,
+    mut boss_round: ResMut<BossRound>,
+    in_laws: Query<Entity, With<InLaw>>,
+) {
+    *boss_round = BossRound::default();
+    for in_law in in_laws.iter() {
+        commands.entity(in_law).despawn_recursive();
+    }
+}
@@ -0,0 +1,537 @@
+//! Furniture Didi interacts with: taking an item from a producer, opening the
+//! fridge to choose an item, ordering one with the phone, sitting on the
+//! couch, washing hands or rocking the crib.
+//!
+//! The interactables are used before the other actions of the items, and a
+//! contextual prompt above Didi tells what the action control does.
+
+use bevy::prelude::*;
+
+use crate::{
+    collisions::{Contact, Position},
+    constants::{GameState, SIT_ENERGY},
+    controllers::InputMap,
+    drawing::Overlay,
+    locale::Language,
+    settings::Settings,
+};
+
+use super::{
+    containers::Container,
+    coop::{helper_action_pressed, HelperCooldown, Player},
+    energy::Energy,
+    entities::GameData,
+    items::{ActionEvent, CarriedItem, Inventory, Item, ItemProducer, PickAndDropCooldown},
+    placement::DropPlacement,
+    prompt::ConsumePrompt,
+    registry::ItemRegistry,
+    status_effects::{StatusEffectKind, StatusEffects},
+};
+
+/// Plugin managing the interactable furniture.
+pub struct InteractablesPlugin;
+
+impl Plugin for InteractablesPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<SelectionEvent>()
+            .init_resource::<SelectionMenu>()
+            .add_startup_system(spawn_interaction_text.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(selection_menu_system.system().label("selection_menu"))
+                    .with_system(
+                        interact_system
+                            .system()
+                            .after("selection_menu")
+                            .before("item_actions"),
+                    )
+                    .with_system(update_interaction_text_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::InGame).with_system(close_menu_system.system()),
+            );
+    }
+}
+
+/// Height of the prompt above Didi.
+const TEXT_HEIGHT: f32 = 150.0;
+
+/// Action done with an interactable furniture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InteractAction {
+    /// Takes the produced item, or puts it back
+    Take,
+    /// Opens the furniture to choose one of its items
+    Open,
+    /// Orders one of the items offered by the furniture
+    Order,
+    /// Sits down to rest a bit
+    SitOn,
+    /// Washes the hands to act quicker
+    WashHands,
+    /// Rocks the crib while the action key is held
+    Rock,
+    /// Waters the plant with the glass in hand
+    Water,
+}
+
+impl InteractAction {
+    /// Returns the name of the action shown in the prompt.
+    const fn label(self, language: Language) -> &'static str {
+        match (self, language) {
+            (Self::Take, Language::English) => "Take",
+            (Self::Take, Language::French) => "Prendre",
+            (Self::Open, Language::English) => "Open",
+            (Self::Open, Language::French) => "Ouvrir",
+            (Self::Order, Language::English) => "Order",
+            (Self::Order, Language::French) => "Commander",
+            (Self::SitOn, Language::English) => "Sit down",
+            (Self::SitOn, Language::French) => "S'asseoir",
+            (Self::WashHands, Language::English) => "Wash hands",
+            (Self::WashHands, Language::French) => "Se laver les mains",
+            (Self::Rock, Language::English) => "Rock the baby (hold)",
+            (Self::Rock, Language::French) => "Bercer le bébé (maintenir)",
+            (Self::Water, Language::English) => "Water the plant",
+            (Self::Water, Language::French) => "Arroser la plante",
+        }
+    }
+}
+
+/// Component on the furniture the players interact with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interactable(pub InteractAction);
+
+/// Component on an opened furniture with the items to choose from, sharing
+/// the stock of its producer if any.
+pub struct Selection(pub Vec<Item>);
+
+/// Event sent when Didi chooses an item of a furniture without producer.
+pub struct SelectionEvent {
+    /// The furniture offering the item
+    pub furniture: Entity,
+    /// The chosen item
+    pub item: Item,
+}
+
+/// Menu choosing the item of the furniture opened by Didi.
+#[derive(Debug, Default)]
+pub struct SelectionMenu {
+    /// The opened furniture, if any
+    opened: Option<Entity>,
+    /// Index of the highlighted item
+    choice: usize,
+}
+
+impl SelectionMenu {
+    /// Returns true while Didi chooses an item.
+    pub const fn is_open(&self) -> bool {
+        self.opened.is_some()
+    }
+}
+
+/// Returns the index reached from the index by the step, wrapping around the
+/// items.
+// The selections hold a few items
+#[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+const fn cycle(index: usize, step: isize, count: usize) -> usize {
+    if count == 0 {
+        return 0;
+    }
+    (index as isize + step).rem_euclid(count as isize) as usize
+}
+
+/// Returns the action of the player using the producer: taking the item,
+/// putting it back or keeping the carried one.
+fn producer_action(
+    player: Entity,
+    carried: Option<Item>,
+    has_room: bool,
+    container_accepts: bool,
+    producer: &mut ItemProducer,
+    item: Item,
+) -> Option<ActionEvent> {
+    match carried {
+        Some(carried) if carried.is_container() => {
+            if container_accepts && producer.take() {
+                Some(ActionEvent::Take(player, item))
+            } else {
+                Some(ActionEvent::Keep(player, carried))
+            }
+        }
+        Some(carried) if carried == item => {
+            producer.put_back();
+            Some(ActionEvent::PutAway(player, carried))
+        }
+        Some(carried) if !has_room || producer.is_empty() => {
+            Some(ActionEvent::Keep(player, carried))
+        }
+        _ if producer.take() => Some(ActionEvent::Take(player, item)),
+        _ => {
+            info!("The {:?} is out of stock", item);
+            None
+        }
+    }
+}
+
+/// Returns true if the container in the active hand of the player has room
+/// for the item.
+fn container_accepts(
+    carried_containers: &Query<(&CarriedItem, &Parent, &Container)>,
+    player: Entity,
+    inventory: &Inventory,
+    item: Item,
+) -> bool {
+    carried_containers
+        .iter()
+        .find(|(carried, parent, _)| parent.0 == player && carried.0 == inventory.active)
+        .map_or(false, |(_, _, container)| container.accepts(item))
+}
+
+/// Uses the interactable each player stands next to when pressing the
+/// action control. The helpers of the co-op take the first item of the
+/// opened furniture.
+#[allow(clippy::too_many_arguments)]
+fn interact_system(
+    keyboard: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    input_map: Res<InputMap>,
+    placement: Res<DropPlacement>,
+    prompt: Res<ConsumePrompt>,
+    mut cooldown: ResMut<PickAndDropCooldown>,
+    mut menu: ResMut<SelectionMenu>,
+    mut action_events: EventWriter<ActionEvent>,
+    contacts: Query<&Contact>,
+    mut interactables: Query<(&Interactable, Option<&mut ItemProducer>, Option<&Selection>)>,
+    carried_containers: Query<(&CarriedItem, &Parent, &Container)>,
+    mut players: Query<(
+        Entity,
+        &Player,
+        &Inventory,
+        Option<&mut HelperCooldown>,
+        Option<&mut Energy>,
+        Option<&mut StatusEffects>,
+    )>,
+) {
+    for (player, number, inventory, mut helper_cooldown, energy, status_effects) in
+        players.iter_mut()
+    {
+        let is_didi = number.0 == 0;
+        if is_didi && (placement.is_active() || prompt.is_open() || menu.is_open()) {
+            continue;
+        }
+        let pressed = if is_didi {
+            keyboard.pressed(KeyCode::Space)
+        } else {
+            helper_action_pressed(&keyboard, &gamepad_buttons, &input_map)
+        };
+        let cooldown = match helper_cooldown.as_mut() {
+            Some(helper_cooldown) => &mut helper_cooldown.0,
+            None => &mut cooldown.0,
+        };
+        if !pressed || !cooldown.available() {
+            continue;
+        }
+
+        let target = contacts
+            .iter()
+            .filter(|contact| contact.0 == player)
+            .map(|contact| contact.1)
+            .find(|entity| interactables.get_mut(*entity).is_ok());
+        let (entity, (interactable, producer, selection)) =
+            match target.and_then(|entity| Some((entity, interactables.get_mut(entity).ok()?))) {
+                Some(target) => target,
+                None => continue,
+            };
+        let carried = inventory.active_item();
+
+        match interactable.0 {
+            InteractAction::Take => {
+                let mut producer = match producer {
+                    Some(producer) => producer,
+                    None => continue,
+                };
+                let item = producer.item;
+                let accepts = container_accepts(&carried_containers, player, inventory, item);
+                let action = producer_action(
+                    player,
+                    carried,
+                    inventory.has_room(),
+                    accepts,
+                    &mut producer,
+                    item,
+                );
+                if let Some(action) = action {
+                    action_events.send(action);
+                }
+            }
+            InteractAction::Open => {
+                let (mut producer, items) = match (producer, selection) {
+                    (Some(producer), Some(selection)) if !selection.0.is_empty() => {
+                        (producer, &selection.0)
+                    }
+                    _ => continue,
+                };
+                match carried {
+                    Some(item) if !item.is_container() && items.contains(&item) => {
+                        producer.put_back();
+                        action_events.send(ActionEvent::PutAway(player, item));
+                    }
+                    Some(item)
+                        if producer.is_empty()
+                            || (!item.is_container() && !inventory.has_room()) =>
+                    {
+                        action_events.send(ActionEvent::Keep(player, item));
+                    }
+                    _ if producer.is_empty() => info!("The furniture is empty"),
+                    _ if is_didi => {
+                        menu.opened = Some(entity);
+                        menu.choice = 0;
+                    }
+                    _ => {
+                        let item = items[0];
+                        let accepts =
+                            container_accepts(&carried_containers, player, inventory, item);
+                        let action = producer_action(
+                            player,
+                            carried,
+                            inventory.has_room(),
+                            accepts,
+                            &mut producer,
+                            item,
+                        );
+                        if let Some(action) = action {
+                            action_events.send(action);
+                        }
+                    }
+                }
+            }
+            InteractAction::Order => match selection {
+                Some(selection) if is_didi && !selection.0.is_empty() => {
+                    menu.opened = Some(entity);
+                    menu.choice = 0;
+                }
+                _ => info!("Nothing to order"),
+            },
+            InteractAction::SitOn => {
+                if let Some(mut energy) = energy {
+                    info!("Sit down to rest");
+                    energy.add(SIT_ENERGY);
+                }
+            }
+            InteractAction::WashHands => {
+                if let Some(mut status_effects) = status_effects {
+                    info!("Wash hands");
+                    status_effects.apply(StatusEffectKind::Refreshed);
+                }
+            }
+            // Rocked as long as the key is held, by the baby, and watered
+            // with the glass in hand, by the plants
+            InteractAction::Rock | InteractAction::Water => continue,
+        }
+        cooldown.start();
+    }
+}
+
+/// Switches the highlighted item of the opened furniture with the arrow keys
+/// and chooses it with the action key, closing the menu when Didi walks
+/// away. The chosen item is taken from the producer of the furniture, or
+/// sent in a `SelectionEvent` without producer.
+#[allow(clippy::too_many_arguments)]
+fn selection_menu_system(
+    keyboard: Res<Input<KeyCode>>,
+    game_data: Res<GameData>,
+    mut menu: ResMut<SelectionMenu>,
+    mut cooldown: ResMut<PickAndDropCooldown>,
+    mut action_events: EventWriter<ActionEvent>,
+    mut selection_events: EventWriter<SelectionEvent>,
+    contacts: Query<&Contact>,
+    mut furniture: Query<(Option<&mut ItemProducer>, &Selection)>,
+    inventories: Query<&Inventory>,
+    carried_containers: Query<(&CarriedItem, &Parent, &Container)>,
+) {
+    let opened = match menu.opened {
+        Some(opened) => opened,
+        None => return,
+    };
+    let didi = game_data.didi_entity;
+    let in_contact = contacts
+        .iter()
+        .any(|contact| contact.0 == didi && contact.1 == opened);
+    let (inventory, (producer, selection)) =
+        match (inventories.get(didi), furniture.get_mut(opened)) {
+            (Ok(inventory), Ok(furniture)) if in_contact => (inventory, furniture),
+            _ => {
+                menu.opened = None;
+                return;
+            }
+        };
+
+    let count = selection.0.len();
+    if keyboard.just_pressed(KeyCode::Left) {
+        menu.choice = cycle(menu.choice, -1, count);
+    }
+    if keyboard.just_pressed(KeyCode::Right) {
+        menu.choice = cycle(menu.choice, 1, count);
+    }
+    if keyboard.just_pressed(KeyCode::Space) {
+        match (selection.0.get(menu.choice).copied(), producer) {
+            (Some(item), Some(mut producer)) => {
+                let accepts = container_accepts(&carried_containers, didi, inventory, item);
+                let action = producer_action(
+                    didi,
+                    inventory.active_item(),
+                    inventory.has_room(),
+                    accepts,
+                    &mut producer,
+                    item,
+                );
+                if let Some(action) = action {
+                    action_events.send(action);
+                }
+            }
+            (Some(item), None) => selection_events.send(SelectionEvent {
+                furniture: opened,
+                item,
+            }),
+            (None, _) => {}
+        }
+        menu.opened = None;
+        cooldown.0.start(); // Do not open the menu again right away
+    }
+}
+
+/// Component on the text of the prompt and of the menu above Didi.
+struct InteractionText;
+
+/// Spawns the hidden text of the prompt.
+fn spawn_interaction_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn()
+        .insert(InteractionText)
+        .insert(Overlay)
+        .insert(Position::default())
+        .insert_bundle(Text2dBundle {
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("FiraSans-Bold.ttf"),
+                    font_size: 22.0,
+                    color: Color::WHITE,
+                },
+                TextAlignment {
+                    vertical: VerticalAlign::Center,
+                    horizontal: HorizontalAlign::Center,
+                },
+            ),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Text2dBundle::default()
+        });
+}
+
+/// Shows above Didi the action of the interactable in contact, or the item
+/// highlighted in the menu.
+#[allow(clippy::too_many_arguments)]
+fn update_interaction_text_system(
+    game_data: Res<GameData>,
+    settings: Res<Settings>,
+    registry: Res<ItemRegistry>,
+    menu: Res<SelectionMenu>,
+    contacts: Query<&Contact>,
+    interactables: Query<&Interactable>,
+    selections: Query<&Selection>,
+    positions: Query<&Position, Without<InteractionText>>,
+    mut texts: Query<(&mut Position, &mut Text, &mut Visible), With<InteractionText>>,
+) {
+    let didi = game_data.didi_entity;
+    let label = if let Some(opened) = menu.opened {
+        selections
+            .get(opened)
+            .ok()
+            .and_then(|selection| selection.0.get(menu.choice))
+            .map(|item| format!("< {} >", registry.name(*item)))
+    } else {
+        contacts
+            .iter()
+            .filter(|contact| contact.0 == didi)
+            .find_map(|contact| interactables.get(contact.1).ok())
+            .map(|interactable| format!("Space: {}", interactable.0.label(settings.language)))
+    };
+    let didi_position = positions
+        .get(didi)
+        .map_or(Vec3::ZERO, |position| position.0);
+
+    for (mut position, mut text, mut visible) in texts.iter_mut() {
+        visible.is_visible = label.is_some();
+        if let Some(label) = &label {
+            if text.sections[0].value != *label {
+                text.sections[0].value = label.clone();
+            }
+            position.0 = Vec3::new(
+                didi_position.x,
+                didi_position.y + didi_position.z + TEXT_HEIGHT,
+                0.0,
+            );
+        }
+    }
+}
+
+/// Closes the menu and hides the prompt when leaving the game.
+fn close_menu_system(
+    mut menu: ResMut<SelectionMenu>,
+    mut texts: Query<&mut Visible, With<InteractionText>>,
+) {
+    menu.opened = None;
+
+    for mut visible in texts.iter_mut() {
+        visible.is_visible = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn menu_choice_wraps_around() {
+        assert_eq!(cycle(0, 1, 3), 1);
+        assert_eq!(cycle(2, 1, 3), 0);
+        assert_eq!(cycle(0, -1, 3), 2);
+        assert_eq!(cycle(0, 1, 0), 0);
+    }
+
+    #[test]
+    fn producer_takes_and_puts_back() {
+        let player = Entity::new(0);
+        let mut producer = ItemProducer::new(Item::IceCream);
+
+        assert_eq!(
+            producer_action(player, None, true, false, &mut producer, Item::IceCream),
+            Some(ActionEvent::Take(player, Item::IceCream))
+        );
+        assert_eq!(
+            producer_action(
+                player,
+                Some(Item::IceCream),
+                true,
+                false,
+                &mut producer,
+                Item::IceCream
+            ),
+            Some(ActionEvent::PutAway(player, Item::IceCream))
+        );
+        assert_eq!(
+            producer_action(
+                player,
+                Some(Item::Chips),
+                false,
+                false,
+                &mut producer,
+                Item::IceCream
+            ),
+            Some(ActionEvent::Keep(player, Item::Chips))
+        );
+    }
+}
@@ -0,0 +1,298 @@
+//! Interruptions: from time to time the doorbell or the phone rings, and
+//! Didi must answer before it stops or Baobei gets upset by the noise.
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    collisions::{Contact, Position, TriggerArea},
+    constants::{GameState, INTERRUPTION_DURATION, INTERRUPTION_INTERVAL, INTERRUPTION_PENALTY},
+    locale::Language,
+    rng::GameRng,
+    scheduler::{ScheduledEvent, Scheduler, SchedulerSystems},
+    settings::Settings,
+    time_scale::TimeScale,
+    widgets::{entity_timer_system, spawn_timer_bar, EntityTimer, Progress, WidgetMaterials},
+};
+
+use super::{
+    bubbles::SayEvent, entities::GameData, happiness::Happiness, items::PickAndDropCooldown,
+    phases::PhaseController, score::Score, Baobei, NewRunAppExt,
+};
+
+/// Plugin managing the interruptions.
+pub struct InterruptionPlugin;
+
+impl Plugin for InterruptionPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<InterruptionEvent>()
+            .init_resource::<InterruptionMaterials>()
+            .add_startup_system(schedule_interruption.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(
+                        spawn_interruption_system
+                            .system()
+                            .label("interruptions")
+                            .after(SchedulerSystems),
+                    )
+                    .with_system(answer_system.system().before("item_actions"))
+                    .with_system(expire_system.system().label("expire_interruptions"))
+                    .with_system(
+                        entity_timer_system::<Interruption>
+                            .system()
+                            .after("expire_interruptions"),
+                    )
+                    .with_system(announce_system.system().after("interruptions")),
+            )
+            .add_new_run_system(reset_interruptions_system);
+    }
+}
+
+/// Scheduled task starting the next interruption.
+const INTERRUPTION_TASK: &str = "interruption";
+/// Seconds added or removed at random to the interval between two
+/// interruptions.
+const INTERVAL_JITTER: f32 = 10.0;
+
+/// Something ringing in the apartment.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InterruptionKind {
+    /// Someone rings at the door, on the right border of the apartment
+    Doorbell,
+    /// The phone rings on the table
+    Phone,
+}
+
+impl InterruptionKind {
+    /// Returns where the interruption rings, the height being how high it is
+    /// above the floor.
+    fn position(self) -> Vec3 {
+        match self {
+            Self::Doorbell => Vec3::new(1240.0, 300.0, 0.0),
+            Self::Phone => Vec3::new(380.0, 195.0, 45.0),
+        }
+    }
+
+    /// Returns what Baobei says when it starts ringing.
+    const fn line(self, language: Language) -> &'static str {
+        match (language, self) {
+            (Language::English, Self::Doorbell) => "Someone is at the door!",
+            (Language::English, Self::Phone) => "Can you get the phone?",
+            (Language::French, Self::Doorbell) => "On sonne à la porte !",
+            (Language::French, Self::Phone) => "Tu peux répondre au téléphone ?",
+        }
+    }
+}
+
+/// Event sent when an interruption starts or ends.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InterruptionEvent {
+    /// It starts ringing
+    Started(InterruptionKind),
+    /// Didi answered in time
+    Answered(InterruptionKind),
+    /// It stopped ringing before Didi answered
+    Expired(InterruptionKind),
+}
+
+/// Component on a ringing interruption, stopping when the timer finishes.
+struct Interruption {
+    /// What is ringing
+    kind: InterruptionKind,
+    /// Timer until it stops ringing
+    timer: Timer,
+}
+
+impl Progress for Interruption {
+    fn remaining(&self) -> f32 {
+        self.timer.percent_left()
+    }
+}
+
+/// Colors of the interruptions.
+struct InterruptionMaterials {
+    /// Ringing doorbell
+    doorbell: Handle<ColorMaterial>,
+    /// Ringing phone
+    phone: Handle<ColorMaterial>,
+}
+
+impl FromWorld for InterruptionMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            doorbell: materials.add(Color::ORANGE.into()),
+            phone: materials.add(Color::CRIMSON.into()),
+        }
+    }
+}
+
+/// Schedules the next interruption after a random interval.
+fn schedule_next(scheduler: &mut Scheduler, rng: &mut GameRng) {
+    let interval = INTERRUPTION_INTERVAL + rng.rng.gen_range(-INTERVAL_JITTER..INTERVAL_JITTER);
+    scheduler.once(INTERRUPTION_TASK, interval);
+}
+
+/// Schedules the first interruption.
+fn schedule_interruption(mut scheduler: ResMut<Scheduler>, mut rng: ResMut<GameRng>) {
+    schedule_next(&mut scheduler, &mut rng);
+}
+
+/// Starts ringing when the interruption is due, except when Baobei naps.
+#[allow(clippy::too_many_arguments)]
+fn spawn_interruption_system(
+    mut commands: Commands,
+    phases: Res<PhaseController>,
+    materials: Res<InterruptionMaterials>,
+    widget_materials: Res<WidgetMaterials>,
+    mut scheduler: ResMut<Scheduler>,
+    mut rng: ResMut<GameRng>,
+    mut scheduled_events: EventReader<ScheduledEvent>,
+    mut interruption_events: EventWriter<InterruptionEvent>,
+) {
+    let due = scheduled_events
+        .iter()
+        .any(|ScheduledEvent(task)| *task == INTERRUPTION_TASK);
+
+    if !due {
+        return;
+    }
+    if phases.is_breather() {
+        schedule_next(&mut scheduler, &mut rng);
+        return;
+    }
+
+    let kind = if rng.rng.gen_bool(0.5) {
+        InterruptionKind::Doorbell
+    } else {
+        InterruptionKind::Phone
+    };
+    let (material, size) = match kind {
+        InterruptionKind::Doorbell => (materials.doorbell.clone(), Vec2::new(20.0, 30.0)),
+        InterruptionKind::Phone => (materials.phone.clone(), Vec2::new(30.0, 15.0)),
+    };
+
+    let interruption = commands
+        .spawn()
+        .insert(Interruption {
+            kind,
+            timer: Timer::from_seconds(INTERRUPTION_DURATION, false),
+        })
+        .insert(Position(kind.position()))
+        .insert(TriggerArea::new(160.0, 120.0))
+        .insert_bundle(SpriteBundle {
+            material,
+            sprite: Sprite::new(size),
+            ..SpriteBundle::default()
+        })
+        .id();
+
+    let ringing_bar = spawn_timer_bar(
+        &mut commands,
+        &widget_materials,
+        Vec3::new(0.0, 35.0, 0.1),
+        Vec2::new(50.0, 8.0),
+    );
+    commands
+        .entity(ringing_bar)
+        .insert(EntityTimer::<Interruption>::new(interruption));
+    commands.entity(interruption).push_children(&[ringing_bar]);
+
+    info!("The {:?} rings", kind);
+    interruption_events.send(InterruptionEvent::Started(kind));
+}
+
+/// Answers the interruption Didi stands next to when pressing `Space`.
+#[allow(clippy::too_many_arguments)]
+fn answer_system(
+    mut commands: Commands,
+    keyboard: Res<Input<KeyCode>>,
+    game_data: Res<GameData>,
+    mut cooldown: ResMut<PickAndDropCooldown>,
+    mut score: ResMut<Score>,
+    mut scheduler: ResMut<Scheduler>,
+    mut rng: ResMut<GameRng>,
+    mut interruption_events: EventWriter<InterruptionEvent>,
+    contacts: Query<&Contact>,
+    interruptions: Query<&Interruption>,
+) {
+    if !cooldown.0.available() || !keyboard.pressed(KeyCode::Space) {
+        return;
+    }
+    let answered = contacts
+        .iter()
+        .filter(|contact| contact.0 == game_data.didi_entity)
+        .find_map(|contact| Some((contact.1, interruptions.get(contact.1).ok()?)));
+
+    if let Some((entity, interruption)) = answered {
+        info!("Answer the {:?}", interruption.kind);
+        commands.entity(entity).despawn_recursive();
+        score.reward_chore();
+        cooldown.0.start();
+        schedule_next(&mut scheduler, &mut rng);
+        interruption_events.send(InterruptionEvent::Answered(interruption.kind));
+    }
+}
+
+/// Stops the interruptions not answered in time, upsetting Baobei.
+#[allow(clippy::too_many_arguments)]
+fn expire_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut scheduler: ResMut<Scheduler>,
+    mut rng: ResMut<GameRng>,
+    mut interruption_events: EventWriter<InterruptionEvent>,
+    mut interruptions: Query<(Entity, &mut Interruption)>,
+    mut baobei: Query<&mut Happiness, With<Baobei>>,
+) {
+    let delta = time_scale.scale(time.delta());
+
+    for (entity, mut interruption) in interruptions.iter_mut() {
+        if !interruption.timer.tick(delta).just_finished() {
+            continue;
+        }
+        info!("The {:?} stopped ringing", interruption.kind);
+        commands.entity(entity).despawn_recursive();
+        for mut happiness in baobei.iter_mut() {
+            happiness.sub(INTERRUPTION_PENALTY);
+        }
+        schedule_next(&mut scheduler, &mut rng);
+        interruption_events.send(InterruptionEvent::Expired(interruption.kind));
+    }
+}
+
+/// Makes Baobei ask Didi to answer the interruptions.
+fn announce_system(
+    settings: Res<Settings>,
+    mut interruption_events: EventReader<InterruptionEvent>,
+    mut say_events: EventWriter<SayEvent>,
+    baobei: Query<Entity, With<Baobei>>,
+) {
+    for event in interruption_events.iter() {
+        if let InterruptionEvent::Started(kind) = event {
+            for speaker in baobei.iter() {
+                say_events.send(SayEvent {
+                    speaker,
+                    line: kind.line(settings.language),
+                });
+            }
+        }
+    }
+}
+
+/// Stops the ringing interruptions and schedules the first one of the new
+/// game.
+fn reset_interruptions_system(
+    mut commands: Commands,
+    mut scheduler: ResMut<Scheduler>,
+    mut rng: ResMut<GameRng>,
+    interruptions: Query<Entity, With<Interruption>>,
+) {
+    for interruption in interruptions.iter() {
+        commands.entity(interruption).despawn_recursive();
+    }
+    schedule_next(&mut scheduler, &mut rng);
+}
@@ -1,13 +1,42 @@
 //! Systems and components managing items in the game.
 
-use bevy::prelude::*;
-use rand::{distributions::Standard, prelude::Distribution, random, Rng};
+use std::collections::VecDeque;
 
-use super::{entities::GameData, happiness::Happiness, materials::GameplayMaterials, Baobei, Didi};
+use bevy::{math::const_vec3, prelude::*};
+use rand::Rng;
+
+use super::{
+    containers::Container,
+    coop::{helper_action_pressed, HelperCooldown, Player},
+    crafting::Recipe,
+    energy::Energy,
+    entities::GameData,
+    happiness::Happiness,
+    interactables::SelectionMenu,
+    levels::Patience,
+    materials::GameplayMaterials,
+    placement::DropPlacement,
+    prompt::ConsumePrompt,
+    registry::ItemRegistry,
+    requests::RequestQueue,
+    score::Score,
+    stats::SessionStats,
+    status_effects::StatusEffects,
+    storage::Storage,
+    NewRunAppExt,
+};
 use crate::{
     collisions::{Contact, Position, TriggerArea},
-    constants::GameState,
+    constants::{
+        GameState, COMBO_MULTIPLIER_STEP, COMBO_WINDOW, INVENTORY_SLOTS, MAX_COMBO_MULTIPLIER,
+        MAX_INVENTORY_SLOTS, MAX_SIMULTANEOUS_REQUESTS, PRODUCER_STOCK,
+    },
+    controllers::{InputAction, InputMap},
     cooldown::Cooldown,
+    difficulty::Difficulty,
+    rng::GameRng,
+    time_scale::TimeScale,
+    widgets::Progress,
 };
 
 /// Label for systems managing items
@@ -20,13 +49,23 @@ pub struct ItemsPlugin;
 impl Plugin for ItemsPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app.add_event::<ActionEvent>()
+            .add_event::<DeliveryEvent>()
+            .add_event::<WrongDeliveryEvent>()
+            .add_event::<SpoiledEvent>()
+            .add_event::<CraftEvent>()
             .insert_resource(PickAndDropCooldown(Cooldown::from_seconds(0.2)))
+            .init_resource::<ComboState>()
             .add_system_set(
                 SystemSet::on_update(GameState::InGame)
                     .label(ItemSystems)
+                    .with_system(combo_timer_system.system().before("item_actions"))
+                    .with_system(swap_items_system.system().before("item_actions"))
                     .with_system(pick_or_drop_system.system().label("item_actions"))
-                    .with_system(handle_actions_system.system().after("item_actions")),
-            );
+                    .with_system(handle_actions_system.system().after("item_actions"))
+                    .with_system(update_asked_items_system.system().after("item_actions"))
+                    .with_system(carried_items_position_system.system().after("item_actions")),
+            )
+            .add_new_run_system(reset_combo_system);
     }
 }
 
@@ -39,247 +78,949 @@ pub enum Item {
     WaterGlass,
     /// A bag of chips
     Chips,
+    /// A cup of coffee, that Didi drinks to restore energy
+    Coffee,
+    /// The basket collecting the dirty clothes
+    LaundryBasket,
+    /// A tray carrying several foods at once
+    Tray,
+    /// A box of tea leaves, brewed with water at the kitchen
+    TeaLeaves,
+    /// A hot tea, crafted at the kitchen
+    HotTea,
+    /// A used glass or bowl to bring back to the sink
+    DirtyDish,
+    /// The mop cleaning the messes on the floor
+    Mop,
+    /// An item only defined in the item registry, with its rank there
+    Custom(usize),
 }
 
-impl Distribution<Item> for Standard {
-    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Item {
-        match rng.gen_range(0..=2) {
-            0 => Item::IceCream,
-            1 => Item::WaterGlass,
-            _ => Item::Chips,
+impl Item {
+    /// Returns true if the item is food that Baobei can ask for or Didi can eat.
+    pub const fn is_food(self) -> bool {
+        matches!(
+            self,
+            Self::IceCream | Self::WaterGlass | Self::Chips | Self::Coffee | Self::HotTea
+        )
+    }
+
+    /// Returns true if the item holds other items.
+    pub const fn is_container(self) -> bool {
+        matches!(self, Self::Tray)
+    }
+
+    /// Returns true if Didi can consume the item instead of giving it.
+    pub const fn is_consumable(self) -> bool {
+        matches!(self, Self::Coffee | Self::Chips)
+    }
+}
+
+/// Component on Didi holding the carried items, one in hand and the others in
+/// the backpack.
+pub struct Inventory {
+    /// Items held in each slot, only the first ones being usable.
+    pub slots: [Option<Item>; MAX_INVENTORY_SLOTS],
+    /// Index of the slot in hand.
+    pub active: usize,
+    /// Number of usable slots.
+    capacity: usize,
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Self {
+            slots: [None; MAX_INVENTORY_SLOTS],
+            active: 0,
+            capacity: INVENTORY_SLOTS,
         }
     }
 }
 
-/// Component on entities carrying an item.
-pub struct Carrying(pub Item);
+impl Inventory {
+    /// Returns the number of usable slots.
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Changes the number of usable slots, emptying the slots no longer
+    /// usable.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.clamp(1, MAX_INVENTORY_SLOTS);
+        for slot in &mut self.slots[self.capacity..] {
+            *slot = None;
+        }
+        self.active = self.active.min(self.capacity - 1);
+    }
+
+    /// Returns the item in hand.
+    pub const fn active_item(&self) -> Option<Item> {
+        self.slots[self.active]
+    }
+
+    /// Returns true if a slot is free.
+    pub fn has_room(&self) -> bool {
+        self.slots[..self.capacity].iter().any(Option::is_none)
+    }
+
+    /// Returns true if a container is carried, in hand or in the backpack.
+    pub fn carries_container(&self) -> bool {
+        self.slots.iter().flatten().any(|item| item.is_container())
+    }
+
+    /// Puts the item in hand, moving the item in hand to the backpack.
+    /// Returns the slot of the item, or `None` if all slots are taken.
+    fn put(&mut self, item: Item) -> Option<usize> {
+        let slot = if self.slots[self.active].is_none() {
+            self.active
+        } else {
+            self.slots[..self.capacity]
+                .iter()
+                .position(Option::is_none)?
+        };
+        self.slots[slot] = Some(item);
+        self.active = slot;
+        Some(slot)
+    }
+
+    /// Empties the hand and returns the slot that held the item.
+    fn take_active(&mut self) -> usize {
+        self.slots[self.active] = None;
+        self.active
+    }
+
+    /// Empties the first slot holding the item and returns it.
+    fn remove(&mut self, item: Item) -> Option<usize> {
+        let slot = self.slots.iter().position(|slot| *slot == Some(item))?;
+        self.slots[slot] = None;
+        Some(slot)
+    }
+
+    /// Puts the item of the backpack in hand.
+    fn swap(&mut self) {
+        self.active = (self.active + 1) % self.capacity;
+    }
+}
+
+/// Component on entities that is a carried item, with its slot in the
+/// inventory.
+pub struct CarriedItem(pub usize);
+/// Component on the icon of an asked item, with its rank in the requests.
+pub struct AskedItem(usize);
 
-/// Component on entities that is a carried item.
-pub struct CarriedItem;
-/// Component on entities that is an asked item.
-pub struct AskedItem;
+/// Component on the interactables producing the item, until out of stock.
+pub struct ItemProducer {
+    /// Item produced
+    pub item: Item,
+    /// Items left to take
+    stock: u32,
+    /// Cooldown until the next item is restocked
+    restock: Cooldown,
+}
 
-/// Component on entities that can produce the item.
-pub struct ItemProducer(pub Item);
+impl ItemProducer {
+    /// Creates a producer of the item with a full stock.
+    pub const fn new(item: Item) -> Self {
+        Self {
+            item,
+            stock: PRODUCER_STOCK,
+            restock: Cooldown::from_seconds(Difficulty::Normal.profile().restock_duration),
+        }
+    }
+
+    /// Returns true if there is no item left to take.
+    pub const fn is_empty(&self) -> bool {
+        self.stock == 0
+    }
 
-/// Component on entities that can ask for the item.
-pub struct AskingItem(pub Item);
+    /// Returns true if there is no item to restock.
+    pub const fn is_full(&self) -> bool {
+        self.stock >= PRODUCER_STOCK
+    }
 
-/// An event about an action the player made.
+    /// Takes an item from the stock, returns false if out of stock.
+    pub fn take(&mut self) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        self.stock -= 1;
+        if self.restock.available() {
+            self.restock.start();
+        }
+        true
+    }
+
+    /// Puts an item back in the stock.
+    pub fn put_back(&mut self) {
+        self.stock = (self.stock + 1).min(PRODUCER_STOCK);
+    }
+
+    /// Advances the restock by `delta` seconds, adding an item to the stock
+    /// each time the cooldown is over.
+    pub fn tick(&mut self, delta: f32) {
+        if self.is_full() {
+            return;
+        }
+        if self.restock.tick(delta).available() {
+            self.stock += 1;
+            if self.stock < PRODUCER_STOCK {
+                self.restock.start();
+            }
+        }
+    }
+
+    /// Fills the stock, restocking it in the given seconds from now on.
+    pub fn refill(&mut self, restock_duration: f32) {
+        self.stock = PRODUCER_STOCK;
+        self.restock = Cooldown::from_seconds(restock_duration);
+    }
+}
+
+impl Progress for ItemProducer {
+    fn remaining(&self) -> f32 {
+        self.restock.remaining_fraction()
+    }
+}
+
+/// Component on entities destroying the items thrown in them.
+pub struct ItemSink;
+
+/// Component on entities asking for a sequence of items, the front one
+/// being asked first.
+pub struct ItemRequestQueue(pub VecDeque<Item>);
+
+impl ItemRequestQueue {
+    /// Creates a queue asking for the item.
+    pub fn new(item: Item) -> Self {
+        let mut items = VecDeque::with_capacity(MAX_SIMULTANEOUS_REQUESTS);
+        items.push_back(item);
+        Self(items)
+    }
+
+    /// Returns the item asked first.
+    pub fn front(&self) -> Option<Item> {
+        self.0.front().copied()
+    }
+
+    /// Removes the front request, then asks for the next pre-rolled item if
+    /// nothing else is asked. The following items are added by the level.
+    pub fn advance<R: Rng + ?Sized>(
+        &mut self,
+        registry: &ItemRegistry,
+        rng: &mut R,
+        queue: Option<&mut RequestQueue>,
+    ) {
+        let previous = match self.0.pop_front() {
+            Some(previous) => previous,
+            None => return,
+        };
+        if self.0.is_empty() {
+            let next_item = match queue {
+                Some(queue) => queue.next(registry, rng, previous),
+                None => registry.random_different(rng, previous),
+            };
+            self.0.push_back(next_item);
+        }
+    }
+}
+
+/// Spawns the row of icons showing the asked items above the head of the
+/// asker, the front item being the biggest.
+pub fn spawn_asked_items(
+    parent: &mut ChildBuilder,
+    materials: &GameplayMaterials,
+    queue: &ItemRequestQueue,
+) {
+    for rank in 0..MAX_SIMULTANEOUS_REQUESTS {
+        let scale = if rank == 0 { 1.5 } else { 1.0 };
+
+        parent
+            .spawn()
+            .insert(AskedItem(rank))
+            .insert_bundle(SpriteBundle {
+                material: queue.0.get(rank).map_or(materials.none.clone(), |item| {
+                    materials.item_sprite_for(*item)
+                }),
+                transform: Transform {
+                    translation: Vec3::new(-300.0 * rank as f32, 475.0, 0.0),
+                    scale: Vec3::new(scale, scale, 0.0),
+                    ..Transform::default()
+                },
+                ..SpriteBundle::default()
+            });
+    }
+}
+
+/// An event about an action the player made. The actions of the players
+/// able to act in co-op start with the entity of the player, the others are
+/// made by Didi.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ActionEvent {
     /// The player takes an item in the item producer.
-    Take(Item),
+    Take(Entity, Item),
     /// The player puts away the item back in the item producer.
-    PutAway(Item),
-    /// The player drops the item on the ground.
-    Drop(Item),
-    /// The player picks up an item on the ground.
-    PickUp(Entity, Item),
+    PutAway(Entity, Item),
+    /// The player drops the item on the ground at the position.
+    Drop(Item, Vec3),
+    /// The player picks up the item entity on the ground.
+    PickUp(Entity, Entity, Item),
     /// The player keeps the item when trying to pick another one.
-    Keep(Item),
-    /// The player gives the item to Baobei.
-    Give(Item),
+    Keep(Entity, Item),
+    /// The player gives the item to the asker (Baobei or a guest).
+    Give(Entity, Entity, Item),
+    /// The player puts the item in the storage furniture.
+    Store(Entity, Item),
+    /// The player takes the last item put in the storage furniture.
+    Retrieve(Entity),
+    /// The character consumes the carried item.
+    Consume(Entity, Item),
+    /// The carried item melts in the hands of Didi.
+    Melt(Item),
+    /// The player throws the item in hand away.
+    Discard(Item),
+    /// The player combines the carried ingredients of the recipe.
+    Craft(Recipe),
+    /// The player washes the item in hand at the sink.
+    Wash(Item),
+}
+
+impl ActionEvent {
+    /// Returns the player making the action, none for the actions only Didi
+    /// makes.
+    pub const fn player(&self) -> Option<Entity> {
+        match self {
+            Self::Take(player, _)
+            | Self::PutAway(player, _)
+            | Self::PickUp(player, _, _)
+            | Self::Keep(player, _)
+            | Self::Give(player, _, _) => Some(*player),
+            _ => None,
+        }
+    }
+}
+
+/// Event sent when a carried item spoils, destroying it.
+pub struct SpoiledEvent(pub Item);
+
+/// Event sent when Didi crafts an item from the carried ingredients.
+pub struct CraftEvent(pub Item);
+
+/// Event sent when an asker receives the item it asked for.
+pub struct DeliveryEvent {
+    /// The entity receiving the item.
+    pub asker: Entity,
+    /// The delivered item.
+    pub item: Item,
+    /// Happiness of the asker just before the delivery.
+    pub happiness: f32,
+    /// Seconds of patience the asker had left, if it has a patience.
+    pub patience_left: Option<f32>,
+}
+
+/// Event sent when an asker receives another item than the one it asked for,
+/// also the hook of the negative sound.
+pub struct WrongDeliveryEvent {
+    /// The entity receiving the item.
+    pub asker: Entity,
+    /// The given item.
+    pub item: Item,
+    /// The item the asker asked for.
+    pub asked: Item,
+}
+
+/// Consecutive correct deliveries made quickly, boosting the score and the
+/// happiness gained.
+pub struct ComboState {
+    /// Number of deliveries in the combo, 0 without combo.
+    count: u32,
+    /// Time left to deliver the next item.
+    window: Timer,
+}
+
+impl Default for ComboState {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            window: Timer::from_seconds(COMBO_WINDOW, false),
+        }
+    }
+}
+
+impl ComboState {
+    /// Returns the number of deliveries in the combo.
+    pub const fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Returns the multiplier of the rewards, growing with the combo.
+    pub fn multiplier(&self) -> f32 {
+        let bonus_deliveries = self.count.saturating_sub(1) as f32;
+        COMBO_MULTIPLIER_STEP
+            .mul_add(bonus_deliveries, 1.0)
+            .min(MAX_COMBO_MULTIPLIER)
+    }
+
+    /// Counts a correct delivery and restarts the window for the next one.
+    fn hit(&mut self) {
+        self.count += 1;
+        self.window.reset();
+    }
+
+    /// Breaks the combo.
+    fn reset(&mut self) {
+        self.count = 0;
+    }
 }
 
 /// Cooldown of the action of picking or dropping items.
 pub struct PickAndDropCooldown(pub Cooldown);
 
-/// Pick or drop an item in an item producer.
+/// Dispatches the action control of each player over the kinds of things in
+/// contact, the interactable furniture being used before. The helpers of the
+/// co-op only use the interactables, the askers and the items on the ground.
 #[allow(clippy::too_many_arguments)]
 pub fn pick_or_drop_system(
     time: Res<Time>,
-    game_data: Res<GameData>,
     mut cooldown: ResMut<PickAndDropCooldown>,
     keyboard: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    input_map: Res<InputMap>,
     mut action_events: EventWriter<ActionEvent>,
     contacts: Query<&Contact>,
-    item_producers: Query<&ItemProducer>,
-    item_askers: Query<&AskingItem>,
+    item_askers: Query<&ItemRequestQueue>,
     items: Query<(Entity, &Item)>,
-    carriers: Query<&Carrying, With<Didi>>,
+    mut players: Query<(Entity, &Player, &Inventory, Option<&mut HelperCooldown>)>,
+    status_effects: Query<&StatusEffects>,
+    energies: Query<&Energy>,
+    storages: Query<&Storage>,
+    mut placement: ResMut<DropPlacement>,
+    mut prompt: ResMut<ConsumePrompt>,
+    menu: Res<SelectionMenu>,
 ) {
-    if !cooldown.0.tick(time.delta_seconds()).available() || !keyboard.pressed(KeyCode::Space) {
-        return;
-    }
-    let didi = game_data.didi_entity;
+    for (player, number, inventory, mut helper_cooldown) in players.iter_mut() {
+        let is_didi = number.0 == 0;
+        if is_didi && placement.is_active() {
+            continue; // The item is dropped when the player releases the key
+        }
+        if is_didi && (prompt.is_open() || menu.is_open()) {
+            continue; // The player chooses what to do with the item
+        }
+        let pressed = if is_didi {
+            keyboard.pressed(KeyCode::Space)
+        } else {
+            helper_action_pressed(&keyboard, &gamepad_buttons, &input_map)
+        };
+        let cooldown = match helper_cooldown.as_mut() {
+            Some(helper_cooldown) => &mut helper_cooldown.0,
+            None => &mut cooldown.0,
+        };
+        let cooldown_rate = status_effects
+            .get(player)
+            .map_or(1.0, StatusEffects::cooldown_rate)
+            * energies.get(player).map_or(1.0, Energy::cooldown_rate);
+
+        if !cooldown
+            .tick(time.delta_seconds() * cooldown_rate)
+            .available()
+            || !pressed
+        {
+            continue;
+        }
 
-    let carried_item = carriers.get(didi);
+        let carried_item = inventory.active_item();
+        let in_contact = || contacts.iter().filter(|contact| contact.0 == player);
 
-    // Pick or put away an item in a producer
-    contacts
-        .iter()
-        .filter(|contact| contact.0 == didi)
-        .filter_map(|contact| item_producers.get(contact.1).ok())
-        .for_each(|ItemProducer(produced_item)| {
+        // Give an item to the asker in contact
+        if let Some(item) = carried_item {
+            let asker = in_contact().find(|contact| item_askers.get(contact.1).is_ok());
+
+            if let Some(Contact(_, asker)) = asker {
+                action_events.send(ActionEvent::Give(player, *asker, item));
+                cooldown.start();
+            }
+        }
+
+        if !cooldown.available() {
+            continue; // Avoid to do more than one action at once.
+        }
+
+        // Store the item in the furniture in contact or take one from it
+        let storage = in_contact()
+            .filter(|_| is_didi)
+            .find_map(|contact| Some((contact.1, storages.get(contact.1).ok()?)));
+
+        if let Some((storage_entity, storage)) = storage {
             match carried_item {
-                Ok(Carrying(item)) if (item == produced_item) => {
-                    action_events.send(ActionEvent::PutAway(*item))
+                Some(item) if !storage.is_full() && !item.is_container() => {
+                    action_events.send(ActionEvent::Store(storage_entity, item));
+                    cooldown.start();
+                }
+                None if !storage.is_empty() => {
+                    action_events.send(ActionEvent::Retrieve(storage_entity));
+                    cooldown.start();
                 }
-                Ok(Carrying(item)) => action_events.send(ActionEvent::Keep(*item)),
-                _ => action_events.send(ActionEvent::Take(*produced_item)),
+                _ => {}
             }
-            cooldown.0.start();
-        });
-
-    if !cooldown.0.available() {
-        return; // Avoid to do more than one action at once.
-    }
-
-    // Give an item to baobei
-    if let Ok(Carrying(item)) = carried_item {
-        contacts
-            .iter()
-            .filter(|contact| contact.0 == didi)
-            .filter_map(|contact| item_askers.get(contact.1).ok())
-            .for_each(|_| {
-                action_events.send(ActionEvent::Give(*item));
-                cooldown.0.start();
-            });
-    }
+        }
 
-    if !cooldown.0.available() {
-        return; // Avoid to do more than one action at once.
-    }
+        if !cooldown.available() {
+            continue; // Avoid to do more than one action at once.
+        }
 
-    // Drop or pick up the item to the ground
-    if let Ok(Carrying(item)) = carried_item {
-        action_events.send(ActionEvent::Drop(*item));
-        cooldown.0.start();
-    } else {
-        let item_on_the_ground = contacts
-            .iter()
-            .filter(|contact| contact.0 == didi)
-            .find_map(|contact| items.get(contact.1).ok());
+        // Consume the item, place it on the ground or pick up one
+        match carried_item {
+            Some(item) if is_didi => {
+                if item.is_consumable() {
+                    prompt.open();
+                } else {
+                    placement.start();
+                }
+                cooldown.start();
+            }
+            Some(_) => {}
+            None => {
+                let item_on_the_ground = in_contact().find_map(|contact| items.get(contact.1).ok());
 
-        if let Some((item_entity, item)) = item_on_the_ground {
-            action_events.send(ActionEvent::PickUp(item_entity, *item));
-            cooldown.0.start();
+                if let Some((item_entity, item)) = item_on_the_ground {
+                    action_events.send(ActionEvent::PickUp(player, item_entity, *item));
+                    cooldown.start();
+                }
+            }
         }
     }
 }
 
 /// Handles action events:
-/// - Tag Didi with Carrying and spawn the item in hand when picking
-/// - Untag Didi with Carrying and despawn the item in hand when dropping
+/// - Put the item in the inventory of the player and spawn it in hand when
+///   picking
+/// - Empty the hand of the player and despawn the item in hand when dropping
 #[allow(clippy::too_many_arguments)]
 pub fn handle_actions_system(
     mut commands: Commands,
     mut action_events: EventReader<ActionEvent>,
+    mut delivery_events: EventWriter<DeliveryEvent>,
+    mut wrong_delivery_events: EventWriter<WrongDeliveryEvent>,
+    mut spoiled_events: EventWriter<SpoiledEvent>,
+    mut craft_events: EventWriter<CraftEvent>,
+    mut rng: ResMut<GameRng>,
+    mut score: ResMut<Score>,
+    mut combo: ResMut<ComboState>,
+    mut stats: ResMut<SessionStats>,
     game_data: Res<GameData>,
     materials: Res<GameplayMaterials>,
-    carried_items: Query<Entity, With<CarriedItem>>,
-    mut baobei_query: Query<(&mut AskingItem, &mut Happiness), With<Baobei>>,
-    mut asked_item_materials: Query<&mut Handle<ColorMaterial>, With<AskedItem>>,
-    positions: Query<&Position>,
+    registry: Res<ItemRegistry>,
+    carried_items: Query<(Entity, &CarriedItem, &Parent)>,
+    mut inventories: Query<&mut Inventory>,
+    mut askers: Query<(
+        &mut ItemRequestQueue,
+        Option<&mut Happiness>,
+        Option<&mut RequestQueue>,
+        Option<&Patience>,
+    )>,
     mut transforms: Query<&mut Transform>,
+    mut storages: Query<&mut Storage>,
+    mut carried_containers: Query<(&CarriedItem, &Parent, &mut Container)>,
 ) {
-    let didi = game_data.didi_entity;
-    let picked_item_translation = Vec3::new(-170.0, -10.0, 0.0);
     let didi_scale = Vec3::new(0.3, 0.3, 0.0);
 
     for action in action_events.iter() {
+        let player = action.player().unwrap_or(game_data.didi_entity);
+        let mut inventory = match inventories.get_mut(player) {
+            Ok(inventory) => inventory,
+            Err(_) => continue,
+        };
+        let carried_by_player = |parent: &Parent| parent.0 == player;
+
         match action {
-            ActionEvent::PutAway(item) => {
+            ActionEvent::PutAway(_, item) => {
                 info!("Put way item {:?}", item);
-                commands.entity(didi).remove::<Carrying>();
-
-                for item_in_hand in carried_items.iter() {
-                    commands.entity(item_in_hand).despawn();
-                }
+                let slot = inventory.take_active();
+                despawn_carried_item(&mut commands, &carried_items, player, slot);
             }
-            ActionEvent::Drop(item) => {
-                info!("Drop the item {:?}", item);
-                commands.entity(didi).remove::<Carrying>();
-
-                for item_to_drop in carried_items.iter() {
-                    let didi_position = positions.get(didi).unwrap();
+            ActionEvent::Drop(item, position) => {
+                info!("Drop the item {:?} at {}", item, position);
+                let slot = inventory.take_active();
 
+                for (item_to_drop, _, _) in carried_items
+                    .iter()
+                    .filter(|(_, carried, parent)| carried_by_player(parent) && carried.0 == slot)
+                {
                     commands
                         .entity(item_to_drop)
                         .remove::<Parent>()
                         .remove::<CarriedItem>()
-                        .insert_bundle((
-                            Position(didi_position.0 + picked_item_translation * didi_scale),
-                            TriggerArea::new(75.0, 100.0),
-                        ));
+                        .insert_bundle((Position(*position), TriggerArea::new(75.0, 100.0)));
 
                     if let Ok(mut transform) = transforms.get_mut(item_to_drop) {
                         transform.scale = didi_scale;
                     }
                 }
             }
-            ActionEvent::PickUp(item_entity, item) => {
+            ActionEvent::PickUp(_, item_entity, item) => {
+                let slot = match inventory.put(*item) {
+                    Some(slot) => slot,
+                    None => continue,
+                };
                 info!("Pick up the item {:?}", item);
 
-                commands
-                    .entity(didi)
-                    .insert(Carrying(*item))
-                    .push_children(&[*item_entity]);
+                commands.entity(player).push_children(&[*item_entity]);
                 commands
                     .entity(*item_entity)
-                    .insert(CarriedItem)
+                    .insert(CarriedItem(slot))
                     .remove::<Position>()
                     .remove::<TriggerArea>();
 
                 if let Ok(mut transform) = transforms.get_mut(*item_entity) {
-                    transform.translation = picked_item_translation;
+                    transform.translation = PICKED_ITEM_TRANSLATION;
                     transform.scale = Vec3::ONE;
                 }
             }
-            ActionEvent::Take(item) => {
+            ActionEvent::Take(_, item) => {
                 info!("Take item {:?}", item);
+                let active_slot = inventory.active;
+                let container = carried_containers.iter_mut().find(|(carried, parent, _)| {
+                    carried_by_player(parent) && carried.0 == active_slot
+                });
 
-                let item_in_hand = commands
-                    .spawn()
-                    .insert(*item)
-                    .insert(CarriedItem)
-                    .insert_bundle(SpriteBundle {
-                        material: materials.item_sprite_for(*item),
-                        transform: Transform::from_translation(picked_item_translation),
-                        ..SpriteBundle::default()
-                    })
-                    .id();
+                match container {
+                    Some((_, _, mut container)) => container.put(*item),
+                    None => {
+                        spawn_item_in_hand(&mut commands, &materials, player, &mut inventory, *item)
+                    }
+                }
+            }
+            ActionEvent::Store(storage, item) => {
+                let stored = storages
+                    .get_mut(*storage)
+                    .map_or(false, |mut storage| storage.deposit(*item));
+                if !stored {
+                    continue;
+                }
+                info!("Store item {:?}", item);
+                let slot = inventory.take_active();
+                despawn_carried_item(&mut commands, &carried_items, player, slot);
+            }
+            ActionEvent::Retrieve(storage) => {
+                let retrieved = storages
+                    .get_mut(*storage)
+                    .ok()
+                    .and_then(|mut storage| storage.retrieve());
 
-                commands
-                    .entity(didi)
-                    .insert(Carrying(*item))
-                    .push_children(&[item_in_hand]);
+                if let Some(item) = retrieved {
+                    info!("Retrieve item {:?}", item);
+                    spawn_item_in_hand(&mut commands, &materials, player, &mut inventory, item);
+                }
             }
-            ActionEvent::Keep(item) => info!("Keep item {:?}", item),
-            ActionEvent::Give(item) => {
-                info!("Give item {:?}", item);
-                for (mut asking_item, mut happiness) in baobei_query.iter_mut() {
-                    if asking_item.0 != *item {
-                        happiness.sub(0.15);
-                        return;
+            ActionEvent::Consume(_, item) => {
+                info!("Consume item {:?}", item);
+                let slot = inventory.take_active();
+                despawn_carried_item(&mut commands, &carried_items, player, slot);
+            }
+            ActionEvent::Melt(item) => {
+                info!("Melt item {:?}", item);
+                if let Some(slot) = inventory.remove(*item) {
+                    despawn_carried_item(&mut commands, &carried_items, player, slot);
+                }
+                spoiled_events.send(SpoiledEvent(*item));
+            }
+            ActionEvent::Discard(item) => {
+                info!("Discard item {:?}", item);
+                let slot = inventory.take_active();
+                despawn_carried_item(&mut commands, &carried_items, player, slot);
+                score.penalize_discarded_item();
+            }
+            ActionEvent::Wash(item) => {
+                info!("Wash item {:?}", item);
+                let slot = inventory.take_active();
+                despawn_carried_item(&mut commands, &carried_items, player, slot);
+                score.reward_chore();
+            }
+            ActionEvent::Craft(recipe) => {
+                info!("Craft item {:?}", recipe.result);
+                for ingredient in &recipe.ingredients {
+                    if let Some(slot) = inventory.remove(*ingredient) {
+                        despawn_carried_item(&mut commands, &carried_items, player, slot);
                     }
+                }
+                spawn_item_in_hand(
+                    &mut commands,
+                    &materials,
+                    player,
+                    &mut inventory,
+                    recipe.result,
+                );
+                craft_events.send(CraftEvent(recipe.result));
+            }
+            ActionEvent::Keep(_, item) => info!("Keep item {:?}", item),
+            ActionEvent::Give(_, asker, item) => {
+                info!("Give item {:?}", item);
+                let (mut requests, happiness, mut queue, patience) = match askers.get_mut(*asker) {
+                    Ok(asker) => asker,
+                    Err(_) => continue,
+                };
+                let asked = match requests.front() {
+                    Some(asked) => asked,
+                    None => continue,
+                };
 
-                    happiness.add(0.15);
-
-                    // Remove item
-                    commands.entity(didi).remove::<Carrying>();
-                    for item_in_hand in carried_items.iter() {
-                        commands.entity(item_in_hand).despawn();
+                // An asked item is taken from the tray, which stays in hand
+                let from_container = item.is_container();
+                let item = if from_container {
+                    let active_slot = inventory.active;
+                    let taken = carried_containers
+                        .iter_mut()
+                        .find(|(carried, parent, _)| {
+                            carried_by_player(parent) && carried.0 == active_slot
+                        })
+                        .map_or(false, |(_, _, mut container)| container.remove(asked));
+                    if !taken {
+                        info!("The asked item {:?} is not on the tray", asked);
+                        continue;
                     }
+                    asked
+                } else {
+                    *item
+                };
 
-                    // Add another item
-                    let next_item = random_different_item(*item);
-                    for mut item_material in asked_item_materials.iter_mut() {
-                        *item_material = materials.item_sprite_for(next_item);
+                if item != asked {
+                    if let Some(mut happiness) = happiness {
+                        happiness.sub(0.15);
                     }
-                    asking_item.0 = next_item;
+                    score.penalize_wrong_delivery();
+                    stats.record_wrong_give();
+                    combo.reset();
+                    wrong_delivery_events.send(WrongDeliveryEvent {
+                        asker: *asker,
+                        item,
+                        asked,
+                    });
+                    continue;
+                }
+                combo.hit();
+                score.reward_delivery(combo.multiplier());
+                stats.record_delivery();
+                stats.record_streak(combo.count());
+
+                let mut happiness_before = 1.0;
+                if let Some(mut happiness) = happiness {
+                    happiness_before = happiness.value();
+                    happiness.satisfy(
+                        registry.need(item),
+                        registry.happiness(item) * combo.multiplier(),
+                    );
                 }
+                delivery_events.send(DeliveryEvent {
+                    asker: *asker,
+                    item,
+                    happiness: happiness_before,
+                    patience_left: patience.map(Patience::seconds_left),
+                });
+
+                // Remove item
+                if !from_container {
+                    let slot = inventory.take_active();
+                    despawn_carried_item(&mut commands, &carried_items, player, slot);
+                }
+
+                // Ask for the next item
+                requests.advance(&registry, &mut rng.rng, queue.as_deref_mut());
             }
         }
     }
 }
 
-/// Returns a random item different than the given one.
-fn random_different_item(item: Item) -> Item {
-    loop {
-        let next_item = random::<Item>();
-        if next_item != item {
-            return next_item;
+/// Puts the item of the backpack in hand when the swap key is pressed.
+fn swap_items_system(
+    keyboard: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    input_map: Res<InputMap>,
+    game_data: Res<GameData>,
+    placement: Res<DropPlacement>,
+    prompt: Res<ConsumePrompt>,
+    mut inventories: Query<&mut Inventory>,
+) {
+    if placement.is_active() || prompt.is_open() {
+        return; // The item in hand is being dropped or consumed
+    }
+    if !input_map.just_pressed(InputAction::SwapItems, &keyboard, &gamepad_buttons) {
+        return;
+    }
+    if let Ok(mut inventory) = inventories.get_mut(game_data.didi_entity) {
+        inventory.swap();
+    }
+}
+
+/// Shows the carried items in the hand or in the backpack of the players.
+fn carried_items_position_system(
+    inventories: Query<&Inventory>,
+    mut carried_items: Query<(&CarriedItem, &Parent, &mut Transform)>,
+) {
+    for (CarriedItem(slot), parent, mut transform) in carried_items.iter_mut() {
+        let inventory = match inventories.get(parent.0) {
+            Ok(inventory) => inventory,
+            Err(_) => continue,
+        };
+        if inventory.slots[*slot].is_none() {
+            continue; // The item is leaving the inventory
         }
+        if *slot == inventory.active {
+            transform.translation = PICKED_ITEM_TRANSLATION;
+            transform.scale = Vec3::ONE;
+        } else {
+            transform.translation = BACKPACK_ITEM_TRANSLATION;
+            transform.scale = Vec3::splat(BACKPACK_ITEM_SCALE);
+        }
+    }
+}
+
+/// Breaks the combo when the next item is not delivered in time.
+fn combo_timer_system(time: Res<Time>, time_scale: Res<TimeScale>, mut combo: ResMut<ComboState>) {
+    let delta = time_scale.scale(time.delta());
+    if combo.count > 0 && combo.window.tick(delta).just_finished() {
+        combo.reset();
+    }
+}
+
+/// Breaks the combo when a new game starts.
+fn reset_combo_system(mut combo: ResMut<ComboState>) {
+    *combo = ComboState::default();
+}
+
+/// Shows the asked items in the row of icons of the askers.
+fn update_asked_items_system(
+    materials: Res<GameplayMaterials>,
+    askers: Query<(&ItemRequestQueue, &Children), Changed<ItemRequestQueue>>,
+    mut icons: Query<(&AskedItem, &mut Handle<ColorMaterial>)>,
+) {
+    for (queue, children) in askers.iter() {
+        for child in children.iter() {
+            if let Ok((icon, mut material)) = icons.get_mut(*child) {
+                *material = queue.0.get(icon.0).map_or(materials.none.clone(), |item| {
+                    materials.item_sprite_for(*item)
+                });
+            }
+        }
+    }
+}
+
+/// Position of the item in the hand of Didi.
+pub const PICKED_ITEM_TRANSLATION: Vec3 = const_vec3!([-170.0, -10.0, 0.0]);
+
+/// Position of the item in the backpack of Didi, behind the sprite.
+const BACKPACK_ITEM_TRANSLATION: Vec3 = const_vec3!([120.0, 60.0, -0.1]);
+
+/// Scale of the item in the backpack of Didi.
+const BACKPACK_ITEM_SCALE: f32 = 0.6;
+
+/// Despawns the item carried by the player in the slot of the inventory.
+fn despawn_carried_item(
+    commands: &mut Commands,
+    carried_items: &Query<(Entity, &CarriedItem, &Parent)>,
+    player: Entity,
+    slot: usize,
+) {
+    for (item_entity, carried, parent) in carried_items.iter() {
+        if parent.0 == player && carried.0 == slot {
+            commands.entity(item_entity).despawn_recursive();
+        }
+    }
+}
+
+/// Spawns the item in the hand of the player, if the inventory has room for
+/// it.
+fn spawn_item_in_hand(
+    commands: &mut Commands,
+    materials: &GameplayMaterials,
+    player: Entity,
+    inventory: &mut Inventory,
+    item: Item,
+) {
+    let slot = match inventory.put(item) {
+        Some(slot) => slot,
+        None => return,
+    };
+    let item_in_hand = commands
+        .spawn()
+        .insert(item)
+        .insert(CarriedItem(slot))
+        .insert_bundle(SpriteBundle {
+            material: materials.item_sprite_for(item),
+            transform: Transform::from_translation(PICKED_ITEM_TRANSLATION),
+            ..SpriteBundle::default()
+        })
+        .id();
+
+    commands.entity(player).push_children(&[item_in_hand]);
+}
+
+/// Spawns the item lying on the ground at the given position.
+pub fn spawn_item_on_ground(
+    commands: &mut Commands,
+    materials: &GameplayMaterials,
+    item: Item,
+    position: Vec3,
+) -> Entity {
+    commands
+        .spawn()
+        .insert(item)
+        .insert_bundle((Position(position), TriggerArea::new(75.0, 100.0)))
+        .insert_bundle(SpriteBundle {
+            material: materials.item_sprite_for(item),
+            transform: Transform::from_scale(Vec3::new(0.3, 0.3, 0.0)),
+            ..SpriteBundle::default()
+        })
+        .id()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inventory_holds_two_items() {
+        let mut inventory = Inventory::default();
+        assert_eq!(inventory.put(Item::Chips), Some(0));
+        assert_eq!(inventory.put(Item::Coffee), Some(1));
+        assert_eq!(inventory.active_item(), Some(Item::Coffee));
+        assert!(!inventory.has_room());
+        assert_eq!(inventory.put(Item::IceCream), None);
+
+        inventory.swap();
+        assert_eq!(inventory.active_item(), Some(Item::Chips));
+        assert_eq!(inventory.take_active(), 0);
+        assert_eq!(inventory.active_item(), None);
+
+        assert_eq!(inventory.put(Item::Tray), Some(0));
+        assert!(inventory.carries_container());
+        assert_eq!(inventory.remove(Item::Coffee), Some(1));
+        assert!(inventory.has_room());
+
+        // The bigger backpack holds one more item
+        inventory.put(Item::Coffee);
+        inventory.set_capacity(MAX_INVENTORY_SLOTS);
+        assert_eq!(inventory.put(Item::Chips), Some(2));
+        inventory.set_capacity(INVENTORY_SLOTS);
+        assert_eq!(inventory.active, 1);
+        assert!(!inventory.slots.contains(&Some(Item::Chips)));
+    }
+
+    #[test]
+    fn producers_restock() {
+        let mut producer = ItemProducer::new(Item::Chips);
+        for _ in 0..PRODUCER_STOCK {
+            assert!(producer.take());
+        }
+        assert!(producer.is_empty());
+        assert!(!producer.take());
+
+        let restock_duration = Difficulty::Normal.profile().restock_duration;
+        producer.tick(restock_duration / 2.0);
+        assert!(producer.is_empty());
+        producer.tick(restock_duration / 2.0);
+        assert!(producer.take());
+
+        producer.put_back();
+        producer.refill(restock_duration);
+        assert_eq!(producer.stock, PRODUCER_STOCK);
     }
 }
@@ -0,0 +1,228 @@
+//! Contextual key prompt: a small floating `Space` key above Didi while Didi
+//! touches something the action key acts on, a producer, Baobei or an item
+//! lying on the ground.
+//!
+//! The touched entities are followed from the `ContactEvent`s, so the prompt
+//! appears as soon as a contact starts and disappears when the last one stops.
+
+use std::collections::HashSet;
+
+use bevy::{math::const_vec2, prelude::*};
+
+use crate::{
+    collisions::{CollisionSystems, ContactEvent, Position},
+    constants::GameState,
+    drawing::Overlay,
+};
+
+use super::{
+    entities::GameData,
+    items::{CarriedItem, Item, ItemProducer},
+    Baobei,
+};
+
+/// Plugin managing the key prompt above Didi.
+pub struct KeyPromptPlugin;
+
+impl Plugin for KeyPromptPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<PromptedContacts>()
+            .init_resource::<KeyPromptMaterials>()
+            .add_startup_system(spawn_key_prompt.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(
+                        track_contacts_system
+                            .system()
+                            .label("key_prompt_contacts")
+                            .after(CollisionSystems),
+                    )
+                    .with_system(key_prompt_system.system().after("key_prompt_contacts")),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::InGame).with_system(hide_key_prompt_system.system()),
+            );
+    }
+}
+
+/// Height of the key above Didi, over the prompt of the interactables.
+const KEY_HEIGHT: f32 = 190.0;
+/// Size of the key cap.
+const KEY_SIZE: Vec2 = const_vec2!([80.0, 30.0]);
+
+/// Entities touched by Didi that the action key acts on.
+#[derive(Debug, Default)]
+struct PromptedContacts(HashSet<Entity>);
+
+impl PromptedContacts {
+    /// Follows the contact of Didi with the entity, starting or stopping.
+    fn update(&mut self, entity: Entity, started: bool) {
+        if started {
+            self.0.insert(entity);
+        } else {
+            self.0.remove(&entity);
+        }
+    }
+
+    /// Returns true if the key prompt is shown.
+    fn is_prompted(&self) -> bool {
+        !self.0.is_empty()
+    }
+}
+
+/// Color of the key cap.
+struct KeyPromptMaterials {
+    /// Dark and translucent, under the white label
+    key_cap: Handle<ColorMaterial>,
+}
+
+impl FromWorld for KeyPromptMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            key_cap: materials.add(Color::rgba(0.1, 0.1, 0.15, 0.75).into()),
+        }
+    }
+}
+
+/// Component on the key cap of the prompt.
+struct KeyPrompt;
+
+/// Spawns the hidden key cap with its label.
+fn spawn_key_prompt(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    materials: Res<KeyPromptMaterials>,
+) {
+    let hidden = || Visible {
+        is_visible: false,
+        is_transparent: true,
+    };
+
+    commands
+        .spawn()
+        .insert(KeyPrompt)
+        .insert(Overlay)
+        .insert(Position::default())
+        .insert_bundle(SpriteBundle {
+            material: materials.key_cap.clone(),
+            sprite: Sprite::new(KEY_SIZE),
+            visible: hidden(),
+            ..SpriteBundle::default()
+        })
+        .with_children(|parent| {
+            parent.spawn_bundle(Text2dBundle {
+                text: Text::with_section(
+                    "Space",
+                    TextStyle {
+                        font: asset_server.load("FiraSans-Bold.ttf"),
+                        font_size: 18.0,
+                        color: Color::WHITE,
+                    },
+                    TextAlignment {
+                        vertical: VerticalAlign::Center,
+                        horizontal: HorizontalAlign::Center,
+                    },
+                ),
+                transform: Transform::from_xyz(0.0, 0.0, 0.1),
+                visible: hidden(),
+                ..Text2dBundle::default()
+            });
+        });
+}
+
+/// Follows the contacts of Didi with the producers, Baobei and the items on
+/// the ground.
+fn track_contacts_system(
+    game_data: Res<GameData>,
+    mut prompted: ResMut<PromptedContacts>,
+    mut contact_events: EventReader<ContactEvent>,
+    targets: Query<(), Or<(With<ItemProducer>, With<Baobei>, With<Item>)>>,
+    carried_items: Query<(), With<CarriedItem>>,
+) {
+    for event in contact_events.iter() {
+        let (contact, started) = match event {
+            ContactEvent::Started(contact, _) => (contact, true),
+            ContactEvent::Stopped(contact) => (contact, false),
+        };
+        if contact.0 != game_data.didi_entity {
+            continue;
+        }
+        let entity = contact.1;
+        // A stopped contact may be with an entity removed meanwhile
+        if !started || (targets.get(entity).is_ok() && carried_items.get(entity).is_err()) {
+            prompted.update(entity, started);
+        }
+    }
+}
+
+/// Shows the key above Didi while Didi touches one of the entities.
+fn key_prompt_system(
+    game_data: Res<GameData>,
+    prompted: Res<PromptedContacts>,
+    positions: Query<&Position, Without<KeyPrompt>>,
+    mut key_prompts: Query<(Entity, &mut Position, &Children), With<KeyPrompt>>,
+    mut visibles: Query<&mut Visible>,
+) {
+    let shown = prompted.is_prompted();
+    let didi_position = positions
+        .get(game_data.didi_entity)
+        .map_or(Vec3::ZERO, |position| position.0);
+
+    for (entity, mut position, children) in key_prompts.iter_mut() {
+        for entity in std::iter::once(&entity).chain(children.iter()) {
+            if let Ok(mut visible) = visibles.get_mut(*entity) {
+                if visible.is_visible != shown {
+                    visible.is_visible = shown;
+                }
+            }
+        }
+        if shown {
+            let above = Vec3::new(
+                didi_position.x,
+                didi_position.y + didi_position.z + KEY_HEIGHT,
+                0.0,
+            );
+            if position.0 != above {
+                position.0 = above;
+            }
+        }
+    }
+}
+
+/// Forgets the contacts and hides the key when leaving the game.
+fn hide_key_prompt_system(
+    mut prompted: ResMut<PromptedContacts>,
+    key_prompts: Query<(Entity, &Children), With<KeyPrompt>>,
+    mut visibles: Query<&mut Visible>,
+) {
+    prompted.0.clear();
+
+    for (entity, children) in key_prompts.iter() {
+        for entity in std::iter::once(&entity).chain(children.iter()) {
+            if let Ok(mut visible) = visibles.get_mut(*entity) {
+                visible.is_visible = false;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prompt_lasts_until_the_last_contact_stops() {
+        let mut prompted = PromptedContacts::default();
+        assert!(!prompted.is_prompted());
+
+        prompted.update(Entity::new(1), true);
+        prompted.update(Entity::new(2), true);
+        prompted.update(Entity::new(1), false);
+        assert!(prompted.is_prompted());
+
+        prompted.update(Entity::new(2), false);
+        assert!(!prompted.is_prompted());
+    }
+}
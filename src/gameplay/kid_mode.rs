@@ -0,0 +1,232 @@
+//! Kid mode, for very young players: the assists enlarge the trigger areas,
+//! steer Didi toward the producer or the Baobei Didi walks to, and each
+//! delivery drops a star to collect instead of giving points.
+
+use bevy::prelude::*;
+
+use crate::{
+    assists::Assists,
+    collisions::{CollisionSystems, ContactEvent, Movement, Position, TriggerArea},
+    constants::GameState,
+};
+
+use super::{
+    entities::GameData,
+    items::{DeliveryEvent, ItemProducer, ItemSystems},
+    Baobei, Didi, NewRunAppExt,
+};
+
+/// Plugin managing the assists of the kid mode.
+pub struct KidModePlugin;
+
+impl Plugin for KidModePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Stars>()
+            .init_resource::<StarMaterials>()
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(
+                        aim_assist_system
+                            .system()
+                            .after("movement")
+                            .before(CollisionSystems),
+                    )
+                    .with_system(drop_stars_system.system().after(ItemSystems))
+                    .with_system(collect_stars_system.system().after(CollisionSystems)),
+            )
+            .add_new_run_system(scale_trigger_areas_system)
+            .add_new_run_system(reset_stars_system);
+    }
+}
+
+/// Distance under which the aim assist steers Didi toward a target.
+const AIM_ASSIST_RANGE: f32 = 250.0;
+/// Smallest cosine between the movement and a target to steer toward it.
+const AIM_ASSIST_ANGLE: f32 = 0.7;
+/// Weight of the direction of the target in the steered movement.
+const AIM_ASSIST_STRENGTH: f32 = 0.6;
+/// Offset from the Baobei where the stars drop.
+const STAR_OFFSET: Vec3 = Vec3::new(0.0, -120.0, 0.0);
+/// Size of a star on the floor.
+const STAR_SIZE: f32 = 30.0;
+
+/// Stars collected during the game in kid mode.
+#[derive(Default)]
+pub struct Stars(pub u32);
+
+/// Color of the stars.
+struct StarMaterials {
+    /// A star waiting on the floor
+    star: Handle<ColorMaterial>,
+}
+
+impl FromWorld for StarMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            star: materials.add(Color::GOLD.into()),
+        }
+    }
+}
+
+/// Component on a star to collect.
+struct Star;
+
+/// Component remembering the size of a trigger area before the assists
+/// scaled it.
+struct BaseTriggerSize(Vec2);
+
+/// Returns the movement bent toward the nearest target in front of it,
+/// keeping its length.
+fn steer(movement: Vec3, from: Vec3, targets: impl Iterator<Item = Vec3>) -> Vec3 {
+    let length = movement.length();
+    if length <= f32::EPSILON {
+        return movement;
+    }
+    let direction = movement / length;
+    let target = targets
+        .map(|target| (target - from) * Vec3::new(1.0, 1.0, 0.0))
+        .filter(|to_target| {
+            let distance = to_target.length();
+            distance > f32::EPSILON
+                && distance < AIM_ASSIST_RANGE
+                && direction.dot(*to_target / distance) > AIM_ASSIST_ANGLE
+        })
+        .min_by(|a, b| a.length().partial_cmp(&b.length()).unwrap());
+
+    match target {
+        Some(to_target) => {
+            let steered = direction.lerp(to_target.normalize(), AIM_ASSIST_STRENGTH);
+            steered.normalize_or_zero() * length
+        }
+        None => movement,
+    }
+}
+
+/// Scales the trigger areas of the producers and the Baobeis when a new
+/// game starts, following the assists.
+#[allow(clippy::type_complexity)]
+fn scale_trigger_areas_system(
+    mut commands: Commands,
+    assists: Res<Assists>,
+    mut areas: Query<
+        (Entity, &mut TriggerArea, Option<&BaseTriggerSize>),
+        Or<(With<ItemProducer>, With<Baobei>)>,
+    >,
+) {
+    for (entity, mut area, base) in areas.iter_mut() {
+        let base_size = match base {
+            Some(base) => base.0,
+            None => {
+                commands.entity(entity).insert(BaseTriggerSize(area.size));
+                area.size
+            }
+        };
+        area.size = base_size * assists.trigger_scale;
+    }
+}
+
+/// Steers Didi toward the producer or the Baobei Didi walks to.
+fn aim_assist_system(
+    assists: Res<Assists>,
+    mut didi: Query<(&Position, &mut Movement), With<Didi>>,
+    targets: Query<&Position, (Or<(With<ItemProducer>, With<Baobei>)>, Without<Didi>)>,
+) {
+    if !assists.aim_assist {
+        return;
+    }
+    for (position, mut movement) in didi.iter_mut() {
+        let steered = steer(
+            movement.0,
+            position.0,
+            targets.iter().map(|target| target.0),
+        );
+        if steered != movement.0 {
+            movement.0 = steered;
+        }
+    }
+}
+
+/// Drops a star next to the Baobeis receiving an item in kid mode.
+fn drop_stars_system(
+    mut commands: Commands,
+    assists: Res<Assists>,
+    materials: Res<StarMaterials>,
+    mut delivery_events: EventReader<DeliveryEvent>,
+    askers: Query<&Position>,
+) {
+    for delivery in delivery_events.iter() {
+        if !assists.stars {
+            continue;
+        }
+        let position = match askers.get(delivery.asker) {
+            Ok(position) => Vec3::new(position.0.x, position.0.y, 0.0) + STAR_OFFSET,
+            Err(_) => continue,
+        };
+        commands
+            .spawn()
+            .insert(Star)
+            .insert(Position(position))
+            .insert(TriggerArea::new(STAR_SIZE + 40.0, STAR_SIZE + 40.0))
+            .insert_bundle(SpriteBundle {
+                material: materials.star.clone(),
+                sprite: Sprite::new(Vec2::splat(STAR_SIZE)),
+                transform: Transform::from_rotation(Quat::from_rotation_z(
+                    std::f32::consts::FRAC_PI_4,
+                )),
+                ..SpriteBundle::default()
+            });
+    }
+}
+
+/// Collects the stars Didi walks on.
+fn collect_stars_system(
+    mut commands: Commands,
+    game_data: Res<GameData>,
+    mut stars: ResMut<Stars>,
+    mut contact_events: EventReader<ContactEvent>,
+    star_entities: Query<(), With<Star>>,
+) {
+    for event in contact_events.iter() {
+        let contact = match event {
+            ContactEvent::Started(contact, _) if contact.0 == game_data.didi_entity => contact,
+            _ => continue,
+        };
+        if star_entities.get(contact.1).is_ok() {
+            stars.0 += 1;
+            commands.entity(contact.1).despawn();
+        }
+    }
+}
+
+/// Forgets the stars of the previous game.
+fn reset_stars_system(
+    mut commands: Commands,
+    mut stars: ResMut<Stars>,
+    star_entities: Query<Entity, With<Star>>,
+) {
+    stars.0 = 0;
+    for star in star_entities.iter() {
+        commands.entity(star).despawn();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steers_toward_targets_ahead() {
+        let movement = Vec3::new(10.0, 0.0, 0.0);
+        let ahead = Vec3::new(100.0, 40.0, 0.0);
+        let behind = Vec3::new(-50.0, 0.0, 0.0);
+
+        let steered = steer(movement, Vec3::ZERO, vec![behind, ahead].into_iter());
+        assert!(steered.y > 0.0);
+        assert!((steered.length() - movement.length()).abs() < 1e-4);
+
+        let unchanged = steer(movement, Vec3::ZERO, vec![behind].into_iter());
+        assert_eq!(unchanged, movement);
+    }
+}
@@ -0,0 +1,358 @@
+//! Laundry chore: dirty clothes piles up on the floor, Didi collects them
+//! in the basket, washes them in the washing machine and unloads it.
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    collisions::{overlaps_colliders, BoxCollider, Contact, Movement, Position, TriggerArea},
+    constants::{
+        GameState, CLOTHES_PILE_INTERVAL, CLOTHES_PILE_PENALTY, MAX_CLOTHES_PILES, WASHING_DURATION,
+    },
+    rng::GameRng,
+    scheduler::{ScheduledEvent, Scheduler, SchedulerSystems},
+    time_scale::TimeScale,
+    widgets::{entity_timer_system, spawn_timer_bar, EntityTimer, Progress, WidgetMaterials},
+};
+
+use super::{
+    entities::GameData,
+    happiness::Happiness,
+    items::{spawn_item_on_ground, Inventory, Item, PickAndDropCooldown},
+    materials::GameplayMaterials,
+    phases::PhaseController,
+    score::Score,
+    storage::Storage,
+    Baobei, Furniture, NewRunAppExt,
+};
+
+/// Plugin managing the laundry chore.
+pub struct LaundryPlugin;
+
+impl Plugin for LaundryPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<LaundryBasket>()
+            .init_resource::<LaundryMaterials>()
+            .add_startup_system(spawn_washing_machine.system())
+            .add_startup_system(schedule_laundry.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(spawn_clothes_piles_system.system().after(SchedulerSystems))
+                    .with_system(ensure_basket_system.system())
+                    .with_system(laundry_actions_system.system().before("item_actions"))
+                    .with_system(washing_system.system().label("washing"))
+                    .with_system(
+                        entity_timer_system::<WashingMachine>
+                            .system()
+                            .after("washing"),
+                    )
+                    .with_system(mess_penalty_system.system().after(SchedulerSystems)),
+            )
+            .add_new_run_system(reset_laundry_system);
+    }
+}
+
+/// Where the basket is put back when it got lost.
+const BASKET_HOME: (f32, f32) = (160.0, 120.0);
+/// Size of a pile of clothes on the floor.
+const PILE_SIZE: (f32, f32) = (50.0, 30.0);
+
+/// Clothes in the laundry basket.
+#[derive(Default)]
+pub struct LaundryBasket {
+    /// Number of dirty piles collected.
+    dirty: u32,
+}
+
+/// Scheduled task dropping a new pile of dirty clothes.
+const PILE_TASK: &str = "clothes_pile";
+/// Scheduled task decreasing the happiness due to the mess every second.
+const PENALTY_TASK: &str = "mess_penalty";
+
+/// Schedules the new piles of clothes and the mess penalty.
+fn schedule_laundry(mut scheduler: ResMut<Scheduler>) {
+    scheduler.every(PILE_TASK, CLOTHES_PILE_INTERVAL);
+    scheduler.every(PENALTY_TASK, 1.0);
+}
+
+/// Colors of the laundry chore.
+struct LaundryMaterials {
+    /// Dirty clothes on the floor
+    clothes: Handle<ColorMaterial>,
+    /// Washing machine
+    machine: Handle<ColorMaterial>,
+}
+
+impl FromWorld for LaundryMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            clothes: materials.add(Color::rgb(0.45, 0.5, 0.65).into()),
+            machine: materials.add(Color::rgb(0.9, 0.9, 0.92).into()),
+        }
+    }
+}
+
+/// Component on a pile of dirty clothes on the floor.
+struct ClothesPile;
+
+/// Component on the washing machine, washing the clothes over time.
+pub enum WashingMachine {
+    /// Nothing inside
+    Empty,
+    /// Washing the loaded piles
+    Washing {
+        /// Number of loaded piles
+        loads: u32,
+        /// Timer until the end of the washing
+        timer: Timer,
+    },
+    /// Clean clothes waiting to be unloaded
+    Done {
+        /// Number of washed piles
+        loads: u32,
+    },
+}
+
+impl Progress for WashingMachine {
+    fn remaining(&self) -> f32 {
+        match self {
+            Self::Washing { timer, .. } => timer.percent_left(),
+            Self::Empty | Self::Done { .. } => 0.0,
+        }
+    }
+}
+
+/// Spawns the washing machine with the timer bar of the washing.
+fn spawn_washing_machine(
+    mut commands: Commands,
+    materials: Res<LaundryMaterials>,
+    widget_materials: Res<WidgetMaterials>,
+) {
+    let size = Vec2::new(70.0, 70.0);
+
+    let machine = commands
+        .spawn()
+        .insert(Furniture)
+        .insert(WashingMachine::Empty)
+        .insert(Position(Vec3::new(110.0, 330.0, 0.0)))
+        .insert(BoxCollider::new(size.x, size.y))
+        .insert(TriggerArea::new(size.x + 80.0, size.y + 80.0))
+        .insert_bundle(SpriteBundle {
+            material: materials.machine.clone(),
+            sprite: Sprite::new(size),
+            ..SpriteBundle::default()
+        })
+        .id();
+
+    let washing_bar = spawn_timer_bar(
+        &mut commands,
+        &widget_materials,
+        Vec3::new(0.0, 60.0, 0.1),
+        Vec2::new(70.0, 10.0),
+    );
+    commands
+        .entity(washing_bar)
+        .insert(EntityTimer::<WashingMachine>::new(machine));
+    commands.entity(machine).push_children(&[washing_bar]);
+}
+
+/// Drops dirty clothes on a free place of the floor from time to time,
+/// except when Baobei naps.
+#[allow(clippy::too_many_arguments)]
+fn spawn_clothes_piles_system(
+    mut commands: Commands,
+    phases: Res<PhaseController>,
+    materials: Res<LaundryMaterials>,
+    mut rng: ResMut<GameRng>,
+    mut scheduled_events: EventReader<ScheduledEvent>,
+    piles: Query<(), With<ClothesPile>>,
+    colliders: Query<(&Position, &BoxCollider), Without<Movement>>,
+) {
+    /// Attempts to find a free place before giving up until the next pile
+    const ATTEMPTS: usize = 10;
+
+    let due = scheduled_events
+        .iter()
+        .any(|ScheduledEvent(task)| *task == PILE_TASK);
+
+    if phases.is_breather() || !due {
+        return;
+    }
+    if piles.iter().count() >= MAX_CLOTHES_PILES {
+        return;
+    }
+
+    let size = Vec2::new(PILE_SIZE.0, PILE_SIZE.1);
+    let free_position = (0..ATTEMPTS)
+        .map(|_| {
+            Vec3::new(
+                rng.rng.gen_range(100.0..1180.0),
+                rng.rng.gen_range(90.0..480.0),
+                0.0,
+            )
+        })
+        .find(|position| !overlaps_colliders(*position, size, colliders.iter()));
+
+    if let Some(position) = free_position {
+        commands
+            .spawn()
+            .insert(ClothesPile)
+            .insert(Position(position))
+            .insert(TriggerArea::new(size.x + 40.0, size.y + 40.0))
+            .insert_bundle(SpriteBundle {
+                material: materials.clothes.clone(),
+                sprite: Sprite::new(size),
+                ..SpriteBundle::default()
+            });
+    }
+}
+
+/// Puts the basket back in the room if it got lost, for instance with a
+/// new attempt of a seed race.
+fn ensure_basket_system(
+    mut commands: Commands,
+    materials: Res<GameplayMaterials>,
+    items: Query<&Item>,
+    storages: Query<&Storage>,
+) {
+    let basket_exists = items.iter().any(|item| *item == Item::LaundryBasket)
+        || storages
+            .iter()
+            .any(|storage| storage.contains(Item::LaundryBasket));
+
+    if !basket_exists {
+        spawn_item_on_ground(
+            &mut commands,
+            &materials,
+            Item::LaundryBasket,
+            Vec3::new(BASKET_HOME.0, BASKET_HOME.1, 0.0),
+        );
+    }
+}
+
+/// With the basket in hand, collects the piles of clothes, loads the
+/// washing machine and unloads it.
+#[allow(clippy::too_many_arguments)]
+fn laundry_actions_system(
+    mut commands: Commands,
+    keyboard: Res<Input<KeyCode>>,
+    game_data: Res<GameData>,
+    mut cooldown: ResMut<PickAndDropCooldown>,
+    mut basket: ResMut<LaundryBasket>,
+    mut score: ResMut<Score>,
+    contacts: Query<&Contact>,
+    inventories: Query<&Inventory>,
+    piles: Query<(), With<ClothesPile>>,
+    mut machines: Query<&mut WashingMachine>,
+) {
+    let didi = game_data.didi_entity;
+    let carries_basket =
+        inventories.get(didi).ok().and_then(Inventory::active_item) == Some(Item::LaundryBasket);
+
+    if !carries_basket || !cooldown.0.available() || !keyboard.pressed(KeyCode::Space) {
+        return;
+    }
+    let touched: Vec<Entity> = contacts
+        .iter()
+        .filter(|contact| contact.0 == didi)
+        .map(|contact| contact.1)
+        .collect();
+
+    if let Some(pile) = touched.iter().find(|entity| piles.get(**entity).is_ok()) {
+        info!("Collect dirty clothes");
+        commands.entity(*pile).despawn();
+        basket.dirty += 1;
+        score.reward_chore();
+        cooldown.0.start();
+        return;
+    }
+
+    let machine = touched
+        .iter()
+        .find_map(|entity| machines.get_mut(*entity).ok());
+
+    if let Some(mut machine) = machine {
+        match *machine {
+            WashingMachine::Empty if basket.dirty > 0 => {
+                info!("Load {} piles in the washing machine", basket.dirty);
+                *machine = WashingMachine::Washing {
+                    loads: basket.dirty,
+                    timer: Timer::from_seconds(WASHING_DURATION, false),
+                };
+                basket.dirty = 0;
+                score.reward_chore();
+            }
+            WashingMachine::Done { loads } => {
+                info!("Unload {} clean piles", loads);
+                *machine = WashingMachine::Empty;
+                for _ in 0..loads {
+                    score.reward_chore();
+                }
+            }
+            _ => return,
+        }
+        cooldown.0.start();
+    }
+}
+
+/// Washes the loaded clothes over time.
+fn washing_system(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut machines: Query<&mut WashingMachine>,
+) {
+    let delta = time_scale.scale(time.delta());
+    for mut machine in machines.iter_mut() {
+        let finished = match &mut *machine {
+            WashingMachine::Washing { loads, timer } if timer.tick(delta).finished() => {
+                Some(*loads)
+            }
+            _ => None,
+        };
+        if let Some(loads) = finished {
+            *machine = WashingMachine::Done { loads };
+        }
+    }
+}
+
+/// Makes Baobei sad while dirty clothes lie on the floor.
+fn mess_penalty_system(
+    mut scheduled_events: EventReader<ScheduledEvent>,
+    piles: Query<(), With<ClothesPile>>,
+    mut baobei: Query<&mut Happiness, With<Baobei>>,
+) {
+    let due = scheduled_events
+        .iter()
+        .any(|ScheduledEvent(task)| *task == PENALTY_TASK);
+
+    if !due {
+        return;
+    }
+    let pile_count = piles.iter().count() as f32;
+
+    for mut happiness in baobei.iter_mut() {
+        happiness.sub(CLOTHES_PILE_PENALTY * pile_count);
+    }
+}
+
+/// Cleans the house and empties the basket and the washing machine when a
+/// new game starts.
+fn reset_laundry_system(
+    mut commands: Commands,
+    mut basket: ResMut<LaundryBasket>,
+    scheduler: ResMut<Scheduler>,
+    piles: Query<Entity, With<ClothesPile>>,
+    mut machines: Query<&mut WashingMachine>,
+) {
+    *basket = LaundryBasket::default();
+    schedule_laundry(scheduler);
+
+    for pile in piles.iter() {
+        commands.entity(pile).despawn();
+    }
+    for mut machine in machines.iter_mut() {
+        *machine = WashingMachine::Empty;
+    }
+}
@@ -0,0 +1,421 @@
+//! Summary of the level: at the end of each level, or when Baobei has no
+//! happiness left in the endless mode, the game stops on the statistics of
+//! the level with a grade, and the player retries the game or continues to
+//! the shop.
+//!
+//! The summary is pushed on top of the game state. Retrying goes through the
+//! menu, which starts a new run: the game is cleaned up and each part of it
+//! reset, the happiness of Baobei included, so the retried game does not end
+//! again right away.
+
+use bevy::prelude::*;
+
+use crate::{
+    assists::Assists,
+    constants::{GameMode, GameState},
+    controllers::{BindingText, InputAction, InputMap},
+};
+
+use super::{happiness::Happiness, levels::LevelEvent, stats::SessionStats, Baobei, NewRunAppExt};
+
+/// Plugin managing the summary of the levels.
+pub struct LevelSummaryPlugin;
+
+impl Plugin for LevelSummaryPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<LevelSummary>()
+            .init_resource::<SummaryMaterials>()
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(level_end_system.system().after("level"))
+                    .with_system(game_over_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::LevelSummary).with_system(setup_summary.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::LevelSummary)
+                    .with_system(summary_button_system.system())
+                    .with_system(summary_input_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::LevelSummary).with_system(cleanup_summary.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Menu).with_system(retry_system.system()),
+            )
+            .add_new_run_system(reset_summary_system);
+    }
+}
+
+/// Share of the items given to the right Baobei for each grade.
+const GRADE_ACCURACIES: [(Grade, f32); 3] = [(Grade::S, 0.95), (Grade::A, 0.8), (Grade::B, 0.6)];
+
+/// Grade of a level, from the best to the worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grade {
+    /// Almost no wrong item given
+    S,
+    /// Few wrong items given
+    A,
+    /// Some wrong items given
+    B,
+    /// Many wrong items given, or none delivered
+    C,
+}
+
+impl Grade {
+    /// Grades the statistics of a level by the share of the items given to
+    /// the right Baobei.
+    // The counts of a level are small
+    #[allow(clippy::cast_precision_loss)]
+    pub fn of(stats: &SessionStats) -> Self {
+        if stats.delivered() == 0 {
+            return Self::C;
+        }
+        let given = stats.delivered() + stats.wrong_gives();
+        let accuracy = stats.delivered() as f32 / given as f32;
+        GRADE_ACCURACIES
+            .iter()
+            .find(|(_, min_accuracy)| accuracy >= *min_accuracy)
+            .map_or(Self::C, |(grade, _)| *grade)
+    }
+
+    /// Returns the color of the grade on the summary.
+    const fn color(self) -> Color {
+        match self {
+            Self::S => Color::GOLD,
+            Self::A => Color::LIME_GREEN,
+            Self::B => Color::CYAN,
+            Self::C => Color::GRAY,
+        }
+    }
+}
+
+/// Why the summary is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SummaryKind {
+    /// The level with the number is over
+    LevelCleared(u32),
+    /// Baobei has no happiness left
+    GameOver,
+}
+
+/// Summary of the current level.
+pub struct LevelSummary {
+    /// Why the summary is shown
+    kind: SummaryKind,
+    /// Statistics of the session when the level started
+    level_start: SessionStats,
+    /// Whether the player asked for a new game
+    retry: bool,
+}
+
+impl Default for LevelSummary {
+    fn default() -> Self {
+        Self {
+            kind: SummaryKind::LevelCleared(1),
+            level_start: SessionStats::default(),
+            retry: false,
+        }
+    }
+}
+
+/// Colors of the summary.
+struct SummaryMaterials {
+    /// Darkened game behind the summary
+    overlay: Handle<ColorMaterial>,
+    /// Default style of a button
+    normal_button: Handle<ColorMaterial>,
+    /// Hovered style of a button
+    hovered_button: Handle<ColorMaterial>,
+}
+
+impl FromWorld for SummaryMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            overlay: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.8).into()),
+            normal_button: materials.add(Color::rgb(0.15, 0.15, 0.15).into()),
+            hovered_button: materials.add(Color::rgb(0.25, 0.25, 0.25).into()),
+        }
+    }
+}
+
+/// Buttons of the summary.
+#[derive(Clone, Copy)]
+enum SummaryButton {
+    /// Goes on to the shop, then the next level
+    Continue,
+    /// Starts a new game in the same mode
+    Retry,
+    /// Abandons the game and goes back to the main menu
+    Quit,
+}
+
+/// Stores entities of the summary.
+struct SummaryData {
+    /// Entity wrapping all the entities of the summary
+    node_wrapper: Entity,
+}
+
+/// Suspends the game on the summary when a level is over.
+fn level_end_system(
+    mut summary: ResMut<LevelSummary>,
+    mut level_events: EventReader<LevelEvent>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if let Some(event) = level_events.iter().last() {
+        summary.kind = SummaryKind::LevelCleared(event.number - 1);
+        // A bonus round may already suspend the game in this frame
+        state.push(GameState::LevelSummary).ok();
+    }
+}
+
+/// Ends the endless game on the summary when Baobei has no happiness left,
+/// except in kid mode. The other modes have their own ending, and the
+/// tutorial or the sandbox the game can be pushed on have none.
+fn game_over_system(
+    mode: Res<GameMode>,
+    assists: Res<Assists>,
+    mut summary: ResMut<LevelSummary>,
+    mut state: ResMut<State<GameState>>,
+    baobei: Query<&Happiness, With<Baobei>>,
+) {
+    if *mode != GameMode::Endless || assists.no_fail || !state.inactives().is_empty() {
+        return;
+    }
+    if baobei.iter().any(|happiness| happiness.value() <= 0.0) {
+        info!("Game over");
+        summary.kind = SummaryKind::GameOver;
+        state.push(GameState::LevelSummary).ok();
+    }
+}
+
+/// Shows the statistics and the grade of the level, then starts the next
+/// level from the current statistics.
+fn setup_summary(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    materials: Res<SummaryMaterials>,
+    stats: Res<SessionStats>,
+    mut summary: ResMut<LevelSummary>,
+) {
+    let level_stats = stats.since(&summary.level_start);
+    let grade = Grade::of(&level_stats);
+    summary.level_start = stats.clone();
+
+    let font = asset_server.load("FiraSans-Bold.ttf");
+    let text = |value: String, font_size: f32, color: Color| TextBundle {
+        text: Text::with_section(
+            value,
+            TextStyle {
+                font: font.clone(),
+                font_size,
+                color,
+            },
+            TextAlignment::default(),
+        ),
+        ..TextBundle::default()
+    };
+
+    let (title, buttons) = match summary.kind {
+        SummaryKind::LevelCleared(number) => (
+            format!("Level {} cleared!", number),
+            [
+                (SummaryButton::Continue, "Continue"),
+                (SummaryButton::Retry, "Retry"),
+            ],
+        ),
+        SummaryKind::GameOver => (
+            "Game over, Baobei is too sad…".to_string(),
+            [
+                (SummaryButton::Retry, "Retry"),
+                (SummaryButton::Quit, "Quit to menu"),
+            ],
+        ),
+    };
+
+    let node_wrapper = commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                padding: Rect::all(Val::Px(50.0)),
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Style::default()
+            },
+            material: materials.overlay.clone(),
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn()
+                .insert_bundle(text(title, 60.0, Color::WHITE));
+            parent
+                .spawn()
+                .insert_bundle(text(format!("Grade: {:?}", grade), 80.0, grade.color()));
+            for line in level_stats.summary_lines() {
+                parent.spawn().insert_bundle(text(line, 30.0, Color::WHITE));
+            }
+            for (button, label) in buttons {
+                parent
+                    .spawn()
+                    .insert(button)
+                    .insert_bundle(ButtonBundle {
+                        style: Style {
+                            margin: Rect::all(Val::Px(15.0)),
+                            size: Size::new(Val::Px(250.0), Val::Px(65.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..Style::default()
+                        },
+                        material: materials.normal_button.clone(),
+                        ..ButtonBundle::default()
+                    })
+                    .with_children(|parent| {
+                        parent
+                            .spawn()
+                            .insert_bundle(text(label.to_string(), 40.0, Color::WHITE));
+                    });
+            }
+            parent
+                .spawn()
+                .insert(BindingText(
+                    "Press {confirm} for the first choice, {back} for the second".to_string(),
+                ))
+                .insert_bundle(text(String::new(), 30.0, Color::WHITE));
+        })
+        .id();
+
+    commands.insert_resource(SummaryData { node_wrapper });
+}
+
+/// Leaves the summary with the choice of the player.
+fn choose(button: SummaryButton, summary: &mut LevelSummary, state: &mut State<GameState>) {
+    match button {
+        SummaryButton::Continue => state.set(GameState::Shop).unwrap(),
+        SummaryButton::Retry => {
+            summary.retry = true;
+            state.replace(GameState::Menu).unwrap();
+        }
+        SummaryButton::Quit => state.replace(GameState::Menu).unwrap(),
+    }
+}
+
+/// A button of the summary interacted by the player.
+type UpdatedButton = (Changed<Interaction>, With<Button>);
+
+/// Handles clicks on the buttons of the summary.
+fn summary_button_system(
+    materials: Res<SummaryMaterials>,
+    mut summary: ResMut<LevelSummary>,
+    mut state: ResMut<State<GameState>>,
+    mut buttons: Query<(&Interaction, &SummaryButton, &mut Handle<ColorMaterial>), UpdatedButton>,
+) {
+    for (interaction, button, mut material) in buttons.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => choose(*button, &mut summary, &mut state),
+            Interaction::Hovered => *material = materials.hovered_button.clone(),
+            Interaction::None => *material = materials.normal_button.clone(),
+        }
+    }
+}
+
+/// Takes the first choice with the confirm control, and the second one with
+/// the back control.
+fn summary_input_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    input_map: Res<InputMap>,
+    mut summary: ResMut<LevelSummary>,
+    mut state: ResMut<State<GameState>>,
+) {
+    let pressed = |action| input_map.just_pressed(action, &keyboard_input, &gamepad_buttons);
+    let (first, second) = match summary.kind {
+        SummaryKind::LevelCleared(_) => (SummaryButton::Continue, SummaryButton::Retry),
+        SummaryKind::GameOver => (SummaryButton::Retry, SummaryButton::Quit),
+    };
+    if pressed(InputAction::Confirm) {
+        choose(first, &mut summary, &mut state);
+    } else if pressed(InputAction::Back) {
+        choose(second, &mut summary, &mut state);
+    }
+}
+
+/// Removes all entities of the summary.
+fn cleanup_summary(mut commands: Commands, summary_data: Res<SummaryData>) {
+    commands
+        .entity(summary_data.node_wrapper)
+        .despawn_recursive();
+}
+
+/// Starts the new game asked from the summary, once back in the menu.
+fn retry_system(summary: Res<LevelSummary>, mut state: ResMut<State<GameState>>) {
+    if summary.retry {
+        state.set(GameState::InGame).unwrap();
+    }
+}
+
+/// Starts the statistics of the first level with the new game.
+fn reset_summary_system(mut summary: ResMut<LevelSummary>) {
+    *summary = LevelSummary::default();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gameplay::happiness::reset_happiness_system;
+
+    use super::*;
+
+    #[test]
+    fn levels_are_graded_by_accuracy() {
+        let mut stats = SessionStats::default();
+        assert_eq!(Grade::of(&stats), Grade::C);
+
+        for _ in 0..9 {
+            stats.record_delivery();
+        }
+        assert_eq!(Grade::of(&stats), Grade::S);
+
+        let level_start = stats.clone();
+        for _ in 0..3 {
+            stats.record_delivery();
+        }
+        stats.record_wrong_give();
+        let level_stats = stats.since(&level_start);
+        assert_eq!(level_stats.delivered(), 3);
+        assert_eq!(Grade::of(&level_stats), Grade::B);
+    }
+
+    #[test]
+    fn retried_game_does_not_end_on_its_first_frame() {
+        let mut world = World::default();
+        world.insert_resource(GameMode::Endless);
+        world.insert_resource(Assists::default());
+        world.insert_resource(LevelSummary::default());
+        world.insert_resource(State::new(GameState::InGame));
+        let mut new_run = SystemStage::single(reset_happiness_system.system());
+        let mut first_frame = SystemStage::single(game_over_system.system());
+
+        // Baobei is left without happiness by the game over
+        let mut happiness = Happiness::happy();
+        happiness.sub(1.0);
+        world.spawn().insert_bundle((Baobei, happiness));
+        first_frame.run(&mut world);
+        assert_eq!(
+            world.get_resource::<LevelSummary>().unwrap().kind,
+            SummaryKind::GameOver
+        );
+
+        *world.get_resource_mut::<LevelSummary>().unwrap() = LevelSummary::default();
+        new_run.run(&mut world);
+        first_frame.run(&mut world);
+        assert_eq!(
+            world.get_resource::<LevelSummary>().unwrap().kind,
+            SummaryKind::LevelCleared(1)
+        );
+    }
+}
@@ -0,0 +1,417 @@
+//! Levels of the game: every few deliveries, Baobei gets sadder faster,
+//! waits less and asks for more items at the same time.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    assists::Assists,
+    constants::{
+        GameState, DELIVERIES_PER_LEVEL, EXPIRED_REQUEST_PENALTY, LEVEL_DECAY_INCREASE,
+        LEVEL_PATIENCE_DECREASE, MAX_SIMULTANEOUS_REQUESTS, PERFECT_DELIVERY_PATIENCE,
+    },
+    difficulty::{Difficulty, DifficultyProfile},
+    preferences::StartPreferences,
+    rng::GameRng,
+    save::Profile,
+    time_scale::{HitStopEvent, TimeScale},
+    widgets::{entity_timer_system, spawn_timer_bar, EntityTimer, Progress, WidgetMaterials},
+};
+
+use super::{
+    adaptive_difficulty::AdaptiveDifficulty,
+    clock::GameClock,
+    cues::{CueEvent, EXPIRING_PATIENCE},
+    happiness::Happiness,
+    items::{DeliveryEvent, ItemRequestQueue, ItemSystems},
+    phases::PhaseController,
+    registry::ItemRegistry,
+    requests::RequestQueue,
+    visitor::Visit,
+    Baobei, NewRunAppExt,
+};
+
+/// Plugin managing the levels.
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<LevelEvent>()
+            .init_resource::<Level>()
+            .add_startup_system(spawn_level_banner.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(add_patience_system.system())
+                    .with_system(
+                        level_progress_system
+                            .system()
+                            .after(ItemSystems)
+                            .label("level"),
+                    )
+                    .with_system(level_banner_system.system().after("level"))
+                    .with_system(
+                        patience_system
+                            .system()
+                            .after("level")
+                            .after("adaptive_difficulty")
+                            .label("patience"),
+                    )
+                    .with_system(entity_timer_system::<Patience>.system().after("patience"))
+                    .with_system(
+                        extra_requests_system
+                            .system()
+                            .after("level")
+                            .after("adaptive_difficulty"),
+                    ),
+            )
+            .add_new_run_system(reset_level_system)
+            .add_system_set(
+                SystemSet::on_exit(GameState::InGame).with_system(remember_level_system.system()),
+            );
+    }
+}
+
+/// Current level of the game.
+pub struct Level {
+    /// Number of the level, starting at 1.
+    pub number: u32,
+    /// Items delivered since the start of the level.
+    delivered: u32,
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Self {
+            number: 1,
+            delivered: 0,
+        }
+    }
+}
+
+impl Level {
+    /// Returns the start of the given level.
+    fn starting_at(number: u32) -> Self {
+        Self {
+            number: number.max(1),
+            delivered: 0,
+        }
+    }
+
+    /// Returns the multiplier of the happiness decay, increasing every level.
+    pub fn decay_multiplier(&self) -> f32 {
+        LEVEL_DECAY_INCREASE.mul_add((self.number - 1) as f32, 1.0)
+    }
+
+    /// Returns the seconds Baobei waits for a request before giving up on it,
+    /// starting from the patience of the difficulty.
+    pub fn patience(&self, profile: &DifficultyProfile) -> f32 {
+        LEVEL_PATIENCE_DECREASE
+            .mul_add(-((self.number - 1) as f32), profile.patience)
+            .max(profile.min_patience)
+    }
+
+    /// Returns the number of items Baobei asks for at the same time, one
+    /// more every two levels.
+    pub fn simultaneous_requests(&self) -> usize {
+        (1 + (self.number as usize - 1) / 2).min(MAX_SIMULTANEOUS_REQUESTS)
+    }
+
+    /// Counts a delivery, returns true if it reaches the next level.
+    fn deliver(&mut self) -> bool {
+        self.delivered += 1;
+        if self.delivered < DELIVERIES_PER_LEVEL {
+            return false;
+        }
+        self.number += 1;
+        self.delivered = 0;
+        true
+    }
+}
+
+/// Event sent when a new level starts.
+pub struct LevelEvent {
+    /// Number of the new level.
+    pub number: u32,
+}
+
+/// Component on Baobei, upset when waiting too long for the front request.
+pub struct Patience(Timer);
+
+impl Patience {
+    /// Returns the seconds left before giving up on the front request.
+    pub fn seconds_left(&self) -> f32 {
+        self.0.duration().as_secs_f32() - self.0.elapsed_secs()
+    }
+}
+
+impl Progress for Patience {
+    fn remaining(&self) -> f32 {
+        self.0.remaining()
+    }
+}
+
+/// Banner announcing the new levels.
+struct LevelBanner {
+    /// Timer until the banner disappears.
+    timer: Timer,
+}
+
+/// Gives Baobei a patience, shown by a bar under the asked items.
+fn add_patience_system(
+    mut commands: Commands,
+    difficulty: Res<Difficulty>,
+    level: Res<Level>,
+    widget_materials: Res<WidgetMaterials>,
+    baobei: Query<Entity, (With<Baobei>, Without<Patience>)>,
+) {
+    for entity in baobei.iter() {
+        let patience_bar = spawn_timer_bar(
+            &mut commands,
+            &widget_materials,
+            Vec3::new(0.0, 350.0, 0.0),
+            Vec2::new(300.0, 30.0),
+        );
+        commands
+            .entity(patience_bar)
+            .insert(EntityTimer::<Patience>::new(entity));
+        commands
+            .entity(entity)
+            .insert(Patience(Timer::from_seconds(
+                level.patience(&difficulty.profile()),
+                true,
+            )))
+            .push_children(&[patience_bar]);
+    }
+}
+
+/// Counts the deliveries and starts the next level.
+fn level_progress_system(
+    mut level: ResMut<Level>,
+    mut delivery_events: EventReader<DeliveryEvent>,
+    mut level_events: EventWriter<LevelEvent>,
+) {
+    for _ in delivery_events.iter() {
+        if level.deliver() {
+            info!("Level {} started", level.number);
+            level_events.send(LevelEvent {
+                number: level.number,
+            });
+        }
+    }
+}
+
+/// Makes Baobei give up on the front request when the patience runs out,
+/// which hurts the happiness a lot, except when napping or in kid mode. The
+/// patience restarts with each request, shortens with the levels and adapts
+/// to the recent performance of the player. Expired requests and perfect
+/// deliveries, served quickly, freeze the game a moment. A request expiring
+/// soon is cued.
+#[allow(clippy::too_many_arguments)]
+fn patience_system(
+    assists: Res<Assists>,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    difficulty: Res<Difficulty>,
+    level: Res<Level>,
+    phases: Res<PhaseController>,
+    registry: Res<ItemRegistry>,
+    mut rng: ResMut<GameRng>,
+    mut adaptive: ResMut<AdaptiveDifficulty>,
+    mut delivery_events: EventReader<DeliveryEvent>,
+    mut level_events: EventReader<LevelEvent>,
+    mut hit_stop_events: EventWriter<HitStopEvent>,
+    mut cue_events: EventWriter<CueEvent>,
+    mut baobei: Query<
+        (
+            Entity,
+            &mut Patience,
+            &mut Happiness,
+            &mut ItemRequestQueue,
+            Option<&mut RequestQueue>,
+        ),
+        With<Baobei>,
+    >,
+) {
+    let served: Vec<Entity> = delivery_events.iter().map(|event| event.asker).collect();
+    let new_level = level_events.iter().count() > 0;
+    let adapted_patience = Duration::from_secs_f32(
+        level.patience(&difficulty.profile()) * adaptive.patience_multiplier(),
+    );
+
+    for (entity, mut patience, mut happiness, mut requests, mut queue) in baobei.iter_mut() {
+        if new_level {
+            patience.0.set_duration(adapted_patience);
+        }
+        if served.contains(&entity) {
+            if patience.remaining() > PERFECT_DELIVERY_PATIENCE {
+                hit_stop_events.send(HitStopEvent);
+            }
+            patience.0.set_duration(adapted_patience);
+            patience.0.reset();
+        }
+        if phases.is_breather() || assists.no_fail {
+            continue;
+        }
+        let remaining_before = patience.remaining();
+        patience.0.tick(time_scale.scale(time.delta()));
+        if remaining_before >= EXPIRING_PATIENCE && patience.remaining() < EXPIRING_PATIENCE {
+            cue_events.send(CueEvent::RequestExpiring(entity));
+        }
+        if patience.0.just_finished() {
+            info!("Baobei gave up on {:?}", requests.front());
+            hit_stop_events.send(HitStopEvent);
+            happiness.sub(EXPIRED_REQUEST_PENALTY);
+            adaptive.record(false);
+            requests.advance(&registry, &mut rng.rng, queue.as_deref_mut());
+        }
+    }
+}
+
+/// Makes Baobei ask for the following items until the number of
+/// simultaneous requests of the level is reached, adapted to the recent
+/// performance of the player, one more while a visitor sits on the couch,
+/// only one at a time during the night or as many as the assists allow.
+fn extra_requests_system(
+    assists: Res<Assists>,
+    level: Res<Level>,
+    adaptive: Res<AdaptiveDifficulty>,
+    clock: Res<GameClock>,
+    visit: Res<Visit>,
+    registry: Res<ItemRegistry>,
+    mut rng: ResMut<GameRng>,
+    mut baobei: Query<(&mut ItemRequestQueue, Option<&mut RequestQueue>), With<Baobei>>,
+) {
+    let wanted = if clock.is_night() {
+        1
+    } else {
+        (adaptive.simultaneous_requests(level.simultaneous_requests()) + visit.extra_requests())
+            .min(MAX_SIMULTANEOUS_REQUESTS)
+            .min(assists.max_requests)
+    };
+
+    for (mut requests, mut queue) in baobei.iter_mut() {
+        while requests.0.len() < wanted {
+            let last = match requests.0.back() {
+                Some(last) => *last,
+                None => break,
+            };
+            let next_item = match &mut queue {
+                Some(queue) => queue.next(&registry, &mut rng.rng, last),
+                None => registry.random_different(&mut rng.rng, last),
+            };
+            requests.0.push_back(next_item);
+        }
+    }
+}
+
+/// Spawns the hidden banner announcing levels.
+fn spawn_level_banner(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn()
+        .insert(LevelBanner {
+            timer: Timer::from_seconds(2.0, false),
+        })
+        .insert_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(170.0),
+                    left: Val::Px(560.0),
+                    ..Rect::default()
+                },
+                ..Style::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("FiraSans-Bold.ttf"),
+                    font_size: 60.0,
+                    color: Color::GOLD,
+                },
+                TextAlignment::default(),
+            ),
+            ..TextBundle::default()
+        });
+}
+
+/// Shows the banner when a level starts and hides it after a while.
+fn level_banner_system(
+    time: Res<Time>,
+    mut level_events: EventReader<LevelEvent>,
+    mut banners: Query<(&mut Text, &mut LevelBanner)>,
+) {
+    for (mut text, mut banner) in banners.iter_mut() {
+        for event in level_events.iter() {
+            text.sections[0].value = format!("Level {}", event.number);
+            banner.timer.reset();
+        }
+
+        if banner.timer.tick(time.delta()).just_finished() {
+            text.sections[0].value.clear();
+        }
+    }
+}
+
+/// Goes back to the first level when a new game starts, or to the last level
+/// reached with a quick start.
+fn reset_level_system(
+    difficulty: Res<Difficulty>,
+    mut level: ResMut<Level>,
+    mut preferences: ResMut<StartPreferences>,
+    mut baobei: Query<(&mut ItemRequestQueue, &mut Patience)>,
+) {
+    *level = if preferences.quick_start {
+        Level::starting_at(preferences.level)
+    } else {
+        Level::default()
+    };
+    preferences.quick_start = false;
+
+    for (mut requests, mut patience) in baobei.iter_mut() {
+        requests.0.truncate(1);
+        patience.0.set_duration(Duration::from_secs_f32(
+            level.patience(&difficulty.profile()),
+        ));
+        patience.0.reset();
+    }
+}
+
+/// Remembers the level reached for the next quick start.
+fn remember_level_system(
+    level: Res<Level>,
+    profile: Res<Profile>,
+    mut preferences: ResMut<StartPreferences>,
+) {
+    if preferences.level != level.number {
+        preferences.level = level.number;
+        preferences.store(&profile);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_get_harder() {
+        let profile = Difficulty::Normal.profile();
+        let mut level = Level::default();
+        let first_decay = level.decay_multiplier();
+        let first_patience = level.patience(&profile);
+
+        for _ in 1..DELIVERIES_PER_LEVEL {
+            assert!(!level.deliver());
+        }
+        assert!(level.deliver());
+        assert_eq!(level.number, 2);
+        assert!(level.decay_multiplier() > first_decay);
+        assert!(level.patience(&profile) < first_patience);
+
+        for _ in 0..DELIVERIES_PER_LEVEL * 20 {
+            level.deliver();
+        }
+        assert!((level.patience(&profile) - profile.min_patience).abs() < f32::EPSILON);
+        assert_eq!(level.simultaneous_requests(), MAX_SIMULTANEOUS_REQUESTS);
+    }
+}
@@ -0,0 +1,181 @@
+//! Pickup assist: dropped items are pulled toward an empty-handed Didi and
+//! picked up when they touch Didi.
+//!
+//! Didi carries the magnet when the assist is enabled in the settings or
+//! once bought in the shop, with a bigger radius. Without it, the items are
+//! picked up by hand.
+
+use bevy::prelude::*;
+
+use crate::{
+    collisions::{Contact, Position},
+    constants::{GameState, MAGNET_RADIUS, MAGNET_SPEED, UPGRADE_MAGNET_RADIUS},
+    settings::Settings,
+    time_scale::TimeScale,
+};
+
+use super::{
+    entities::GameData,
+    items::{ActionEvent, CarriedItem, Inventory, Item},
+    shop::{Upgrade, UpgradeRegistry},
+};
+
+/// Plugin managing the pickup magnetism.
+pub struct MagnetismPlugin;
+
+impl Plugin for MagnetismPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::InGame)
+                .with_system(unmagnetize_dropped_items_system.system())
+                .with_system(equip_magnet_system.system().label("equip_magnet"))
+                .with_system(
+                    magnetism_system
+                        .system()
+                        .after("equip_magnet")
+                        .before("item_actions"),
+                ),
+        );
+    }
+}
+
+/// Component on Didi pulling the dropped items around and picking them up
+/// when empty-handed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PickupMagnet {
+    /// Distance under which the dropped items are pulled
+    pub radius: f32,
+}
+
+impl PickupMagnet {
+    /// Returns the magnet of the settings or of the shop, the biggest one if
+    /// both, or none.
+    pub fn equipped(assist: bool, upgrade: bool) -> Option<Self> {
+        if upgrade {
+            Some(Self {
+                radius: UPGRADE_MAGNET_RADIUS,
+            })
+        } else if assist {
+            Some(Self {
+                radius: MAGNET_RADIUS,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// Component on a dropped item that Didi did not walk away from yet, to not
+/// pick it up again right after dropping it.
+struct Unmagnetized;
+
+/// Query filter for items lying on the ground
+type DroppedItem = (With<Item>, Without<CarriedItem>);
+
+/// Marks the items that have just been dropped.
+fn unmagnetize_dropped_items_system(
+    mut commands: Commands,
+    dropped_items: Query<Entity, (DroppedItem, Added<Position>)>,
+) {
+    for item in dropped_items.iter() {
+        commands.entity(item).insert(Unmagnetized);
+    }
+}
+
+/// Gives Didi the magnet of the settings or of the shop, or takes it back.
+fn equip_magnet_system(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    upgrades: Res<UpgradeRegistry>,
+    game_data: Res<GameData>,
+    magnets: Query<Option<&PickupMagnet>>,
+) {
+    let didi = game_data.didi_entity;
+    let current = match magnets.get(didi) {
+        Ok(magnet) => magnet.copied(),
+        Err(_) => return,
+    };
+    let equipped = PickupMagnet::equipped(settings.pickup_magnet, upgrades.has(Upgrade::Magnet));
+    if current == equipped {
+        return;
+    }
+    match equipped {
+        Some(magnet) => commands.entity(didi).insert(magnet),
+        None => commands.entity(didi).remove::<PickupMagnet>(),
+    };
+}
+
+/// Pulls the dropped items close to Didi and picks up the ones touching Didi,
+/// if Didi carries the magnet.
+#[allow(clippy::too_many_arguments)]
+fn magnetism_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    game_data: Res<GameData>,
+    mut action_events: EventWriter<ActionEvent>,
+    inventories: Query<&Inventory>,
+    contacts: Query<&Contact>,
+    magnets: Query<(&Position, &PickupMagnet), Without<Item>>,
+    mut dropped_items: Query<(Entity, &Item, &mut Position, Option<&Unmagnetized>), DroppedItem>,
+) {
+    let didi = game_data.didi_entity;
+    let (didi_position, radius) = match magnets.get(didi) {
+        Ok((position, magnet)) => (position.0, magnet.radius),
+        Err(_) => return,
+    };
+    let empty_handed = inventories
+        .get(didi)
+        .map_or(true, |inventory| inventory.active_item().is_none());
+
+    for (item_entity, item, mut item_position, unmagnetized) in dropped_items.iter_mut() {
+        let to_didi = (didi_position - item_position.0) * Vec3::new(1.0, 1.0, 0.0);
+        let distance = to_didi.length();
+
+        if unmagnetized.is_some() {
+            if distance > radius {
+                commands.entity(item_entity).remove::<Unmagnetized>();
+            }
+            continue;
+        }
+        if !empty_handed || distance > radius {
+            continue;
+        }
+
+        if contacts
+            .iter()
+            .any(|contact| *contact == Contact(didi, item_entity))
+        {
+            action_events.send(ActionEvent::PickUp(didi, item_entity, *item));
+            return; // Didi picks up only one item
+        }
+
+        if distance > f32::EPSILON {
+            let seconds = time_scale.scale(time.delta()).as_secs_f32();
+            let step = (MAGNET_SPEED * seconds).min(distance);
+            item_position.0 += to_didi / distance * step;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn magnet_is_equipped_by_the_assist_or_the_upgrade() {
+        assert_eq!(PickupMagnet::equipped(false, false), None);
+        assert_eq!(
+            PickupMagnet::equipped(true, false),
+            Some(PickupMagnet {
+                radius: MAGNET_RADIUS
+            })
+        );
+        assert_eq!(
+            PickupMagnet::equipped(true, true),
+            Some(PickupMagnet {
+                radius: UPGRADE_MAGNET_RADIUS
+            })
+        );
+    }
+}
@@ -2,7 +2,7 @@
 
 use bevy::prelude::*;
 
-use super::items::Item;
+use super::{items::Item, registry::ItemSprites};
 
 /// Sprites and colors in the gameplay phase.
 pub struct GameplayMaterials {
@@ -12,14 +12,16 @@ pub struct GameplayMaterials {
     pub background_sprite: Handle<ColorMaterial>,
     /// Sprite of didi
     pub didi_sprite: Handle<ColorMaterial>,
+    /// Sprite of the helper in local co-op, a tinted didi
+    pub helper_sprite: Handle<ColorMaterial>,
     /// Sprite of baobei
     pub baobei_sprite: Handle<ColorMaterial>,
-    /// Sprite for the ice cream item
-    pub ice_cream_sprite: Handle<ColorMaterial>,
-    /// Sprite for the water glass item
-    pub water_glass_sprite: Handle<ColorMaterial>,
-    /// Sprite for the chips item
-    pub chips_sprite: Handle<ColorMaterial>,
+    /// Sprite of the in-laws, a tinted baobei
+    pub in_law_sprite: Handle<ColorMaterial>,
+    /// Sprite of the visitors, a tinted baobei
+    pub visitor_sprite: Handle<ColorMaterial>,
+    /// Sprites of the items of the registry
+    pub item_sprites: ItemSprites,
     /// Sprite for the fridge
     pub fridge_sprite: Handle<ColorMaterial>,
     /// Sprite for the couch
@@ -50,14 +52,56 @@ impl FromWorld for GameplayMaterials {
             texture_atlases.add(atlas)
         };
 
+        let in_law_sprite = {
+            let texture = world
+                .get_resource::<AssetServer>()
+                .unwrap()
+                .load("baobei.png");
+
+            let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+            materials.add(ColorMaterial::modulated_texture(
+                texture,
+                Color::rgb(0.7, 0.75, 0.9),
+            ))
+        };
+
+        let visitor_sprite = {
+            let texture = world
+                .get_resource::<AssetServer>()
+                .unwrap()
+                .load("baobei.png");
+
+            let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+            materials.add(ColorMaterial::modulated_texture(
+                texture,
+                Color::rgb(0.75, 0.9, 0.7),
+            ))
+        };
+
+        let helper_sprite = {
+            let texture = world
+                .get_resource::<AssetServer>()
+                .unwrap()
+                .load("didi.png");
+
+            let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+            materials.add(ColorMaterial::modulated_texture(
+                texture,
+                Color::rgb(0.9, 0.8, 0.6),
+            ))
+        };
+
+        let item_sprites = ItemSprites::load(world, Color::WHITE);
+
         Self {
             none,
+            in_law_sprite,
+            visitor_sprite,
+            helper_sprite,
+            item_sprites,
             didi_sprite: load_sprite(world, "didi.png"),
             background_sprite: load_sprite(world, "background.png"),
             baobei_sprite: load_sprite(world, "baobei.png"),
-            ice_cream_sprite: load_sprite(world, "items/ice_cream.png"),
-            water_glass_sprite: load_sprite(world, "items/water_glass.png"),
-            chips_sprite: load_sprite(world, "items/chips.png"),
             fridge_sprite: load_sprite(world, "furniture/fridge.png"),
             couch_sprite: load_sprite(world, "furniture/couch.png"),
             kitchen_sprite: load_sprite(world, "furniture/kitchen.png"),
@@ -80,10 +124,6 @@ fn load_sprite(world: &mut World, file_name: &str) -> Handle<ColorMaterial> {
 impl GameplayMaterials {
     /// Returns the sprite handle for the given item
     pub fn item_sprite_for(&self, item: Item) -> Handle<ColorMaterial> {
-        match item {
-            Item::IceCream => self.ice_cream_sprite.clone(),
-            Item::WaterGlass => self.water_glass_sprite.clone(),
-            Item::Chips => self.chips_sprite.clone(),
-        }
+        self.item_sprites.get(item)
     }
 }
@@ -0,0 +1,355 @@
+//! "Where did I put it?" requests: Baobei asks for an item Didi dropped a
+//! while ago somewhere in the apartment, and finding it back quickly earns a
+//! bonus. Jogging the memory shows a blurry hint of where it was dropped, at
+//! the cost of half the bonus.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    collisions::{BoxCollider, Position},
+    constants::{
+        GameState, MAX_SIMULTANEOUS_REQUESTS, MEMORY_REQUEST_INTERVAL, MEMORY_RETRIEVAL_WINDOW,
+    },
+    drawing::Overlay,
+    locale::Language,
+    rng::GameRng,
+    scheduler::{ScheduledEvent, Scheduler, SchedulerSystems},
+    settings::Settings,
+    time_scale::TimeScale,
+};
+
+use super::{
+    bubbles::SayEvent,
+    items::{ActionEvent, CarriedItem, DeliveryEvent, Item, ItemRequestQueue, ItemSystems},
+    score::Score,
+    Baobei, NewRunAppExt,
+};
+
+/// Plugin managing the requests for forgotten items.
+pub struct MemoryPlugin;
+
+impl Plugin for MemoryPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<DropHistory>()
+            .init_resource::<MemoryMaterials>()
+            .add_startup_system(schedule_memory_requests.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(record_drops_system.system().after(ItemSystems))
+                    .with_system(memory_request_system.system().after(SchedulerSystems))
+                    .with_system(retrieval_system.system().after(ItemSystems))
+                    .with_system(memory_jog_system.system())
+                    .with_system(fade_jogs_system.system()),
+            )
+            .add_new_run_system(reset_memory_system);
+    }
+}
+
+/// Scheduled task asking for a forgotten item.
+const MEMORY_TASK: &str = "memory_request";
+/// Number of drops remembered.
+const HISTORY_LENGTH: usize = 8;
+/// Seconds after which a dropped item can be forgotten.
+const FORGET_DELAY: f32 = 15.0;
+/// Distance under which a dropped item is still where it was dropped.
+const SAME_SPOT_DISTANCE: f32 = 5.0;
+/// Seconds the memory jog stays on the screen.
+const JOG_DURATION: f32 = 3.0;
+/// Largest distance between the memory jog and the dropped item.
+const JOG_FUZZ: f32 = 60.0;
+/// Multiplier of the bonus when the memory was jogged.
+const JOG_BONUS_MULTIPLIER: f32 = 0.5;
+
+/// An item Didi dropped on the floor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct DroppedItem {
+    /// The dropped item
+    item: Item,
+    /// Where it was dropped
+    position: Vec3,
+    /// Seconds of game when it was dropped
+    time: f32,
+}
+
+/// Last items Didi dropped, the latest first.
+#[derive(Default)]
+struct DropHistory {
+    /// The remembered drops
+    drops: VecDeque<DroppedItem>,
+    /// Seconds of game elapsed
+    clock: f32,
+}
+
+impl DropHistory {
+    /// Remembers the drop, forgetting the oldest ones.
+    fn record(&mut self, item: Item, position: Vec3) {
+        self.drops.push_front(DroppedItem {
+            item,
+            position,
+            time: self.clock,
+        });
+        self.drops.truncate(HISTORY_LENGTH);
+    }
+
+    /// Removes and returns the oldest drop old enough to be forgotten whose
+    /// item is still where it was dropped.
+    fn forget(&mut self, still_there: impl Fn(&DroppedItem) -> bool) -> Option<DroppedItem> {
+        let clock = self.clock;
+        let index = self
+            .drops
+            .iter()
+            .rposition(|dropped| clock - dropped.time >= FORGET_DELAY && still_there(dropped))?;
+        self.drops.remove(index)
+    }
+}
+
+/// Component on a Baobei asking for a forgotten item.
+struct MemoryRequest {
+    /// The forgotten item
+    item: Item,
+    /// Where Didi dropped it
+    position: Vec3,
+    /// Seconds since it was asked
+    elapsed: f32,
+    /// True if Didi jogged the memory
+    jogged: bool,
+}
+
+impl MemoryRequest {
+    /// Returns the fraction of the bonus earned, lower the longer it took.
+    fn bonus_fraction(&self) -> f32 {
+        let fraction = (1.0 - self.elapsed / MEMORY_RETRIEVAL_WINDOW).max(0.0);
+        if self.jogged {
+            fraction * JOG_BONUS_MULTIPLIER
+        } else {
+            fraction
+        }
+    }
+}
+
+/// Component on a memory jog, vanishing when the timer finishes.
+struct MemoryJog(Timer);
+
+/// Color of the memory jogs.
+struct MemoryMaterials {
+    /// Layers of haze blurring the hint
+    haze: Handle<ColorMaterial>,
+}
+
+impl FromWorld for MemoryMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            haze: materials.add(Color::rgba(1.0, 1.0, 0.9, 0.2).into()),
+        }
+    }
+}
+
+/// Returns what Baobei says when asking for a forgotten item.
+const fn memory_line(language: Language) -> &'static str {
+    match language {
+        Language::English => "Where did you put it?",
+        Language::French => "Tu l'as mis où ?",
+    }
+}
+
+/// Schedules the requests for forgotten items.
+fn schedule_memory_requests(mut scheduler: ResMut<Scheduler>) {
+    scheduler.every(MEMORY_TASK, MEMORY_REQUEST_INTERVAL);
+}
+
+/// Remembers the items Didi drops on the floor.
+fn record_drops_system(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut history: ResMut<DropHistory>,
+    mut action_events: EventReader<ActionEvent>,
+) {
+    history.clock += time_scale.scale(time.delta()).as_secs_f32();
+
+    for action in action_events.iter() {
+        if let ActionEvent::Drop(item, position) = action {
+            history.record(*item, *position);
+        }
+    }
+}
+
+/// Makes a Baobei with room for a request ask for an item Didi dropped a
+/// while ago and did not pick up since.
+#[allow(clippy::type_complexity)]
+fn memory_request_system(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut history: ResMut<DropHistory>,
+    mut scheduled_events: EventReader<ScheduledEvent>,
+    mut say_events: EventWriter<SayEvent>,
+    mut baobei: Query<(Entity, &mut ItemRequestQueue), (With<Baobei>, Without<MemoryRequest>)>,
+    ground_items: Query<(&Item, &Position), (Without<CarriedItem>, Without<BoxCollider>)>,
+) {
+    let due = scheduled_events
+        .iter()
+        .any(|ScheduledEvent(task)| *task == MEMORY_TASK);
+
+    if !due {
+        return;
+    }
+    let (asker, mut requests) = match baobei
+        .iter_mut()
+        .find(|(_, requests)| requests.0.len() < MAX_SIMULTANEOUS_REQUESTS)
+    {
+        Some(asker) => asker,
+        None => return,
+    };
+    let forgotten = history.forget(|dropped| {
+        ground_items.iter().any(|(item, position)| {
+            *item == dropped.item && position.0.distance(dropped.position) < SAME_SPOT_DISTANCE
+        })
+    });
+
+    if let Some(dropped) = forgotten {
+        info!(
+            "Baobei asks for the {:?} dropped at {}",
+            dropped.item, dropped.position
+        );
+        requests.0.push_front(dropped.item);
+        commands.entity(asker).insert(MemoryRequest {
+            item: dropped.item,
+            position: dropped.position,
+            elapsed: 0.0,
+            jogged: false,
+        });
+        say_events.send(SayEvent {
+            speaker: asker,
+            line: memory_line(settings.language),
+        });
+    }
+}
+
+/// Rewards the forgotten items found back, more when found quickly.
+fn retrieval_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut score: ResMut<Score>,
+    mut delivery_events: EventReader<DeliveryEvent>,
+    mut requests: Query<&mut MemoryRequest>,
+) {
+    let seconds = time_scale.scale(time.delta()).as_secs_f32();
+    for mut request in requests.iter_mut() {
+        request.elapsed += seconds;
+    }
+
+    for delivery in delivery_events.iter() {
+        let request = match requests.get_mut(delivery.asker) {
+            Ok(request) if request.item == delivery.item => request,
+            _ => continue,
+        };
+        info!(
+            "Found the {:?} back in {:.1}s",
+            request.item, request.elapsed
+        );
+        score.reward_retrieval(request.bonus_fraction());
+        commands.entity(delivery.asker).remove::<MemoryRequest>();
+    }
+}
+
+/// Shows a blurry hint of where the forgotten items were dropped when the
+/// player presses `J`, halving the bonus.
+fn memory_jog_system(
+    mut commands: Commands,
+    keyboard: Res<Input<KeyCode>>,
+    materials: Res<MemoryMaterials>,
+    mut rng: ResMut<GameRng>,
+    mut requests: Query<&mut MemoryRequest>,
+) {
+    if !keyboard.just_pressed(KeyCode::J) {
+        return;
+    }
+    for mut request in requests.iter_mut() {
+        request.jogged = true;
+
+        let fuzz = Vec3::new(
+            rng.rng.gen_range(-JOG_FUZZ..JOG_FUZZ),
+            rng.rng.gen_range(-JOG_FUZZ..JOG_FUZZ),
+            0.0,
+        );
+        commands
+            .spawn()
+            .insert(MemoryJog(Timer::from_seconds(JOG_DURATION, false)))
+            .insert(Overlay)
+            .insert(Position(request.position + fuzz))
+            .insert_bundle(SpriteBundle {
+                material: materials.haze.clone(),
+                sprite: Sprite::new(Vec2::splat(220.0)),
+                ..SpriteBundle::default()
+            })
+            .with_children(|parent| {
+                // Smaller layers make the hint denser toward its center
+                for layer in 1..4 {
+                    parent.spawn().insert_bundle(SpriteBundle {
+                        material: materials.haze.clone(),
+                        sprite: Sprite::new(Vec2::splat(220.0 - 50.0 * layer as f32)),
+                        transform: Transform::from_xyz(0.0, 0.0, 0.1 * layer as f32),
+                        ..SpriteBundle::default()
+                    });
+                }
+            });
+    }
+}
+
+/// Removes the memory jogs once their time is up.
+fn fade_jogs_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut jogs: Query<(Entity, &mut MemoryJog)>,
+) {
+    for (entity, mut jog) in jogs.iter_mut() {
+        if jog.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Forgets the drops and the requests of the previous game.
+fn reset_memory_system(
+    mut commands: Commands,
+    mut history: ResMut<DropHistory>,
+    requests: Query<Entity, With<MemoryRequest>>,
+    jogs: Query<Entity, With<MemoryJog>>,
+) {
+    *history = DropHistory::default();
+    for asker in requests.iter() {
+        commands.entity(asker).remove::<MemoryRequest>();
+    }
+    for jog in jogs.iter() {
+        commands.entity(jog).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_oldest_item_still_there_is_forgotten() {
+        let mut history = DropHistory::default();
+        history.record(Item::Chips, Vec3::new(100.0, 100.0, 0.0));
+        history.record(Item::IceCream, Vec3::new(200.0, 100.0, 0.0));
+        history.clock = FORGET_DELAY / 2.0;
+        history.record(Item::WaterGlass, Vec3::new(300.0, 100.0, 0.0));
+
+        // Too early to forget anything
+        assert_eq!(history.forget(|_| true), None);
+
+        history.clock = FORGET_DELAY;
+        let picked_up = Item::Chips;
+        let forgotten = history.forget(|dropped| dropped.item != picked_up);
+        assert_eq!(forgotten.map(|dropped| dropped.item), Some(Item::IceCream));
+        // The glass was dropped too recently
+        assert_eq!(history.forget(|dropped| dropped.item != picked_up), None);
+    }
+}
@@ -0,0 +1,221 @@
+//! Messes on the floor: from time to time something is spilled or crumbled
+//! on the floor, slowing down Didi walking through it. Didi takes the mop
+//! from the closet and cleans the mess with the action key.
+
+use bevy::{math::const_vec3, prelude::*};
+use rand::Rng;
+
+use crate::{
+    collisions::{
+        overlaps_colliders, BoxCollider, Contact, Movement, Position, SlowZone, TriggerArea,
+    },
+    constants::{GameState, MAX_MESSES, MESS_INTERVAL, MESS_SPEED_MULTIPLIER},
+    rng::GameRng,
+    scheduler::{ScheduledEvent, Scheduler, SchedulerSystems},
+};
+
+use super::{
+    entities::GameData,
+    interactables::{InteractAction, Interactable},
+    items::{Inventory, Item, ItemProducer, PickAndDropCooldown},
+    phases::PhaseController,
+    score::Score,
+    NewRunAppExt,
+};
+
+/// Plugin managing the messes and the mop.
+pub struct MessPlugin;
+
+impl Plugin for MessPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<MessMaterials>()
+            .add_startup_system(spawn_mop_closet.system())
+            .add_startup_system(schedule_messes.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(spawn_mess_system.system().after(SchedulerSystems))
+                    .with_system(clean_system.system().before("item_actions")),
+            )
+            .add_new_run_system(reset_messes_system);
+    }
+}
+
+/// Scheduled task making a new mess.
+const MESS_TASK: &str = "mess";
+/// Where the mop is stored, in the bottom right corner.
+const CLOSET_POSITION: Vec3 = const_vec3!([1215.0, 110.0, 0.0]);
+
+/// Kind of mess on the floor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MessKind {
+    /// A puddle of spilled water
+    Spill,
+    /// Crumbs of chips
+    Crumbs,
+}
+
+impl MessKind {
+    /// All the kinds of mess.
+    const ALL: [Self; 2] = [Self::Spill, Self::Crumbs];
+
+    /// Returns the size of the mess on the floor.
+    fn size(self) -> Vec2 {
+        match self {
+            Self::Spill => Vec2::new(70.0, 40.0),
+            Self::Crumbs => Vec2::new(50.0, 30.0),
+        }
+    }
+}
+
+/// Component on a mess on the floor.
+struct Mess(MessKind);
+
+/// Colors of the messes and of the mop closet.
+struct MessMaterials {
+    /// Spilled water
+    spill: Handle<ColorMaterial>,
+    /// Crumbs of chips
+    crumbs: Handle<ColorMaterial>,
+    /// Closet of the mop
+    closet: Handle<ColorMaterial>,
+}
+
+impl FromWorld for MessMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            spill: materials.add(Color::rgba(0.4, 0.6, 0.9, 0.7).into()),
+            crumbs: materials.add(Color::rgb(0.8, 0.65, 0.3).into()),
+            closet: materials.add(Color::rgb(0.5, 0.4, 0.35).into()),
+        }
+    }
+}
+
+impl MessMaterials {
+    /// Returns the material of the given kind of mess.
+    fn material_for(&self, kind: MessKind) -> Handle<ColorMaterial> {
+        match kind {
+            MessKind::Spill => self.spill.clone(),
+            MessKind::Crumbs => self.crumbs.clone(),
+        }
+    }
+}
+
+/// Schedules the new messes.
+fn schedule_messes(mut scheduler: ResMut<Scheduler>) {
+    scheduler.every(MESS_TASK, MESS_INTERVAL);
+}
+
+/// Spawns the closet where Didi takes the mop.
+fn spawn_mop_closet(mut commands: Commands, materials: Res<MessMaterials>) {
+    let size = Vec2::new(40.0, 60.0);
+
+    commands
+        .spawn()
+        .insert(ItemProducer::new(Item::Mop))
+        .insert(Interactable(InteractAction::Take))
+        .insert(Position(CLOSET_POSITION))
+        .insert(BoxCollider::new(size.x, size.y))
+        .insert(TriggerArea::new(size.x + 60.0, size.y + 60.0))
+        .insert_bundle(SpriteBundle {
+            material: materials.closet.clone(),
+            sprite: Sprite::new(size),
+            ..SpriteBundle::default()
+        });
+}
+
+/// Makes a mess on a free place of the floor from time to time, except when
+/// Baobei naps.
+#[allow(clippy::too_many_arguments)]
+fn spawn_mess_system(
+    mut commands: Commands,
+    phases: Res<PhaseController>,
+    materials: Res<MessMaterials>,
+    mut rng: ResMut<GameRng>,
+    mut scheduled_events: EventReader<ScheduledEvent>,
+    messes: Query<(), With<Mess>>,
+    colliders: Query<(&Position, &BoxCollider), Without<Movement>>,
+) {
+    /// Attempts to find a free place before giving up until the next mess
+    const ATTEMPTS: usize = 10;
+
+    let due = scheduled_events
+        .iter()
+        .any(|ScheduledEvent(task)| *task == MESS_TASK);
+
+    if phases.is_breather() || !due || messes.iter().count() >= MAX_MESSES {
+        return;
+    }
+
+    let kind = MessKind::ALL[rng.rng.gen_range(0..MessKind::ALL.len())];
+    let size = kind.size();
+    let free_position = (0..ATTEMPTS)
+        .map(|_| {
+            Vec3::new(
+                rng.rng.gen_range(100.0..1180.0),
+                rng.rng.gen_range(90.0..480.0),
+                0.0,
+            )
+        })
+        .find(|position| !overlaps_colliders(*position, size, colliders.iter()));
+
+    if let Some(position) = free_position {
+        info!("{:?} on the floor at {}", kind, position);
+        commands
+            .spawn()
+            .insert(Mess(kind))
+            .insert(Position(position))
+            .insert(TriggerArea::new(size.x, size.y))
+            .insert(SlowZone {
+                speed_multiplier: MESS_SPEED_MULTIPLIER,
+            })
+            .insert_bundle(SpriteBundle {
+                material: materials.material_for(kind),
+                sprite: Sprite::new(size),
+                ..SpriteBundle::default()
+            });
+    }
+}
+
+/// Cleans the mess Didi stands on when pressing `Space` with the mop in
+/// hand.
+fn clean_system(
+    mut commands: Commands,
+    keyboard: Res<Input<KeyCode>>,
+    game_data: Res<GameData>,
+    mut cooldown: ResMut<PickAndDropCooldown>,
+    mut score: ResMut<Score>,
+    contacts: Query<&Contact>,
+    inventories: Query<&Inventory>,
+    messes: Query<&Mess>,
+) {
+    if !cooldown.0.available() || !keyboard.pressed(KeyCode::Space) {
+        return;
+    }
+    let didi = game_data.didi_entity;
+    let holds_mop = inventories.get(didi).map_or(false, |inventory| {
+        inventory.active_item() == Some(Item::Mop)
+    });
+    if !holds_mop {
+        return;
+    }
+
+    let mess = contacts
+        .iter()
+        .filter(|contact| contact.0 == didi)
+        .find_map(|contact| Some((contact.1, messes.get(contact.1).ok()?)));
+    if let Some((entity, Mess(kind))) = mess {
+        info!("Clean the {:?}", kind);
+        commands.entity(entity).despawn_recursive();
+        score.reward_chore();
+        cooldown.0.start();
+    }
+}
+
+/// Cleans the floor for the new game.
+fn reset_messes_system(mut commands: Commands, messes: Query<Entity, With<Mess>>) {
+    for mess in messes.iter() {
+        commands.entity(mess).despawn_recursive();
+    }
+}
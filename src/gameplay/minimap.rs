@@ -0,0 +1,172 @@
+//! Mini-map in the bottom left corner showing where Didi, the Baobeis and the
+//! producers are in the room, toggled with the `M` key.
+
+use bevy::{math::const_vec2, prelude::*};
+
+use crate::{
+    collisions::Position,
+    constants::GameState,
+    drawing::{world_to_ui, UiObject},
+};
+
+use super::{items::ItemProducer, Baobei, Didi};
+
+/// Plugin managing the mini-map.
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Minimap>()
+            .add_startup_system(spawn_minimap.system())
+            .add_system(spawn_markers_system.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(toggle_minimap_system.system().label("toggle_minimap"))
+                    .with_system(update_markers_system.system().after("toggle_minimap")),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::InGame).with_system(hide_minimap_system.system()),
+            );
+    }
+}
+
+/// Size of the mini-map on the screen, with the proportions of the room.
+const MINIMAP_SIZE: Vec2 = const_vec2!([256.0, 144.0]);
+/// Space between the mini-map and the corner of the screen.
+const MINIMAP_MARGIN: f32 = 20.0;
+/// Key showing or hiding the mini-map.
+const TOGGLE_KEY: KeyCode = KeyCode::M;
+
+/// Whether the mini-map is shown.
+struct Minimap {
+    /// True when shown during the game
+    shown: bool,
+}
+
+impl Default for Minimap {
+    fn default() -> Self {
+        Self { shown: true }
+    }
+}
+
+/// Component on the background of the mini-map.
+struct MinimapBackground;
+
+/// Component on a marker of the mini-map, with the entity it follows.
+struct MinimapMarker(Entity);
+
+/// Spawns the hidden background of the mini-map.
+fn spawn_minimap(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    let center = Vec2::splat(MINIMAP_MARGIN) + MINIMAP_SIZE / 2.0;
+    commands
+        .spawn()
+        .insert(MinimapBackground)
+        .insert(UiObject)
+        .insert(Position(center.extend(0.0)))
+        .insert_bundle(SpriteBundle {
+            material: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.5).into()),
+            sprite: Sprite::new(MINIMAP_SIZE),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..SpriteBundle::default()
+        });
+}
+
+/// Spawns a marker on the mini-map for each new character or producer,
+/// colored by kind.
+fn spawn_markers_system(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    backgrounds: Query<Entity, With<MinimapBackground>>,
+    tracked: Query<
+        (Entity, Option<&Didi>, Option<&Baobei>),
+        Or<(Added<Didi>, Added<Baobei>, Added<ItemProducer>)>,
+    >,
+) {
+    let background = match backgrounds.iter().next() {
+        Some(background) => background,
+        None => return,
+    };
+    for (entity, didi, baobei) in tracked.iter() {
+        let (color, size) = match (didi, baobei) {
+            (Some(_), _) => (Color::CYAN, 10.0),
+            (_, Some(_)) => (Color::PINK, 10.0),
+            _ => (Color::YELLOW, 6.0),
+        };
+        let marker = commands
+            .spawn()
+            .insert(MinimapMarker(entity))
+            .insert_bundle(SpriteBundle {
+                material: materials.add(color.into()),
+                sprite: Sprite::new(Vec2::splat(size)),
+                transform: Transform::from_xyz(0.0, 0.0, 0.1),
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: true,
+                },
+                ..SpriteBundle::default()
+            })
+            .id();
+        commands.entity(background).push_children(&[marker]);
+    }
+}
+
+/// Shows or hides the mini-map when the player presses the toggle key.
+fn toggle_minimap_system(keyboard: Res<Input<KeyCode>>, mut minimap: ResMut<Minimap>) {
+    if keyboard.just_pressed(TOGGLE_KEY) {
+        minimap.shown = !minimap.shown;
+    }
+}
+
+/// Moves the markers to the positions of their entities, removing the ones
+/// of the despawned entities.
+fn update_markers_system(
+    mut commands: Commands,
+    minimap: Res<Minimap>,
+    positions: Query<&Position, Without<MinimapBackground>>,
+    mut backgrounds: Query<&mut Visible, (With<MinimapBackground>, Without<MinimapMarker>)>,
+    mut markers: Query<(Entity, &MinimapMarker, &mut Transform, &mut Visible)>,
+) {
+    for mut visible in backgrounds.iter_mut() {
+        visible.is_visible = minimap.shown;
+    }
+    for (marker, followed, mut transform, mut visible) in markers.iter_mut() {
+        let position = match positions.get(followed.0) {
+            Ok(position) => position,
+            Err(_) => {
+                commands.entity(marker).despawn_recursive();
+                continue;
+            }
+        };
+        visible.is_visible = minimap.shown;
+        transform.translation = world_to_ui(position.0, MINIMAP_SIZE).extend(0.1);
+    }
+}
+
+/// Hides the mini-map out of the game.
+fn hide_minimap_system(
+    mut visibles: Query<&mut Visible, Or<(With<MinimapBackground>, With<MinimapMarker>)>>,
+) {
+    for mut visible in visibles.iter_mut() {
+        visible.is_visible = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::constants::{WINDOW_HEIGHT, WINDOW_WIDTH};
+
+    #[test]
+    fn room_fits_the_minimap() {
+        let center = Vec3::new(WINDOW_WIDTH / 2.0, WINDOW_HEIGHT / 2.0, 0.0);
+        assert_eq!(world_to_ui(center, MINIMAP_SIZE), Vec2::ZERO);
+        assert_eq!(
+            world_to_ui(Vec3::new(WINDOW_WIDTH, 0.0, 50.0), MINIMAP_SIZE),
+            Vec2::new(MINIMAP_SIZE.x / 2.0, -MINIMAP_SIZE.y / 2.0)
+        );
+    }
+}
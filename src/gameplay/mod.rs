@@ -1,49 +1,301 @@
-//! Systems of the game phase
+//! Systems of the game phase, with the plugins of each part of the gameplay
+//! gathered by the `GameplayPlugin`.
 
 use bevy::prelude::*;
 
-use crate::{collisions::CollisionSystems, constants::GameState, controllers::ControllerSystems};
+use crate::{
+    collisions::CollisionSystems, constants::GameState, controllers::ControllerSystems,
+    rng::GameRng,
+};
+
+pub use self::{
+    achievements::AchievementsPlugin,
+    adaptive_difficulty::AdaptiveDifficultyPlugin,
+    affection::AffectionPlugin,
+    baby::BabyPlugin,
+    bonus_round::BonusRoundPlugin,
+    bubbles::BubblesPlugin,
+    clock::ClockPlugin,
+    containers::ContainersPlugin,
+    coop::CoopPlugin,
+    crafting::CraftingPlugin,
+    cues::CuesPlugin,
+    decorate::DecoratePlugin,
+    dishes::DishesPlugin,
+    energy::EnergyPlugin,
+    entities::{MissingEntityEvent, SpawnEntitiesPlugin},
+    feedback::FeedbackPlugin,
+    happiness::HappinessPlugin,
+    highlight::HighlightPlugin,
+    hud::HudPlugin,
+    in_laws::InLawsPlugin,
+    interactables::InteractablesPlugin,
+    interruptions::InterruptionPlugin,
+    items::ItemsPlugin,
+    key_prompt::KeyPromptPlugin,
+    kid_mode::KidModePlugin,
+    laundry::LaundryPlugin,
+    level_summary::LevelSummaryPlugin,
+    levels::LevelPlugin,
+    magnetism::MagnetismPlugin,
+    memory::MemoryPlugin,
+    mess::MessPlugin,
+    minimap::MinimapPlugin,
+    pet::PetPlugin,
+    phases::PhasesPlugin,
+    phone::PhonePlugin,
+    placement::PlacementPlugin,
+    plants::PlantsPlugin,
+    power_ups::PowerUpsPlugin,
+    prompt::PromptPlugin,
+    race::RacePlugin,
+    replay::ReplayPlugin,
+    requests::RequestsPlugin,
+    roaming::RoamingPlugin,
+    routine::RoutinePlugin,
+    sandbox::SandboxPlugin,
+    seasons::SeasonsPlugin,
+    share_card::ShareCardPlugin,
+    shop::ShopPlugin,
+    speedrun::SpeedrunPlugin,
+    spoilage::SpoilagePlugin,
+    stamina::StaminaPlugin,
+    stats::StatsPlugin,
+    status_effects::StatusEffectsPlugin,
+    stock::StockPlugin,
+    storage::StoragePlugin,
+    story::StoryPlugin,
+    survival::SurvivalPlugin,
+    trash::TrashPlugin,
+    tutorial::TutorialPlugin,
+    visitor::VisitorPlugin,
+};
 
 use self::{
-    entities::SpawnEntitiesPlugin, happiness::HappinessPlugin, items::ItemsPlugin,
-    materials::GameplayMaterials, movement::movement_system,
+    materials::GameplayMaterials,
+    movement::movement_system,
+    registry::ItemRegistry,
+    score::{reset_score_system, Score},
+    seasons::ActiveSeason,
 };
 
+mod achievements;
+mod adaptive_difficulty;
+mod affection;
+mod baby;
+mod bonus_round;
+mod bubbles;
+mod clock;
+mod containers;
+mod coop;
+mod crafting;
+mod cues;
+mod decorate;
+mod dishes;
+mod energy;
 mod entities;
+mod feedback;
 mod happiness;
+mod highlight;
+mod hud;
+mod in_laws;
+mod interactables;
+mod interruptions;
 mod items;
+mod key_prompt;
+mod kid_mode;
+mod laundry;
+mod level_summary;
+mod levels;
+mod magnetism;
 mod materials;
+mod memory;
+mod mess;
+mod minimap;
 mod movement;
+mod pet;
+mod phases;
+mod phone;
+mod placement;
+mod plants;
+mod power_ups;
+mod prompt;
+mod race;
+mod registry;
+mod replay;
+mod requests;
+mod roaming;
+mod routine;
+mod sandbox;
+mod score;
+mod seasons;
+mod share_card;
+mod shop;
+mod speedrun;
+mod spoilage;
+mod stamina;
+mod stats;
+mod status_effects;
+mod stock;
+mod storage;
+mod story;
+mod survival;
+mod trash;
+mod tutorial;
+mod visitor;
 
 /// Plugin the gameplay of the game
 pub struct GameplayPlugin;
 
 impl Plugin for GameplayPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.init_resource::<GameplayMaterials>()
+        app.init_resource::<ActiveSeason>()
+            .init_resource::<ItemRegistry>()
+            .init_resource::<GameplayMaterials>()
+            .init_resource::<GameRng>()
+            .init_resource::<Score>()
             .register_type::<Didi>()
             .register_type::<Furniture>()
             .register_type::<Baobei>()
             .add_plugin(SpawnEntitiesPlugin)
+            .add_new_run_system(reset_score_system)
             .add_system_set(
                 SystemSet::on_update(GameState::InGame)
                     .with_system(back_to_menu_system.system())
                     .with_system(
                         movement_system
                             .system()
+                            .label("movement")
                             .after(ControllerSystems)
                             .before(CollisionSystems),
                     ),
             )
             .add_plugin(ItemsPlugin)
-            .add_plugin(HappinessPlugin);
+            .add_plugin(MagnetismPlugin)
+            .add_plugin(PlacementPlugin)
+            .add_plugin(PromptPlugin)
+            .add_plugin(StoragePlugin)
+            .add_plugin(RequestsPlugin)
+            .add_plugin(BubblesPlugin)
+            .add_plugin(HappinessPlugin)
+            .add_plugin(PhasesPlugin)
+            .add_plugin(InLawsPlugin)
+            .add_plugin(AffectionPlugin)
+            .add_plugin(SeasonsPlugin)
+            .add_plugin(RacePlugin)
+            .add_plugin(StatusEffectsPlugin)
+            .add_plugin(ReplayPlugin)
+            .add_plugin(HudPlugin)
+            .add_plugin(EnergyPlugin)
+            .add_plugin(LaundryPlugin)
+            .add_plugin(ContainersPlugin)
+            .add_plugin(LevelPlugin)
+            .add_plugin(DecoratePlugin)
+            .add_plugin(SurvivalPlugin)
+            .add_plugin(RoutinePlugin)
+            .add_plugin(SpoilagePlugin)
+            .add_plugin(TrashPlugin)
+            .add_plugin(StockPlugin)
+            .add_plugin(CraftingPlugin)
+            .add_plugin(PetPlugin)
+            .add_plugin(DishesPlugin)
+            .add_plugin(ClockPlugin)
+            .add_plugin(InterruptionPlugin)
+            .add_plugin(PowerUpsPlugin)
+            .add_plugin(BonusRoundPlugin)
+            .add_plugin(StaminaPlugin)
+            .add_plugin(RoamingPlugin)
+            .add_plugin(MemoryPlugin)
+            .add_plugin(TutorialPlugin)
+            .add_plugin(SandboxPlugin)
+            .add_plugin(KidModePlugin)
+            .add_plugin(AchievementsPlugin)
+            .add_plugin(StatsPlugin)
+            .add_plugin(CuesPlugin)
+            .add_plugin(CoopPlugin)
+            .add_plugin(HighlightPlugin)
+            .add_plugin(VisitorPlugin)
+            .add_plugin(ShareCardPlugin)
+            .add_plugin(FeedbackPlugin)
+            .add_plugin(InteractablesPlugin)
+            .add_plugin(MinimapPlugin)
+            .add_plugin(PhonePlugin)
+            .add_plugin(ShopPlugin)
+            .add_plugin(LevelSummaryPlugin)
+            .add_plugin(BabyPlugin)
+            .add_plugin(MessPlugin)
+            .add_plugin(PlantsPlugin)
+            .add_plugin(AdaptiveDifficultyPlugin)
+            .add_plugin(StoryPlugin)
+            .add_plugin(KeyPromptPlugin)
+            .add_plugin(SpeedrunPlugin);
+    }
+}
+
+/// Label of the systems resetting the game for a new run.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub struct NewRunSystems;
+
+/// Registration of the systems resetting the game for a new run, so the
+/// games started from the menu and the attempts of a seed race start in the
+/// same conditions.
+trait NewRunAppExt {
+    /// Adds a system resetting a part of the game when a new run starts: a
+    /// game from the menu, or the next attempt of a seed race from its
+    /// results.
+    fn add_new_run_system<Params, S>(
+        &mut self,
+        system: impl IntoSystem<Params, S> + Copy,
+    ) -> &mut Self
+    where
+        S: System<In = (), Out = ()>;
+}
+
+impl NewRunAppExt for AppBuilder {
+    fn add_new_run_system<Params, S>(
+        &mut self,
+        system: impl IntoSystem<Params, S> + Copy,
+    ) -> &mut Self
+    where
+        S: System<In = (), Out = ()>,
+    {
+        self.add_system_set(
+            SystemSet::on_exit(GameState::Menu).with_system(system.system().label(NewRunSystems)),
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::RaceResults)
+                .with_system(system.system().label(NewRunSystems)),
+        )
     }
 }
 
-/// Goes back to the menu state when the player press `Escape`.
-fn back_to_menu_system(keyboard_input: Res<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
-    if keyboard_input.just_pressed(KeyCode::Escape) {
-        state.set(GameState::Menu).unwrap();
+/// Ends the session with its summary when the player press `Escape`, or goes
+/// back to the menu state when an entity the game cannot go on without is
+/// missing.
+fn back_to_menu_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut missing_events: EventReader<MissingEntityEvent>,
+    mut state: ResMut<State<GameState>>,
+) {
+    let mut missing = false;
+    for event in missing_events.iter() {
+        error!(
+            "{} ({:?}) is missing, back to the menu",
+            event.name, event.entity
+        );
+        missing = true;
+    }
+    if missing {
+        state.replace(GameState::Menu).unwrap();
+    } else if keyboard_input.just_pressed(KeyCode::Escape) {
+        // Also leaves the tutorial or the sandbox the game can be pushed on,
+        // which have no summary
+        let next = if state.inactives().is_empty() {
+            GameState::SessionSummary
+        } else {
+            GameState::Menu
+        };
+        state.replace(next).unwrap();
     }
 }
 
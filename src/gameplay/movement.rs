@@ -2,19 +2,58 @@
 
 use bevy::prelude::*;
 
-use crate::{collisions::Movement, constants::SPEED, controllers::DirectionEvent};
+use crate::{
+    collisions::Movement,
+    constants::{SPEED, TRAY_SPEED_MULTIPLIER},
+    controllers::DirectionEvent,
+    time_scale::TimeScale,
+};
 
-use super::Didi;
+use super::{
+    coop::Player, energy::Energy, items::Inventory, prompt::ConsumePrompt, shop::UpgradeRegistry,
+    stamina::Stamina, stats::SessionStats, status_effects::StatusEffects,
+};
 
-/// Moves Didi toward the direction sent by controllers.
+/// Moves the players toward the direction sent by their controllers, faster
+/// while sprinting as long as the stamina lasts and with the shoes of the
+/// shop, counting the walked distance.
 pub fn movement_system(
     time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    prompt: Res<ConsumePrompt>,
+    upgrades: Res<UpgradeRegistry>,
+    mut stats: ResMut<SessionStats>,
     mut direction_events: EventReader<DirectionEvent>,
-    mut query: Query<&mut Movement, With<Didi>>,
+    mut query: Query<(
+        &Player,
+        &mut Movement,
+        Option<&StatusEffects>,
+        Option<&Energy>,
+        Option<&Inventory>,
+        Option<&mut Stamina>,
+    )>,
 ) {
+    if prompt.is_open() {
+        return; // Didi stands still while choosing
+    }
     for event in direction_events.iter() {
-        for mut movement in query.iter_mut() {
-            movement.0 = event.direction * time.delta_seconds() * SPEED;
+        for (player, mut movement, status_effects, energy, inventory, stamina) in query.iter_mut() {
+            if player.0 != event.player {
+                continue;
+            }
+            let speed = status_effects.map_or(SPEED, |effects| SPEED * effects.speed_multiplier())
+                * energy.map_or(1.0, Energy::speed_multiplier)
+                * upgrades.speed_multiplier()
+                * match inventory {
+                    Some(inventory) if inventory.carries_container() => TRAY_SPEED_MULTIPLIER,
+                    _ => 1.0,
+                }
+                * match stamina {
+                    Some(mut stamina) if event.sprint => stamina.sprint(),
+                    _ => 1.0,
+                };
+            movement.0 = event.direction * time.delta_seconds() * time_scale.value() * speed;
+            stats.record_walk(movement.0.length());
         }
     }
 }
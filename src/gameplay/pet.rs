@@ -0,0 +1,184 @@
+//! Pet cat wandering around the room: it walks to random places, rests a
+//! while, blocks Didi on the way and sometimes knocks the dropped items.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    collisions::{
+        overlaps_colliders, BoxCollider, CollisionSystems, Contact, ContactEvent, Movement,
+        Position,
+    },
+    constants::{GameState, PET_KNOCK_CHANCE, PET_SPEED},
+    rng::GameRng,
+    time_scale::TimeScale,
+};
+
+use super::items::{CarriedItem, Item};
+
+/// Plugin managing the pet cat.
+pub struct PetPlugin;
+
+impl Plugin for PetPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<PetMaterials>()
+            .add_startup_system(spawn_pet.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(wander_system.system().before(CollisionSystems))
+                    .with_system(knock_items_system.system().after(CollisionSystems)),
+            );
+    }
+}
+
+/// Distance under which the pet reached its target.
+const ARRIVAL_DISTANCE: f32 = 10.0;
+/// Seconds the pet rests between two walks, at least and at most.
+const REST_DURATION: (f32, f32) = (1.0, 4.0);
+/// Seconds the pet walks before giving up on a target it cannot reach.
+const WALK_PATIENCE: f32 = 5.0;
+/// Distance an item is pushed when knocked.
+const KNOCK_DISTANCE: f32 = 70.0;
+/// Size of the free space needed by a knocked item.
+const KNOCKED_ITEM_SIZE: (f32, f32) = (40.0, 40.0);
+/// Corners of the floor where the pet walks and the items are knocked.
+const FLOOR: ((f32, f32), (f32, f32)) = ((100.0, 90.0), (1180.0, 480.0));
+
+/// Component on the pet cat, walking to a target then resting.
+struct Pet {
+    /// Where the pet walks to
+    target: Vec3,
+    /// Timer until the pet walks again, ticking once the target is reached
+    rest: Timer,
+    /// Timer until the pet gives up on its target
+    patience: Timer,
+    /// Direction of the last step, where the items are knocked
+    heading: Vec3,
+}
+
+/// Color of the pet.
+struct PetMaterials {
+    /// Ginger fur
+    fur: Handle<ColorMaterial>,
+}
+
+impl FromWorld for PetMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            fur: materials.add(Color::rgb(0.9, 0.55, 0.2).into()),
+        }
+    }
+}
+
+/// Returns a random place on the floor.
+fn random_spot<R: Rng + ?Sized>(rng: &mut R) -> Vec3 {
+    let ((min_x, min_y), (max_x, max_y)) = FLOOR;
+    Vec3::new(
+        rng.gen_range(min_x..max_x),
+        rng.gen_range(min_y..max_y),
+        0.0,
+    )
+}
+
+/// Spawns the pet resting in the middle of the room.
+fn spawn_pet(mut commands: Commands, materials: Res<PetMaterials>) {
+    let size = Vec2::new(50.0, 30.0);
+    let position = Vec3::new(640.0, 150.0, 0.0);
+
+    commands
+        .spawn()
+        .insert(Pet {
+            target: position,
+            rest: Timer::from_seconds(REST_DURATION.1, false),
+            patience: Timer::from_seconds(WALK_PATIENCE, false),
+            heading: Vec3::X,
+        })
+        .insert(Position(position))
+        .insert(BoxCollider::new(size.x, size.y))
+        .insert(Movement::default())
+        .insert_bundle(SpriteBundle {
+            material: materials.fur.clone(),
+            sprite: Sprite::new(size),
+            ..SpriteBundle::default()
+        });
+}
+
+/// Walks the pet toward its target, then rests a random time before picking
+/// another one. A target it cannot reach is given up after a while.
+fn wander_system(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut rng: ResMut<GameRng>,
+    mut pets: Query<(&mut Pet, &Position, &mut Movement)>,
+) {
+    let delta = time_scale.scale(time.delta());
+
+    for (mut pet, position, mut movement) in pets.iter_mut() {
+        let to_target = (pet.target - position.0) * Vec3::new(1.0, 1.0, 0.0);
+        let distance = to_target.length();
+        let stuck = pet.patience.tick(delta).finished();
+
+        if distance < ARRIVAL_DISTANCE || stuck {
+            if pet.rest.tick(delta).just_finished() {
+                let rest = rng.rng.gen_range(REST_DURATION.0..REST_DURATION.1);
+                pet.target = random_spot(&mut rng.rng);
+                pet.rest.set_duration(Duration::from_secs_f32(rest));
+                pet.rest.reset();
+                pet.patience.reset();
+            }
+            continue;
+        }
+
+        pet.heading = to_target / distance;
+        let step = (PET_SPEED * delta.as_secs_f32()).min(distance);
+        movement.0 = pet.heading * step;
+    }
+}
+
+/// Knocks the dropped items the pet walks into, now and then.
+fn knock_items_system(
+    mut rng: ResMut<GameRng>,
+    mut contact_events: EventReader<ContactEvent>,
+    pets: Query<&Pet>,
+    colliders: Query<(&Position, &BoxCollider), Without<Movement>>,
+    mut dropped_items: Query<
+        &mut Position,
+        (With<Item>, Without<CarriedItem>, Without<BoxCollider>),
+    >,
+) {
+    let ((min_x, min_y), (max_x, max_y)) = FLOOR;
+    let size = Vec2::new(KNOCKED_ITEM_SIZE.0, KNOCKED_ITEM_SIZE.1);
+
+    for event in contact_events.iter() {
+        let Contact(pet, item) = match event {
+            ContactEvent::Started(contact, _) => *contact,
+            ContactEvent::Stopped(_) => continue,
+        };
+        let heading = match pets.get(pet) {
+            Ok(pet) => pet.heading,
+            Err(_) => continue,
+        };
+        let mut item_position = match dropped_items.get_mut(item) {
+            Ok(item_position) => item_position,
+            Err(_) => continue,
+        };
+        if !rng.rng.gen_bool(PET_KNOCK_CHANCE) {
+            continue;
+        }
+
+        let knocked = item_position.0 + heading * KNOCK_DISTANCE;
+        let knocked = Vec3::new(
+            knocked.x.max(min_x).min(max_x),
+            knocked.y.max(min_y).min(max_y),
+            knocked.z,
+        );
+        if !overlaps_colliders(knocked, size, colliders.iter()) {
+            info!("The cat knocks an item over");
+            item_position.0 = knocked;
+        }
+    }
+}
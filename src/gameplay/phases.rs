@@ -0,0 +1,189 @@
+//! Phases of the endless mode: requests phases followed by short breathers
+//! where Baobei naps, each new phase being harder than the previous one.
+
+use bevy::prelude::*;
+
+use crate::{
+    constants::{GameState, BREATHER_DURATION, PHASE_DECAY_INCREASE, REQUESTS_PHASE_DURATION},
+    drawing::Fog,
+    settings::Settings,
+    time_scale::TimeScale,
+};
+
+use super::{clock::GameClock, NewRunAppExt};
+
+/// Plugin managing the phases of the game.
+pub struct PhasesPlugin;
+
+impl Plugin for PhasesPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<PhaseEvent>()
+            .init_resource::<PhaseController>()
+            .add_startup_system(spawn_phase_banner.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(phase_controller_system.system().label("phase_controller"))
+                    .with_system(phase_banner_system.system().after("phase_controller"))
+                    .with_system(night_fog_system.system().after("clock")),
+            )
+            .add_new_run_system(reset_phases_system);
+    }
+}
+
+/// Kind of phase of the game.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PhaseKind {
+    /// Baobei asks for items
+    Requests,
+    /// Baobei naps: no decay and producers restock
+    Breather,
+}
+
+/// Event sent when a new phase starts.
+pub struct PhaseEvent {
+    /// Number of the phase, starting at 1.
+    pub number: u32,
+    /// Kind of the phase.
+    pub kind: PhaseKind,
+}
+
+/// Controls the current phase of the game.
+pub struct PhaseController {
+    /// Number of the current phase, starting at 1.
+    pub number: u32,
+    /// Kind of the current phase.
+    pub kind: PhaseKind,
+    /// Timer until the end of the current phase.
+    timer: Timer,
+}
+
+impl Default for PhaseController {
+    fn default() -> Self {
+        Self {
+            number: 1,
+            kind: PhaseKind::Requests,
+            timer: Timer::from_seconds(REQUESTS_PHASE_DURATION, false),
+        }
+    }
+}
+
+impl PhaseController {
+    /// Returns true if Baobei is napping.
+    pub fn is_breather(&self) -> bool {
+        self.kind == PhaseKind::Breather
+    }
+
+    /// Returns the multiplier of the happiness decay, increasing every phase.
+    pub fn decay_multiplier(&self) -> f32 {
+        PHASE_DECAY_INCREASE.mul_add((self.number - 1) as f32, 1.0)
+    }
+
+    /// Starts the phase following the current one.
+    fn next_phase(&mut self) {
+        let (kind, duration) = match self.kind {
+            PhaseKind::Requests => (PhaseKind::Breather, BREATHER_DURATION),
+            PhaseKind::Breather => {
+                self.number += 1;
+                (PhaseKind::Requests, REQUESTS_PHASE_DURATION)
+            }
+        };
+        self.kind = kind;
+        self.timer = Timer::from_seconds(duration, false);
+    }
+}
+
+/// Advances the current phase and announces the next ones.
+fn phase_controller_system(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut phases: ResMut<PhaseController>,
+    mut phase_events: EventWriter<PhaseEvent>,
+) {
+    let delta = time_scale.scale(time.delta());
+    if !phases.timer.tick(delta).just_finished() {
+        return;
+    }
+    phases.next_phase();
+
+    info!("Phase {} started: {:?}", phases.number, phases.kind);
+    phase_events.send(PhaseEvent {
+        number: phases.number,
+        kind: phases.kind,
+    });
+}
+
+/// Starts the new game from the first phase.
+fn reset_phases_system(mut phases: ResMut<PhaseController>) {
+    *phases = PhaseController::default();
+}
+
+/// Covers the apartment with fog during the night when the hard mode
+/// mutator is enabled.
+fn night_fog_system(settings: Res<Settings>, clock: Res<GameClock>, mut fog: ResMut<Fog>) {
+    let enabled = settings.night_mutator && clock.is_night();
+    if fog.enabled != enabled {
+        fog.enabled = enabled;
+    }
+}
+
+/// Banner announcing the new phases.
+struct PhaseBanner {
+    /// Timer until the banner disappears.
+    timer: Timer,
+}
+
+/// Spawns the hidden banner announcing phases.
+fn spawn_phase_banner(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn()
+        .insert(PhaseBanner {
+            timer: Timer::from_seconds(3.0, false),
+        })
+        .insert_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(100.0),
+                    left: Val::Px(400.0),
+                    ..Rect::default()
+                },
+                ..Style::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("FiraSans-Bold.ttf"),
+                    font_size: 50.0,
+                    color: Color::WHITE,
+                },
+                TextAlignment::default(),
+            ),
+            ..TextBundle::default()
+        });
+}
+
+/// Shows the banner when a phase starts and hides it after a while.
+fn phase_banner_system(
+    time: Res<Time>,
+    phases: Res<PhaseController>,
+    mut phase_events: EventReader<PhaseEvent>,
+    mut banners: Query<(&mut Text, &mut PhaseBanner)>,
+) {
+    for (mut text, mut banner) in banners.iter_mut() {
+        for event in phase_events.iter() {
+            text.sections[0].value = match event.kind {
+                PhaseKind::Requests => format!(
+                    "Phase {}: Baobei is needier (x{:.1})",
+                    event.number,
+                    phases.decay_multiplier()
+                ),
+                PhaseKind::Breather => "Break time: Baobei naps".to_string(),
+            };
+            banner.timer.reset();
+        }
+
+        if banner.timer.tick(time.delta()).just_finished() {
+            text.sections[0].value.clear();
+        }
+    }
+}
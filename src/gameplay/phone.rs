@@ -0,0 +1,175 @@
+//! Phone ordering the items the producers cannot give, because none
+//! produces them or they are out of stock. After a delay, the package is
+//! delivered at the door.
+
+use bevy::{math::const_vec3, prelude::*};
+
+use crate::{
+    collisions::{Position, TriggerArea},
+    constants::{GameState, MAX_PENDING_ORDERS, ORDER_DELAY},
+    time_scale::TimeScale,
+};
+
+use super::{
+    interactables::{InteractAction, Interactable, Selection, SelectionEvent},
+    items::{spawn_item_on_ground, Item, ItemProducer},
+    materials::GameplayMaterials,
+    registry::ItemRegistry,
+    visitor::DOOR_POSITION,
+    NewRunAppExt,
+};
+
+/// Plugin managing the phone and the deliveries.
+pub struct PhonePlugin;
+
+impl Plugin for PhonePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<PendingDeliveries>()
+            .add_startup_system(spawn_phone.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(update_catalog_system.system())
+                    .with_system(order_system.system())
+                    .with_system(deliver_system.system()),
+            )
+            .add_new_run_system(reset_deliveries_system);
+    }
+}
+
+/// Where the packages are left, inside next to the door.
+const PACKAGE_OFFSET: Vec3 = const_vec3!([-60.0, 0.0, 0.0]);
+
+/// Component on the phone.
+struct Phone;
+
+/// Items ordered with the phone, with the seconds before their delivery.
+#[derive(Debug, Default)]
+pub struct PendingDeliveries(Vec<(Item, f32)>);
+
+impl PendingDeliveries {
+    /// Orders the item, returns false if too many items are on their way.
+    fn order(&mut self, item: Item) -> bool {
+        if self.0.len() >= MAX_PENDING_ORDERS {
+            return false;
+        }
+        self.0.push((item, ORDER_DELAY));
+        true
+    }
+
+    /// Advances the deliveries by `delta` seconds, returning the delivered
+    /// items.
+    fn tick(&mut self, delta: f32) -> Vec<Item> {
+        for (_, remaining) in &mut self.0 {
+            *remaining -= delta;
+        }
+        let (delivered, pending) = self
+            .0
+            .drain(..)
+            .partition::<Vec<_>, _>(|(_, remaining)| *remaining <= 0.0);
+        self.0 = pending;
+        delivered.into_iter().map(|(item, _)| item).collect()
+    }
+}
+
+/// Spawns the phone on the table.
+fn spawn_phone(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    commands
+        .spawn()
+        .insert(Phone)
+        .insert(Interactable(InteractAction::Order))
+        .insert(Selection(Vec::new()))
+        .insert(Position(Vec3::new(340.0, 200.0, 40.0)))
+        .insert(TriggerArea::new(200.0, 120.0))
+        .insert_bundle(SpriteBundle {
+            material: materials.add(Color::rgb(0.25, 0.25, 0.3).into()),
+            sprite: Sprite::new(Vec2::new(20.0, 36.0)),
+            ..SpriteBundle::default()
+        });
+}
+
+/// Offers with the phone the requested items that no producer can give.
+fn update_catalog_system(
+    registry: Res<ItemRegistry>,
+    producers: Query<&ItemProducer>,
+    mut phones: Query<&mut Selection, With<Phone>>,
+) {
+    let catalog: Vec<Item> = registry
+        .definitions()
+        .filter(|def| def.weight > 0)
+        .map(|def| def.item)
+        .filter(|item| {
+            producers
+                .iter()
+                .all(|producer| producer.item != *item || producer.is_empty())
+        })
+        .collect();
+
+    for mut selection in phones.iter_mut() {
+        if selection.0 != catalog {
+            selection.0 = catalog.clone();
+        }
+    }
+}
+
+/// Orders the item chosen with the phone.
+fn order_system(
+    mut selection_events: EventReader<SelectionEvent>,
+    mut deliveries: ResMut<PendingDeliveries>,
+    phones: Query<(), With<Phone>>,
+) {
+    for event in selection_events.iter() {
+        if phones.get(event.furniture).is_err() {
+            continue;
+        }
+        if deliveries.order(event.item) {
+            info!("Order {:?}, delivered in {}s", event.item, ORDER_DELAY);
+        } else {
+            info!("Too many orders on their way");
+        }
+    }
+}
+
+/// Leaves the packages of the delivered items next to the door.
+fn deliver_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    materials: Res<GameplayMaterials>,
+    mut deliveries: ResMut<PendingDeliveries>,
+) {
+    let delta = time_scale.scale(time.delta()).as_secs_f32();
+    for item in deliveries.tick(delta) {
+        info!("The package of {:?} is delivered", item);
+        spawn_item_on_ground(
+            &mut commands,
+            &materials,
+            item,
+            DOOR_POSITION + PACKAGE_OFFSET,
+        );
+    }
+}
+
+/// Cancels the orders of the previous game.
+fn reset_deliveries_system(mut deliveries: ResMut<PendingDeliveries>) {
+    *deliveries = PendingDeliveries::default();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_are_delivered_after_the_delay() {
+        let mut deliveries = PendingDeliveries::default();
+        assert!(deliveries.order(Item::HotTea));
+        assert!(deliveries.order(Item::Chips));
+        assert!(!deliveries.order(Item::IceCream));
+
+        assert!(deliveries.tick(ORDER_DELAY / 2.0).is_empty());
+        assert_eq!(
+            deliveries.tick(ORDER_DELAY / 2.0),
+            vec![Item::HotTea, Item::Chips]
+        );
+        assert!(deliveries.order(Item::IceCream));
+    }
+}
@@ -0,0 +1,238 @@
+//! Precise placement of the dropped items: a translucent ghost of the carried
+//! item shows where it would land, its base turning red inside furniture.
+//! Holding the action key chooses where to drop the item and releasing it
+//! drops the item there.
+
+use bevy::prelude::*;
+
+use crate::{
+    collisions::{overlaps_colliders, BoxCollider, Movement, Position},
+    constants::GameState,
+};
+
+use super::{
+    entities::GameData,
+    items::{ActionEvent, Inventory, Item, PICKED_ITEM_TRANSLATION},
+    materials::GameplayMaterials,
+};
+
+/// Plugin managing the placement of the dropped items.
+pub struct PlacementPlugin;
+
+impl Plugin for PlacementPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<DropPlacement>()
+            .init_resource::<PlacementMaterials>()
+            .add_startup_system(spawn_drop_ghost.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(drop_placement_system.system().before("item_actions")),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::InGame).with_system(cancel_placement_system.system()),
+            );
+    }
+}
+
+/// Scale of the items on the ground.
+const GHOST_SCALE: f32 = 0.3;
+/// Opacity of the preview of the dropped item.
+const GHOST_ALPHA: f32 = 0.45;
+/// Size of the base of an item, that must not be inside a collider.
+const FOOTPRINT_SIZE: (f32, f32) = (40.0, 30.0);
+/// Bottom left corner of the floor where items can be dropped.
+const FLOOR_MIN: (f32, f32) = (70.0, 70.0);
+/// Top right corner of the floor where items can be dropped.
+const FLOOR_MAX: (f32, f32) = (1210.0, 515.0);
+
+/// State of the placement of the item carried by Didi.
+#[derive(Default)]
+pub struct DropPlacement {
+    /// Whether the player is choosing where to drop the item.
+    active: bool,
+    /// Item shown by the preview.
+    ghost_item: Option<Item>,
+}
+
+impl DropPlacement {
+    /// Starts choosing where to drop the carried item.
+    pub fn start(&mut self) {
+        self.active = true;
+    }
+
+    /// Returns true if the player is choosing where to drop the item.
+    pub const fn is_active(&self) -> bool {
+        self.active
+    }
+}
+
+/// Colors of the ghost item and of its base.
+struct PlacementMaterials {
+    /// Translucent copy of the sprite of the carried item
+    ghost: Handle<ColorMaterial>,
+    /// The item can be dropped here
+    valid: Handle<ColorMaterial>,
+    /// The item would be inside furniture
+    invalid: Handle<ColorMaterial>,
+}
+
+impl FromWorld for PlacementMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            ghost: materials.add(Color::rgba(1.0, 1.0, 1.0, GHOST_ALPHA).into()),
+            valid: materials.add(Color::rgba(0.3, 0.9, 0.4, 0.5).into()),
+            invalid: materials.add(Color::rgba(0.9, 0.2, 0.2, 0.5).into()),
+        }
+    }
+}
+
+/// Component on the preview of the dropped item.
+struct DropGhost;
+/// Component on the base of the preview, showing if the position is valid.
+struct GhostFootprint;
+
+/// Spawns the hidden preview of the dropped item.
+fn spawn_drop_ghost(mut commands: Commands, materials: Res<PlacementMaterials>) {
+    let hidden = Visible {
+        is_visible: false,
+        is_transparent: true,
+    };
+
+    commands
+        .spawn()
+        .insert(DropGhost)
+        .insert(Position::default())
+        .insert_bundle(SpriteBundle {
+            material: materials.ghost.clone(),
+            transform: Transform::from_scale(Vec3::new(GHOST_SCALE, GHOST_SCALE, 0.0)),
+            visible: hidden.clone(),
+            ..SpriteBundle::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn()
+                .insert(GhostFootprint)
+                .insert_bundle(SpriteBundle {
+                    material: materials.valid.clone(),
+                    sprite: Sprite::new(footprint_size() / GHOST_SCALE),
+                    transform: Transform::from_xyz(0.0, 0.0, -0.1),
+                    visible: hidden,
+                    ..SpriteBundle::default()
+                });
+        });
+}
+
+/// Returns the size of the base of an item.
+fn footprint_size() -> Vec2 {
+    Vec2::new(FOOTPRINT_SIZE.0, FOOTPRINT_SIZE.1)
+}
+
+/// Returns where the item carried by Didi lands, on the floor.
+fn drop_position(didi_position: Vec3) -> Vec3 {
+    let position = didi_position + PICKED_ITEM_TRANSLATION * GHOST_SCALE;
+
+    Vec3::new(
+        position.x.max(FLOOR_MIN.0).min(FLOOR_MAX.0),
+        position.y.max(FLOOR_MIN.1).min(FLOOR_MAX.1),
+        position.z,
+    )
+}
+
+/// Query filter for the preview of the dropped item and its base
+type Preview = Or<(With<DropGhost>, With<GhostFootprint>)>;
+
+/// Moves the preview of the carried item with Didi and drops the item when
+/// the action key is released during the placement, unless the item would be
+/// inside furniture.
+#[allow(clippy::too_many_arguments)]
+fn drop_placement_system(
+    keyboard: Res<Input<KeyCode>>,
+    game_data: Res<GameData>,
+    materials: Res<GameplayMaterials>,
+    placement_materials: Res<PlacementMaterials>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+    mut placement: ResMut<DropPlacement>,
+    mut action_events: EventWriter<ActionEvent>,
+    carriers: Query<(&Inventory, &Position), Without<DropGhost>>,
+    colliders: Query<(&Position, &BoxCollider), (Without<Movement>, Without<DropGhost>)>,
+    mut ghosts: Query<&mut Position, With<DropGhost>>,
+    mut footprints: Query<&mut Handle<ColorMaterial>, With<GhostFootprint>>,
+    mut previews: Query<&mut Visible, Preview>,
+) {
+    let carried = carriers
+        .get(game_data.didi_entity)
+        .ok()
+        .and_then(|(inventory, didi_position)| Some((inventory.active_item()?, didi_position)));
+    let released = !keyboard.pressed(KeyCode::Space);
+
+    let (item, position) = match carried {
+        Some((item, didi_position)) => (item, drop_position(didi_position.0)),
+        None => {
+            // The item left the hands of Didi, during the placement or not
+            placement.active = false;
+            if placement.ghost_item.take().is_some() {
+                for mut visible in previews.iter_mut() {
+                    visible.is_visible = false;
+                }
+            }
+            return;
+        }
+    };
+    let valid = !overlaps_colliders(position, footprint_size(), colliders.iter());
+
+    if placement.ghost_item != Some(item) {
+        placement.ghost_item = Some(item);
+        let sprite = color_materials
+            .get(materials.item_sprite_for(item))
+            .cloned();
+        if let (Some(sprite), Some(ghost)) =
+            (sprite, color_materials.get_mut(&placement_materials.ghost))
+        {
+            ghost.texture = sprite.texture;
+            ghost.color = sprite.color;
+            ghost.color.set_a(GHOST_ALPHA);
+        }
+    }
+    for mut ghost_position in ghosts.iter_mut() {
+        if ghost_position.0 != position {
+            ghost_position.0 = position;
+        }
+    }
+    for mut material in footprints.iter_mut() {
+        *material = if valid {
+            placement_materials.valid.clone()
+        } else {
+            placement_materials.invalid.clone()
+        };
+    }
+    for mut visible in previews.iter_mut() {
+        if !visible.is_visible {
+            visible.is_visible = true;
+        }
+    }
+
+    if placement.active && released {
+        placement.active = false;
+
+        if valid {
+            action_events.send(ActionEvent::Drop(item, position));
+        } else {
+            info!("The item {:?} cannot be dropped inside furniture", item);
+        }
+    }
+}
+
+/// Stops the placement and hides the preview when leaving the game.
+fn cancel_placement_system(
+    mut placement: ResMut<DropPlacement>,
+    mut previews: Query<&mut Visible, Preview>,
+) {
+    placement.active = false;
+    placement.ghost_item = None;
+
+    for mut visible in previews.iter_mut() {
+        visible.is_visible = false;
+    }
+}
@@ -0,0 +1,272 @@
+//! Houseplants: their water dries out over time, wilting them stage by stage.
+//! Didi waters a plant by pressing the action key next to it with a glass of
+//! water in hand, and a plant left to die costs points.
+
+use bevy::{math::const_vec3, prelude::*};
+
+use crate::{
+    collisions::{BoxCollider, Contact, Position, TriggerArea},
+    constants::{GameState, PLANT_DRYING_RATE},
+    scheduler::{ScheduledEvent, Scheduler, SchedulerSystems},
+};
+
+use super::{
+    entities::GameData,
+    happiness::NeedMeter,
+    interactables::{InteractAction, Interactable},
+    items::{ActionEvent, Inventory, Item, PickAndDropCooldown},
+    phases::PhaseController,
+    score::Score,
+    NewRunAppExt,
+};
+
+/// Plugin managing the houseplants.
+pub struct PlantsPlugin;
+
+impl Plugin for PlantsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<PlantMaterials>()
+            .add_startup_system(spawn_plants.system())
+            .add_startup_system(schedule_drying.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(
+                        drying_system
+                            .system()
+                            .label("drying")
+                            .after(SchedulerSystems),
+                    )
+                    .with_system(water_system.system().label("water").before("item_actions"))
+                    .with_system(plant_sprite_system.system().after("drying").after("water")),
+            )
+            .add_new_run_system(reset_plants_system);
+    }
+}
+
+/// Scheduled task drying out the plants every second.
+const DRYING_TASK: &str = "plant_drying";
+/// Where the plants stand, in the corners of the room.
+const PLANT_POSITIONS: [Vec3; 2] = [
+    const_vec3!([80.0, 110.0, 0.0]),
+    const_vec3!([800.0, 110.0, 0.0]),
+];
+
+/// Stage of a plant, shown by its sprite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PlantStage {
+    /// Green and watered
+    Healthy,
+    /// Asking for water
+    Drooping,
+    /// About to die
+    Dry,
+    /// Dead of thirst until the next game
+    Dead,
+}
+
+impl PlantStage {
+    /// Returns the stage of a plant with the given water meter.
+    fn of(water: NeedMeter) -> Self {
+        match water.value() {
+            value if value > 0.5 => Self::Healthy,
+            value if value > 0.2 => Self::Drooping,
+            _ if !water.is_empty() => Self::Dry,
+            _ => Self::Dead,
+        }
+    }
+}
+
+/// Component on a houseplant, drying out over time.
+#[derive(Debug, Default)]
+struct Plant {
+    /// Water left in the pot
+    water: NeedMeter,
+}
+
+impl Plant {
+    /// Returns the stage of the plant.
+    fn stage(&self) -> PlantStage {
+        PlantStage::of(self.water)
+    }
+
+    /// Dries out the plant over the seconds. Returns true if the plant dies.
+    fn dry(&mut self, seconds: f32) -> bool {
+        if self.water.is_empty() {
+            return false;
+        }
+        self.water.decay(PLANT_DRYING_RATE * seconds);
+        self.water.is_empty()
+    }
+
+    /// Fills the pot with water, returns false if the plant is already dead.
+    fn water(&mut self) -> bool {
+        if self.water.is_empty() {
+            return false;
+        }
+        self.water = NeedMeter::FULL;
+        true
+    }
+}
+
+/// Colors of the stages of the plants.
+struct PlantMaterials {
+    /// Green and watered
+    healthy: Handle<ColorMaterial>,
+    /// Asking for water
+    drooping: Handle<ColorMaterial>,
+    /// About to die
+    dry: Handle<ColorMaterial>,
+    /// Dead of thirst
+    dead: Handle<ColorMaterial>,
+}
+
+impl FromWorld for PlantMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            healthy: materials.add(Color::rgb(0.2, 0.65, 0.25).into()),
+            drooping: materials.add(Color::rgb(0.55, 0.65, 0.2).into()),
+            dry: materials.add(Color::rgb(0.7, 0.55, 0.25).into()),
+            dead: materials.add(Color::rgb(0.4, 0.3, 0.2).into()),
+        }
+    }
+}
+
+impl PlantMaterials {
+    /// Returns the material of the given stage of a plant.
+    fn material_for(&self, stage: PlantStage) -> Handle<ColorMaterial> {
+        match stage {
+            PlantStage::Healthy => self.healthy.clone(),
+            PlantStage::Drooping => self.drooping.clone(),
+            PlantStage::Dry => self.dry.clone(),
+            PlantStage::Dead => self.dead.clone(),
+        }
+    }
+}
+
+/// Schedules the drying out of the plants.
+fn schedule_drying(mut scheduler: ResMut<Scheduler>) {
+    scheduler.every(DRYING_TASK, 1.0);
+}
+
+/// Spawns the plants in their pots.
+fn spawn_plants(mut commands: Commands, materials: Res<PlantMaterials>) {
+    let size = Vec2::new(40.0, 60.0);
+
+    for position in PLANT_POSITIONS {
+        commands
+            .spawn()
+            .insert(Plant::default())
+            .insert(Interactable(InteractAction::Water))
+            .insert(Position(position))
+            .insert(BoxCollider::new(size.x, size.y))
+            .insert(TriggerArea::new(size.x + 60.0, size.y + 60.0))
+            .insert_bundle(SpriteBundle {
+                material: materials.healthy.clone(),
+                sprite: Sprite::new(size),
+                ..SpriteBundle::default()
+            });
+    }
+}
+
+/// Dries out the plants every second, except when Baobei naps, and removes
+/// the points of the plants dying.
+fn drying_system(
+    phases: Res<PhaseController>,
+    mut score: ResMut<Score>,
+    mut scheduled_events: EventReader<ScheduledEvent>,
+    mut plants: Query<&mut Plant>,
+) {
+    let due = scheduled_events
+        .iter()
+        .any(|ScheduledEvent(task)| *task == DRYING_TASK);
+
+    if !due || phases.is_breather() {
+        return;
+    }
+    for mut plant in plants.iter_mut() {
+        if plant.dry(1.0) {
+            info!("A plant died of thirst");
+            score.penalize_dead_plant();
+        }
+    }
+}
+
+/// Waters the plant in contact when Didi presses `Space` with a glass of
+/// water in hand, using up the glass.
+fn water_system(
+    keyboard: Res<Input<KeyCode>>,
+    game_data: Res<GameData>,
+    mut cooldown: ResMut<PickAndDropCooldown>,
+    mut action_events: EventWriter<ActionEvent>,
+    contacts: Query<&Contact>,
+    inventories: Query<&Inventory>,
+    mut plants: Query<&mut Plant>,
+) {
+    if !cooldown.0.available() || !keyboard.pressed(KeyCode::Space) {
+        return;
+    }
+    let didi = game_data.didi_entity;
+    let holds_water = inventories.get(didi).map_or(false, |inventory| {
+        inventory.active_item() == Some(Item::WaterGlass)
+    });
+    if !holds_water {
+        return;
+    }
+
+    for contact in contacts.iter().filter(|contact| contact.0 == didi) {
+        if let Ok(mut plant) = plants.get_mut(contact.1) {
+            if plant.water() {
+                info!("Water the plant");
+                action_events.send(ActionEvent::Consume(contact.1, Item::WaterGlass));
+                cooldown.0.start();
+                return;
+            }
+        }
+    }
+}
+
+/// Shows the stage of the plants.
+fn plant_sprite_system(
+    materials: Res<PlantMaterials>,
+    mut plants: Query<(&Plant, &mut Handle<ColorMaterial>), Changed<Plant>>,
+) {
+    for (plant, mut material) in plants.iter_mut() {
+        let stage_material = materials.material_for(plant.stage());
+        if *material != stage_material {
+            *material = stage_material;
+        }
+    }
+}
+
+/// Brings the plants back to life for the new game.
+fn reset_plants_system(mut plants: Query<&mut Plant>) {
+    for mut plant in plants.iter_mut() {
+        *plant = Plant::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plants_wilt_until_watered_or_dead() {
+        let mut plant = Plant::default();
+        let seconds_to_die = 1.0 / PLANT_DRYING_RATE;
+
+        assert!(!plant.dry(seconds_to_die * 0.6));
+        assert_eq!(plant.stage(), PlantStage::Drooping);
+        assert!(plant.water());
+        assert_eq!(plant.stage(), PlantStage::Healthy);
+
+        assert!(!plant.dry(seconds_to_die * 0.9));
+        assert_eq!(plant.stage(), PlantStage::Dry);
+        assert!(plant.dry(seconds_to_die * 0.2));
+        assert_eq!(plant.stage(), PlantStage::Dead);
+        // A dead plant dies only once and cannot be watered anymore
+        assert!(!plant.dry(1.0));
+        assert!(!plant.water());
+    }
+}
@@ -0,0 +1,204 @@
+//! Power-ups appearing on the floor from time to time: the speed slippers
+//! make Didi run faster and the patience candy stops Baobei getting sad,
+//! for a while.
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    collisions::{
+        overlaps_colliders, BoxCollider, CollisionSystems, ContactEvent, Movement, Position,
+        TriggerArea,
+    },
+    constants::{GameState, POWER_UP_INTERVAL, POWER_UP_LIFETIME},
+    rng::GameRng,
+    scheduler::{ScheduledEvent, Scheduler, SchedulerSystems},
+    time_scale::TimeScale,
+};
+
+use super::{
+    entities::GameData,
+    status_effects::{StatusEffectKind, StatusEffects},
+    Baobei, NewRunAppExt,
+};
+
+/// Plugin managing the power-ups.
+pub struct PowerUpsPlugin;
+
+impl Plugin for PowerUpsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<PowerUpMaterials>()
+            .add_startup_system(schedule_power_ups.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(spawn_power_ups_system.system().after(SchedulerSystems))
+                    .with_system(
+                        collect_power_ups_system
+                            .system()
+                            .after(CollisionSystems)
+                            .after("status_effects"),
+                    )
+                    .with_system(vanish_power_ups_system.system()),
+            )
+            .add_new_run_system(reset_power_ups_system);
+    }
+}
+
+/// Scheduled task dropping a new power-up.
+const POWER_UP_TASK: &str = "power_up";
+/// Size of a power-up on the floor.
+const POWER_UP_SIZE: (f32, f32) = (25.0, 25.0);
+
+/// A kind of power-up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PowerUpKind {
+    /// Didi runs faster
+    Slippers,
+    /// Baobei does not get sad
+    PatienceCandy,
+}
+
+/// Component on a power-up lying on the floor until the timer finishes.
+struct PowerUp {
+    /// What the power-up gives
+    kind: PowerUpKind,
+    /// Timer until the power-up vanishes
+    timer: Timer,
+}
+
+/// Colors of the power-ups.
+struct PowerUpMaterials {
+    /// Speed slippers
+    slippers: Handle<ColorMaterial>,
+    /// Patience candy
+    candy: Handle<ColorMaterial>,
+}
+
+impl FromWorld for PowerUpMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            slippers: materials.add(Color::AZURE.into()),
+            candy: materials.add(Color::FUCHSIA.into()),
+        }
+    }
+}
+
+/// Schedules the power-ups.
+fn schedule_power_ups(mut scheduler: ResMut<Scheduler>) {
+    scheduler.every(POWER_UP_TASK, POWER_UP_INTERVAL);
+}
+
+/// Drops a random power-up on a free place of the floor from time to time.
+fn spawn_power_ups_system(
+    mut commands: Commands,
+    materials: Res<PowerUpMaterials>,
+    mut rng: ResMut<GameRng>,
+    mut scheduled_events: EventReader<ScheduledEvent>,
+    colliders: Query<(&Position, &BoxCollider), Without<Movement>>,
+) {
+    /// Attempts to find a free place before giving up until the next power-up
+    const ATTEMPTS: usize = 10;
+
+    let due = scheduled_events
+        .iter()
+        .any(|ScheduledEvent(task)| *task == POWER_UP_TASK);
+
+    if !due {
+        return;
+    }
+
+    let size = Vec2::new(POWER_UP_SIZE.0, POWER_UP_SIZE.1);
+    let free_position = (0..ATTEMPTS)
+        .map(|_| {
+            Vec3::new(
+                rng.rng.gen_range(100.0..1180.0),
+                rng.rng.gen_range(90.0..480.0),
+                0.0,
+            )
+        })
+        .find(|position| !overlaps_colliders(*position, size, colliders.iter()));
+
+    let position = match free_position {
+        Some(position) => position,
+        None => return,
+    };
+    let (kind, material) = if rng.rng.gen_bool(0.5) {
+        (PowerUpKind::Slippers, materials.slippers.clone())
+    } else {
+        (PowerUpKind::PatienceCandy, materials.candy.clone())
+    };
+
+    commands
+        .spawn()
+        .insert(PowerUp {
+            kind,
+            timer: Timer::from_seconds(POWER_UP_LIFETIME, false),
+        })
+        .insert(Position(position))
+        .insert(TriggerArea::new(size.x + 20.0, size.y + 20.0))
+        .insert_bundle(SpriteBundle {
+            material,
+            sprite: Sprite::new(size),
+            ..SpriteBundle::default()
+        });
+}
+
+/// Gives the effect of the power-ups Didi walks on.
+fn collect_power_ups_system(
+    mut commands: Commands,
+    game_data: Res<GameData>,
+    mut contact_events: EventReader<ContactEvent>,
+    power_ups: Query<&PowerUp>,
+    mut holders: Query<&mut StatusEffects>,
+    baobei: Query<Entity, With<Baobei>>,
+) {
+    for event in contact_events.iter() {
+        let contact = match event {
+            ContactEvent::Started(contact, _) if contact.0 == game_data.didi_entity => contact,
+            _ => continue,
+        };
+        let power_up = match power_ups.get(contact.1) {
+            Ok(power_up) => power_up,
+            Err(_) => continue,
+        };
+        info!("Collect the {:?}", power_up.kind);
+        commands.entity(contact.1).despawn();
+
+        let (kind, receivers): (StatusEffectKind, Vec<Entity>) = match power_up.kind {
+            PowerUpKind::Slippers => (StatusEffectKind::Slippers, vec![game_data.didi_entity]),
+            PowerUpKind::PatienceCandy => {
+                (StatusEffectKind::PatienceCandy, baobei.iter().collect())
+            }
+        };
+        for receiver in receivers {
+            if let Ok(mut status_effects) = holders.get_mut(receiver) {
+                status_effects.apply(kind);
+            }
+        }
+    }
+}
+
+/// Removes the power-ups left on the floor for too long.
+fn vanish_power_ups_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut power_ups: Query<(Entity, &mut PowerUp)>,
+) {
+    let delta = time_scale.scale(time.delta());
+
+    for (entity, mut power_up) in power_ups.iter_mut() {
+        if power_up.timer.tick(delta).just_finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Removes the power-ups of the previous game.
+fn reset_power_ups_system(mut commands: Commands, power_ups: Query<Entity, With<PowerUp>>) {
+    for power_up in power_ups.iter() {
+        commands.entity(power_up).despawn();
+    }
+}
@@ -0,0 +1,210 @@
+//! Tiny menu asking the player whether Didi eats the carried snack or keeps
+//! it for Baobei.
+
+use bevy::prelude::*;
+
+use crate::{collisions::Position, constants::GameState, drawing::Overlay};
+
+use super::{
+    entities::GameData,
+    items::{ActionEvent, Inventory, PickAndDropCooldown},
+};
+
+/// Plugin managing the consume prompt.
+pub struct PromptPlugin;
+
+impl Plugin for PromptPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<ConsumePrompt>()
+            .add_startup_system(spawn_prompt_menu.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(consume_prompt_system.system().before("item_actions"))
+                    .with_system(update_prompt_menu_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::InGame).with_system(close_prompt_system.system()),
+            );
+    }
+}
+
+/// Height of the menu above Didi.
+const MENU_HEIGHT: f32 = 190.0;
+
+/// An option of the consume prompt.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PromptChoice {
+    /// Didi eats the item
+    Eat,
+    /// Didi keeps the item
+    Keep,
+}
+
+/// State of the prompt asking what to do with the carried snack.
+pub struct ConsumePrompt {
+    /// Whether the player is choosing.
+    open: bool,
+    /// The highlighted option.
+    choice: PromptChoice,
+}
+
+impl Default for ConsumePrompt {
+    fn default() -> Self {
+        Self {
+            open: false,
+            choice: PromptChoice::Keep,
+        }
+    }
+}
+
+impl ConsumePrompt {
+    /// Opens the prompt, keeping the item being highlighted.
+    pub fn open(&mut self) {
+        self.open = true;
+        self.choice = PromptChoice::Keep;
+    }
+
+    /// Returns true if the player is choosing what to do with the item.
+    pub const fn is_open(&self) -> bool {
+        self.open
+    }
+}
+
+/// Component on the background of the menu.
+struct PromptMenu;
+/// Component on the text of an option of the menu.
+struct PromptOption(PromptChoice);
+
+/// Spawns the hidden menu.
+fn spawn_prompt_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let font = asset_server.load("FiraSans-Bold.ttf");
+    let hidden = Visible {
+        is_visible: false,
+        is_transparent: true,
+    };
+
+    commands
+        .spawn()
+        .insert(PromptMenu)
+        .insert(Overlay)
+        .insert(Position::default())
+        .insert_bundle(SpriteBundle {
+            material: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.7).into()),
+            sprite: Sprite::new(Vec2::new(170.0, 40.0)),
+            visible: hidden.clone(),
+            ..SpriteBundle::default()
+        })
+        .with_children(|parent| {
+            for (choice, label, x) in [
+                (PromptChoice::Eat, "Eat it", -40.0),
+                (PromptChoice::Keep, "Keep", 45.0),
+            ] {
+                parent
+                    .spawn()
+                    .insert(PromptOption(choice))
+                    .insert_bundle(Text2dBundle {
+                        text: Text::with_section(
+                            label,
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 22.0,
+                                color: Color::WHITE,
+                            },
+                            TextAlignment {
+                                vertical: VerticalAlign::Center,
+                                horizontal: HorizontalAlign::Center,
+                            },
+                        ),
+                        transform: Transform::from_xyz(x, 0.0, 0.1),
+                        visible: hidden.clone(),
+                        ..Text2dBundle::default()
+                    });
+            }
+        });
+}
+
+/// Switches the highlighted option with the arrow keys and confirms it with
+/// the action key.
+fn consume_prompt_system(
+    keyboard: Res<Input<KeyCode>>,
+    game_data: Res<GameData>,
+    mut prompt: ResMut<ConsumePrompt>,
+    mut cooldown: ResMut<PickAndDropCooldown>,
+    mut action_events: EventWriter<ActionEvent>,
+    inventories: Query<&Inventory>,
+) {
+    if !prompt.open {
+        return;
+    }
+    let didi = game_data.didi_entity;
+    let item = match inventories.get(didi).ok().and_then(Inventory::active_item) {
+        Some(item) if item.is_consumable() => item,
+        _ => {
+            prompt.open = false; // The item left the hands of Didi
+            return;
+        }
+    };
+
+    if keyboard.just_pressed(KeyCode::Left) {
+        prompt.choice = PromptChoice::Eat;
+    }
+    if keyboard.just_pressed(KeyCode::Right) {
+        prompt.choice = PromptChoice::Keep;
+    }
+    if keyboard.just_pressed(KeyCode::Space) {
+        if prompt.choice == PromptChoice::Eat {
+            action_events.send(ActionEvent::Consume(didi, item));
+        }
+        prompt.open = false;
+        cooldown.0.start(); // Do not open the prompt again right away
+    }
+}
+
+/// Shows the menu above Didi while open and highlights the chosen option.
+fn update_prompt_menu_system(
+    game_data: Res<GameData>,
+    prompt: Res<ConsumePrompt>,
+    positions: Query<&Position, Without<PromptMenu>>,
+    mut menus: Query<(&mut Position, &mut Visible), With<PromptMenu>>,
+    mut options: Query<(&PromptOption, &mut Text, &mut Visible), Without<PromptMenu>>,
+) {
+    if !prompt.is_changed() && !prompt.open {
+        return;
+    }
+    let didi_position = positions
+        .get(game_data.didi_entity)
+        .map_or(Vec3::ZERO, |position| position.0);
+
+    for (mut position, mut visible) in menus.iter_mut() {
+        visible.is_visible = prompt.open;
+        position.0 = Vec3::new(
+            didi_position.x,
+            didi_position.y + didi_position.z + MENU_HEIGHT,
+            0.0,
+        );
+    }
+    for (option, mut text, mut visible) in options.iter_mut() {
+        visible.is_visible = prompt.open;
+        text.sections[0].style.color = if option.0 == prompt.choice {
+            Color::GOLD
+        } else {
+            Color::WHITE
+        };
+    }
+}
+
+/// Query filter for the menu and its options
+type Menu = Or<(With<PromptMenu>, With<PromptOption>)>;
+
+/// Closes the prompt and hides the menu when leaving the game.
+fn close_prompt_system(mut prompt: ResMut<ConsumePrompt>, mut menus: Query<&mut Visible, Menu>) {
+    prompt.open = false;
+
+    for mut visible in menus.iter_mut() {
+        visible.is_visible = false;
+    }
+}
@@ -0,0 +1,360 @@
+//! Seed race: two players alternate attempts on the same seed, then compare
+//! their deliveries over time.
+
+use bevy::prelude::*;
+use rand::random;
+
+use crate::{
+    collisions::Position,
+    constants::{GameState, RACE_DURATION},
+    controllers::{BindingText, InputAction, InputMap},
+    drawing::UiObject,
+    rng::GameRng,
+    time_scale::TimeScale,
+    widgets::{resource_timer_system, spawn_timer_ring, Progress, ResourceTimer, WidgetMaterials},
+};
+
+use super::{
+    entities::GameData,
+    items::{CarriedItem, DeliveryEvent, Inventory, Item, ItemRequestQueue, ItemSystems},
+    registry::ItemRegistry,
+    requests::RequestQueue,
+    storage::Storage,
+    NewRunSystems,
+};
+
+/// Plugin managing seed races.
+pub struct RacePlugin;
+
+impl Plugin for RacePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Race>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Menu).with_system(abort_race_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Menu).with_system(start_race_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::InGame).with_system(start_attempt_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(
+                        race_attempt_system
+                            .system()
+                            .label("race_attempt")
+                            .after(ItemSystems),
+                    )
+                    .with_system(resource_timer_system::<Race>.system().after("race_attempt")),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::InGame).with_system(end_attempt_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::RaceResults).with_system(setup_results.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::RaceResults)
+                    .with_system(results_input_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::RaceResults)
+                    .with_system(cleanup_results.system())
+                    .with_system(restart_seed_system.system().before(NewRunSystems)),
+            );
+    }
+}
+
+/// Number of players in a race.
+const RACE_PLAYERS: usize = 2;
+/// Duration in seconds covered by each bar of the deliveries graph.
+const GRAPH_BAR_DURATION: f32 = 10.0;
+/// Colors of the players in the results.
+const PLAYER_COLORS: [Color; RACE_PLAYERS] = [Color::TOMATO, Color::TURQUOISE];
+
+/// State of the seed race.
+#[derive(Default)]
+pub struct Race {
+    /// Whether a race is in progress.
+    active: bool,
+    /// Number of the players who finished their attempt.
+    finished_attempts: usize,
+    /// Times of the deliveries of each player since the start of the attempt.
+    deliveries: [Vec<f32>; RACE_PLAYERS],
+    /// Timer of the current attempt.
+    timer: Timer,
+}
+
+impl Progress for Race {
+    fn remaining(&self) -> f32 {
+        self.timer.remaining()
+    }
+}
+
+/// Cancels the race when going back to the menu.
+fn abort_race_system(mut race: ResMut<Race>) {
+    race.active = false;
+}
+
+/// Starts a race on a new seed when the player press `R` in the menu.
+fn start_race_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut race: ResMut<Race>,
+    mut rng: ResMut<GameRng>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::R) {
+        return;
+    }
+    *race = Race {
+        active: true,
+        finished_attempts: 0,
+        deliveries: [Vec::new(), Vec::new()],
+        timer: Timer::from_seconds(RACE_DURATION, false),
+    };
+    *rng = GameRng::from_seed(random());
+    info!("Seed race on seed {}", rng.seed);
+
+    state.set(GameState::InGame).unwrap();
+}
+
+/// Restarts the seed before the game is reset for the next attempt, so the
+/// resets draw the same numbers as for the first one.
+fn restart_seed_system(race: Res<Race>, mut rng: ResMut<GameRng>) {
+    if race.active {
+        rng.restart();
+    }
+}
+
+/// Restarts the game in the same conditions for each attempt, the rest of
+/// the game being reset with the new run.
+#[allow(clippy::too_many_arguments)]
+fn start_attempt_system(
+    mut commands: Commands,
+    mut race: ResMut<Race>,
+    mut rng: ResMut<GameRng>,
+    game_data: Res<GameData>,
+    registry: Res<ItemRegistry>,
+    widget_materials: Res<WidgetMaterials>,
+    mut askers: Query<(&mut ItemRequestQueue, Option<&mut RequestQueue>)>,
+    mut positions: Query<&mut Position>,
+    mut inventories: Query<&mut Inventory>,
+    items: Query<Entity, Or<(With<Item>, With<CarriedItem>)>>,
+    mut storages: Query<&mut Storage>,
+) {
+    if !race.active {
+        return;
+    }
+    race.timer.reset();
+    rng.restart();
+
+    for (mut requests, queue) in askers.iter_mut() {
+        let asked_item = registry.random_request(&mut rng.rng);
+        *requests = ItemRequestQueue::new(asked_item);
+        if let Some(mut queue) = queue {
+            *queue = RequestQueue::new(&registry, &mut rng.rng, asked_item);
+        }
+    }
+
+    // Didi starts empty-handed at the same place
+    if let Ok(mut inventory) = inventories.get_mut(game_data.didi_entity) {
+        *inventory = Inventory::default();
+    }
+    for item in items.iter() {
+        commands.entity(item).despawn_recursive();
+    }
+    for mut storage in storages.iter_mut() {
+        storage.clear();
+    }
+    if let Ok(mut didi_position) = positions.get_mut(game_data.didi_entity) {
+        didi_position.0 = Vec3::new(640.0, 260.0, 0.0);
+    }
+    let timer_ring = spawn_timer_ring(&mut commands, &widget_materials, Vec3::ZERO, 25.0, 12);
+    commands
+        .entity(timer_ring)
+        .insert(ResourceTimer::<Race>::default())
+        .insert(UiObject)
+        .insert(Position(Vec3::new(1200.0, 660.0, 0.0)));
+}
+
+/// Removes the timer of the attempt.
+fn end_attempt_system(
+    mut commands: Commands,
+    timer_rings: Query<Entity, With<ResourceTimer<Race>>>,
+) {
+    for timer_ring in timer_rings.iter() {
+        commands.entity(timer_ring).despawn_recursive();
+    }
+}
+
+/// Records the deliveries of the current player and ends the attempt.
+fn race_attempt_system(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut race: ResMut<Race>,
+    mut delivery_events: EventReader<DeliveryEvent>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if !race.active {
+        return;
+    }
+    let elapsed = race.timer.elapsed_secs();
+    let player = race.finished_attempts;
+
+    for _ in delivery_events.iter() {
+        race.deliveries[player].push(elapsed);
+    }
+
+    let delta = time_scale.scale(time.delta());
+    if race.timer.tick(delta).just_finished() {
+        info!(
+            "Player {} delivered {}",
+            player + 1,
+            race.deliveries[player].len()
+        );
+        race.finished_attempts += 1;
+        state.set(GameState::RaceResults).unwrap();
+    }
+}
+
+/// Stores entities of the results screen.
+struct ResultsData {
+    /// Entity wrapping all the results entities
+    node_wrapper: Entity,
+}
+
+/// Shows the deliveries of each player as a bar graph over time.
+fn setup_results(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    race: Res<Race>,
+    rng: Res<GameRng>,
+) {
+    let font = asset_server.load("FiraSans-Bold.ttf");
+    let text = |value: String, font_size: f32| TextBundle {
+        text: Text::with_section(
+            value,
+            TextStyle {
+                font: font.clone(),
+                font_size,
+                color: Color::WHITE,
+            },
+            TextAlignment::default(),
+        ),
+        ..TextBundle::default()
+    };
+
+    let hint = if race.finished_attempts < RACE_PLAYERS {
+        format!(
+            "Player {}: press {{confirm}} to race",
+            race.finished_attempts + 1
+        )
+    } else {
+        "Press {confirm} to go back to the menu".to_string()
+    };
+
+    let node_wrapper = commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                padding: Rect::all(Val::Px(50.0)),
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Style::default()
+            },
+            material: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.8).into()),
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn()
+                .insert_bundle(text(format!("Seed race #{}", rng.seed), 60.0));
+
+            for (player, deliveries) in race.deliveries.iter().enumerate() {
+                let label = format!("Player {}: {} deliveries", player + 1, deliveries.len());
+                parent.spawn().insert_bundle(text(label, 35.0));
+
+                let material = materials.add(PLAYER_COLORS[player].into());
+                parent
+                    .spawn()
+                    .insert_bundle(NodeBundle {
+                        style: Style {
+                            margin: Rect::all(Val::Px(10.0)),
+                            align_items: AlignItems::FlexStart,
+                            ..Style::default()
+                        },
+                        material: materials.add(Color::NONE.into()),
+                        ..NodeBundle::default()
+                    })
+                    .with_children(|graph| {
+                        for bar_height in delivery_bars(deliveries) {
+                            graph.spawn().insert_bundle(NodeBundle {
+                                style: Style {
+                                    margin: Rect::all(Val::Px(2.0)),
+                                    size: Size::new(Val::Px(30.0), Val::Px(bar_height)),
+                                    ..Style::default()
+                                },
+                                material: material.clone(),
+                                ..NodeBundle::default()
+                            });
+                        }
+                    });
+            }
+
+            parent
+                .spawn()
+                .insert(BindingText(hint))
+                .insert_bundle(text(String::new(), 30.0));
+        })
+        .id();
+
+    commands.insert_resource(ResultsData { node_wrapper });
+}
+
+/// Returns the height of the bars of the graph, one per period of the attempt.
+// The times of the deliveries are positive and within the attempt
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn delivery_bars(deliveries: &[f32]) -> Vec<f32> {
+    /// Height of a bar for one delivery
+    const DELIVERY_HEIGHT: f32 = 15.0;
+
+    let bar_count = (RACE_DURATION / GRAPH_BAR_DURATION).ceil() as usize;
+    let mut bars = vec![2.0; bar_count]; // Empty bars are still visible
+
+    for time in deliveries {
+        let bar = (time / GRAPH_BAR_DURATION) as usize;
+        if let Some(height) = bars.get_mut(bar) {
+            *height += DELIVERY_HEIGHT;
+        }
+    }
+    bars
+}
+
+/// Starts the attempt of the next player or goes back to the menu.
+fn results_input_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    input_map: Res<InputMap>,
+    race: Res<Race>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if input_map.just_pressed(InputAction::Confirm, &keyboard_input, &gamepad_buttons) {
+        if race.finished_attempts < RACE_PLAYERS {
+            state.set(GameState::InGame).unwrap();
+        } else {
+            state.set(GameState::Menu).unwrap();
+        }
+    } else if input_map.just_pressed(InputAction::Back, &keyboard_input, &gamepad_buttons) {
+        state.set(GameState::Menu).unwrap();
+    }
+}
+
+/// Removes all entities of the results screen.
+fn cleanup_results(mut commands: Commands, results_data: Res<ResultsData>) {
+    commands
+        .entity(results_data.node_wrapper)
+        .despawn_recursive();
+}
@@ -0,0 +1,414 @@
+//! Registry of the items, loaded from `assets/items.cfg` so new items can be
+//! added without recompiling the game.
+//!
+//! Each item is described by `<id>.<field> = <value>` lines, the fields
+//! being `name`, `sprite`, `color` (red, green and blue from 0 to 1),
+//! `happiness` (gained by Baobei on delivery), `need` (hunger, thirst or fun,
+//! the need satisfied by the item) and `weight` (chance of being requested,
+//! never requested at 0). The built-in items keep their gameplay
+//! rules and can only be tweaked, the other ids define new items. The
+//! themed items of the active seasonal event are added after the file.
+
+use std::fs;
+
+use bevy::prelude::*;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::save::SaveData;
+
+use super::{
+    happiness::Need,
+    items::Item,
+    seasons::{add_seasonal_items, ActiveSeason},
+};
+
+/// File describing the items.
+const REGISTRY_FILE: &str = "assets/items.cfg";
+/// Happiness gained by Baobei when receiving an item not describing it.
+const DEFAULT_HAPPINESS: f32 = 0.15;
+/// Happiness gained by Baobei when receiving a crafted item, longer to get.
+const CRAFTED_HAPPINESS: f32 = 0.25;
+
+/// Description of an item.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemDefinition {
+    /// The described item
+    pub item: Item,
+    /// Identifier used in the registry file
+    pub id: String,
+    /// Displayed name
+    pub name: String,
+    /// Path of the sprite in the assets
+    pub sprite: String,
+    /// Color modulating the sprite
+    pub color: Color,
+    /// Happiness gained by Baobei on delivery
+    pub happiness: f32,
+    /// Need of Baobei satisfied on delivery
+    pub need: Need,
+    /// Chance of being requested, never requested at 0
+    pub weight: u32,
+}
+
+impl ItemDefinition {
+    /// Creates the definition of an item.
+    fn new(item: Item, id: &str, name: &str, sprite: &str, color: Color, weight: u32) -> Self {
+        Self {
+            item,
+            id: id.to_string(),
+            name: name.to_string(),
+            sprite: sprite.to_string(),
+            color,
+            happiness: DEFAULT_HAPPINESS,
+            need: default_need(item),
+            weight,
+        }
+    }
+}
+
+/// Returns the need satisfied by the item when the file does not tell it.
+const fn default_need(item: Item) -> Need {
+    match item {
+        Item::IceCream | Item::Chips | Item::Coffee => Need::Hunger,
+        Item::WaterGlass | Item::HotTea => Need::Thirst,
+        _ => Need::Fun,
+    }
+}
+
+/// Definitions of all the items of the game.
+pub struct ItemRegistry {
+    /// The built-in items first, then the items defined in the file.
+    definitions: Vec<ItemDefinition>,
+}
+
+impl Default for ItemRegistry {
+    /// Returns the built-in items.
+    fn default() -> Self {
+        Self {
+            definitions: vec![
+                ItemDefinition::new(
+                    Item::IceCream,
+                    "ice_cream",
+                    "Ice cream",
+                    "items/ice_cream.png",
+                    Color::WHITE,
+                    3,
+                ),
+                ItemDefinition::new(
+                    Item::WaterGlass,
+                    "water_glass",
+                    "Glass of water",
+                    "items/water_glass.png",
+                    Color::WHITE,
+                    3,
+                ),
+                ItemDefinition::new(
+                    Item::Chips,
+                    "chips",
+                    "Chips",
+                    "items/chips.png",
+                    Color::WHITE,
+                    3,
+                ),
+                ItemDefinition::new(
+                    Item::Coffee,
+                    "coffee",
+                    "Coffee",
+                    "items/water_glass.png",
+                    Color::rgb(0.45, 0.3, 0.2),
+                    0,
+                ),
+                ItemDefinition::new(
+                    Item::LaundryBasket,
+                    "laundry_basket",
+                    "Laundry basket",
+                    "items/chips.png",
+                    Color::rgb(0.8, 0.6, 0.35),
+                    0,
+                ),
+                ItemDefinition::new(
+                    Item::Tray,
+                    "tray",
+                    "Tray",
+                    "items/chips.png",
+                    Color::rgb(0.75, 0.75, 0.8),
+                    0,
+                ),
+                ItemDefinition::new(
+                    Item::TeaLeaves,
+                    "tea_leaves",
+                    "Tea leaves",
+                    "items/chips.png",
+                    Color::rgb(0.45, 0.6, 0.3),
+                    0,
+                ),
+                ItemDefinition::new(
+                    Item::DirtyDish,
+                    "dirty_dish",
+                    "Dirty dish",
+                    "items/water_glass.png",
+                    Color::rgb(0.55, 0.5, 0.4),
+                    0,
+                ),
+                ItemDefinition::new(
+                    Item::Mop,
+                    "mop",
+                    "Mop",
+                    "items/chips.png",
+                    Color::rgb(0.3, 0.55, 0.75),
+                    0,
+                ),
+                ItemDefinition {
+                    happiness: CRAFTED_HAPPINESS,
+                    ..ItemDefinition::new(
+                        Item::HotTea,
+                        "hot_tea",
+                        "Hot tea",
+                        "items/water_glass.png",
+                        Color::rgb(0.75, 0.55, 0.3),
+                        1,
+                    )
+                },
+            ],
+        }
+    }
+}
+
+impl FromWorld for ItemRegistry {
+    fn from_world(world: &mut World) -> Self {
+        let mut registry = match fs::read_to_string(REGISTRY_FILE) {
+            Ok(content) => Self::from_save_data(&SaveData::parse(&content)),
+            Err(error) => {
+                warn!(
+                    "Fail to read {}, using the built-in items: {}",
+                    REGISTRY_FILE, error
+                );
+                Self::default()
+            }
+        };
+        if let Some(ActiveSeason(Some(event))) = world.get_resource::<ActiveSeason>() {
+            add_seasonal_items(&mut registry, *event);
+        }
+        registry
+    }
+}
+
+impl ItemRegistry {
+    /// Returns the built-in items tweaked and completed by the data.
+    pub fn from_save_data(data: &SaveData) -> Self {
+        let mut registry = Self::default();
+        let mut ids: Vec<&str> = Vec::new();
+        for key in data.keys() {
+            match key.split_once('.') {
+                Some((id, _)) if !ids.contains(&id) => ids.push(id),
+                _ => {}
+            }
+        }
+
+        for id in ids {
+            let definition = registry.define(id);
+            let field = |name: &str| format!("{}.{}", id, name);
+
+            if let Some(name) = data.get(&field("name")) {
+                definition.name = name;
+            }
+            if let Some(sprite) = data.get(&field("sprite")) {
+                definition.sprite = sprite;
+            }
+            if let Some(color) = data
+                .get::<String>(&field("color"))
+                .and_then(|c| parse_color(&c))
+            {
+                definition.color = color;
+            }
+            if let Some(happiness) = data.get(&field("happiness")) {
+                definition.happiness = happiness;
+            }
+            if let Some(need) = data.get(&field("need")) {
+                definition.need = need;
+            }
+            if let Some(weight) = data.get(&field("weight")) {
+                definition.weight = weight;
+            }
+        }
+        registry
+    }
+
+    /// Returns the definition of the identifier to fill in, adding a new
+    /// item if the identifier is not defined yet.
+    pub fn define(&mut self, id: &str) -> &mut ItemDefinition {
+        let index = match self.definitions.iter().position(|def| def.id == id) {
+            Some(index) => index,
+            None => {
+                let custom_count = self
+                    .definitions
+                    .iter()
+                    .filter(|def| matches!(def.item, Item::Custom(_)))
+                    .count();
+                let item = Item::Custom(custom_count);
+                self.definitions.push(ItemDefinition::new(
+                    item,
+                    id,
+                    id,
+                    "items/chips.png",
+                    Color::WHITE,
+                    1,
+                ));
+                self.definitions.len() - 1
+            }
+        };
+        &mut self.definitions[index]
+    }
+
+    /// Returns the definitions of all the items.
+    pub fn definitions(&self) -> impl Iterator<Item = &ItemDefinition> {
+        self.definitions.iter()
+    }
+
+    /// Returns the definition of the item.
+    pub fn definition(&self, item: Item) -> Option<&ItemDefinition> {
+        self.definitions.iter().find(|def| def.item == item)
+    }
+
+    /// Returns the item of the identifier used in the registry file.
+    pub fn find(&self, id: &str) -> Option<Item> {
+        self.definitions
+            .iter()
+            .find(|def| def.id == id)
+            .map(|def| def.item)
+    }
+
+    /// Returns the displayed name of the item.
+    pub fn name(&self, item: Item) -> &str {
+        self.definition(item).map_or("?", |def| def.name.as_str())
+    }
+
+    /// Returns the happiness gained by Baobei when receiving the item.
+    pub fn happiness(&self, item: Item) -> f32 {
+        self.definition(item)
+            .map_or(DEFAULT_HAPPINESS, |def| def.happiness)
+    }
+
+    /// Returns the need of Baobei satisfied by the item.
+    pub fn need(&self, item: Item) -> Need {
+        self.definition(item).map_or(Need::Fun, |def| def.need)
+    }
+
+    /// Returns a random requested item.
+    pub fn random_request<R: Rng + ?Sized>(&self, rng: &mut R) -> Item {
+        self.roll(rng, None).unwrap_or(Item::Chips)
+    }
+
+    /// Returns a random requested item different than the given one, or the
+    /// same one if no other item is requested.
+    pub fn random_different<R: Rng + ?Sized>(&self, rng: &mut R, item: Item) -> Item {
+        self.roll(rng, Some(item)).unwrap_or(item)
+    }
+
+    /// Rolls an item according to the weights, except the given one.
+    fn roll<R: Rng + ?Sized>(&self, rng: &mut R, except: Option<Item>) -> Option<Item> {
+        let candidates: Vec<&ItemDefinition> = self
+            .definitions
+            .iter()
+            .filter(|def| def.weight > 0 && Some(def.item) != except)
+            .collect();
+
+        candidates
+            .choose_weighted(rng, |def| def.weight)
+            .ok()
+            .map(|def| def.item)
+    }
+}
+
+/// Parses a color written as its red, green and blue components.
+fn parse_color(value: &str) -> Option<Color> {
+    let components: Vec<f32> = value
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .ok()?;
+
+    match components[..] {
+        [red, green, blue] => Some(Color::rgb(red, green, blue)),
+        _ => None,
+    }
+}
+
+/// Sprites of the items of the registry, modulated by a tint.
+pub struct ItemSprites(Vec<(Item, Handle<ColorMaterial>)>);
+
+impl ItemSprites {
+    /// Loads the sprites of the registered items, their color being
+    /// multiplied by the tint.
+    pub fn load(world: &mut World, tint: Color) -> Self {
+        let definitions: Vec<(Item, String, Color)> = world
+            .get_resource::<ItemRegistry>()
+            .expect("The item registry is loaded before the sprites")
+            .definitions()
+            .map(|def| (def.item, def.sprite.clone(), def.color))
+            .collect();
+        let asset_server = world.get_resource::<AssetServer>().unwrap().clone();
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        let sprites = definitions
+            .into_iter()
+            .map(|(item, sprite, color)| {
+                let color = Color::rgba(
+                    color.r() * tint.r(),
+                    color.g() * tint.g(),
+                    color.b() * tint.b(),
+                    color.a() * tint.a(),
+                );
+                let texture = asset_server.load(sprite.as_str());
+                (
+                    item,
+                    materials.add(ColorMaterial::modulated_texture(texture, color)),
+                )
+            })
+            .collect();
+
+        Self(sprites)
+    }
+
+    /// Returns the sprite of the item.
+    pub fn get(&self, item: Item) -> Handle<ColorMaterial> {
+        self.0
+            .iter()
+            .find(|(sprite_item, _)| *sprite_item == item)
+            .map(|(_, sprite)| sprite.clone())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn registry_adds_new_items() {
+        let data = SaveData::parse(
+            "tea.name = Tea\ntea.happiness = 0.2\ntea.need = thirst\ntea.color = 0.5 0.8 0.4\nchips.weight = 0\n",
+        );
+        let registry = ItemRegistry::from_save_data(&data);
+
+        let tea = Item::Custom(0);
+        assert_eq!(registry.name(tea), "Tea");
+        assert!((registry.happiness(tea) - 0.2).abs() < f32::EPSILON);
+        assert_eq!(registry.need(tea), Need::Thirst);
+        assert_eq!(registry.need(Item::Chips), Need::Hunger);
+        assert_eq!(registry.name(Item::Chips), "Chips");
+        assert!((registry.happiness(Item::Chips) - DEFAULT_HAPPINESS).abs() < f32::EPSILON);
+
+        let mut rng = StdRng::seed_from_u64(42);
+        for _ in 0..50 {
+            let item = registry.random_request(&mut rng);
+            assert!(matches!(
+                item,
+                Item::IceCream | Item::WaterGlass | Item::HotTea | Item::Custom(0)
+            ));
+            assert_ne!(registry.random_different(&mut rng, item), item);
+        }
+    }
+}
@@ -0,0 +1,337 @@
+//! Instant replay of the last seconds before a clutch delivery, shown as a
+//! picture-in-picture in a corner of the screen.
+
+use std::{cmp::Ordering, collections::VecDeque};
+
+use bevy::prelude::*;
+
+use crate::{
+    collisions::Position,
+    constants::{GameState, WINDOW_HEIGHT, WINDOW_WIDTH},
+    drawing::UiObject,
+};
+
+use super::{
+    entities::GameData,
+    items::{DeliveryEvent, Inventory, Item, ItemSystems, PICKED_ITEM_TRANSLATION},
+    materials::GameplayMaterials,
+    Baobei,
+};
+
+/// Plugin managing the instant replays.
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<ReplayBuffer>()
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(record_replay_system.system())
+                    .with_system(start_replay_system.system().after(ItemSystems))
+                    .with_system(play_replay_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::InGame).with_system(stop_replay_system.system()),
+            );
+    }
+}
+
+/// Seconds recorded and replayed.
+const REPLAY_DURATION: f32 = 3.0;
+/// Scale of the replayed room in the corner of the screen.
+const PIP_SCALE: f32 = 0.25;
+/// Position of the picture-in-picture on the screen.
+const PIP_POSITION: (f32, f32) = (190.0, 600.0);
+/// Scale of the characters in the room.
+const CHARACTER_SCALE: f32 = 0.3;
+
+/// What happened during one frame.
+#[derive(Clone)]
+struct ReplayFrame {
+    /// Seconds since the start of the recording
+    time: f32,
+    /// Position of Didi on the screen
+    didi: Vec2,
+    /// Position of Baobei on the screen
+    baobei: Vec2,
+    /// Item carried by Didi
+    carried: Option<Item>,
+}
+
+/// Rolling record of the last seconds of the game.
+#[derive(Default, Clone)]
+struct ReplayBuffer {
+    /// Seconds since the start of the recording
+    clock: f32,
+    /// Frames of the last seconds, from the oldest one
+    frames: VecDeque<ReplayFrame>,
+}
+
+impl ReplayBuffer {
+    /// Records a frame and forgets the ones older than the replay duration.
+    fn record(&mut self, delta: f32, didi: Vec2, baobei: Vec2, carried: Option<Item>) {
+        self.clock += delta;
+        self.frames.push_back(ReplayFrame {
+            time: self.clock,
+            didi,
+            baobei,
+            carried,
+        });
+
+        while let Some(oldest) = self.frames.front() {
+            if oldest.time >= self.clock - REPLAY_DURATION {
+                break;
+            }
+            self.frames.pop_front();
+        }
+    }
+
+    /// Returns the frame shown after the given seconds of replay.
+    fn frame_at(&self, elapsed: f32) -> Option<&ReplayFrame> {
+        let start = self.frames.front()?.time;
+
+        self.frames
+            .iter()
+            .rev()
+            .find(|frame| frame.time - start <= elapsed)
+            .or_else(|| self.frames.front())
+    }
+}
+
+/// Component on the picture-in-picture replaying the recorded frames.
+struct InstantReplay {
+    /// Frames to replay
+    record: ReplayBuffer,
+    /// Timer until the end of the replay
+    timer: Timer,
+}
+
+/// Component on a replayed character.
+enum ReplayActor {
+    /// Didi
+    Didi,
+    /// Baobei
+    Baobei,
+}
+
+/// Component on the item carried by the replayed Didi.
+struct ReplayCarriedItem;
+
+/// Returns the translation of a position of the room in the scaled
+/// background of the picture-in-picture.
+fn pip_translation(position: Vec2, z: f32) -> Vec3 {
+    let room_center = Vec2::new(WINDOW_WIDTH / 2.0, WINDOW_HEIGHT / 2.0);
+    (position - room_center).extend(z)
+}
+
+/// Records the characters every frame.
+fn record_replay_system(
+    time: Res<Time>,
+    game_data: Res<GameData>,
+    mut buffer: ResMut<ReplayBuffer>,
+    positions: Query<&Position>,
+    inventories: Query<&Inventory>,
+    baobeis: Query<Entity, With<Baobei>>,
+) {
+    let screen_position = |entity| {
+        positions
+            .get(entity)
+            .map(|position| Vec2::new(position.0.x, position.0.y + position.0.z))
+    };
+    let didi = match screen_position(game_data.didi_entity) {
+        Ok(didi) => didi,
+        Err(_) => return,
+    };
+    // The Baobei Didi is probably serving is the nearest one
+    let baobei = baobeis
+        .iter()
+        .filter_map(|baobei| screen_position(baobei).ok())
+        .min_by(|a, b| {
+            a.distance_squared(didi)
+                .partial_cmp(&b.distance_squared(didi))
+                .unwrap_or(Ordering::Equal)
+        });
+    let baobei = match baobei {
+        Some(baobei) => baobei,
+        None => return,
+    };
+    let carried = inventories
+        .get(game_data.didi_entity)
+        .ok()
+        .and_then(Inventory::active_item);
+
+    buffer.record(time.delta_seconds(), didi, baobei, carried);
+}
+
+/// Seconds of patience left under which a delivery is a clutch.
+const CLUTCH_PATIENCE: f32 = 1.0;
+
+/// Starts a replay when an item is delivered with less than one second of
+/// patience left.
+fn start_replay_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    materials: Res<GameplayMaterials>,
+    buffer: Res<ReplayBuffer>,
+    mut delivery_events: EventReader<DeliveryEvent>,
+    replays: Query<(), With<InstantReplay>>,
+) {
+    let clutch = delivery_events.iter().any(|delivery| {
+        delivery
+            .patience_left
+            .map_or(false, |seconds| seconds < CLUTCH_PATIENCE)
+    });
+
+    if !clutch || buffer.frames.is_empty() || replays.iter().next().is_some() {
+        return;
+    }
+    info!("Clutch delivery!");
+
+    let character_scale = Vec3::new(CHARACTER_SCALE, CHARACTER_SCALE, 1.0);
+
+    commands
+        .spawn()
+        .insert(InstantReplay {
+            record: buffer.clone(),
+            timer: Timer::from_seconds(REPLAY_DURATION, false),
+        })
+        .insert(UiObject)
+        .insert(Position(Vec3::new(PIP_POSITION.0, PIP_POSITION.1, 0.0)))
+        .insert_bundle(SpriteBundle {
+            material: materials.background_sprite.clone(),
+            transform: Transform::from_scale(Vec3::new(PIP_SCALE, PIP_SCALE, 1.0)),
+            ..SpriteBundle::default()
+        })
+        .with_children(|parent| {
+            // The banner keeps its size despite the scaled background
+            let unscaled = 1.0 / PIP_SCALE;
+
+            parent
+                .spawn()
+                .insert(ReplayActor::Baobei)
+                .insert_bundle(SpriteBundle {
+                    material: materials.baobei_sprite.clone(),
+                    transform: Transform {
+                        scale: character_scale,
+                        ..Transform::default()
+                    },
+                    ..SpriteBundle::default()
+                });
+            parent
+                .spawn()
+                .insert(ReplayActor::Didi)
+                .insert_bundle(SpriteBundle {
+                    material: materials.didi_sprite.clone(),
+                    transform: Transform {
+                        scale: character_scale,
+                        ..Transform::default()
+                    },
+                    ..SpriteBundle::default()
+                })
+                .with_children(|didi| {
+                    didi.spawn()
+                        .insert(ReplayCarriedItem)
+                        .insert_bundle(SpriteBundle {
+                            transform: Transform::from_translation(
+                                PICKED_ITEM_TRANSLATION + Vec3::new(0.0, 0.0, 0.1),
+                            ),
+                            ..SpriteBundle::default()
+                        });
+                });
+            parent.spawn().insert_bundle(Text2dBundle {
+                text: Text::with_section(
+                    "Clutch!",
+                    TextStyle {
+                        font: asset_server.load("FiraSans-Bold.ttf"),
+                        font_size: 30.0,
+                        color: Color::GOLD,
+                    },
+                    TextAlignment {
+                        vertical: VerticalAlign::Center,
+                        horizontal: HorizontalAlign::Center,
+                    },
+                ),
+                transform: Transform {
+                    translation: Vec3::new(0.0, 70.0 * unscaled, 0.5),
+                    scale: Vec3::splat(unscaled),
+                    ..Transform::default()
+                },
+                ..Text2dBundle::default()
+            });
+        });
+}
+
+/// Moves the replayed characters and stops the replay at its end or when
+/// the player presses a key.
+fn play_replay_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    keyboard: Res<Input<KeyCode>>,
+    materials: Res<GameplayMaterials>,
+    mut replays: Query<(Entity, &mut InstantReplay)>,
+    mut actors: Query<(&ReplayActor, &mut Transform)>,
+    mut carried_items: Query<(&mut Handle<ColorMaterial>, &mut Visible), With<ReplayCarriedItem>>,
+) {
+    for (entity, mut replay) in replays.iter_mut() {
+        let skipped = keyboard.get_just_pressed().next().is_some();
+        let finished = replay.timer.tick(time.delta()).finished();
+
+        let frame = match replay.record.frame_at(replay.timer.elapsed_secs()) {
+            Some(frame) if !skipped && !finished => frame,
+            _ => {
+                commands.entity(entity).despawn_recursive();
+                continue;
+            }
+        };
+
+        for (actor, mut transform) in actors.iter_mut() {
+            // Characters in the front are drawn above the ones in the back
+            let (position, z) = match actor {
+                ReplayActor::Didi => (frame.didi, 0.3),
+                ReplayActor::Baobei => (frame.baobei, 0.2),
+            };
+            transform.translation = pip_translation(position, z);
+        }
+        for (mut material, mut visible) in carried_items.iter_mut() {
+            match frame.carried {
+                Some(item) => {
+                    *material = materials.item_sprite_for(item);
+                    visible.is_visible = true;
+                }
+                None => visible.is_visible = false,
+            }
+        }
+    }
+}
+
+/// Removes the replay when leaving the game.
+fn stop_replay_system(mut commands: Commands, replays: Query<Entity, With<InstantReplay>>) {
+    for replay in replays.iter() {
+        commands.entity(replay).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec2;
+
+    use super::{ReplayBuffer, REPLAY_DURATION};
+
+    #[test]
+    fn test_rolling_replay_buffer() {
+        let mut buffer = ReplayBuffer::default();
+
+        for frame in 0..100 {
+            let didi = Vec2::new(frame as f32, 0.0);
+            buffer.record(0.1, didi, Vec2::ZERO, None);
+        }
+
+        // Only the last seconds are kept
+        let oldest = buffer.frame_at(0.0).unwrap();
+        assert!(buffer.clock - oldest.time <= REPLAY_DURATION);
+        assert!(oldest.didi.x >= 69.0);
+
+        let later = buffer.frame_at(1.0).unwrap();
+        assert!((later.didi.x - oldest.didi.x - 10.0).abs() <= 1.0);
+    }
+}
@@ -0,0 +1,256 @@
+//! Pre-rolled requests of Baobei and the order ticket showing the upcoming
+//! ones, bought in the shop and extended with affection.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{collisions::Position, constants::PRECOGNITION_UNLOCK, drawing::UiObject};
+
+use super::{
+    affection::Affection,
+    items::Item,
+    registry::{ItemRegistry, ItemSprites},
+    shop::{Upgrade, UpgradeRegistry},
+    Baobei,
+};
+
+/// Plugin managing the order ticket.
+pub struct RequestsPlugin;
+
+impl Plugin for RequestsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<TicketMaterials>()
+            .add_startup_system(spawn_order_ticket.system())
+            .add_system(update_order_ticket_system.system());
+    }
+}
+
+/// Number of requests rolled in advance.
+const QUEUE_LENGTH: usize = 2;
+
+/// Items that can be requested, with their weights.
+pub type RequestTable = &'static [(Item, u32)];
+
+/// Component on an asker whose next requests are rolled in advance, so they
+/// can be shown before being asked.
+pub struct RequestQueue {
+    /// The next requests, the first one being asked next.
+    upcoming: VecDeque<Item>,
+    /// Items requested, any item of the registry if none.
+    table: Option<RequestTable>,
+}
+
+impl RequestQueue {
+    /// Rolls the requests following the current one.
+    pub fn new<R: Rng + ?Sized>(registry: &ItemRegistry, rng: &mut R, current: Item) -> Self {
+        let mut queue = Self {
+            upcoming: VecDeque::with_capacity(QUEUE_LENGTH),
+            table: None,
+        };
+        queue.refill(registry, rng, current);
+        queue
+    }
+
+    /// Returns the next request and rolls a new one at the end of the queue.
+    pub fn next<R: Rng + ?Sized>(
+        &mut self,
+        registry: &ItemRegistry,
+        rng: &mut R,
+        current: Item,
+    ) -> Item {
+        let next = self
+            .upcoming
+            .pop_front()
+            .unwrap_or_else(|| registry.random_different(rng, current));
+
+        self.refill(registry, rng, next);
+        next
+    }
+
+    /// Changes the items requested and rolls the upcoming requests again.
+    pub fn set_table<R: Rng + ?Sized>(
+        &mut self,
+        registry: &ItemRegistry,
+        rng: &mut R,
+        current: Item,
+        table: Option<RequestTable>,
+    ) {
+        self.table = table;
+        self.upcoming.clear();
+        self.refill(registry, rng, current);
+    }
+
+    /// Returns the upcoming requests, from the next one.
+    pub fn upcoming(&self) -> impl Iterator<Item = Item> + '_ {
+        self.upcoming.iter().copied()
+    }
+
+    /// Rolls requests until the queue is full, two following requests being
+    /// always different.
+    fn refill<R: Rng + ?Sized>(&mut self, registry: &ItemRegistry, rng: &mut R, current: Item) {
+        while self.upcoming.len() < QUEUE_LENGTH {
+            let last = self.upcoming.back().copied().unwrap_or(current);
+            let next = match self.table {
+                Some(table) => roll_in_table(rng, table, last),
+                None => registry.random_different(rng, last),
+            };
+            self.upcoming.push_back(next);
+        }
+    }
+}
+
+/// Returns a random item of the table different than the given one, if the
+/// table has another item.
+fn roll_in_table<R: Rng + ?Sized>(rng: &mut R, table: RequestTable, last: Item) -> Item {
+    let others: Vec<(Item, u32)> = table
+        .iter()
+        .copied()
+        .filter(|(item, _)| *item != last)
+        .collect();
+
+    others
+        .choose_weighted(rng, |(_, weight)| *weight)
+        .map_or(last, |(item, _)| *item)
+}
+
+/// Faded sprites of the items on the order ticket.
+struct TicketMaterials {
+    /// Color of the paper
+    paper: Handle<ColorMaterial>,
+    /// Faded items
+    items: ItemSprites,
+}
+
+impl FromWorld for TicketMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let items = ItemSprites::load(world, Color::rgba(1.0, 1.0, 1.0, 0.5));
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            paper: materials.add(Color::rgba(0.95, 0.93, 0.85, 0.6).into()),
+            items,
+        }
+    }
+}
+
+impl TicketMaterials {
+    /// Returns the faded sprite of the item.
+    fn item_sprite_for(&self, item: Item) -> Handle<ColorMaterial> {
+        self.items.get(item)
+    }
+}
+
+/// Component on the paper of the order ticket.
+struct OrderTicket;
+/// Component on an upcoming request of the ticket, with its rank.
+struct TicketSlot(usize);
+
+/// Spawns the hidden order ticket above the happiness smiley.
+fn spawn_order_ticket(mut commands: Commands, materials: Res<TicketMaterials>) {
+    let hidden = Visible {
+        is_visible: false,
+        is_transparent: true,
+    };
+
+    commands
+        .spawn()
+        .insert(OrderTicket)
+        .insert(UiObject)
+        .insert(Position(Vec3::new(1125.0, 420.0, 0.0)))
+        .insert_bundle(SpriteBundle {
+            material: materials.paper.clone(),
+            sprite: Sprite::new(Vec2::new(120.0, 70.0)),
+            visible: hidden.clone(),
+            ..SpriteBundle::default()
+        })
+        .with_children(|parent| {
+            for rank in 0..QUEUE_LENGTH {
+                let x = (rank as f32 - 0.5) * 55.0;
+
+                parent
+                    .spawn()
+                    .insert(TicketSlot(rank))
+                    .insert_bundle(SpriteBundle {
+                        transform: Transform {
+                            translation: Vec3::new(x, 0.0, 0.1),
+                            scale: Vec3::new(0.2, 0.2, 0.0),
+                            ..Transform::default()
+                        },
+                        visible: hidden.clone(),
+                        ..SpriteBundle::default()
+                    });
+            }
+        });
+}
+
+/// Shows the next request of Baobei once the order ticket is bought in the
+/// shop, and the one after with enough affection.
+fn update_order_ticket_system(
+    affection: Res<Affection>,
+    upgrades: Res<UpgradeRegistry>,
+    materials: Res<TicketMaterials>,
+    queues: Query<&RequestQueue, With<Baobei>>,
+    mut tickets: Query<&mut Visible, With<OrderTicket>>,
+    mut slots: Query<(&TicketSlot, &mut Handle<ColorMaterial>, &mut Visible), Without<OrderTicket>>,
+) {
+    let unlocked = match (
+        upgrades.has(Upgrade::Precognition),
+        affection.points >= PRECOGNITION_UNLOCK,
+    ) {
+        (false, _) => 0,
+        (true, false) => 1,
+        (true, true) => 2,
+    };
+    let upcoming: Vec<Item> = queues
+        .iter()
+        .next()
+        .map(|queue| queue.upcoming().take(unlocked).collect())
+        .unwrap_or_default();
+
+    for mut visible in tickets.iter_mut() {
+        if visible.is_visible != !upcoming.is_empty() {
+            visible.is_visible = !upcoming.is_empty();
+        }
+    }
+    for (slot, mut material, mut visible) in slots.iter_mut() {
+        match upcoming.get(slot.0) {
+            Some(item) => {
+                let item_material = materials.item_sprite_for(*item);
+                if *material != item_material {
+                    *material = item_material;
+                }
+                if !visible.is_visible {
+                    visible.is_visible = true;
+                }
+            }
+            None if visible.is_visible => visible.is_visible = false,
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{prelude::StdRng, SeedableRng};
+
+    use super::{Item, ItemRegistry, RequestQueue};
+
+    #[test]
+    fn test_pre_rolled_requests() {
+        let registry = ItemRegistry::default();
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut queue = RequestQueue::new(&registry, &mut rng, Item::Chips);
+        let mut current = Item::Chips;
+
+        for _ in 0..20 {
+            let announced: Vec<Item> = queue.upcoming().collect();
+            let next = queue.next(&registry, &mut rng, current);
+
+            assert_eq!(next, announced[0]);
+            assert_ne!(next, current);
+            current = next;
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! Baobei does not stay on the couch all day: now and then Baobei walks to
+//! another place of the apartment, around the furniture, and Didi has to find
+//! where before delivering the items.
+
+use bevy::prelude::*;
+use rand::seq::SliceRandom;
+
+use crate::{
+    collisions::{CollisionSystems, Movement, NavGrid, Position},
+    constants::{GameState, BAOBEI_RELOCATION_INTERVAL, BAOBEI_SPEED},
+    rng::GameRng,
+    scheduler::{ScheduledEvent, Scheduler, SchedulerSystems},
+    time_scale::TimeScale,
+};
+
+use super::NewRunAppExt;
+
+/// Plugin moving Baobei between the places of the apartment.
+pub struct RoamingPlugin;
+
+impl Plugin for RoamingPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_startup_system(schedule_roaming.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(
+                        relocate_system
+                            .system()
+                            .label("relocate")
+                            .after(SchedulerSystems),
+                    )
+                    .with_system(
+                        walk_system
+                            .system()
+                            .after("relocate")
+                            .before(CollisionSystems),
+                    ),
+            )
+            .add_new_run_system(reset_roamers_system);
+    }
+}
+
+/// Scheduled task moving a Baobei to another place.
+const ROAM_TASK: &str = "baobei_roam";
+/// Distance under which a Baobei reached its target.
+const ARRIVAL_DISTANCE: f32 = 1.0;
+/// Places where a Baobei settles, with the height it sits at.
+const ANCHORS: [(f32, f32, f32); 3] = [
+    (1050.0, 150.0, 85.0), // On the couch
+    (300.0, 200.0, 85.0),  // At the table
+    (720.0, 440.0, 0.0),   // In front of the fridge
+];
+
+/// Component on a Baobei walking between the anchors.
+pub struct Roamer {
+    /// Where the Baobei sits when a game starts
+    home: Vec3,
+    /// Where the Baobei walks to, or sits once reached
+    target: Vec3,
+    /// Waypoints around the furniture to the target, the next one last
+    path: Vec<Vec3>,
+}
+
+impl Roamer {
+    /// Returns a roamer sitting at its home.
+    pub const fn new(home: Vec3) -> Self {
+        Self {
+            home,
+            target: home,
+            path: Vec::new(),
+        }
+    }
+
+    /// Sends the roamer to the target along the path found in the grid.
+    fn walk_to(&mut self, target: Vec3, position: Vec3, nav_grid: &NavGrid) {
+        self.target = target;
+        self.path = nav_grid.find_path(position, target);
+        self.path.reverse();
+    }
+
+    /// Returns the movement toward the next waypoint for the given seconds,
+    /// never going past it, and forgets the waypoints reached.
+    fn step(&mut self, position: Vec3, seconds: f32) -> Vec3 {
+        while self.path.last().map_or(false, |waypoint| {
+            waypoint.distance(position) < ARRIVAL_DISTANCE
+        }) {
+            self.path.pop();
+        }
+        let next = self.path.last().copied().unwrap_or(self.target);
+
+        let to_target = next - position;
+        let distance = to_target.length();
+        if distance < ARRIVAL_DISTANCE {
+            return to_target;
+        }
+        to_target / distance * (BAOBEI_SPEED * seconds).min(distance)
+    }
+}
+
+/// Schedules the moves of Baobei.
+fn schedule_roaming(mut scheduler: ResMut<Scheduler>) {
+    scheduler.every(ROAM_TASK, BAOBEI_RELOCATION_INTERVAL);
+}
+
+/// Sends a sitting Baobei to an anchor no other Baobei sits at or walks to.
+fn relocate_system(
+    mut rng: ResMut<GameRng>,
+    nav_grid: Res<NavGrid>,
+    mut scheduled_events: EventReader<ScheduledEvent>,
+    mut roamers: Query<(&mut Roamer, &Position)>,
+) {
+    let due = scheduled_events
+        .iter()
+        .any(|ScheduledEvent(task)| *task == ROAM_TASK);
+
+    if !due {
+        return;
+    }
+    let taken: Vec<Vec3> = roamers.iter().map(|(roamer, _)| roamer.target).collect();
+    let free_anchors: Vec<Vec3> = ANCHORS
+        .iter()
+        .map(|&(x, y, z)| Vec3::new(x, y, z))
+        .filter(|anchor| !taken.contains(anchor))
+        .collect();
+    let mut sitting: Vec<(Mut<Roamer>, &Position)> = roamers
+        .iter_mut()
+        .filter(|(roamer, position)| roamer.target.distance(position.0) < ARRIVAL_DISTANCE)
+        .collect();
+
+    if let Some(anchor) = free_anchors.choose(&mut rng.rng) {
+        if let Some((roamer, position)) = sitting.choose_mut(&mut rng.rng) {
+            info!("Baobei walks to {}", anchor);
+            roamer.walk_to(*anchor, position.0, &nav_grid);
+        }
+    }
+}
+
+/// Walks the Baobeis toward their target, waypoint by waypoint.
+fn walk_system(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut roamers: Query<(&mut Roamer, &Position, &mut Movement)>,
+) {
+    let seconds = time_scale.scale(time.delta()).as_secs_f32();
+
+    for (mut roamer, position, mut movement) in roamers.iter_mut() {
+        if roamer.target != position.0 {
+            movement.0 = roamer.step(position.0, seconds);
+        }
+    }
+}
+
+/// Brings the Baobeis back home when a new game starts.
+fn reset_roamers_system(mut roamers: Query<(&mut Roamer, &mut Position)>) {
+    for (mut roamer, mut position) in roamers.iter_mut() {
+        roamer.target = roamer.home;
+        roamer.path.clear();
+        position.0 = roamer.home;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steps_stop_at_the_target() {
+        let mut roamer = Roamer::new(Vec3::ZERO);
+        roamer.target = Vec3::new(BAOBEI_SPEED * 1.5, 0.0, 0.0);
+
+        let step = roamer.step(Vec3::ZERO, 1.0);
+        assert!((step.x - BAOBEI_SPEED).abs() < f32::EPSILON);
+
+        let last_step = roamer.step(step, 1.0);
+        assert!((step + last_step).distance(roamer.target) < f32::EPSILON);
+    }
+}
@@ -0,0 +1,332 @@
+//! Daily routine of the story mode: the day goes from the morning to the
+//! evening, each moment with its own requests, and the alarm clock rings a
+//! new day after the evening.
+
+use bevy::prelude::*;
+
+use crate::{
+    constants::{GameMode, GameState, ROUTINE_PERIOD_DURATION},
+    locale::Language,
+    rng::GameRng,
+    scheduler::{ScheduledEvent, Scheduler, SchedulerSystems},
+    settings::Settings,
+};
+
+use super::{
+    bubbles::SayEvent,
+    items::{Item, ItemRequestQueue},
+    registry::ItemRegistry,
+    requests::{RequestQueue, RequestTable},
+    Baobei,
+};
+
+/// Plugin managing the daily routine.
+pub struct RoutinePlugin;
+
+impl Plugin for RoutinePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Routine>()
+            .add_startup_system(spawn_clock_text.system())
+            .add_system_set(
+                SystemSet::on_enter(GameState::InGame).with_system(start_routine_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(routine_system.system().after(SchedulerSystems))
+                    .with_system(update_clock_text_system.system()),
+            );
+    }
+}
+
+/// Name of the scheduled task ending the current moment of the day.
+const ROUTINE_TASK: &str = "routine";
+
+/// Moment of the day in the story mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeOfDay {
+    /// Breakfast time
+    Morning,
+    /// Time for the chores
+    Afternoon,
+    /// Time to relax
+    Evening,
+}
+
+impl TimeOfDay {
+    /// Returns the moment following this one, the morning after the evening.
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Morning => Self::Afternoon,
+            Self::Afternoon => Self::Evening,
+            Self::Evening => Self::Morning,
+        }
+    }
+
+    /// Returns the displayed name of the moment.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Morning => "Morning",
+            Self::Afternoon => "Afternoon",
+            Self::Evening => "Evening",
+        }
+    }
+
+    /// Returns the items requested during the moment, with their weights.
+    pub const fn request_table(self) -> RequestTable {
+        match self {
+            Self::Morning => &[(Item::Coffee, 3), (Item::WaterGlass, 2), (Item::Chips, 1)],
+            Self::Afternoon => &[(Item::WaterGlass, 3), (Item::Chips, 2), (Item::IceCream, 1)],
+            Self::Evening => &[
+                (Item::IceCream, 3),
+                (Item::Chips, 3),
+                (Item::WaterGlass, 1),
+                (Item::HotTea, 1),
+            ],
+        }
+    }
+
+    /// Returns the hours of the clock at the start and at the end of the
+    /// moment.
+    const fn hours(self) -> (f32, f32) {
+        match self {
+            Self::Morning => (7.0, 12.0),
+            Self::Afternoon => (12.0, 18.0),
+            Self::Evening => (18.0, 22.0),
+        }
+    }
+
+    /// Returns what Baobei says when the moment starts.
+    const fn line(self, language: Language) -> &'static str {
+        match (language, self) {
+            (Language::English, Self::Morning) => "Beep beep! Time for breakfast…",
+            (Language::English, Self::Afternoon) => "So many chores this afternoon…",
+            (Language::English, Self::Evening) => "Finally, time to relax!",
+            (Language::French, Self::Morning) => "Bip bip ! C'est l'heure du petit-déj…",
+            (Language::French, Self::Afternoon) => "Tant de corvées cet après-midi…",
+            (Language::French, Self::Evening) => "Enfin, on se détend !",
+        }
+    }
+}
+
+/// State of the routine of the story mode.
+pub struct Routine {
+    /// Whether a story is being played.
+    active: bool,
+    /// Current moment of the day.
+    pub time_of_day: TimeOfDay,
+    /// Number of the day, starting at 1.
+    pub day: u32,
+}
+
+impl Default for Routine {
+    fn default() -> Self {
+        Self {
+            active: false,
+            time_of_day: TimeOfDay::Morning,
+            day: 1,
+        }
+    }
+}
+
+impl Routine {
+    /// Returns the hour shown by the clock when a story is being played.
+    pub fn hour(&self, scheduler: &Scheduler) -> Option<f32> {
+        if !self.active {
+            return None;
+        }
+        let progress = scheduler.progress(ROUTINE_TASK).unwrap_or_default();
+        Some(hour_of_day(self.time_of_day, progress))
+    }
+
+    /// Goes to the next moment of the day, and to the next day after the
+    /// evening.
+    fn advance(&mut self) {
+        self.time_of_day = self.time_of_day.next();
+        if self.time_of_day == TimeOfDay::Morning {
+            self.day += 1;
+        }
+    }
+}
+
+/// Tag the text displaying the clock of the story mode.
+struct ClockText;
+
+/// Returns the hour of the clock after the elapsed fraction of the moment of
+/// the day.
+fn hour_of_day(time_of_day: TimeOfDay, progress: f32) -> f32 {
+    let (start, end) = time_of_day.hours();
+    (end - start).mul_add(progress.clamp(0.0, 1.0), start)
+}
+
+/// Returns the time shown by the clock after the elapsed fraction of the
+/// moment of the day.
+// The hours are positive and below 24
+#[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+fn clock_time(time_of_day: TimeOfDay, progress: f32) -> String {
+    let minutes = (hour_of_day(time_of_day, progress) * 60.0) as u32;
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// Starts the first morning when a game starts in the story mode.
+#[allow(clippy::too_many_arguments)]
+fn start_routine_system(
+    mode: Res<GameMode>,
+    settings: Res<Settings>,
+    registry: Res<ItemRegistry>,
+    mut routine: ResMut<Routine>,
+    mut scheduler: ResMut<Scheduler>,
+    mut rng: ResMut<GameRng>,
+    mut say_events: EventWriter<SayEvent>,
+    mut baobei: Query<(Entity, &ItemRequestQueue, &mut RequestQueue), With<Baobei>>,
+) {
+    *routine = Routine {
+        active: *mode == GameMode::Story,
+        ..Routine::default()
+    };
+    if !routine.active {
+        scheduler.cancel(ROUTINE_TASK);
+        for (_, requests, mut queue) in baobei.iter_mut() {
+            if let Some(last) = requests.0.back() {
+                queue.set_table(&registry, &mut rng.rng, *last, None);
+            }
+        }
+        return;
+    }
+    scheduler.once(ROUTINE_TASK, ROUTINE_PERIOD_DURATION);
+    start_time_of_day(
+        &routine,
+        &settings,
+        &registry,
+        &mut rng,
+        &mut say_events,
+        &mut baobei,
+    );
+}
+
+/// Goes to the next moment of the day when the current one ends.
+#[allow(clippy::too_many_arguments)]
+fn routine_system(
+    settings: Res<Settings>,
+    registry: Res<ItemRegistry>,
+    mut routine: ResMut<Routine>,
+    mut scheduler: ResMut<Scheduler>,
+    mut rng: ResMut<GameRng>,
+    mut scheduled_events: EventReader<ScheduledEvent>,
+    mut say_events: EventWriter<SayEvent>,
+    mut baobei: Query<(Entity, &ItemRequestQueue, &mut RequestQueue), With<Baobei>>,
+) {
+    let ended = scheduled_events
+        .iter()
+        .any(|ScheduledEvent(task)| *task == ROUTINE_TASK);
+    if !routine.active || !ended {
+        return;
+    }
+    routine.advance();
+    info!("Day {}: {}", routine.day, routine.time_of_day.name());
+
+    scheduler.once(ROUTINE_TASK, ROUTINE_PERIOD_DURATION);
+    start_time_of_day(
+        &routine,
+        &settings,
+        &registry,
+        &mut rng,
+        &mut say_events,
+        &mut baobei,
+    );
+}
+
+/// Rolls the requests of the moment of the day and makes Baobei announce it.
+fn start_time_of_day(
+    routine: &Routine,
+    settings: &Settings,
+    registry: &ItemRegistry,
+    rng: &mut GameRng,
+    say_events: &mut EventWriter<SayEvent>,
+    baobei: &mut Query<(Entity, &ItemRequestQueue, &mut RequestQueue), With<Baobei>>,
+) {
+    let table = routine.time_of_day.request_table();
+
+    for (speaker, requests, mut queue) in baobei.iter_mut() {
+        if let Some(last) = requests.0.back() {
+            queue.set_table(registry, &mut rng.rng, *last, Some(table));
+        }
+        say_events.send(SayEvent {
+            speaker,
+            line: routine.time_of_day.line(settings.language),
+        });
+    }
+}
+
+/// Spawns the text showing the clock at the top of the screen.
+fn spawn_clock_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn()
+        .insert(ClockText)
+        .insert_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(20.0),
+                    left: Val::Px(520.0),
+                    ..Rect::default()
+                },
+                ..Style::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("FiraSans-Bold.ttf"),
+                    font_size: 40.0,
+                    color: Color::WHITE,
+                },
+                TextAlignment::default(),
+            ),
+            ..TextBundle::default()
+        });
+}
+
+/// Updates the clock, empty outside of the story mode.
+fn update_clock_text_system(
+    routine: Res<Routine>,
+    scheduler: Res<Scheduler>,
+    mut texts: Query<&mut Text, With<ClockText>>,
+) {
+    let clock = if routine.active {
+        let progress = scheduler.progress(ROUTINE_TASK).unwrap_or_default();
+        format!(
+            "Day {} - {} {}",
+            routine.day,
+            routine.time_of_day.name(),
+            clock_time(routine.time_of_day, progress)
+        )
+    } else {
+        String::new()
+    };
+
+    for mut text in texts.iter_mut() {
+        if text.sections[0].value != clock {
+            text.sections[0].value = clock.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routine_goes_through_the_days() {
+        let mut routine = Routine::default();
+
+        routine.advance();
+        assert_eq!(routine.time_of_day, TimeOfDay::Afternoon);
+        routine.advance();
+        routine.advance();
+        assert_eq!(routine.time_of_day, TimeOfDay::Morning);
+        assert_eq!(routine.day, 2);
+
+        assert_eq!(clock_time(TimeOfDay::Morning, 0.0), "07:00");
+        assert_eq!(clock_time(TimeOfDay::Afternoon, 0.5), "15:00");
+        assert_eq!(clock_time(TimeOfDay::Evening, 1.0), "22:00");
+    }
+}
@@ -0,0 +1,410 @@
+//! Sandbox mode, started with `S` in the menu: the game phase is pushed on
+//! top of the sandbox, without decay nor scheduled tasks. The player spawns
+//! any item or furnishing from a palette, moves them around and tries the
+//! interactions freely. Nothing is saved and the placements are not checked.
+
+use bevy::prelude::*;
+
+use crate::{
+    collisions::{BoxCollider, CollisionSystems, Position},
+    constants::{GameMode, GameState},
+    scheduler::Scheduler,
+};
+
+use super::{
+    decorate::{spawn_furnishing, Furnishing, FurnishingMaterials, PlacedFurnishing},
+    energy::Energy,
+    items::{spawn_item_on_ground, Item},
+    materials::GameplayMaterials,
+    registry::ItemRegistry,
+    Didi,
+};
+
+/// Plugin managing the sandbox mode.
+pub struct SandboxPlugin;
+
+impl Plugin for SandboxPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<SandboxPalette>()
+            .add_system_set(
+                SystemSet::on_update(GameState::Menu).with_system(start_sandbox_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Sandbox).with_system(setup_sandbox.system()),
+            )
+            .add_system_set(
+                SystemSet::on_in_stack_update(GameState::Sandbox)
+                    .with_system(palette_system.system().label("sandbox_palette"))
+                    .with_system(palette_text_system.system().after("sandbox_palette"))
+                    .with_system(spawn_system.system().after("sandbox_palette"))
+                    .with_system(grab_system.system().label("sandbox_grab"))
+                    .with_system(
+                        carry_system
+                            .system()
+                            .after("sandbox_grab")
+                            .after(CollisionSystems),
+                    )
+                    .with_system(remove_system.system())
+                    .with_system(rest_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Sandbox).with_system(cleanup_sandbox.system()),
+            );
+    }
+}
+
+/// Distance from Didi where the palette entries are spawned.
+const SPAWN_OFFSET: Vec3 = Vec3::new(100.0, 0.0, 0.0);
+/// Distance under which Didi can grab or remove a spawned entity.
+const REACH_DISTANCE: f32 = 120.0;
+
+/// Something the player can spawn from the palette.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PaletteEntry {
+    /// An item of the registry, lying on the ground
+    Item(Item),
+    /// A furnishing of the decorate catalog
+    Furnishing(Furnishing),
+}
+
+/// Entries of the palette and the selected one.
+#[derive(Default)]
+struct SandboxPalette {
+    /// Every item of the registry, then every furnishing
+    entries: Vec<PaletteEntry>,
+    /// Index of the selected entry
+    selected: usize,
+}
+
+impl SandboxPalette {
+    /// Returns the palette of the items of the registry and the furnishings.
+    fn new(registry: &ItemRegistry) -> Self {
+        let items = registry
+            .definitions()
+            .map(|def| PaletteEntry::Item(def.item));
+        let furnishings = Furnishing::ALL
+            .iter()
+            .copied()
+            .map(PaletteEntry::Furnishing);
+
+        Self {
+            entries: items.chain(furnishings).collect(),
+            selected: 0,
+        }
+    }
+
+    /// Selects the next entry, or the previous one if `forward` is false,
+    /// wrapping around the palette.
+    fn cycle(&mut self, forward: bool) {
+        let count = self.entries.len();
+        if count == 0 {
+            return;
+        }
+        self.selected = if forward {
+            (self.selected + 1) % count
+        } else {
+            (self.selected + count - 1) % count
+        };
+    }
+
+    /// Returns the selected entry.
+    fn selected(&self) -> Option<PaletteEntry> {
+        self.entries.get(self.selected).copied()
+    }
+}
+
+/// Stores entities and settings of the sandbox.
+struct SandboxData {
+    /// Entity wrapping the palette and the controls
+    node_wrapper: Entity,
+    /// Mode chosen in the menu, restored when leaving the sandbox
+    mode: GameMode,
+}
+
+/// Tag the text listing the palette.
+struct PaletteText;
+
+/// Component on the entities spawned in the sandbox, removed when leaving it.
+struct SandboxObject;
+
+/// Component on the spawned entity Didi moves around.
+struct Grabbed;
+
+/// Opens the sandbox when the player presses `S` in the menu.
+fn start_sandbox_system(keyboard_input: Res<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
+    if keyboard_input.just_pressed(KeyCode::S) {
+        state.set(GameState::Sandbox).unwrap();
+    }
+}
+
+/// Builds the palette and suspends the scheduled tasks, then starts an
+/// endless game on top of the sandbox.
+#[allow(clippy::too_many_arguments)]
+fn setup_sandbox(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    registry: Res<ItemRegistry>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+    mut palette: ResMut<SandboxPalette>,
+    mut scheduler: ResMut<Scheduler>,
+    mut mode: ResMut<GameMode>,
+    mut state: ResMut<State<GameState>>,
+) {
+    *palette = SandboxPalette::new(&registry);
+    scheduler.set_suspended(true);
+
+    let font = asset_server.load("FiraSans-Bold.ttf");
+    let node_wrapper = commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                padding: Rect::all(Val::Px(20.0)),
+                justify_content: JustifyContent::SpaceBetween,
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Style::default()
+            },
+            material: color_materials.add(Color::NONE.into()),
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn()
+                .insert(PaletteText)
+                .insert_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 24.0,
+                            color: Color::WHITE,
+                        },
+                        TextAlignment::default(),
+                    ),
+                    ..TextBundle::default()
+                });
+            parent.spawn().insert_bundle(TextBundle {
+                text: Text::with_section(
+                    "Q/E: choose, Return: spawn, G: grab or release, X: remove, Escape: leave",
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 24.0,
+                        color: Color::WHITE,
+                    },
+                    TextAlignment::default(),
+                ),
+                ..TextBundle::default()
+            });
+        })
+        .id();
+
+    commands.insert_resource(SandboxData {
+        node_wrapper,
+        mode: *mode,
+    });
+    // The countdown of the survival and the routine of the story are timers
+    *mode = GameMode::Endless;
+    state.push(GameState::InGame).unwrap();
+}
+
+/// Selects another palette entry when the player presses `Q` or `E`.
+fn palette_system(keyboard_input: Res<Input<KeyCode>>, mut palette: ResMut<SandboxPalette>) {
+    if keyboard_input.just_pressed(KeyCode::Q) {
+        palette.cycle(false);
+    }
+    if keyboard_input.just_pressed(KeyCode::E) {
+        palette.cycle(true);
+    }
+}
+
+/// Lists the palette entries, marking the selected one.
+fn palette_text_system(
+    palette: Res<SandboxPalette>,
+    registry: Res<ItemRegistry>,
+    mut texts: Query<&mut Text, With<PaletteText>>,
+) {
+    if !palette.is_changed() {
+        return;
+    }
+    let lines: Vec<String> = palette
+        .entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            let marker = if index == palette.selected { ">" } else { " " };
+            let name = match entry {
+                PaletteEntry::Item(item) => registry.name(*item),
+                PaletteEntry::Furnishing(furnishing) => furnishing.name(),
+            };
+            format!("{} {}", marker, name)
+        })
+        .collect();
+
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = lines.join("\n");
+    }
+}
+
+/// Spawns the selected entry next to Didi when the player presses `Return`.
+fn spawn_system(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    palette: Res<SandboxPalette>,
+    materials: Res<GameplayMaterials>,
+    furnishing_materials: Res<FurnishingMaterials>,
+    didi: Query<&Position, With<Didi>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::Return) {
+        return;
+    }
+    let (entry, didi_position) = match (palette.selected(), didi.iter().next()) {
+        (Some(entry), Some(position)) => (entry, position.0),
+        _ => return,
+    };
+    let position = Vec3::new(didi_position.x, didi_position.y, 0.0) + SPAWN_OFFSET;
+    let spawned = match entry {
+        PaletteEntry::Item(item) => spawn_item_on_ground(&mut commands, &materials, item, position),
+        PaletteEntry::Furnishing(furnishing) => spawn_furnishing(
+            &mut commands,
+            &furnishing_materials,
+            furnishing,
+            position.truncate(),
+        ),
+    };
+
+    info!("Sandbox spawns {:?} at {}", entry, position);
+    commands.entity(spawned).insert(SandboxObject);
+}
+
+/// Grabs the nearest spawned entity when the player presses `G`, or releases
+/// the grabbed one. A grabbed furnishing loses its collider until released.
+fn grab_system(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    didi: Query<&Position, With<Didi>>,
+    objects: Query<(Entity, &Position, Option<&PlacedFurnishing>), With<SandboxObject>>,
+    grabbed: Query<(Entity, Option<&PlacedFurnishing>), With<Grabbed>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::G) {
+        return;
+    }
+    if let Some((entity, placed)) = grabbed.iter().next() {
+        commands.entity(entity).remove::<Grabbed>();
+        if let Some(PlacedFurnishing(furnishing)) = placed {
+            if furnishing.is_obstacle() {
+                let size = furnishing.size();
+                commands
+                    .entity(entity)
+                    .insert(BoxCollider::new(size.x, size.y));
+            }
+        }
+        return;
+    }
+    let didi_position = match didi.iter().next() {
+        Some(position) => position.0,
+        None => return,
+    };
+    if let Some((entity, _, _)) = nearest_object(objects.iter(), didi_position) {
+        commands
+            .entity(entity)
+            .insert(Grabbed)
+            .remove::<BoxCollider>();
+    }
+}
+
+/// Moves the grabbed entity along with Didi.
+fn carry_system(
+    didi: Query<&Position, With<Didi>>,
+    mut grabbed: Query<&mut Position, (With<Grabbed>, Without<Didi>)>,
+) {
+    let didi_position = match didi.iter().next() {
+        Some(position) => position.0,
+        None => return,
+    };
+    let target = Vec3::new(didi_position.x, didi_position.y, 0.0) + SPAWN_OFFSET;
+
+    for mut position in grabbed.iter_mut() {
+        if position.0 != target {
+            position.0 = target;
+        }
+    }
+}
+
+/// Removes the nearest spawned entity when the player presses `X`.
+fn remove_system(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    didi: Query<&Position, With<Didi>>,
+    objects: Query<(Entity, &Position, Option<&PlacedFurnishing>), With<SandboxObject>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::X) {
+        return;
+    }
+    let didi_position = match didi.iter().next() {
+        Some(position) => position.0,
+        None => return,
+    };
+    if let Some((entity, _, _)) = nearest_object(objects.iter(), didi_position) {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Returns the spawned entity nearest to the position, within reach.
+fn nearest_object<'a, T>(
+    objects: impl Iterator<Item = (Entity, &'a Position, T)>,
+    position: Vec3,
+) -> Option<(Entity, &'a Position, T)> {
+    objects
+        .filter(|(_, object, _)| object.0.truncate().distance(position.truncate()) < REACH_DISTANCE)
+        .min_by(|(_, a, _), (_, b, _)| {
+            let distance_a = a.0.distance(position);
+            let distance_b = b.0.distance(position);
+            distance_a.partial_cmp(&distance_b).unwrap()
+        })
+}
+
+/// Keeps the characters rested, the energy not draining in the sandbox.
+fn rest_system(mut energies: Query<&mut Energy>) {
+    for mut energy in energies.iter_mut() {
+        *energy = Energy::full();
+    }
+}
+
+/// Removes the palette and the spawned entities, then resumes the scheduled
+/// tasks and the mode chosen in the menu.
+fn cleanup_sandbox(
+    mut commands: Commands,
+    sandbox_data: Res<SandboxData>,
+    mut scheduler: ResMut<Scheduler>,
+    mut mode: ResMut<GameMode>,
+    objects: Query<Entity, With<SandboxObject>>,
+) {
+    commands
+        .entity(sandbox_data.node_wrapper)
+        .despawn_recursive();
+    for object in objects.iter() {
+        commands.entity(object).despawn_recursive();
+    }
+    scheduler.set_suspended(false);
+    *mode = sandbox_data.mode;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette_wraps_around() {
+        let mut palette = SandboxPalette::new(&ItemRegistry::default());
+        let count = palette.entries.len();
+        assert_eq!(
+            palette.entries.last(),
+            Some(&PaletteEntry::Furnishing(Furnishing::Bookshelf))
+        );
+
+        palette.cycle(false);
+        assert_eq!(palette.selected, count - 1);
+        palette.cycle(true);
+        assert_eq!(palette.selected(), palette.entries.first().copied());
+    }
+}
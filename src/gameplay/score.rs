@@ -0,0 +1,102 @@
+//! Score of the player, rewarding the correct deliveries.
+
+use bevy::prelude::*;
+
+use crate::constants::{
+    BONUS_NOTE_POINTS, CHORE_POINTS, DEAD_PLANT_PENALTY, DELIVERY_POINTS, DISCARDED_ITEM_PENALTY,
+    IN_LAWS_POINTS, RETRIEVAL_POINTS, SPOILED_ITEM_PENALTY, WRONG_DELIVERY_PENALTY,
+};
+
+/// Points earned by the player during the game.
+#[derive(Default)]
+pub struct Score {
+    /// Current points, never negative.
+    points: u32,
+}
+
+impl Score {
+    /// Returns the current points.
+    pub const fn points(&self) -> u32 {
+        self.points
+    }
+
+    /// Adds the points of a correct delivery, boosted by the combo
+    /// multiplier.
+    // The multiplier is positive and the points stay small
+    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+    pub fn reward_delivery(&mut self, multiplier: f32) {
+        self.points += (DELIVERY_POINTS as f32 * multiplier).round() as u32;
+    }
+
+    /// Adds the points of a step of a chore.
+    pub fn reward_chore(&mut self) {
+        self.points += CHORE_POINTS;
+    }
+
+    /// Adds the points of the notes hit in a bonus round.
+    pub fn reward_bonus_notes(&mut self, hits: u32) {
+        self.points += BONUS_NOTE_POINTS * hits;
+    }
+
+    /// Adds the big bonus of a visit of the in-laws survived.
+    pub fn reward_in_laws(&mut self) {
+        self.points += IN_LAWS_POINTS;
+    }
+
+    /// Adds the bonus of a forgotten item found back, the fraction being
+    /// between 0 (too late) and 1 (right away).
+    // The fraction is clamped and the points stay small
+    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+    pub fn reward_retrieval(&mut self, fraction: f32) {
+        self.points += (RETRIEVAL_POINTS as f32 * fraction.clamp(0.0, 1.0)).round() as u32;
+    }
+
+    /// Removes the points of a wrong delivery, without going under zero.
+    pub fn penalize_wrong_delivery(&mut self) {
+        self.points = self.points.saturating_sub(WRONG_DELIVERY_PENALTY);
+    }
+
+    /// Removes the points of a spoiled item, without going under zero.
+    pub fn penalize_spoiled_item(&mut self) {
+        self.points = self.points.saturating_sub(SPOILED_ITEM_PENALTY);
+    }
+
+    /// Removes the points of an item thrown away, without going under zero.
+    pub fn penalize_discarded_item(&mut self) {
+        self.points = self.points.saturating_sub(DISCARDED_ITEM_PENALTY);
+    }
+
+    /// Removes the points of a plant dead of thirst, without going under
+    /// zero.
+    pub fn penalize_dead_plant(&mut self) {
+        self.points = self.points.saturating_sub(DEAD_PLANT_PENALTY);
+    }
+}
+
+/// Resets the score when a new game starts.
+pub fn reset_score_system(mut score: ResMut<Score>) {
+    *score = Score::default();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Score, DELIVERY_POINTS, WRONG_DELIVERY_PENALTY};
+
+    #[test]
+    fn test_score_never_negative() {
+        let mut score = Score::default();
+        score.penalize_wrong_delivery();
+        assert_eq!(score.points(), 0);
+
+        score.reward_delivery(1.0);
+        score.reward_delivery(1.0);
+        score.penalize_wrong_delivery();
+        assert_eq!(score.points(), 2 * DELIVERY_POINTS - WRONG_DELIVERY_PENALTY);
+
+        score.reward_delivery(1.5);
+        assert_eq!(
+            score.points(),
+            3 * DELIVERY_POINTS + DELIVERY_POINTS / 2 - WRONG_DELIVERY_PENALTY
+        );
+    }
+}
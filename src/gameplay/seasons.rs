@@ -0,0 +1,225 @@
+//! Themed content activated during seasonal events: decorations of the
+//! apartment and themed items Baobei asks for, added to the item registry.
+
+use bevy::prelude::*;
+
+use crate::{
+    calendar::{Date, SeasonalEvent},
+    collisions::Position,
+    constants::GameState,
+    settings::Settings,
+};
+
+use super::{happiness::Need, registry::ItemRegistry};
+
+/// Plugin toggling the content pack of the current seasonal event.
+pub struct SeasonsPlugin;
+
+impl Plugin for SeasonsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        // The active season is loaded before the item registry, with the
+        // gameplay resources
+        app.add_system_set(
+            SystemSet::on_enter(GameState::InGame)
+                .with_system(spawn_seasonal_decorations_system.system()),
+        );
+    }
+}
+
+/// The seasonal event active during this session, if any.
+pub struct ActiveSeason(pub Option<SeasonalEvent>);
+
+impl FromWorld for ActiveSeason {
+    fn from_world(world: &mut World) -> Self {
+        let settings = world.get_resource::<Settings>().unwrap();
+        let event = settings.season.resolve(Date::today());
+
+        if let Some(event) = event {
+            info!("Seasonal event: {}", event);
+        }
+        Self(event)
+    }
+}
+
+/// Chance of a themed item being requested, like the everyday items.
+const SEASONAL_ITEM_WEIGHT: u32 = 2;
+
+/// A themed item requested during a seasonal event.
+struct SeasonalItem {
+    /// Identifier in the item registry
+    id: &'static str,
+    /// Displayed name
+    name: &'static str,
+    /// Path of the sprite in the assets, tinted by the color
+    sprite: &'static str,
+    /// Color modulating the sprite
+    color: Color,
+    /// Need of Baobei satisfied on delivery
+    need: Need,
+}
+
+/// Themed content of a seasonal event.
+struct ContentPack {
+    /// Decorations of the apartment: position, size and color.
+    decorations: Vec<(Vec3, Vec2, Color)>,
+    /// Items Baobei asks for during the event.
+    items: Vec<SeasonalItem>,
+}
+
+/// Adds the themed items of the seasonal event to the registry, requested
+/// like the everyday items.
+pub fn add_seasonal_items(registry: &mut ItemRegistry, event: SeasonalEvent) {
+    for item in content_pack(event).items {
+        let definition = registry.define(item.id);
+        definition.name = item.name.to_string();
+        definition.sprite = item.sprite.to_string();
+        definition.color = item.color;
+        definition.need = item.need;
+        definition.weight = SEASONAL_ITEM_WEIGHT;
+    }
+}
+
+/// Returns the content pack of the given seasonal event.
+fn content_pack(event: SeasonalEvent) -> ContentPack {
+    let decorations = match event {
+        SeasonalEvent::LunarNewYear => vec![
+            // Red lanterns and couplets around the kitchen
+            (
+                Vec3::new(130.0, 650.0, 0.0),
+                Vec2::new(40.0, 50.0),
+                Color::RED,
+            ),
+            (
+                Vec3::new(470.0, 650.0, 0.0),
+                Vec2::new(40.0, 50.0),
+                Color::RED,
+            ),
+            (
+                Vec3::new(100.0, 600.0, 0.0),
+                Vec2::new(20.0, 110.0),
+                Color::CRIMSON,
+            ),
+            (
+                Vec3::new(500.0, 600.0, 0.0),
+                Vec2::new(20.0, 110.0),
+                Color::CRIMSON,
+            ),
+        ],
+        SeasonalEvent::Halloween => vec![
+            // Pumpkins next to the couch
+            (
+                Vec3::new(820.0, 110.0, 0.0),
+                Vec2::new(45.0, 35.0),
+                Color::ORANGE,
+            ),
+            (
+                Vec3::new(1180.0, 110.0, 0.0),
+                Vec2::new(35.0, 28.0),
+                Color::ORANGE,
+            ),
+        ],
+        SeasonalEvent::Christmas => vec![
+            // A tree with its star in the corner
+            (
+                Vec3::new(1180.0, 300.0, 0.0),
+                Vec2::new(60.0, 140.0),
+                Color::DARK_GREEN,
+            ),
+            (
+                Vec3::new(1180.0, 300.0, 80.0),
+                Vec2::new(20.0, 20.0),
+                Color::GOLD,
+            ),
+        ],
+    };
+    let items = match event {
+        SeasonalEvent::LunarNewYear => vec![
+            SeasonalItem {
+                id: "dumplings",
+                name: "Dumplings",
+                sprite: "items/chips.png",
+                color: Color::rgb(0.95, 0.9, 0.8),
+                need: Need::Hunger,
+            },
+            SeasonalItem {
+                id: "tangerine",
+                name: "Tangerine",
+                sprite: "items/ice_cream.png",
+                color: Color::ORANGE,
+                need: Need::Fun,
+            },
+        ],
+        SeasonalEvent::Halloween => vec![SeasonalItem {
+            id: "candy",
+            name: "Candy",
+            sprite: "items/chips.png",
+            color: Color::rgb(0.9, 0.4, 0.7),
+            need: Need::Fun,
+        }],
+        SeasonalEvent::Christmas => vec![
+            SeasonalItem {
+                id: "hot_chocolate",
+                name: "Hot chocolate",
+                sprite: "items/water_glass.png",
+                color: Color::rgb(0.45, 0.28, 0.15),
+                need: Need::Thirst,
+            },
+            SeasonalItem {
+                id: "gingerbread",
+                name: "Gingerbread",
+                sprite: "items/chips.png",
+                color: Color::rgb(0.7, 0.45, 0.25),
+                need: Need::Hunger,
+            },
+        ],
+    };
+
+    ContentPack { decorations, items }
+}
+
+/// Component tagging a decoration of a seasonal event.
+struct SeasonalDecoration;
+
+/// Spawns the decorations of the active seasonal event, once.
+fn spawn_seasonal_decorations_system(
+    mut commands: Commands,
+    active_season: Res<ActiveSeason>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    spawned_decorations: Query<(), With<SeasonalDecoration>>,
+) {
+    let event = match active_season.0 {
+        Some(event) if spawned_decorations.iter().next().is_none() => event,
+        _ => return,
+    };
+
+    for (position, size, color) in content_pack(event).decorations {
+        commands
+            .spawn()
+            .insert(SeasonalDecoration)
+            .insert(Position(position))
+            .insert_bundle(SpriteBundle {
+                material: materials.add(color.into()),
+                sprite: Sprite::new(size),
+                ..SpriteBundle::default()
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seasonal_items_are_requested() {
+        let mut registry = ItemRegistry::default();
+        add_seasonal_items(&mut registry, SeasonalEvent::Christmas);
+
+        let chocolate = registry.find("hot_chocolate").unwrap();
+        assert_eq!(registry.name(chocolate), "Hot chocolate");
+        assert_eq!(registry.need(chocolate), Need::Thirst);
+        assert!(registry
+            .definitions()
+            .any(|def| def.item == chocolate && def.weight == SEASONAL_ITEM_WEIGHT));
+        assert_eq!(registry.find("candy"), None);
+    }
+}
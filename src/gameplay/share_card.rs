@@ -0,0 +1,421 @@
+//! Share card: a small image summing up the run, composited in software at
+//! the end of the run and saved next to the save files of the profile, with
+//! a button copying its path to share it.
+
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use bevy::prelude::*;
+
+use crate::{constants::GameState, png, rng::GameRng, save::Profile};
+
+use super::{
+    score::Score,
+    stats::{SessionStats, HEATMAP_SIZE},
+};
+
+/// Plugin saving the share card on the end-of-run screens.
+pub struct ShareCardPlugin;
+
+impl Plugin for ShareCardPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<ShareCardMaterials>();
+        for state in [GameState::SessionSummary, GameState::SurvivalResults] {
+            app.add_system_set(
+                SystemSet::on_enter(state).with_system(save_share_card_system.system()),
+            )
+            .add_system_set(SystemSet::on_update(state).with_system(copy_path_system.system()))
+            .add_system_set(SystemSet::on_exit(state).with_system(cleanup_share_card.system()));
+        }
+    }
+}
+
+/// Name of the image file in the directory of the profile.
+const SHARE_CARD_FILE: &str = "share_card.png";
+/// Width and height of the card in pixels.
+const CARD_SIZE: (u32, u32) = (480, 270);
+/// Side of a cell of the heatmap in pixels.
+const HEATMAP_CELL: u32 = 10;
+/// Longest profile name written on the card.
+const MAX_NAME_LENGTH: usize = 24;
+
+/// Background of the card.
+const BACKGROUND: [u8; 3] = [40, 30, 60];
+/// Color of the texts.
+const INK: [u8; 3] = [255, 255, 255];
+/// Color of the cells of the apartment Didi never visited.
+const COLD: [u8; 3] = [70, 60, 100];
+/// Color of the most visited cell of the apartment.
+const HOT: [u8; 3] = [255, 120, 80];
+
+/// An RGB image drawn in software.
+struct Canvas {
+    /// Width in pixels
+    width: u32,
+    /// Height in pixels
+    height: u32,
+    /// Pixels row by row from the top
+    pixels: Vec<[u8; 3]>,
+}
+
+impl Canvas {
+    /// Creates an image filled with the color.
+    fn new(width: u32, height: u32, color: [u8; 3]) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![color; (width * height) as usize],
+        }
+    }
+
+    /// Fills the rectangle with its top left corner at the position, cut at
+    /// the borders of the image.
+    fn fill_rect(&mut self, x: u32, y: u32, width: u32, height: u32, color: [u8; 3]) {
+        for row in y..(y + height).min(self.height) {
+            for column in x..(x + width).min(self.width) {
+                self.pixels[(row * self.width + column) as usize] = color;
+            }
+        }
+    }
+
+    /// Writes the text with the pixel font, each dot of the font being a
+    /// square of the scale.
+    fn draw_text(&mut self, x: u32, y: u32, scale: u32, text: &str, color: [u8; 3]) {
+        for (index, character) in (0..).zip(text.chars()) {
+            let left = x + index * 4 * scale;
+            for (row, bits) in (0..).zip(glyph(character)) {
+                for column in 0..3 {
+                    if bits & (0b100 >> column) != 0 {
+                        let (dot_x, dot_y) = (left + column * scale, y + row * scale);
+                        self.fill_rect(dot_x, dot_y, scale, scale, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns the rows of the character in the pixel font, three dots wide and
+/// five dots high. The letters are all written in capitals.
+const fn glyph(character: char) -> [u8; 5] {
+    match character.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        ' ' => [0; 5],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        _ => [0b111, 0b001, 0b010, 0b000, 0b010], // ?
+    }
+}
+
+/// Returns the color between the cold and the hot colors for the heat,
+/// from 0 to 1.
+// The channels stay between the ones of the two colors
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn heat_color(heat: f32) -> [u8; 3] {
+    let mut color = COLD;
+    for (channel, hot) in color.iter_mut().zip(HOT) {
+        let cold = f32::from(*channel);
+        *channel = (f32::from(hot) - cold).mul_add(heat, cold).round() as u8;
+    }
+    color
+}
+
+/// Draws the card of the run: its score, its longest streak, its seed, the
+/// name of the profile and the heatmap of the apartment.
+// The heatmap has a few cells
+#[allow(clippy::cast_possible_truncation)]
+fn compose_card(score: u32, stats: &SessionStats, seed: u64, profile: &str) -> Canvas {
+    let (width, height) = CARD_SIZE;
+    let mut canvas = Canvas::new(width, height, BACKGROUND);
+
+    canvas.draw_text(20, 20, 5, "Baobei needs", INK);
+    let name: String = profile.chars().take(MAX_NAME_LENGTH).collect();
+    let lines = [
+        format!("Score: {}", score),
+        format!("Best streak: {}", stats.best_streak()),
+        format!("Seed: {}", seed),
+        format!("Player: {}", name),
+    ];
+    for (index, line) in (0..).zip(lines.iter()) {
+        canvas.draw_text(20, 70 + index * 25, 3, line, INK);
+    }
+
+    let (columns, rows) = HEATMAP_SIZE;
+    let left = width - 20 - columns as u32 * HEATMAP_CELL;
+    let top = height - 10 - rows as u32 * HEATMAP_CELL;
+    for row in 0..rows {
+        for column in 0..columns {
+            // The rows of the heatmap start from the bottom of the apartment
+            let y = top + (rows - 1 - row) as u32 * HEATMAP_CELL;
+            let x = left + column as u32 * HEATMAP_CELL;
+            let color = heat_color(stats.heat(column, row));
+            canvas.fill_rect(x, y, HEATMAP_CELL, HEATMAP_CELL, color);
+        }
+    }
+    canvas
+}
+
+/// Copies the text in the clipboard with the tool of the system.
+fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let tools: &[(&str, &[&str])] = if cfg!(target_os = "macos") {
+        &[("pbcopy", &[])]
+    } else if cfg!(target_os = "windows") {
+        &[("clip", &[])]
+    } else {
+        &[("wl-copy", &[]), ("xclip", &["-selection", "clipboard"])]
+    };
+
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, "no clipboard tool");
+    for (program, args) in tools {
+        let child = Command::new(program)
+            .args(*args)
+            .stdin(Stdio::piped())
+            .spawn();
+        match child {
+            Ok(mut child) => {
+                if let Some(stdin) = child.stdin.as_mut() {
+                    stdin.write_all(text.as_bytes())?;
+                }
+                child.wait()?;
+                return Ok(());
+            }
+            Err(error) => last_error = error,
+        }
+    }
+    Err(last_error)
+}
+
+/// Colors of the button.
+struct ShareCardMaterials {
+    /// Transparent color
+    none: Handle<ColorMaterial>,
+    /// Default style of the button
+    normal_button: Handle<ColorMaterial>,
+    /// Hovered style of the button
+    hovered_button: Handle<ColorMaterial>,
+}
+
+impl FromWorld for ShareCardMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            none: materials.add(Color::NONE.into()),
+            normal_button: materials.add(Color::rgb(0.15, 0.15, 0.15).into()),
+            hovered_button: materials.add(Color::rgb(0.25, 0.25, 0.25).into()),
+        }
+    }
+}
+
+/// Stores the saved card and the entities showing it.
+struct ShareCardData {
+    /// Entity wrapping all the entities of the card
+    node_wrapper: Entity,
+    /// Where the card is saved, none if it could not be written
+    path: Option<PathBuf>,
+}
+
+/// Tag the button copying the path of the card.
+struct CopyPathButton;
+/// Tag the text telling where the card is saved.
+struct CardPathText;
+
+/// Writes the image in the file, creating its directory.
+fn write_card(path: &Path, canvas: &Canvas) -> io::Result<()> {
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
+    let png = png::encode_rgb(canvas.width, canvas.height, &canvas.pixels);
+    std::fs::write(path, png)
+}
+
+/// Saves the card of the run and shows its path with the copy button at the
+/// bottom of the screen.
+#[allow(clippy::too_many_arguments)]
+fn save_share_card_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    materials: Res<ShareCardMaterials>,
+    profile: Res<Profile>,
+    score: Res<Score>,
+    stats: Res<SessionStats>,
+    rng: Res<GameRng>,
+) {
+    let canvas = compose_card(score.points(), &stats, rng.seed, &profile.name);
+    let path = profile.save_path(SHARE_CARD_FILE);
+    let (path, message) = match write_card(&path, &canvas) {
+        Ok(()) => {
+            info!("Share card saved to {:?}", path);
+            let message = format!("Share card saved to {}", path.display());
+            (Some(path), message)
+        }
+        Err(error) => {
+            warn!("Fail to save the share card {:?}: {}", path, error);
+            (None, "The share card could not be saved".to_string())
+        }
+    };
+
+    let font = asset_server.load("FiraSans-Bold.ttf");
+    let text = |value: String| TextBundle {
+        text: Text::with_section(
+            value,
+            TextStyle {
+                font: font.clone(),
+                font_size: 25.0,
+                color: Color::WHITE,
+            },
+            TextAlignment::default(),
+        ),
+        ..TextBundle::default()
+    };
+    let can_copy = path.is_some();
+
+    let node_wrapper = commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(20.0),
+                    left: Val::Px(0.0),
+                    ..Rect::default()
+                },
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Style::default()
+            },
+            material: materials.none.clone(),
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn()
+                .insert(CardPathText)
+                .insert_bundle(text(message));
+            if !can_copy {
+                return;
+            }
+            parent
+                .spawn()
+                .insert(CopyPathButton)
+                .insert_bundle(ButtonBundle {
+                    style: Style {
+                        margin: Rect::all(Val::Px(15.0)),
+                        size: Size::new(Val::Px(160.0), Val::Px(45.0)),
+                        justify_content: JustifyContent::Center, // horizontally center child text
+                        align_items: AlignItems::Center,         // vertically center child text
+                        ..Style::default()
+                    },
+                    material: materials.normal_button.clone(),
+                    ..ButtonBundle::default()
+                })
+                .with_children(|parent| {
+                    parent.spawn().insert_bundle(text("Copy path".to_string()));
+                });
+        })
+        .id();
+
+    commands.insert_resource(ShareCardData { node_wrapper, path });
+}
+
+/// A button interacted by the player.
+type UpdatedButton = (Changed<Interaction>, With<CopyPathButton>);
+
+/// Copies the path of the card when the button is clicked.
+fn copy_path_system(
+    materials: Res<ShareCardMaterials>,
+    card: Res<ShareCardData>,
+    mut buttons: Query<(&Interaction, &mut Handle<ColorMaterial>), UpdatedButton>,
+    mut texts: Query<&mut Text, With<CardPathText>>,
+) {
+    for (interaction, mut material) in buttons.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                let path = match &card.path {
+                    Some(path) => path.display().to_string(),
+                    None => continue,
+                };
+                let message = match copy_to_clipboard(&path) {
+                    Ok(()) => format!("Copied {}", path),
+                    Err(error) => {
+                        warn!("Fail to copy the path of the share card: {}", error);
+                        format!("Could not copy the path, the card is at {}", path)
+                    }
+                };
+                for mut text in texts.iter_mut() {
+                    text.sections[0].value = message.clone();
+                }
+            }
+            Interaction::Hovered => *material = materials.hovered_button.clone(),
+            Interaction::None => *material = materials.normal_button.clone(),
+        }
+    }
+}
+
+/// Removes all entities of the card.
+fn cleanup_share_card(mut commands: Commands, card: Res<ShareCardData>) {
+    commands.entity(card.node_wrapper).despawn_recursive();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn card_shows_the_run() {
+        let canvas = compose_card(1234, &SessionStats::default(), 42, "default");
+        let pixel = |x: u32, y: u32| canvas.pixels[(y * canvas.width + x) as usize];
+        assert_eq!(canvas.pixels.len(), (CARD_SIZE.0 * CARD_SIZE.1) as usize);
+
+        // The title is written in the top left corner
+        assert_eq!(pixel(20, 20), INK);
+        assert_eq!(pixel(19, 19), BACKGROUND);
+        // The apartment was never visited
+        assert_eq!(pixel(CARD_SIZE.0 - 21, CARD_SIZE.1 - 11), COLD);
+
+        assert_eq!(heat_color(0.0), COLD);
+        assert_eq!(heat_color(1.0), HOT);
+    }
+}
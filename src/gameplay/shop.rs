@@ -0,0 +1,384 @@
+//! Shop between the levels: the deliveries earn coins, spent when a new level
+//! starts in upgrades lasting until the end of the game, like faster shoes, a
+//! bigger backpack, a slower decay of the needs, a magnet pulling the
+//! dropped items or the order ticket showing the upcoming requests.
+//!
+//! The shop follows the summary of the level on top of the game state, so
+//! the game is suspended while the player shops.
+
+use bevy::prelude::*;
+
+use crate::{
+    constants::{
+        GameState, COINS_PER_DELIVERY, INVENTORY_SLOTS, MAX_INVENTORY_SLOTS,
+        SHOES_SPEED_MULTIPLIER, SLOW_DECAY_MULTIPLIER,
+    },
+    locale::Language,
+    settings::Settings,
+};
+
+use super::{
+    entities::GameData,
+    items::{DeliveryEvent, Inventory, ItemSystems},
+    NewRunAppExt,
+};
+
+/// Plugin managing the coins and the shop.
+pub struct ShopPlugin;
+
+impl Plugin for ShopPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Wallet>()
+            .init_resource::<UpgradeRegistry>()
+            .init_resource::<ShopMaterials>()
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(earn_coins_system.system().after(ItemSystems))
+                    .with_system(inventory_capacity_system.system().before("item_actions")),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::Shop).with_system(setup_shop.system()))
+            .add_system_set(
+                SystemSet::on_update(GameState::Shop)
+                    .with_system(shop_system.system().label("shop"))
+                    .with_system(shop_text_system.system().after("shop")),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Shop).with_system(cleanup_shop.system()))
+            .add_new_run_system(reset_shop_system);
+    }
+}
+
+/// Coins earned by the deliveries of the game, to spend in the shop.
+#[derive(Debug, Default)]
+pub struct Wallet {
+    /// Coins not spent yet
+    coins: u32,
+}
+
+impl Wallet {
+    /// Returns the coins not spent yet.
+    pub const fn coins(&self) -> u32 {
+        self.coins
+    }
+
+    /// Adds the coins to the wallet.
+    pub fn earn(&mut self, coins: u32) {
+        self.coins += coins;
+    }
+
+    /// Pays the price, returns false if the coins are not enough.
+    pub fn spend(&mut self, price: u32) -> bool {
+        if self.coins < price {
+            return false;
+        }
+        self.coins -= price;
+        true
+    }
+}
+
+/// Upgrade the player can buy in the shop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Upgrade {
+    /// Didi walks faster
+    FastShoes,
+    /// Didi carries one more item in the backpack
+    BigBackpack,
+    /// The needs of Baobei decay slower
+    SlowDecay,
+    /// The dropped items are pulled toward Didi and picked up
+    Magnet,
+    /// The order ticket shows the upcoming requests of Baobei
+    Precognition,
+}
+
+impl Upgrade {
+    /// All the upgrades of the shop.
+    pub const ALL: [Self; 5] = [
+        Self::FastShoes,
+        Self::BigBackpack,
+        Self::SlowDecay,
+        Self::Magnet,
+        Self::Precognition,
+    ];
+
+    /// Returns the coins needed to buy the upgrade.
+    pub const fn price(self) -> u32 {
+        match self {
+            Self::FastShoes => 25,
+            Self::BigBackpack => 40,
+            Self::SlowDecay => 30,
+            Self::Magnet => 35,
+            Self::Precognition => 45,
+        }
+    }
+
+    /// Returns the name of the upgrade shown in the shop.
+    pub const fn label(self, language: Language) -> &'static str {
+        match (self, language) {
+            (Self::FastShoes, Language::English) => "Faster shoes",
+            (Self::FastShoes, Language::French) => "Chaussures rapides",
+            (Self::BigBackpack, Language::English) => "Bigger backpack",
+            (Self::BigBackpack, Language::French) => "Sac plus grand",
+            (Self::SlowDecay, Language::English) => "Slower decay",
+            (Self::SlowDecay, Language::French) => "Besoins plus lents",
+            (Self::Magnet, Language::English) => "Item magnet",
+            (Self::Magnet, Language::French) => "Aimant à objets",
+            (Self::Precognition, Language::English) => "Order ticket",
+            (Self::Precognition, Language::French) => "Bon de commande",
+        }
+    }
+}
+
+/// Upgrades bought in the shop during the game, with their effects.
+#[derive(Debug, Default)]
+pub struct UpgradeRegistry {
+    /// Upgrades bought, each at most once
+    bought: Vec<Upgrade>,
+}
+
+impl UpgradeRegistry {
+    /// Returns true if the upgrade is bought.
+    pub fn has(&self, upgrade: Upgrade) -> bool {
+        self.bought.contains(&upgrade)
+    }
+
+    /// Pays for the upgrade with the coins of the wallet, returns false if
+    /// already bought or too expensive.
+    pub fn buy(&mut self, upgrade: Upgrade, wallet: &mut Wallet) -> bool {
+        if self.has(upgrade) || !wallet.spend(upgrade.price()) {
+            return false;
+        }
+        self.bought.push(upgrade);
+        true
+    }
+
+    /// Returns the multiplier of the speed of Didi.
+    pub fn speed_multiplier(&self) -> f32 {
+        if self.has(Upgrade::FastShoes) {
+            SHOES_SPEED_MULTIPLIER
+        } else {
+            1.0
+        }
+    }
+
+    /// Returns the multiplier of the decay of the needs.
+    pub fn decay_multiplier(&self) -> f32 {
+        if self.has(Upgrade::SlowDecay) {
+            SLOW_DECAY_MULTIPLIER
+        } else {
+            1.0
+        }
+    }
+
+    /// Returns the number of items Didi carries.
+    pub fn inventory_slots(&self) -> usize {
+        if self.has(Upgrade::BigBackpack) {
+            MAX_INVENTORY_SLOTS
+        } else {
+            INVENTORY_SLOTS
+        }
+    }
+}
+
+/// Returns the controls shown at the bottom of the shop.
+const fn controls_line(language: Language) -> &'static str {
+    match language {
+        Language::English => "Up/Down: choose  Space: buy  Enter: next level",
+        Language::French => "Haut/Bas : choisir  Espace : acheter  Entrée : niveau suivant",
+    }
+}
+
+/// Returns the mark of the upgrades already bought.
+const fn bought_label(language: Language) -> &'static str {
+    match language {
+        Language::English => "bought",
+        Language::French => "acheté",
+    }
+}
+
+/// Colors of the shop.
+struct ShopMaterials {
+    /// Darkened game behind the shop
+    overlay: Handle<ColorMaterial>,
+}
+
+impl FromWorld for ShopMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            overlay: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.7).into()),
+        }
+    }
+}
+
+/// Stores entities of the shop.
+struct ShopData {
+    /// Entity wrapping all the entities of the shop
+    node_wrapper: Entity,
+    /// Index of the selected upgrade
+    selected: usize,
+}
+
+/// Tag the text listing the upgrades.
+struct ShopText;
+
+/// Earns coins for each item delivered.
+fn earn_coins_system(mut wallet: ResMut<Wallet>, mut delivery_events: EventReader<DeliveryEvent>) {
+    for _ in delivery_events.iter() {
+        wallet.earn(COINS_PER_DELIVERY);
+    }
+}
+
+/// Gives Didi the slots of the backpack bought in the shop.
+fn inventory_capacity_system(
+    game_data: Res<GameData>,
+    upgrades: Res<UpgradeRegistry>,
+    mut inventories: Query<&mut Inventory>,
+) {
+    let capacity = upgrades.inventory_slots();
+    if let Ok(mut inventory) = inventories.get_mut(game_data.didi_entity) {
+        if inventory.capacity() != capacity {
+            inventory.set_capacity(capacity);
+        }
+    }
+}
+
+/// Shows the upgrades over the game.
+fn setup_shop(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    materials: Res<ShopMaterials>,
+) {
+    let node_wrapper = commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                ..Style::default()
+            },
+            material: materials.overlay.clone(),
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            parent.spawn().insert(ShopText).insert_bundle(TextBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        top: Val::Px(120.0),
+                        left: Val::Px(320.0),
+                        ..Rect::default()
+                    },
+                    ..Style::default()
+                },
+                text: Text::with_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load("FiraSans-Bold.ttf"),
+                        font_size: 35.0,
+                        color: Color::WHITE,
+                    },
+                    TextAlignment::default(),
+                ),
+                ..TextBundle::default()
+            });
+        })
+        .id();
+
+    commands.insert_resource(ShopData {
+        node_wrapper,
+        selected: 0,
+    });
+}
+
+/// Selects an upgrade with the arrows, buys it with `Space` and goes back to
+/// the game with `Enter`.
+fn shop_system(
+    keyboard: Res<Input<KeyCode>>,
+    mut shop_data: ResMut<ShopData>,
+    mut wallet: ResMut<Wallet>,
+    mut upgrades: ResMut<UpgradeRegistry>,
+    mut state: ResMut<State<GameState>>,
+) {
+    let count = Upgrade::ALL.len();
+    if keyboard.just_pressed(KeyCode::Up) {
+        shop_data.selected = (shop_data.selected + count - 1) % count;
+    }
+    if keyboard.just_pressed(KeyCode::Down) {
+        shop_data.selected = (shop_data.selected + 1) % count;
+    }
+    if keyboard.just_pressed(KeyCode::Space) {
+        let upgrade = Upgrade::ALL[shop_data.selected];
+        if upgrades.buy(upgrade, &mut wallet) {
+            info!("Buy {:?}, {} coins left", upgrade, wallet.coins());
+        }
+    }
+    if keyboard.just_pressed(KeyCode::Return) {
+        state.pop().unwrap();
+    }
+}
+
+/// Lists the coins and the upgrades, marking the selected one.
+fn shop_text_system(
+    settings: Res<Settings>,
+    shop_data: Res<ShopData>,
+    wallet: Res<Wallet>,
+    upgrades: Res<UpgradeRegistry>,
+    mut texts: Query<&mut Text, With<ShopText>>,
+) {
+    let language = settings.language;
+    let mut value = format!("Shop - {} coins\n\n", wallet.coins());
+    for (index, upgrade) in Upgrade::ALL.iter().enumerate() {
+        let cursor = if index == shop_data.selected {
+            ">"
+        } else {
+            " "
+        };
+        let price = if upgrades.has(*upgrade) {
+            bought_label(language).to_string()
+        } else {
+            upgrade.price().to_string()
+        };
+        value += &format!("{} {} ({})\n", cursor, upgrade.label(language), price);
+    }
+    value += "\n";
+    value += controls_line(language);
+
+    for mut text in texts.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+/// Removes all entities of the shop.
+fn cleanup_shop(mut commands: Commands, shop_data: Res<ShopData>) {
+    commands.entity(shop_data.node_wrapper).despawn_recursive();
+}
+
+/// Empties the wallet and forgets the upgrades for the new game.
+fn reset_shop_system(mut wallet: ResMut<Wallet>, mut upgrades: ResMut<UpgradeRegistry>) {
+    *wallet = Wallet::default();
+    *upgrades = UpgradeRegistry::default();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upgrades_are_bought_once_with_enough_coins() {
+        let mut wallet = Wallet::default();
+        let mut upgrades = UpgradeRegistry::default();
+        assert!(!upgrades.buy(Upgrade::BigBackpack, &mut wallet));
+        assert_eq!(upgrades.inventory_slots(), INVENTORY_SLOTS);
+
+        wallet.earn(Upgrade::BigBackpack.price() + 5);
+        assert!(upgrades.buy(Upgrade::BigBackpack, &mut wallet));
+        assert_eq!(wallet.coins(), 5);
+        assert_eq!(upgrades.inventory_slots(), MAX_INVENTORY_SLOTS);
+
+        wallet.earn(Upgrade::BigBackpack.price());
+        assert!(!upgrades.buy(Upgrade::BigBackpack, &mut wallet));
+        assert!((upgrades.speed_multiplier() - 1.0).abs() < f32::EPSILON);
+    }
+}
@@ -0,0 +1,231 @@
+//! Opt-in speedrun timer, enabled by the `speedrun_timer` setting: a precise
+//! timer of the game with a split at each delivery, shown under the score.
+//!
+//! The timer adds up the real time between the frames, so it neither depends
+//! on the frame rate nor on the time scale, and it stops while the game is
+//! paused. The final time and the splits are exported next to the save files
+//! of the profile when the game ends.
+
+use std::{
+    fs, io,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use bevy::prelude::*;
+
+use crate::{constants::GameState, save::Profile, settings::Settings};
+
+use super::items::{DeliveryEvent, ItemSystems};
+
+/// Plugin managing the speedrun timer.
+pub struct SpeedrunPlugin;
+
+impl Plugin for SpeedrunPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Speedrun>()
+            .add_startup_system(spawn_speedrun_text.system())
+            .add_system_set(
+                SystemSet::on_enter(GameState::InGame).with_system(start_speedrun_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(
+                        speedrun_timer_system
+                            .system()
+                            .label("speedrun")
+                            .after(ItemSystems),
+                    )
+                    .with_system(speedrun_text_system.system().after("speedrun")),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::InGame).with_system(finish_speedrun_system.system()),
+            );
+    }
+}
+
+/// Timer of the run and its splits.
+#[derive(Debug, Default)]
+struct Speedrun {
+    /// Whether the run is timed
+    active: bool,
+    /// Time spent in the game
+    elapsed: Duration,
+    /// Time of each delivery
+    splits: Vec<Duration>,
+}
+
+impl Speedrun {
+    /// Returns the final time and the splits, one per line, each split
+    /// followed by the time since the previous one.
+    fn report(&self) -> String {
+        let mut report = format!("Final time: {}\n", format_time(self.elapsed));
+        let mut previous = Duration::default();
+        for (number, split) in self.splits.iter().enumerate() {
+            report.push_str(&format!(
+                "Split {}: {} (+{})\n",
+                number + 1,
+                format_time(*split),
+                format_time(*split - previous)
+            ));
+            previous = *split;
+        }
+        report
+    }
+}
+
+/// Returns the time as minutes, seconds and milliseconds.
+fn format_time(time: Duration) -> String {
+    let millis = time.as_millis();
+    format!(
+        "{}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// Writes the report of the run, creating its directory.
+fn write_report(path: &Path, report: &str) -> io::Result<()> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    fs::write(path, report)
+}
+
+/// Tag the text displaying the speedrun timer.
+struct SpeedrunText;
+
+/// Spawns the hidden text of the timer under the score.
+fn spawn_speedrun_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn()
+        .insert(SpeedrunText)
+        .insert_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(170.0),
+                    right: Val::Px(40.0),
+                    ..Rect::default()
+                },
+                ..Style::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("FiraSans-Bold.ttf"),
+                    font_size: 30.0,
+                    color: Color::WHITE,
+                },
+                TextAlignment::default(),
+            ),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..TextBundle::default()
+        });
+}
+
+/// Starts the timer from zero when a game starts with the setting.
+fn start_speedrun_system(
+    settings: Res<Settings>,
+    mut speedrun: ResMut<Speedrun>,
+    mut texts: Query<&mut Visible, With<SpeedrunText>>,
+) {
+    *speedrun = Speedrun {
+        active: settings.speedrun_timer,
+        ..Speedrun::default()
+    };
+    for mut visible in texts.iter_mut() {
+        visible.is_visible = speedrun.active;
+    }
+}
+
+/// Adds the real time of the frame and captures a split at each delivery.
+fn speedrun_timer_system(
+    time: Res<Time>,
+    mut speedrun: ResMut<Speedrun>,
+    mut delivery_events: EventReader<DeliveryEvent>,
+) {
+    if !speedrun.active {
+        return;
+    }
+    speedrun.elapsed += time.delta();
+
+    for _ in delivery_events.iter() {
+        let split = speedrun.elapsed;
+        speedrun.splits.push(split);
+    }
+}
+
+/// Shows the time of the run and its last split.
+fn speedrun_text_system(speedrun: Res<Speedrun>, mut texts: Query<&mut Text, With<SpeedrunText>>) {
+    if !speedrun.active {
+        return;
+    }
+    let mut value = format_time(speedrun.elapsed);
+    if let Some(split) = speedrun.splits.last() {
+        value = format!(
+            "{}\nSplit {}: {}",
+            value,
+            speedrun.splits.len(),
+            format_time(*split)
+        );
+    }
+
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
+
+/// Stops the timer, hides it and exports the report of the run.
+fn finish_speedrun_system(
+    profile: Res<Profile>,
+    mut speedrun: ResMut<Speedrun>,
+    mut texts: Query<&mut Visible, With<SpeedrunText>>,
+) {
+    for mut visible in texts.iter_mut() {
+        visible.is_visible = false;
+    }
+    if !speedrun.active {
+        return;
+    }
+    speedrun.active = false;
+
+    let seconds = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let path = profile.save_path(&format!("speedrun_{}.txt", seconds));
+    match write_report(&path, &speedrun.report()) {
+        Ok(()) => info!(
+            "Speedrun of {} saved to {:?}",
+            format_time(speedrun.elapsed),
+            path
+        ),
+        Err(error) => warn!("Fail to save the speedrun {:?}: {}", path, error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_lists_the_splits() {
+        let speedrun = Speedrun {
+            active: true,
+            elapsed: Duration::from_millis(75_250),
+            splits: vec![Duration::from_millis(8_120), Duration::from_millis(20_005)],
+        };
+
+        assert_eq!(format_time(Duration::from_millis(3_723_004)), "62:03.004");
+        assert_eq!(
+            speedrun.report(),
+            "Final time: 1:15.250\n\
+             Split 1: 0:08.120 (+0:08.120)\n\
+             Split 2: 0:20.005 (+0:11.885)\n"
+        );
+    }
+}
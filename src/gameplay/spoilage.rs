@@ -0,0 +1,170 @@
+//! Spoilage of the perishable items: the ice cream melts when carried for
+//! too long, leaving a puddle on the floor. A bar above the carried ice cream
+//! shows the time left before it melts.
+
+use bevy::prelude::*;
+
+use crate::{
+    collisions::Position,
+    constants::{GameState, ICE_CREAM_MELT_DURATION},
+    pool::Pool,
+    time_scale::TimeScale,
+    widgets::{entity_timer_system, spawn_timer_bar, EntityTimer, Progress, WidgetMaterials},
+};
+
+use super::{
+    entities::GameData,
+    items::{ActionEvent, CarriedItem, Item, ItemSystems, SpoiledEvent},
+    score::Score,
+};
+
+/// Plugin managing the perishable items.
+pub struct SpoilagePlugin;
+
+impl Plugin for SpoilagePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<SpoilageMaterials>()
+            .init_resource::<Pool<Puddle>>()
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(add_perishable_system.system())
+                    .with_system(melt_system.system().label("melt").before("item_actions"))
+                    .with_system(entity_timer_system::<Perishable>.system().after("melt"))
+                    .with_system(spoiled_system.system().after(ItemSystems))
+                    .with_system(puddle_system.system()),
+            );
+    }
+}
+
+/// Seconds the puddle of a melted item stays on the floor.
+const PUDDLE_DURATION: f32 = 1.5;
+
+/// Component on a carried item spoiling when the timer finishes.
+struct Perishable(Timer);
+
+impl Progress for Perishable {
+    fn remaining(&self) -> f32 {
+        self.0.percent_left()
+    }
+}
+
+/// Component on the puddle left by a melted item.
+struct Puddle(Timer);
+
+/// Sprite of the melted items.
+struct SpoilageMaterials {
+    /// Melted ice cream
+    puddle: Handle<ColorMaterial>,
+}
+
+impl FromWorld for SpoilageMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let texture = world
+            .get_resource::<AssetServer>()
+            .unwrap()
+            .load("items/ice_cream.png");
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            puddle: materials.add(ColorMaterial::modulated_texture(
+                texture,
+                Color::rgba(1.0, 0.8, 0.85, 0.7),
+            )),
+        }
+    }
+}
+
+/// Makes the ice cream perishable once carried, with a bar above it showing
+/// the time left before it melts.
+fn add_perishable_system(
+    mut commands: Commands,
+    widget_materials: Res<WidgetMaterials>,
+    carried_items: Query<(Entity, &Item), (Added<CarriedItem>, Without<Perishable>)>,
+) {
+    for (entity, item) in carried_items.iter() {
+        if *item == Item::IceCream {
+            commands
+                .entity(entity)
+                .insert(Perishable(Timer::from_seconds(
+                    ICE_CREAM_MELT_DURATION,
+                    false,
+                )));
+
+            let melting_bar = spawn_timer_bar(
+                &mut commands,
+                &widget_materials,
+                Vec3::new(0.0, 150.0, 0.1),
+                Vec2::new(200.0, 20.0),
+            );
+            commands
+                .entity(melting_bar)
+                .insert(EntityTimer::<Perishable>::new(entity));
+            commands.entity(entity).push_children(&[melting_bar]);
+        }
+    }
+}
+
+/// Melts the perishable items carried for too long.
+fn melt_system(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut action_events: EventWriter<ActionEvent>,
+    mut carried_items: Query<(&Item, &mut Perishable), With<CarriedItem>>,
+) {
+    let delta = time_scale.scale(time.delta());
+
+    for (item, mut perishable) in carried_items.iter_mut() {
+        if perishable.0.tick(delta).just_finished() {
+            action_events.send(ActionEvent::Melt(*item));
+        }
+    }
+}
+
+/// Penalizes the spoiled items and leaves a puddle under Didi.
+fn spoiled_system(
+    mut commands: Commands,
+    game_data: Res<GameData>,
+    materials: Res<SpoilageMaterials>,
+    mut score: ResMut<Score>,
+    mut pool: ResMut<Pool<Puddle>>,
+    mut spoiled_events: EventReader<SpoiledEvent>,
+    positions: Query<&Position>,
+) {
+    for SpoiledEvent(item) in spoiled_events.iter() {
+        info!("The {:?} spoiled", item);
+        score.penalize_spoiled_item();
+
+        let didi_position = positions
+            .get(game_data.didi_entity)
+            .map_or(Vec3::ZERO, |position| position.0);
+
+        let (puddle, _) = pool.acquire(&mut commands);
+        commands
+            .entity(puddle)
+            .insert(Puddle(Timer::from_seconds(PUDDLE_DURATION, false)))
+            .insert(Position(Vec3::new(
+                didi_position.x,
+                didi_position.y - 40.0,
+                0.0,
+            )))
+            .insert_bundle(SpriteBundle {
+                material: materials.puddle.clone(),
+                transform: Transform::from_scale(Vec3::new(0.35, 0.12, 0.0)),
+                ..SpriteBundle::default()
+            });
+    }
+}
+
+/// Removes the puddles after a while.
+fn puddle_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut pool: ResMut<Pool<Puddle>>,
+    mut puddles: Query<(Entity, &mut Puddle)>,
+) {
+    for (entity, mut puddle) in puddles.iter_mut() {
+        if puddle.0.tick(time.delta()).finished() {
+            pool.release(&mut commands, entity);
+        }
+    }
+}
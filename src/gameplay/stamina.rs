@@ -0,0 +1,145 @@
+//! Stamina of Didi, drained by sprinting and restored when walking.
+
+use bevy::prelude::*;
+
+use crate::{
+    collisions::Position,
+    constants::{GameState, SPRINT_SPEED_MULTIPLIER, STAMINA_DRAIN, STAMINA_REGEN},
+    drawing::UiObject,
+    time_scale::TimeScale,
+    widgets::{entity_timer_system, spawn_timer_bar, EntityTimer, Progress, WidgetMaterials},
+};
+
+use super::NewRunAppExt;
+
+/// Plugin managing the stamina of Didi.
+pub struct StaminaPlugin;
+
+impl Plugin for StaminaPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(spawn_stamina_bars_system.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(stamina_system.system().label("stamina").after("movement"))
+                    .with_system(entity_timer_system::<Stamina>.system().after("stamina")),
+            )
+            .add_new_run_system(reset_stamina_system);
+    }
+}
+
+/// Stamina from which Didi can sprint again after running out of it.
+const RECOVERED_STAMINA: f32 = 0.3; // 30%
+
+/// Component on a character able to sprint until out of breath.
+pub struct Stamina {
+    /// Stamina left, between 0 and 1
+    value: f32,
+    /// True when the stamina ran out, until it is recovered
+    exhausted: bool,
+    /// True if the character sprinted this frame
+    sprinting: bool,
+}
+
+impl Stamina {
+    /// Returns a full stamina.
+    pub const fn full() -> Self {
+        Self {
+            value: 1.0,
+            exhausted: false,
+            sprinting: false,
+        }
+    }
+
+    /// Returns true if the character has the breath to sprint.
+    pub fn can_sprint(&self) -> bool {
+        !self.exhausted && self.value > 0.0
+    }
+
+    /// Makes the character sprint this frame if it can, and returns the
+    /// multiplier of its speed.
+    pub fn sprint(&mut self) -> f32 {
+        if self.can_sprint() {
+            self.sprinting = true;
+            SPRINT_SPEED_MULTIPLIER
+        } else {
+            1.0
+        }
+    }
+
+    /// Drains the stamina if the character sprinted during the given seconds,
+    /// restores it otherwise.
+    fn update(&mut self, seconds: f32) {
+        if self.sprinting {
+            self.value = (self.value - STAMINA_DRAIN * seconds).max(0.0);
+            self.exhausted = self.value <= 0.0;
+        } else {
+            self.value = (self.value + STAMINA_REGEN * seconds).min(1.0);
+            self.exhausted = self.exhausted && self.value < RECOVERED_STAMINA;
+        }
+        self.sprinting = false;
+    }
+}
+
+impl Progress for Stamina {
+    fn remaining(&self) -> f32 {
+        self.value
+    }
+}
+
+/// Spawns a stamina bar in the bottom left corner of the screen for the
+/// characters having a stamina.
+fn spawn_stamina_bars_system(
+    mut commands: Commands,
+    widget_materials: Res<WidgetMaterials>,
+    characters: Query<Entity, Added<Stamina>>,
+) {
+    for character in characters.iter() {
+        let stamina_bar = spawn_timer_bar(
+            &mut commands,
+            &widget_materials,
+            Vec3::ZERO,
+            Vec2::new(200.0, 15.0),
+        );
+        commands
+            .entity(stamina_bar)
+            .insert(EntityTimer::<Stamina>::new(character))
+            .insert(UiObject)
+            .insert(Position(Vec3::new(130.0, 30.0, 0.0)));
+    }
+}
+
+/// Drains or restores the stamina, depending on whether the character
+/// sprinted this frame.
+fn stamina_system(time: Res<Time>, time_scale: Res<TimeScale>, mut staminas: Query<&mut Stamina>) {
+    let seconds = time_scale.scale(time.delta()).as_secs_f32();
+    for mut stamina in staminas.iter_mut() {
+        stamina.update(seconds);
+    }
+}
+
+/// Restores the stamina when a new game starts.
+fn reset_stamina_system(mut staminas: Query<&mut Stamina>) {
+    for mut stamina in staminas.iter_mut() {
+        *stamina = Stamina::full();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_out_of_breath_until_recovered() {
+        let mut stamina = Stamina::full();
+        assert!((stamina.sprint() - SPRINT_SPEED_MULTIPLIER).abs() < f32::EPSILON);
+        stamina.update(1.0 / STAMINA_DRAIN);
+        assert!(!stamina.can_sprint());
+        assert!((stamina.sprint() - 1.0).abs() < f32::EPSILON);
+
+        // Walking restores the stamina, but not enough to sprint at first
+        stamina.update(RECOVERED_STAMINA / STAMINA_REGEN / 2.0);
+        assert!(!stamina.can_sprint());
+        stamina.update(RECOVERED_STAMINA / STAMINA_REGEN);
+        assert!(stamina.can_sprint());
+    }
+}
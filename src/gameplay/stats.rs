@@ -0,0 +1,306 @@
+//! Statistics of the session, shown on a summary screen when the player
+//! leaves the game.
+
+use bevy::prelude::*;
+
+use crate::{
+    collisions::Position,
+    constants::{GameState, WINDOW_HEIGHT, WINDOW_WIDTH},
+    controllers::{BindingText, InputAction, InputMap},
+    time_scale::TimeScale,
+};
+
+use super::{entities::GameData, happiness::Happiness, Baobei, NewRunAppExt};
+
+/// Plugin managing the statistics of the session and the summary screen.
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<SessionStats>()
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(full_happiness_system.system())
+                    .with_system(heatmap_system.system()),
+            )
+            .add_new_run_system(reset_stats_system)
+            .add_system_set(
+                SystemSet::on_enter(GameState::SessionSummary).with_system(setup_summary.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::SessionSummary)
+                    .with_system(summary_input_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::SessionSummary).with_system(cleanup_summary.system()),
+            );
+    }
+}
+
+/// Pixels walked by Didi for a meter on the summary.
+const PIXELS_PER_METER: f32 = 100.0;
+/// Number of columns and rows of the heatmap of the apartment.
+pub const HEATMAP_SIZE: (usize, usize) = (16, 9);
+
+/// Statistics of the current session, updated by the movement and the items
+/// systems.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SessionStats {
+    /// Items received by the Baobeis
+    delivered: u32,
+    /// Items given to a Baobei asking for another one
+    wrong_gives: u32,
+    /// Pixels walked by Didi
+    distance: f32,
+    /// Seconds of game while every Baobei is fully happy
+    full_happiness: f32,
+    /// Longest combo of deliveries
+    best_streak: u32,
+    /// Seconds spent by Didi in each cell of the apartment, row by row from
+    /// the bottom
+    heatmap: Vec<f32>,
+}
+
+impl SessionStats {
+    /// Counts an item received by a Baobei.
+    pub fn record_delivery(&mut self) {
+        self.delivered += 1;
+    }
+
+    /// Counts an item given to a Baobei asking for another one.
+    pub fn record_wrong_give(&mut self) {
+        self.wrong_gives += 1;
+    }
+
+    /// Adds the pixels walked by Didi.
+    pub fn record_walk(&mut self, distance: f32) {
+        self.distance += distance;
+    }
+
+    /// Keeps the longest combo of deliveries.
+    pub fn record_streak(&mut self, streak: u32) {
+        self.best_streak = self.best_streak.max(streak);
+    }
+
+    /// Returns the longest combo of deliveries.
+    pub const fn best_streak(&self) -> u32 {
+        self.best_streak
+    }
+
+    /// Returns the items received by the Baobeis.
+    pub const fn delivered(&self) -> u32 {
+        self.delivered
+    }
+
+    /// Returns the items given to a Baobei asking for another one.
+    pub const fn wrong_gives(&self) -> u32 {
+        self.wrong_gives
+    }
+
+    /// Returns the statistics recorded since the given ones were taken, the
+    /// longest combo and the heatmap being kept for the whole session.
+    pub fn since(&self, start: &Self) -> Self {
+        Self {
+            delivered: self.delivered.saturating_sub(start.delivered),
+            wrong_gives: self.wrong_gives.saturating_sub(start.wrong_gives),
+            distance: (self.distance - start.distance).max(0.0),
+            full_happiness: (self.full_happiness - start.full_happiness).max(0.0),
+            best_streak: self.best_streak,
+            heatmap: self.heatmap.clone(),
+        }
+    }
+
+    /// Adds the seconds spent by Didi at the position in the heatmap.
+    // The position is clamped in the apartment before the cast
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn record_presence(&mut self, position: Vec3, seconds: f32) {
+        let (columns, rows) = HEATMAP_SIZE;
+        let cell = |value: f32, size: f32, count: usize| {
+            ((value / size).clamp(0.0, 1.0) * count as f32).min(count as f32 - 1.0) as usize
+        };
+        let column = cell(position.x, WINDOW_WIDTH, columns);
+        let row = cell(position.y, WINDOW_HEIGHT, rows);
+
+        self.heatmap.resize(columns * rows, 0.0);
+        self.heatmap[row * columns + column] += seconds;
+    }
+
+    /// Returns the time spent by Didi in the cell of the heatmap, from 0 to 1
+    /// in the most visited cell. The rows start from the bottom.
+    pub fn heat(&self, column: usize, row: usize) -> f32 {
+        let max = self.heatmap.iter().copied().fold(0.0, f32::max);
+        let seconds = self
+            .heatmap
+            .get(row * HEATMAP_SIZE.0 + column)
+            .copied()
+            .unwrap_or(0.0);
+        if max > 0.0 {
+            seconds / max
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns the lines shown on the summary screen.
+    pub fn summary_lines(&self) -> Vec<String> {
+        vec![
+            format!("Items delivered: {}", self.delivered),
+            format!("Wrong items given: {}", self.wrong_gives),
+            format!("Distance walked: {:.0} m", self.distance / PIXELS_PER_METER),
+            format!("Time at full happiness: {:.0} s", self.full_happiness),
+        ]
+    }
+}
+
+/// Counts the game time while every Baobei is fully happy.
+fn full_happiness_system(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut stats: ResMut<SessionStats>,
+    baobei: Query<&Happiness, With<Baobei>>,
+) {
+    let mut happiness = baobei.iter().peekable();
+    if happiness.peek().is_none() {
+        return;
+    }
+    if happiness.all(|happiness| happiness.value() >= 1.0) {
+        stats.full_happiness += time_scale.scale(time.delta()).as_secs_f32();
+    }
+}
+
+/// Counts the game time spent by Didi in each part of the apartment.
+fn heatmap_system(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    game_data: Res<GameData>,
+    mut stats: ResMut<SessionStats>,
+    positions: Query<&Position>,
+) {
+    if let Ok(position) = positions.get(game_data.didi_entity) {
+        let seconds = time_scale.scale(time.delta()).as_secs_f32();
+        stats.record_presence(position.0, seconds);
+    }
+}
+
+/// Resets the statistics when a new game starts.
+fn reset_stats_system(mut stats: ResMut<SessionStats>) {
+    *stats = SessionStats::default();
+}
+
+/// Stores entities of the summary screen.
+struct SummaryData {
+    /// Entity wrapping all the entities of the screen
+    node_wrapper: Entity,
+}
+
+/// Shows the statistics of the session.
+fn setup_summary(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    stats: Res<SessionStats>,
+) {
+    let font = asset_server.load("FiraSans-Bold.ttf");
+    let text = |value: String, font_size: f32| TextBundle {
+        text: Text::with_section(
+            value,
+            TextStyle {
+                font: font.clone(),
+                font_size,
+                color: Color::WHITE,
+            },
+            TextAlignment::default(),
+        ),
+        ..TextBundle::default()
+    };
+
+    let node_wrapper = commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                padding: Rect::all(Val::Px(50.0)),
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Style::default()
+            },
+            material: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.8).into()),
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn()
+                .insert_bundle(text("Session summary".to_string(), 60.0));
+            for line in stats.summary_lines() {
+                parent.spawn().insert_bundle(text(line, 40.0));
+            }
+            parent
+                .spawn()
+                .insert(BindingText(
+                    "Press {confirm} to go back to the menu".to_string(),
+                ))
+                .insert_bundle(text(String::new(), 30.0));
+        })
+        .id();
+
+    commands.insert_resource(SummaryData { node_wrapper });
+}
+
+/// Goes back to the menu.
+fn summary_input_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    input_map: Res<InputMap>,
+    mut state: ResMut<State<GameState>>,
+) {
+    let pressed = |action| input_map.just_pressed(action, &keyboard_input, &gamepad_buttons);
+    if pressed(InputAction::Confirm) || pressed(InputAction::Back) {
+        state.set(GameState::Menu).unwrap();
+    }
+}
+
+/// Removes all entities of the summary screen.
+fn cleanup_summary(mut commands: Commands, summary_data: Res<SummaryData>) {
+    commands
+        .entity(summary_data.node_wrapper)
+        .despawn_recursive();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_shows_the_recorded_stats() {
+        let mut stats = SessionStats::default();
+        stats.record_delivery();
+        stats.record_delivery();
+        stats.record_wrong_give();
+        stats.record_walk(250.0);
+        stats.record_walk(250.0);
+
+        assert_eq!(
+            stats.summary_lines(),
+            vec![
+                "Items delivered: 2",
+                "Wrong items given: 1",
+                "Distance walked: 5 m",
+                "Time at full happiness: 0 s",
+            ]
+        );
+    }
+
+    #[test]
+    fn heatmap_is_relative_to_the_most_visited_cell() {
+        let mut stats = SessionStats::default();
+        assert!(stats.heat(0, 0).abs() < f32::EPSILON);
+
+        stats.record_presence(Vec3::new(10.0, 10.0, 0.0), 2.0);
+        stats.record_presence(Vec3::new(WINDOW_WIDTH, WINDOW_HEIGHT, 0.0), 1.0);
+        stats.record_presence(Vec3::new(-50.0, -50.0, 0.0), 2.0);
+
+        let (columns, rows) = HEATMAP_SIZE;
+        assert!((stats.heat(0, 0) - 1.0).abs() < f32::EPSILON);
+        assert!((stats.heat(columns - 1, rows - 1) - 0.25).abs() < f32::EPSILON);
+    }
+}
@@ -0,0 +1,436 @@
+//! Timed buffs and debuffs modifying Didi and Baobei, coming from the mood of
+//! Baobei, the delivered items and the mutators.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    collisions::Position, constants::GameState, drawing::UiObject, settings::Settings,
+    time_scale::TimeScale,
+};
+
+use super::{
+    clock::GameClock,
+    entities::GameData,
+    happiness::Happiness,
+    items::{ActionEvent, DeliveryEvent, Item, ItemSystems},
+    Baobei,
+};
+
+/// Plugin managing the status effects.
+pub struct StatusEffectsPlugin;
+
+impl Plugin for StatusEffectsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<StatusIconMaterials>()
+            .add_startup_system(spawn_status_icons.system())
+            .add_system_set(
+                SystemSet::on_enter(GameState::InGame)
+                    .with_system(clear_status_effects_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(tick_status_effects_system.system().label("status_effects"))
+                    .with_system(mood_effects_system.system().after("status_effects"))
+                    .with_system(
+                        item_effects_system
+                            .system()
+                            .after("status_effects")
+                            .after(ItemSystems),
+                    )
+                    .with_system(
+                        consume_effects_system
+                            .system()
+                            .after("status_effects")
+                            .after(ItemSystems),
+                    )
+                    .with_system(
+                        mutator_effects_system
+                            .system()
+                            .after("status_effects")
+                            .after("clock"),
+                    )
+                    .with_system(update_status_icons_system.system().after("status_effects")),
+            );
+    }
+}
+
+/// Happiness above which Baobei is content.
+const CONTENT_HAPPINESS: f32 = 0.8; // 80%
+
+/// A kind of status effect.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StatusEffectKind {
+    /// Baobei is happy and gets sad slower
+    Content,
+    /// Didi ate the rest of an ice cream and runs faster
+    SugarRush,
+    /// Didi drank some water or washed hands and acts quicker
+    Refreshed,
+    /// Didi is sleepy during the night and walks slower
+    Drowsy,
+    /// Didi ate a bag of chips and walks faster
+    Snacked,
+    /// Baobei sees dirty dishes lying around and gets sad faster
+    Disgusted,
+    /// Baobei is sleepy during the night and gets sad slower
+    Sleepy,
+    /// Didi picked up the speed slippers and runs much faster
+    Slippers,
+    /// Baobei ate a patience candy and does not get sad
+    PatienceCandy,
+}
+
+/// How an effect modifies its holder.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Modifier {
+    /// Multiplies the movement speed
+    Speed(f32),
+    /// Multiplies the happiness decay
+    Decay(f32),
+    /// Reduces the duration of the cooldowns by the fraction
+    CooldownReduction(f32),
+}
+
+/// What happens when an effect is applied while already active.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stacking {
+    /// The duration restarts
+    Refresh,
+    /// The effect stacks up to the maximum and its duration restarts
+    Stack(u32),
+}
+
+impl StatusEffectKind {
+    /// All the kinds of effect, in the order of their icons.
+    pub const ALL: [Self; 9] = [
+        Self::Content,
+        Self::SugarRush,
+        Self::Refreshed,
+        Self::Drowsy,
+        Self::Snacked,
+        Self::Disgusted,
+        Self::Sleepy,
+        Self::Slippers,
+        Self::PatienceCandy,
+    ];
+
+    /// Returns the modifier of one stack of the effect.
+    pub const fn modifier(self) -> Modifier {
+        match self {
+            Self::Content => Modifier::Decay(0.75),
+            Self::SugarRush => Modifier::Speed(1.15),
+            Self::Refreshed => Modifier::CooldownReduction(0.5),
+            Self::Drowsy => Modifier::Speed(0.85),
+            Self::Snacked => Modifier::Speed(1.1),
+            Self::Disgusted => Modifier::Decay(1.5),
+            Self::Sleepy => Modifier::Decay(0.5),
+            Self::Slippers => Modifier::Speed(1.5),
+            Self::PatienceCandy => Modifier::Decay(0.0),
+        }
+    }
+
+    /// Returns how the effect stacks.
+    pub const fn stacking(self) -> Stacking {
+        match self {
+            Self::SugarRush => Stacking::Stack(3),
+            Self::Content
+            | Self::Refreshed
+            | Self::Drowsy
+            | Self::Snacked
+            | Self::Disgusted
+            | Self::Sleepy
+            | Self::Slippers
+            | Self::PatienceCandy => Stacking::Refresh,
+        }
+    }
+
+    /// Returns the duration of the effect in seconds.
+    pub const fn duration(self) -> f32 {
+        match self {
+            // Refreshed while the condition holds
+            Self::Content | Self::Drowsy | Self::Disgusted | Self::Sleepy => 1.0,
+            Self::SugarRush => 5.0,
+            Self::Refreshed => 8.0,
+            Self::Snacked | Self::Slippers => 10.0,
+            Self::PatienceCandy => 15.0,
+        }
+    }
+
+    /// Returns the color of the icon of the effect.
+    const fn icon_color(self) -> Color {
+        match self {
+            Self::Content => Color::GOLD,
+            Self::SugarRush => Color::PINK,
+            Self::Refreshed => Color::CYAN,
+            Self::Drowsy => Color::INDIGO,
+            Self::Snacked => Color::TOMATO,
+            Self::Disgusted => Color::OLIVE,
+            Self::Sleepy => Color::MIDNIGHT_BLUE,
+            Self::Slippers => Color::AZURE,
+            Self::PatienceCandy => Color::FUCHSIA,
+        }
+    }
+}
+
+/// An effect active on an entity.
+#[derive(Debug)]
+struct ActiveEffect {
+    /// Kind of the effect
+    kind: StatusEffectKind,
+    /// Number of times the effect is stacked
+    stacks: u32,
+    /// Timer until the end of the effect
+    timer: Timer,
+}
+
+/// Component holding the effects active on an entity.
+#[derive(Debug, Default)]
+pub struct StatusEffects {
+    /// The active effects, at most one per kind.
+    effects: Vec<ActiveEffect>,
+}
+
+impl StatusEffects {
+    /// Applies the effect following its stacking rule.
+    pub fn apply(&mut self, kind: StatusEffectKind) {
+        let timer = Timer::from_seconds(kind.duration(), false);
+
+        match self.effects.iter_mut().find(|effect| effect.kind == kind) {
+            Some(effect) => {
+                if let Stacking::Stack(max) = kind.stacking() {
+                    effect.stacks = (effect.stacks + 1).min(max);
+                }
+                effect.timer = timer;
+            }
+            None => self.effects.push(ActiveEffect {
+                kind,
+                stacks: 1,
+                timer,
+            }),
+        }
+    }
+
+    /// Returns true if the effect is active.
+    pub fn has(&self, kind: StatusEffectKind) -> bool {
+        self.effects.iter().any(|effect| effect.kind == kind)
+    }
+
+    /// Advances the effects and removes the finished ones.
+    fn tick(&mut self, delta: Duration) {
+        for effect in &mut self.effects {
+            effect.timer.tick(delta);
+        }
+        self.effects.retain(|effect| !effect.timer.finished());
+    }
+
+    /// Returns the product of the factors of the active modifiers.
+    // Effects stack only a few times
+    #[allow(clippy::cast_possible_wrap)]
+    fn product(&self, factor: impl Fn(Modifier) -> Option<f32>) -> f32 {
+        self.effects
+            .iter()
+            .filter_map(|effect| Some(factor(effect.kind.modifier())?.powi(effect.stacks as i32)))
+            .product()
+    }
+
+    /// Returns the multiplier of the movement speed.
+    pub fn speed_multiplier(&self) -> f32 {
+        self.product(|modifier| match modifier {
+            Modifier::Speed(factor) => Some(factor),
+            _ => None,
+        })
+    }
+
+    /// Returns the multiplier of the happiness decay.
+    pub fn decay_multiplier(&self) -> f32 {
+        self.product(|modifier| match modifier {
+            Modifier::Decay(factor) => Some(factor),
+            _ => None,
+        })
+    }
+
+    /// Returns how much faster the cooldowns pass.
+    pub fn cooldown_rate(&self) -> f32 {
+        1.0 / self.product(|modifier| match modifier {
+            Modifier::CooldownReduction(reduction) => Some(1.0 - reduction),
+            _ => None,
+        })
+    }
+}
+
+/// Removes the effects of the previous game.
+fn clear_status_effects_system(mut holders: Query<&mut StatusEffects>) {
+    for mut status_effects in holders.iter_mut() {
+        *status_effects = StatusEffects::default();
+    }
+}
+
+/// Advances the effects of all the entities.
+fn tick_status_effects_system(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut holders: Query<&mut StatusEffects>,
+) {
+    let delta = time_scale.scale(time.delta());
+    for mut status_effects in holders.iter_mut() {
+        status_effects.tick(delta);
+    }
+}
+
+/// Keeps Baobei content while happy.
+fn mood_effects_system(mut baobei: Query<(&Happiness, &mut StatusEffects), With<Baobei>>) {
+    for (happiness, mut status_effects) in baobei.iter_mut() {
+        if happiness.value() >= CONTENT_HAPPINESS {
+            status_effects.apply(StatusEffectKind::Content);
+        }
+    }
+}
+
+/// Gives Didi an effect from the rest of the delivered items.
+fn item_effects_system(
+    game_data: Res<GameData>,
+    mut delivery_events: EventReader<DeliveryEvent>,
+    mut holders: Query<&mut StatusEffects>,
+) {
+    let mut didi_effects = match holders.get_mut(game_data.didi_entity) {
+        Ok(status_effects) => status_effects,
+        Err(_) => return,
+    };
+    for event in delivery_events.iter() {
+        match event.item {
+            Item::IceCream => didi_effects.apply(StatusEffectKind::SugarRush),
+            Item::WaterGlass | Item::HotTea => didi_effects.apply(StatusEffectKind::Refreshed),
+            Item::Chips
+            | Item::Coffee
+            | Item::LaundryBasket
+            | Item::Tray
+            | Item::TeaLeaves
+            | Item::DirtyDish
+            | Item::Mop
+            | Item::Custom(_) => {}
+        }
+    }
+}
+
+/// Gives an effect to the characters eating a snack themselves.
+fn consume_effects_system(
+    mut action_events: EventReader<ActionEvent>,
+    mut holders: Query<&mut StatusEffects>,
+) {
+    for action in action_events.iter() {
+        if let ActionEvent::Consume(consumer, Item::Chips) = action {
+            if let Ok(mut status_effects) = holders.get_mut(*consumer) {
+                status_effects.apply(StatusEffectKind::Snacked);
+            }
+        }
+    }
+}
+
+/// Makes Didi drowsy during the night with the hard mode mutator.
+fn mutator_effects_system(
+    game_data: Res<GameData>,
+    settings: Res<Settings>,
+    clock: Res<GameClock>,
+    mut holders: Query<&mut StatusEffects>,
+) {
+    if !settings.night_mutator || !clock.is_night() {
+        return;
+    }
+    if let Ok(mut didi_effects) = holders.get_mut(game_data.didi_entity) {
+        didi_effects.apply(StatusEffectKind::Drowsy);
+    }
+}
+
+/// Colors of the icons of the effects.
+struct StatusIconMaterials(Vec<(StatusEffectKind, Handle<ColorMaterial>)>);
+
+impl FromWorld for StatusIconMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self(
+            StatusEffectKind::ALL
+                .iter()
+                .map(|kind| (*kind, materials.add(kind.icon_color().into())))
+                .collect(),
+        )
+    }
+}
+
+/// Component on the icon of an effect, displayed near the happiness smiley.
+struct StatusIcon(StatusEffectKind);
+
+/// Spawns the hidden icons of the effects.
+fn spawn_status_icons(mut commands: Commands, materials: Res<StatusIconMaterials>) {
+    for (kind, material) in &materials.0 {
+        commands
+            .spawn()
+            .insert(StatusIcon(*kind))
+            .insert(UiObject)
+            .insert(Position(Vec3::ZERO))
+            .insert_bundle(SpriteBundle {
+                material: material.clone(),
+                sprite: Sprite::new(Vec2::new(30.0, 30.0)),
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: true,
+                },
+                ..SpriteBundle::default()
+            });
+    }
+}
+
+/// Shows the icons of the active effects in a row under the happiness smiley.
+fn update_status_icons_system(
+    holders: Query<&StatusEffects>,
+    mut icons: Query<(&StatusIcon, &mut Position, &mut Visible)>,
+) {
+    let mut next_x = 1065.0;
+
+    for (icon, mut position, mut visible) in icons.iter_mut() {
+        let active = holders.iter().any(|effects| effects.has(icon.0));
+
+        if visible.is_visible != active {
+            visible.is_visible = active;
+        }
+        if active {
+            let icon_position = Vec3::new(next_x, 220.0, 0.0);
+            if position.0 != icon_position {
+                position.0 = icon_position;
+            }
+            next_x += 40.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{StatusEffectKind, StatusEffects};
+
+    #[test]
+    fn test_stacking() {
+        let mut effects = StatusEffects::default();
+        assert!((effects.speed_multiplier() - 1.0).abs() < f32::EPSILON);
+
+        // Stacks are limited
+        for _ in 0..5 {
+            effects.apply(StatusEffectKind::SugarRush);
+        }
+        assert!((effects.speed_multiplier() - 1.15_f32.powi(3)).abs() < f32::EPSILON);
+
+        // Refreshed effects do not stack
+        effects.apply(StatusEffectKind::Refreshed);
+        effects.apply(StatusEffectKind::Refreshed);
+        assert!((effects.cooldown_rate() - 2.0).abs() < f32::EPSILON);
+
+        // Finished effects are removed
+        effects.tick(Duration::from_secs_f32(6.0));
+        assert!(!effects.has(StatusEffectKind::SugarRush));
+        assert!(effects.has(StatusEffectKind::Refreshed));
+        assert!((effects.speed_multiplier() - 1.0).abs() < f32::EPSILON);
+    }
+}
@@ -0,0 +1,180 @@
+//! Stock of the item producers: taking an item empties the producer for a
+//! while, shown by a greyed-out icon, until it restocks. A ring around the
+//! icon shows the time until the next item is restocked. The producers are
+//! also filled during the breathers, while Baobei naps.
+
+use bevy::prelude::*;
+
+use crate::{
+    constants::GameState,
+    difficulty::Difficulty,
+    time_scale::TimeScale,
+    widgets::{entity_timer_system, spawn_timer_ring, EntityTimer, WidgetMaterials},
+};
+
+use super::{
+    cues::CueEvent,
+    items::ItemProducer,
+    materials::GameplayMaterials,
+    phases::{PhaseEvent, PhaseKind},
+    registry::ItemSprites,
+    NewRunAppExt,
+};
+
+/// Plugin managing the stock of the producers.
+pub struct StockPlugin;
+
+impl Plugin for StockPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<StockMaterials>()
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(add_stock_icon_system.system())
+                    .with_system(
+                        restock_system
+                            .system()
+                            .label("restock")
+                            .before("item_actions"),
+                    )
+                    .with_system(
+                        entity_timer_system::<ItemProducer>
+                            .system()
+                            .after("restock"),
+                    )
+                    .with_system(
+                        breather_restock_system
+                            .system()
+                            .after("phase_controller")
+                            .before("item_actions"),
+                    )
+                    .with_system(update_stock_icon_system.system().after("item_actions")),
+            )
+            .add_new_run_system(refill_producers_system);
+    }
+}
+
+/// Component on the icon showing whether a producer has items in stock.
+struct StockIcon;
+
+/// Greyed-out sprites of the producers out of stock.
+struct StockMaterials(ItemSprites);
+
+impl FromWorld for StockMaterials {
+    fn from_world(world: &mut World) -> Self {
+        Self(ItemSprites::load(world, Color::rgba(0.3, 0.3, 0.3, 0.6)))
+    }
+}
+
+/// Shows an icon of the produced item above the new producers, in a ring
+/// bound to the restock.
+fn add_stock_icon_system(
+    mut commands: Commands,
+    materials: Res<GameplayMaterials>,
+    widget_materials: Res<WidgetMaterials>,
+    producers: Query<(Entity, &ItemProducer), Added<ItemProducer>>,
+) {
+    for (entity, producer) in producers.iter() {
+        commands
+            .entity(entity)
+            .insert_bundle((Transform::default(), GlobalTransform::default()))
+            .with_children(|parent| {
+                parent
+                    .spawn()
+                    .insert(StockIcon)
+                    .insert_bundle(SpriteBundle {
+                        material: materials.item_sprite_for(producer.item),
+                        transform: Transform {
+                            translation: Vec3::new(0.0, 40.0, 1.0),
+                            scale: Vec3::new(0.12, 0.12, 0.0),
+                            ..Transform::default()
+                        },
+                        ..SpriteBundle::default()
+                    });
+            });
+
+        let restock_ring = spawn_timer_ring(
+            &mut commands,
+            &widget_materials,
+            Vec3::new(0.0, 40.0, 1.1),
+            18.0,
+            12,
+        );
+        commands
+            .entity(restock_ring)
+            .insert(EntityTimer::<ItemProducer>::new(entity));
+        commands.entity(entity).push_children(&[restock_ring]);
+    }
+}
+
+/// Restocks the producers in game time, cueing the empty ones getting an
+/// item again.
+fn restock_system(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut cue_events: EventWriter<CueEvent>,
+    mut producers: Query<(Entity, &mut ItemProducer)>,
+) {
+    let delta = time_scale.scale(time.delta()).as_secs_f32();
+
+    for (entity, mut producer) in producers.iter_mut() {
+        // Reading the full producers does not mark them as changed
+        if producer.is_full() {
+            continue;
+        }
+        let was_empty = producer.is_empty();
+        producer.tick(delta);
+        if was_empty && !producer.is_empty() {
+            cue_events.send(CueEvent::Restocked(entity));
+        }
+    }
+}
+
+/// Fills the producers when a breather starts.
+fn breather_restock_system(
+    difficulty: Res<Difficulty>,
+    mut phase_events: EventReader<PhaseEvent>,
+    mut producers: Query<&mut ItemProducer>,
+) {
+    let breather = phase_events
+        .iter()
+        .any(|event| event.kind == PhaseKind::Breather);
+    if !breather {
+        return;
+    }
+    let restock_duration = difficulty.profile().restock_duration;
+    for mut producer in producers.iter_mut() {
+        producer.refill(restock_duration);
+    }
+}
+
+/// Greys out the icon of the producers out of stock.
+fn update_stock_icon_system(
+    materials: Res<GameplayMaterials>,
+    stock_materials: Res<StockMaterials>,
+    producers: Query<(&ItemProducer, &Children), Changed<ItemProducer>>,
+    mut icons: Query<&mut Handle<ColorMaterial>, With<StockIcon>>,
+) {
+    for (producer, children) in producers.iter() {
+        let sprite = if producer.is_empty() {
+            stock_materials.0.get(producer.item)
+        } else {
+            materials.item_sprite_for(producer.item)
+        };
+        for child in children.iter() {
+            if let Ok(mut material) = icons.get_mut(*child) {
+                if *material != sprite {
+                    *material = sprite.clone();
+                }
+            }
+        }
+    }
+}
+
+/// Fills the producers when a new game starts, restocking at the pace of
+/// the difficulty.
+fn refill_producers_system(difficulty: Res<Difficulty>, mut producers: Query<&mut ItemProducer>) {
+    let restock_duration = difficulty.profile().restock_duration;
+    for mut producer in producers.iter_mut() {
+        producer.refill(restock_duration);
+    }
+}
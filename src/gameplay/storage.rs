@@ -0,0 +1,207 @@
+//! Storage furniture where Didi can stage items to give them later.
+
+use bevy::prelude::*;
+
+use crate::{
+    collisions::{BoxCollider, Position, TriggerArea},
+    constants::GameState,
+};
+
+use super::{items::Item, materials::GameplayMaterials, Furniture};
+
+/// Plugin managing the storage furniture.
+pub struct StoragePlugin;
+
+impl Plugin for StoragePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<StorageMaterials>()
+            .add_startup_system(spawn_storages.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(update_storage_slots_system.system()),
+            );
+    }
+}
+
+/// Component on furniture storing a limited number of items.
+pub struct Storage {
+    /// The slots, each one holding at most one item.
+    slots: Vec<Option<Item>>,
+}
+
+impl Storage {
+    /// Creates an empty storage with the given number of slots.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            slots: vec![None; capacity],
+        }
+    }
+
+    /// Returns true if all the slots hold an item.
+    pub fn is_full(&self) -> bool {
+        self.slots.iter().all(Option::is_some)
+    }
+
+    /// Returns true if no slot holds an item.
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(Option::is_none)
+    }
+
+    /// Returns true if a slot holds the item.
+    pub fn contains(&self, item: Item) -> bool {
+        self.slots.contains(&Some(item))
+    }
+
+    /// Puts the item in the first free slot, returns false if the storage is
+    /// full.
+    pub fn deposit(&mut self, item: Item) -> bool {
+        match self.slots.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => {
+                *slot = Some(item);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Takes the item of the last filled slot.
+    pub fn retrieve(&mut self) -> Option<Item> {
+        self.slots.iter_mut().rev().find_map(Option::take)
+    }
+
+    /// Removes all the stored items.
+    pub fn clear(&mut self) {
+        for slot in &mut self.slots {
+            *slot = None;
+        }
+    }
+}
+
+/// Component on the sprite of a slot of a storage, with its index.
+struct StorageSlot(usize);
+
+/// Colors of the storage furniture.
+struct StorageMaterials {
+    /// Color of the wooden furniture
+    wood: Handle<ColorMaterial>,
+}
+
+impl FromWorld for StorageMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            wood: materials.add(Color::rgb(0.5, 0.33, 0.2).into()),
+        }
+    }
+}
+
+/// Spawns the shelf and the side table.
+fn spawn_storages(mut commands: Commands, materials: Res<StorageMaterials>) {
+    // Shelf
+    spawn_storage(
+        &mut commands,
+        &materials,
+        Vec3::new(585.0, 530.0, 0.0),
+        Vec2::new(120.0, 40.0),
+        3,
+    );
+    // Side table
+    spawn_storage(
+        &mut commands,
+        &materials,
+        Vec3::new(1190.0, 160.0, 0.0),
+        Vec2::new(60.0, 50.0),
+        1,
+    );
+}
+
+/// Spawns a storage furniture with empty slots on top of it.
+fn spawn_storage(
+    commands: &mut Commands,
+    materials: &StorageMaterials,
+    position: Vec3,
+    size: Vec2,
+    capacity: usize,
+) {
+    /// Horizontal space between two slots
+    const SLOT_SPACING: f32 = 35.0;
+
+    commands
+        .spawn()
+        .insert(Furniture)
+        .insert(Storage::new(capacity))
+        .insert(Position(position))
+        .insert(BoxCollider::new(size.x, size.y))
+        .insert(TriggerArea::new(size.x + 80.0, size.y + 80.0))
+        .insert_bundle(SpriteBundle {
+            material: materials.wood.clone(),
+            sprite: Sprite::new(size),
+            ..SpriteBundle::default()
+        })
+        .with_children(|parent| {
+            for index in 0..capacity {
+                let x = (index as f32 - (capacity - 1) as f32 / 2.0) * SLOT_SPACING;
+
+                parent
+                    .spawn()
+                    .insert(StorageSlot(index))
+                    .insert_bundle(SpriteBundle {
+                        transform: Transform {
+                            translation: Vec3::new(x, size.y / 2.0, 0.1),
+                            scale: Vec3::new(0.15, 0.15, 0.0),
+                            ..Transform::default()
+                        },
+                        visible: Visible {
+                            is_visible: false,
+                            is_transparent: true,
+                        },
+                        ..SpriteBundle::default()
+                    });
+            }
+        });
+}
+
+/// Shows the stored items on top of the furniture.
+fn update_storage_slots_system(
+    materials: Res<GameplayMaterials>,
+    storages: Query<(&Storage, &Children), Changed<Storage>>,
+    mut slots: Query<(&StorageSlot, &mut Handle<ColorMaterial>, &mut Visible)>,
+) {
+    for (storage, children) in storages.iter() {
+        for &child in children.iter() {
+            if let Ok((slot, mut material, mut visible)) = slots.get_mut(child) {
+                match storage.slots.get(slot.0).copied().flatten() {
+                    Some(item) => {
+                        *material = materials.item_sprite_for(item);
+                        visible.is_visible = true;
+                    }
+                    None => visible.is_visible = false,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Item, Storage};
+
+    #[test]
+    fn test_storage_slots() {
+        let mut storage = Storage::new(2);
+        assert!(storage.is_empty());
+
+        assert!(storage.deposit(Item::Chips));
+        assert!(storage.deposit(Item::IceCream));
+        assert!(storage.is_full());
+        assert!(!storage.deposit(Item::WaterGlass));
+
+        // The last stored item is retrieved first
+        assert_eq!(storage.retrieve(), Some(Item::IceCream));
+        assert!(storage.deposit(Item::WaterGlass));
+        assert_eq!(storage.retrieve(), Some(Item::WaterGlass));
+        assert_eq!(storage.retrieve(), Some(Item::Chips));
+        assert_eq!(storage.retrieve(), None);
+    }
+}
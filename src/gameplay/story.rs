@@ -0,0 +1,395 @@
+//! Script of the story mode: a short campaign of timed events played on top
+//! of the daily routine, where Baobei and Didi talk, Baobei asks for items
+//! and the camera frames a part of the room like in a cutscene.
+//!
+//! The events are scripted in `assets/story.cfg`, written like the tutorial.
+//! Each event is described by `<event>.<field>` lines: the second of the game
+//! it happens `at` and its `action`. The dialogues are written by
+//! `text.<key>.<language>` lines.
+
+use std::{cmp::Ordering, fs, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::{
+    camera::CameraShot,
+    constants::{GameMode, GameState},
+    locale::Language,
+    save::{parse_argument, parse_seconds, split_argument, SaveData},
+    settings::Settings,
+    time_scale::TimeScale,
+};
+
+use super::{
+    items::{Item, ItemRequestQueue},
+    registry::ItemRegistry,
+    Baobei,
+};
+
+/// Plugin playing the script of the story mode.
+pub struct StoryPlugin;
+
+impl Plugin for StoryPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<StoryScript>()
+            .init_resource::<Story>()
+            .add_startup_system(spawn_dialogue_text.system())
+            .add_system_set(
+                SystemSet::on_enter(GameState::InGame).with_system(start_story_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(story_system.system().label("story"))
+                    .with_system(dialogue_text_system.system().after("story")),
+            );
+    }
+}
+
+/// File scripting the events of the story.
+const SCRIPT_FILE: &str = "assets/story.cfg";
+/// Script used when the file cannot be read, a copy of the file at build
+/// time.
+const BUILT_IN_SCRIPT: &str = include_str!("../../assets/story.cfg");
+/// Seconds a line of dialogue stays on screen.
+const DIALOGUE_DURATION: f32 = 4.0;
+
+/// Character saying a line of the story.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Speaker {
+    /// Baobei, asking for the items
+    Baobei,
+    /// Didi, played by the player
+    Didi,
+}
+
+impl Speaker {
+    /// Returns the name shown before the lines of the speaker.
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Baobei => "Baobei",
+            Self::Didi => "Didi",
+        }
+    }
+}
+
+/// What happens at an event of the story.
+#[derive(Debug, Clone, PartialEq)]
+enum StoryAction {
+    /// The speaker says the text of the key
+    Say(Speaker, String),
+    /// Baobei asks for the item right away
+    Request(Item),
+    /// The camera frames a point of the room
+    Camera {
+        /// Point of the room at the center of the screen
+        center: Vec2,
+        /// Fraction of the room shown
+        zoom: f32,
+        /// Seconds before the camera frames the characters again
+        duration: f32,
+    },
+}
+
+/// An event of the script.
+#[derive(Debug, Clone, PartialEq)]
+struct StoryEvent {
+    /// Seconds since the start of the game
+    at: f32,
+    /// What happens
+    action: StoryAction,
+}
+
+/// Events and texts of the story.
+#[derive(Debug, Clone, PartialEq)]
+struct StoryScript {
+    /// The events, in the order of their time
+    events: Vec<StoryEvent>,
+    /// Entries of the script, holding the texts
+    data: SaveData,
+}
+
+impl FromWorld for StoryScript {
+    fn from_world(world: &mut World) -> Self {
+        let registry = world.get_resource::<ItemRegistry>().unwrap();
+        let script = fs::read_to_string(SCRIPT_FILE)
+            .map_err(|error| error.to_string())
+            .and_then(|content| Self::parse(&SaveData::parse(&content), registry));
+
+        script.unwrap_or_else(|error| {
+            warn!(
+                "Fail to read {}, using the built-in story: {}",
+                SCRIPT_FILE, error
+            );
+            Self::parse(&SaveData::parse(BUILT_IN_SCRIPT), registry).unwrap()
+        })
+    }
+}
+
+impl StoryScript {
+    /// Reads the events of the script, failing on the first invalid field.
+    fn parse(data: &SaveData, registry: &ItemRegistry) -> Result<Self, String> {
+        let ids = data.get::<String>("events").unwrap_or_default();
+
+        let mut events = ids
+            .split_whitespace()
+            .map(|id| {
+                let field = |name: &str| {
+                    data.get::<String>(&format!("{}.{}", id, name))
+                        .ok_or_else(|| format!("Missing field {}.{}", id, name))
+                };
+                let action = match split_argument(&field("action")?) {
+                    ("say", argument) => {
+                        let (speaker, text) = split_argument(argument);
+                        let speaker = match speaker {
+                            "baobei" => Speaker::Baobei,
+                            "didi" => Speaker::Didi,
+                            other => return Err(format!("Unknown speaker: {}", other)),
+                        };
+                        StoryAction::Say(speaker, text.trim().to_string())
+                    }
+                    ("request", id) => StoryAction::Request(
+                        registry
+                            .find(id)
+                            .ok_or_else(|| format!("Unknown item: {}", id))?,
+                    ),
+                    ("camera", argument) => {
+                        match argument.split_whitespace().collect::<Vec<_>>()[..] {
+                            [x, y, zoom, duration] => StoryAction::Camera {
+                                center: Vec2::new(parse_argument(x)?, parse_argument(y)?),
+                                zoom: parse_argument(zoom)?,
+                                duration: parse_seconds(duration)?,
+                            },
+                            _ => return Err(format!("Invalid camera: {}", argument)),
+                        }
+                    }
+                    (other, _) => return Err(format!("Unknown action: {}", other)),
+                };
+
+                Ok(StoryEvent {
+                    at: parse_seconds(&field("at")?)?,
+                    action,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        events.sort_by(|a, b| a.at.partial_cmp(&b.at).unwrap_or(Ordering::Equal));
+        Ok(Self {
+            events,
+            data: data.clone(),
+        })
+    }
+
+    /// Returns the text of the key in the language, in English if missing.
+    fn text(&self, key: &str, language: Language) -> String {
+        self.data
+            .get(&format!("text.{}.{}", key, language))
+            .or_else(|| {
+                self.data
+                    .get(&format!("text.{}.{}", key, Language::English))
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Progress of the story.
+struct Story {
+    /// Whether a story is being played
+    active: bool,
+    /// Seconds since the start of the game
+    elapsed: f32,
+    /// Index of the next event, the number of events once all played
+    next: usize,
+    /// Speaker and text key of the line on screen
+    dialogue: Option<(Speaker, String)>,
+    /// Timer until the line disappears
+    dialogue_timer: Timer,
+    /// Timer until the camera frames the characters again
+    shot_timer: Timer,
+}
+
+impl Default for Story {
+    fn default() -> Self {
+        Self {
+            active: false,
+            elapsed: 0.0,
+            next: 0,
+            dialogue: None,
+            dialogue_timer: Timer::from_seconds(DIALOGUE_DURATION, false),
+            shot_timer: Timer::from_seconds(0.0, false),
+        }
+    }
+}
+
+impl Story {
+    /// Goes forward by the seconds, returning the events happening meanwhile.
+    fn advance<'a>(&mut self, script: &'a StoryScript, seconds: f32) -> &'a [StoryEvent] {
+        self.elapsed += seconds;
+        let first = self.next;
+        while script
+            .events
+            .get(self.next)
+            .map_or(false, |event| event.at <= self.elapsed)
+        {
+            self.next += 1;
+        }
+        &script.events[first..self.next]
+    }
+}
+
+/// Tag the text showing the line of dialogue.
+struct DialogueText;
+
+/// Spawns the text showing the lines of dialogue at the bottom of the
+/// screen.
+fn spawn_dialogue_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn()
+        .insert(DialogueText)
+        .insert_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(40.0),
+                    left: Val::Px(320.0),
+                    ..Rect::default()
+                },
+                ..Style::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("FiraSans-Bold.ttf"),
+                    font_size: 35.0,
+                    color: Color::WHITE,
+                },
+                TextAlignment::default(),
+            ),
+            ..TextBundle::default()
+        });
+}
+
+/// Starts the story from its first event when a game starts in the story
+/// mode.
+fn start_story_system(mode: Res<GameMode>, mut story: ResMut<Story>) {
+    *story = Story {
+        active: *mode == GameMode::Story,
+        ..Story::default()
+    };
+}
+
+/// Plays the events of the story at their time: shows the lines, makes
+/// Baobei ask for the items and moves the camera until the end of the shot.
+fn story_system(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    script: Res<StoryScript>,
+    mut story: ResMut<Story>,
+    mut shot: ResMut<CameraShot>,
+    mut baobei: Query<&mut ItemRequestQueue, With<Baobei>>,
+) {
+    if !story.active {
+        return;
+    }
+    let delta = time_scale.scale(time.delta());
+
+    if story.dialogue.is_some() && story.dialogue_timer.tick(delta).just_finished() {
+        story.dialogue = None;
+    }
+    if shot.0.is_some() && story.shot_timer.tick(delta).just_finished() {
+        shot.0 = None;
+    }
+
+    for event in story.advance(&script, delta.as_secs_f32()) {
+        info!("Story event at {}s: {:?}", event.at, event.action);
+        match &event.action {
+            StoryAction::Say(speaker, key) => {
+                story.dialogue = Some((*speaker, key.clone()));
+                story.dialogue_timer.reset();
+            }
+            StoryAction::Request(item) => {
+                for mut requests in baobei.iter_mut() {
+                    if requests.front() != Some(*item) {
+                        requests.0.push_front(*item);
+                    }
+                }
+            }
+            StoryAction::Camera {
+                center,
+                zoom,
+                duration,
+            } => {
+                shot.0 = Some((*center, *zoom));
+                story.shot_timer = Timer::new(Duration::from_secs_f32(*duration), false);
+            }
+        }
+    }
+}
+
+/// Shows the line of dialogue on screen, in the language of the settings.
+fn dialogue_text_system(
+    script: Res<StoryScript>,
+    settings: Res<Settings>,
+    story: Res<Story>,
+    mut texts: Query<&mut Text, With<DialogueText>>,
+) {
+    let line = story
+        .dialogue
+        .as_ref()
+        .map(|(speaker, key)| {
+            format!(
+                "{}: {}",
+                speaker.name(),
+                script.text(key, settings.language)
+            )
+        })
+        .unwrap_or_default();
+
+    for mut text in texts.iter_mut() {
+        if text.sections[0].value != line {
+            text.sections[0].value = line.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_played_at_their_time() {
+        let registry = ItemRegistry::default();
+        let script = StoryScript::parse(&SaveData::parse(BUILT_IN_SCRIPT), &registry).unwrap();
+        let mut story = Story::default();
+
+        assert!(story.advance(&script, 0.5).is_empty());
+        let intro = story.advance(&script, 1.0);
+        assert_eq!(intro.len(), 2);
+        assert!(matches!(intro[0].action, StoryAction::Camera { .. }));
+        assert_eq!(
+            intro[1].action,
+            StoryAction::Say(Speaker::Baobei, "wake_up".to_string())
+        );
+        assert_eq!(
+            story.advance(&script, 3.0)[0].action,
+            StoryAction::Request(Item::Coffee)
+        );
+
+        story.advance(&script, 1000.0);
+        assert_eq!(story.next, script.events.len());
+        assert!(story.advance(&script, 1.0).is_empty());
+        assert_eq!(script.text("on_it", Language::French), "J'arrive, Baobei !");
+    }
+
+    #[test]
+    fn invalid_scripts_are_rejected() {
+        let registry = ItemRegistry::default();
+        let parse = |content: &str| StoryScript::parse(&SaveData::parse(content), &registry);
+
+        assert!(parse("events = a\na.at = 1\na.action = say nobody hello").is_err());
+        assert!(parse("events = a\na.at = 1\na.action = camera 1 2").is_err());
+        assert!(parse("events = a\na.action = request coffee").is_err());
+        assert!(parse("events = a\na.at = 1\na.action = camera 1 2 0.5 -3").is_err());
+        assert!(parse("events = a\na.at = -1\na.action = request coffee").is_err());
+        assert!(parse("events = a\na.at = 1\na.action = camera 1 2 0.5 3").is_ok());
+    }
+}
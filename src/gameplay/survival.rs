@@ -0,0 +1,226 @@
+//! Survival mode: the player keeps Baobei happy until the end of a
+//! countdown, with a victory screen at the end.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    assists::Assists,
+    constants::{GameMode, GameState},
+    controllers::{BindingText, InputAction, InputMap},
+    settings::Settings,
+    time_scale::TimeScale,
+};
+
+use super::{happiness::Happiness, score::Score, stats::SessionStats, Baobei};
+
+/// Plugin managing the survival mode.
+pub struct SurvivalPlugin;
+
+impl Plugin for SurvivalPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Survival>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::InGame).with_system(start_survival_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame).with_system(survival_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::SurvivalResults).with_system(setup_results.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::SurvivalResults)
+                    .with_system(results_input_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::SurvivalResults)
+                    .with_system(cleanup_results.system()),
+            );
+    }
+}
+
+/// State of the survival run.
+pub struct Survival {
+    /// Whether a survival run is being played.
+    active: bool,
+    /// Countdown until the victory.
+    timer: Timer,
+    /// Whether Baobei stayed happy until the end of the last run.
+    won: bool,
+}
+
+impl Default for Survival {
+    fn default() -> Self {
+        Self {
+            active: false,
+            timer: Timer::from_seconds(0.0, false),
+            won: false,
+        }
+    }
+}
+
+impl Survival {
+    /// Returns the seconds left before the victory, if a survival run is
+    /// being played.
+    pub fn remaining_secs(&self) -> Option<f32> {
+        self.active.then(|| self.secs_left())
+    }
+
+    /// Returns the seconds left on the countdown.
+    fn secs_left(&self) -> f32 {
+        self.timer.duration().as_secs_f32() - self.timer.elapsed_secs()
+    }
+}
+
+/// Formats the seconds left as minutes and seconds, rounded up.
+// The countdown is positive and far from `u32::MAX` seconds
+#[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+pub fn format_countdown(seconds: f32) -> String {
+    let seconds = seconds.max(0.0).ceil() as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Starts the countdown when a game starts in the survival mode.
+fn start_survival_system(
+    mode: Res<GameMode>,
+    settings: Res<Settings>,
+    mut survival: ResMut<Survival>,
+) {
+    survival.active = *mode == GameMode::Survival;
+    survival
+        .timer
+        .set_duration(Duration::from_secs_f32(settings.survival_duration));
+    survival.timer.reset();
+}
+
+/// Ends the survival run with a victory at the end of the countdown, or
+/// with a defeat when Baobei has no happiness left, except in kid mode.
+fn survival_system(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    assists: Res<Assists>,
+    mut survival: ResMut<Survival>,
+    mut state: ResMut<State<GameState>>,
+    baobei: Query<&Happiness, With<Baobei>>,
+) {
+    if !survival.active {
+        return;
+    }
+    let lost = !assists.no_fail && baobei.iter().any(|happiness| happiness.value() <= 0.0);
+    let delta = time_scale.scale(time.delta());
+    let won = !lost && survival.timer.tick(delta).finished();
+
+    if lost || won {
+        info!("Survival ended, victory: {}", won);
+        survival.active = false;
+        survival.won = won;
+        state.set(GameState::SurvivalResults).unwrap();
+    }
+}
+
+/// Stores entities of the results screen
+struct ResultsData {
+    /// Entity wrapping all the results entities
+    node_wrapper: Entity,
+}
+
+/// Shows the victory or the defeat with the score and the statistics of the
+/// run.
+fn setup_results(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    survival: Res<Survival>,
+    score: Res<Score>,
+    stats: Res<SessionStats>,
+) {
+    let font = asset_server.load("FiraSans-Bold.ttf");
+    let text = |value: String, font_size: f32| TextBundle {
+        text: Text::with_section(
+            value,
+            TextStyle {
+                font: font.clone(),
+                font_size,
+                color: Color::WHITE,
+            },
+            TextAlignment::default(),
+        ),
+        ..TextBundle::default()
+    };
+
+    let title = if survival.won {
+        "Victory! Baobei stayed happy".to_string()
+    } else {
+        format!(
+            "Baobei is too sad… {} left",
+            format_countdown(survival.secs_left())
+        )
+    };
+
+    let node_wrapper = commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                padding: Rect::all(Val::Px(50.0)),
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Style::default()
+            },
+            material: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.8).into()),
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            parent.spawn().insert_bundle(text(title, 60.0));
+            parent
+                .spawn()
+                .insert_bundle(text(format!("Score: {}", score.points()), 40.0));
+            for line in stats.summary_lines() {
+                parent.spawn().insert_bundle(text(line, 30.0));
+            }
+            parent
+                .spawn()
+                .insert(BindingText(
+                    "Press {confirm} to go back to the menu".to_string(),
+                ))
+                .insert_bundle(text(String::new(), 30.0));
+        })
+        .id();
+
+    commands.insert_resource(ResultsData { node_wrapper });
+}
+
+/// Goes back to the menu.
+fn results_input_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    input_map: Res<InputMap>,
+    mut state: ResMut<State<GameState>>,
+) {
+    let pressed = |action| input_map.just_pressed(action, &keyboard_input, &gamepad_buttons);
+    if pressed(InputAction::Confirm) || pressed(InputAction::Back) {
+        state.set(GameState::Menu).unwrap();
+    }
+}
+
+/// Removes all entities of the results screen.
+fn cleanup_results(mut commands: Commands, results_data: Res<ResultsData>) {
+    commands
+        .entity(results_data.node_wrapper)
+        .despawn_recursive();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format_countdown;
+
+    #[test]
+    fn test_format_countdown() {
+        assert_eq!(format_countdown(180.0), "3:00");
+        assert_eq!(format_countdown(61.2), "1:02");
+        assert_eq!(format_countdown(0.4), "0:01");
+        assert_eq!(format_countdown(-3.0), "0:00");
+    }
+}
@@ -0,0 +1,92 @@
+//! Trash can where Didi throws away the unwanted items, instead of walking
+//! back to their producer.
+
+use bevy::prelude::*;
+
+use crate::{
+    collisions::{BoxCollider, Contact, Position, TriggerArea},
+    constants::GameState,
+};
+
+use super::{
+    entities::GameData,
+    items::{ActionEvent, Inventory, Item, ItemSink, PickAndDropCooldown},
+    Furniture,
+};
+
+/// Plugin managing the trash can.
+pub struct TrashPlugin;
+
+impl Plugin for TrashPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<TrashMaterials>()
+            .add_startup_system(spawn_trash_can.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(discard_system.system().before("item_actions")),
+            );
+    }
+}
+
+/// Colors of the trash can.
+struct TrashMaterials {
+    /// Color of the metal can
+    metal: Handle<ColorMaterial>,
+}
+
+impl FromWorld for TrashMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            metal: materials.add(Color::rgb(0.45, 0.5, 0.55).into()),
+        }
+    }
+}
+
+/// Spawns the trash can next to the couch.
+fn spawn_trash_can(mut commands: Commands, materials: Res<TrashMaterials>) {
+    let size = Vec2::new(50.0, 40.0);
+
+    commands
+        .spawn()
+        .insert(Furniture)
+        .insert(ItemSink)
+        .insert(Position(Vec3::new(760.0, 130.0, 0.0)))
+        .insert(BoxCollider::new(size.x, size.y))
+        .insert(TriggerArea::new(size.x + 80.0, size.y + 80.0))
+        .insert_bundle(SpriteBundle {
+            material: materials.metal.clone(),
+            sprite: Sprite::new(size),
+            ..SpriteBundle::default()
+        });
+}
+
+/// Throws away the item in hand when Didi uses the trash can. The laundry
+/// basket is needed for the chore and cannot be thrown away.
+fn discard_system(
+    keyboard: Res<Input<KeyCode>>,
+    game_data: Res<GameData>,
+    mut cooldown: ResMut<PickAndDropCooldown>,
+    mut action_events: EventWriter<ActionEvent>,
+    contacts: Query<&Contact>,
+    inventories: Query<&Inventory>,
+    sinks: Query<(), With<ItemSink>>,
+) {
+    let didi = game_data.didi_entity;
+    if !cooldown.0.available() || !keyboard.pressed(KeyCode::Space) {
+        return;
+    }
+    let item = match inventories.get(didi).ok().and_then(Inventory::active_item) {
+        Some(item) if item != Item::LaundryBasket => item,
+        _ => return,
+    };
+    let touches_sink = contacts
+        .iter()
+        .any(|contact| contact.0 == didi && sinks.get(contact.1).is_ok());
+
+    if touches_sink {
+        action_events.send(ActionEvent::Discard(item));
+        cooldown.0.start();
+    }
+}
@@ -0,0 +1,569 @@
+//! Interactive tutorial, started with `T` in the menu: the game phase is
+//! pushed on top of the tutorial, which shows the next step to do and
+//! highlights its target. A step is done when the contact or the action it
+//! waits for happens, the later steps being ignored until then.
+//!
+//! The steps are scripted in `assets/tutorial.cfg`, so new tutorials can be
+//! written without recompiling the game. Each step is described by
+//! `<step>.<field> = <value>` lines: its `trigger`, the `highlight` target,
+//! the `text` key of its instruction, what it is `done` by and the item
+//! Baobei asks for once done (`request`). The instructions are written by
+//! `text.<key>.<language>` lines.
+
+use std::fs;
+
+use bevy::prelude::*;
+
+use crate::{
+    collisions::{Contact, ContactEvent, Position},
+    constants::GameState,
+    drawing::Overlay,
+    locale::Language,
+    save::{parse_seconds, split_argument, SaveData},
+    settings::Settings,
+};
+
+use super::{
+    entities::GameData,
+    items::{ActionEvent, DeliveryEvent, Item, ItemProducer, ItemRequestQueue, ItemSystems},
+    registry::ItemRegistry,
+    Baobei,
+};
+
+/// Plugin managing the tutorial.
+pub struct TutorialPlugin;
+
+impl Plugin for TutorialPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<TutorialScript>()
+            .init_resource::<Tutorial>()
+            .init_resource::<TutorialMaterials>()
+            .add_system_set(
+                SystemSet::on_update(GameState::Menu).with_system(start_tutorial_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Tutorial).with_system(setup_tutorial.system()),
+            )
+            .add_system_set(
+                SystemSet::on_in_stack_update(GameState::Tutorial)
+                    .with_system(trigger_system.system().before("tutorial_progress"))
+                    .with_system(
+                        progress_system
+                            .system()
+                            .label("tutorial_progress")
+                            .after(ItemSystems),
+                    )
+                    .with_system(instruction_system.system().after("tutorial_progress"))
+                    .with_system(highlight_system.system().after("tutorial_progress"))
+                    .with_system(finish_tutorial_system.system().after("tutorial_progress")),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Tutorial).with_system(cleanup_tutorial.system()),
+            );
+    }
+}
+
+/// File scripting the steps of the tutorial.
+const SCRIPT_FILE: &str = "assets/tutorial.cfg";
+/// Script used when the file cannot be read, a copy of the file at build
+/// time.
+const BUILT_IN_SCRIPT: &str = include_str!("../../assets/tutorial.cfg");
+/// Seconds the last message stays before going back to the menu.
+const OUTRO_DURATION: f32 = 4.0;
+
+/// When a step starts once the previous one is done.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Trigger {
+    /// Right away
+    Now,
+    /// After the seconds
+    Wait(f32),
+}
+
+/// What the tutorial highlights during a step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Highlight {
+    /// The producer of the item
+    Producer(Item),
+    /// Baobei
+    Baobei,
+    /// Nothing
+    None,
+}
+
+/// What completes a step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Completion {
+    /// Didi reaches the producer of the item
+    Reach(Item),
+    /// Didi takes the item in a producer
+    Take(Item),
+    /// Baobei receives an item
+    Deliver,
+}
+
+/// A step of the script.
+#[derive(Debug, Clone, PartialEq)]
+struct ScriptStep {
+    /// When the step starts
+    trigger: Trigger,
+    /// Target highlighted during the step
+    highlight: Highlight,
+    /// Key of the instruction shown during the step
+    text: String,
+    /// What completes the step
+    done: Completion,
+    /// Item Baobei asks for once the step is done
+    request: Option<Item>,
+}
+
+/// Steps and texts of the tutorial.
+#[derive(Debug, Clone, PartialEq)]
+struct TutorialScript {
+    /// The steps, done in order
+    steps: Vec<ScriptStep>,
+    /// Key of the text shown once all the steps are done
+    outro: String,
+    /// Entries of the script, holding the texts
+    data: SaveData,
+}
+
+impl FromWorld for TutorialScript {
+    fn from_world(world: &mut World) -> Self {
+        let registry = world.get_resource::<ItemRegistry>().unwrap();
+        let script = fs::read_to_string(SCRIPT_FILE)
+            .map_err(|error| error.to_string())
+            .and_then(|content| Self::parse(&SaveData::parse(&content), registry));
+
+        script.unwrap_or_else(|error| {
+            warn!(
+                "Fail to read {}, using the built-in tutorial: {}",
+                SCRIPT_FILE, error
+            );
+            Self::parse(&SaveData::parse(BUILT_IN_SCRIPT), registry).unwrap()
+        })
+    }
+}
+
+impl TutorialScript {
+    /// Reads the steps of the script, failing on the first invalid field.
+    fn parse(data: &SaveData, registry: &ItemRegistry) -> Result<Self, String> {
+        let item = |id: &str| {
+            registry
+                .find(id)
+                .ok_or_else(|| format!("Unknown item: {}", id))
+        };
+        let ids = data.get::<String>("steps").unwrap_or_default();
+
+        let steps = ids
+            .split_whitespace()
+            .map(|id| {
+                let field = |name: &str| {
+                    data.get::<String>(&format!("{}.{}", id, name))
+                        .ok_or_else(|| format!("Missing field {}.{}", id, name))
+                };
+                let trigger = match field("trigger").ok().as_deref().map(split_argument) {
+                    None | Some(("now", _)) => Trigger::Now,
+                    Some(("wait", seconds)) => Trigger::Wait(parse_seconds(seconds)?),
+                    Some((other, _)) => return Err(format!("Unknown trigger: {}", other)),
+                };
+                let highlight = match split_argument(&field("highlight")?) {
+                    ("producer", id) => Highlight::Producer(item(id)?),
+                    ("baobei", _) => Highlight::Baobei,
+                    ("none", _) => Highlight::None,
+                    (other, _) => return Err(format!("Unknown highlight: {}", other)),
+                };
+                let done = match split_argument(&field("done")?) {
+                    ("reach", id) => Completion::Reach(item(id)?),
+                    ("take", id) => Completion::Take(item(id)?),
+                    ("deliver", _) => Completion::Deliver,
+                    (other, _) => return Err(format!("Unknown completion: {}", other)),
+                };
+                let request = match field("request") {
+                    Ok(id) => Some(item(&id)?),
+                    Err(_) => None,
+                };
+
+                Ok(ScriptStep {
+                    trigger,
+                    highlight,
+                    text: field("text")?,
+                    done,
+                    request,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        if steps.is_empty() {
+            return Err("No steps".to_string());
+        }
+        Ok(Self {
+            steps,
+            outro: data.get("outro").unwrap_or_default(),
+            data: data.clone(),
+        })
+    }
+
+    /// Returns the text of the key in the language, in English if missing.
+    fn text(&self, key: &str, language: Language) -> String {
+        self.data
+            .get(&format!("text.{}.{}", key, language))
+            .or_else(|| {
+                self.data
+                    .get(&format!("text.{}.{}", key, Language::English))
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Progress of the tutorial.
+struct Tutorial {
+    /// Index of the current step, the number of steps once all done
+    step: usize,
+    /// Timer until the current step starts
+    trigger: Timer,
+    /// Timer until going back to the menu once done
+    outro: Timer,
+}
+
+impl Default for Tutorial {
+    fn default() -> Self {
+        Self {
+            step: 0,
+            trigger: Timer::from_seconds(0.0, false),
+            outro: Timer::from_seconds(OUTRO_DURATION, false),
+        }
+    }
+}
+
+impl Tutorial {
+    /// Starts the tutorial from the first step of the script.
+    fn start(script: &TutorialScript) -> Self {
+        let mut tutorial = Self::default();
+        tutorial.trigger = trigger_timer(&script.steps[0]);
+        tutorial
+    }
+
+    /// Returns the current step, if started and not all done.
+    fn current<'a>(&self, script: &'a TutorialScript) -> Option<&'a ScriptStep> {
+        script
+            .steps
+            .get(self.step)
+            .filter(|_| self.trigger.finished())
+    }
+
+    /// Returns true if all the steps are done.
+    fn is_done(&self, script: &TutorialScript) -> bool {
+        self.step >= script.steps.len()
+    }
+
+    /// Goes to the next step if the current one waits for the completion,
+    /// returning the item Baobei asks for then.
+    fn complete(&mut self, script: &TutorialScript, completion: Completion) -> Option<Item> {
+        let step = self
+            .current(script)
+            .filter(|step| step.done == completion)?;
+        info!("Tutorial step {} done", self.step);
+        let request = step.request;
+
+        self.step += 1;
+        if let Some(next) = script.steps.get(self.step) {
+            self.trigger = trigger_timer(next);
+        }
+        request
+    }
+}
+
+/// Returns the timer until the step starts.
+fn trigger_timer(step: &ScriptStep) -> Timer {
+    match step.trigger {
+        Trigger::Now => Timer::from_seconds(0.0, false),
+        Trigger::Wait(seconds) => Timer::from_seconds(seconds, false),
+    }
+}
+
+/// Color of the highlight of the targets.
+struct TutorialMaterials {
+    /// Glow around the target of the step
+    highlight: Handle<ColorMaterial>,
+}
+
+impl FromWorld for TutorialMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            highlight: materials.add(Color::rgba(1.0, 0.9, 0.3, 0.35).into()),
+        }
+    }
+}
+
+/// Stores entities of the tutorial.
+struct TutorialData {
+    /// Entity wrapping the instruction
+    node_wrapper: Entity,
+    /// Highlight of the target of the step
+    highlight: Entity,
+}
+
+/// Tag the text showing the instruction of the step.
+struct InstructionText;
+
+/// Opens the tutorial when the player presses `T` in the menu.
+fn start_tutorial_system(keyboard_input: Res<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
+    if keyboard_input.just_pressed(KeyCode::T) {
+        state.set(GameState::Tutorial).unwrap();
+    }
+}
+
+/// Shows the instruction and the highlight, then starts the game on top of
+/// the tutorial.
+fn setup_tutorial(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    materials: Res<TutorialMaterials>,
+    script: Res<TutorialScript>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+    mut tutorial: ResMut<Tutorial>,
+    mut state: ResMut<State<GameState>>,
+) {
+    *tutorial = Tutorial::start(&script);
+
+    let node_wrapper = commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                padding: Rect::all(Val::Px(20.0)),
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Style::default()
+            },
+            material: color_materials.add(Color::NONE.into()),
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn()
+                .insert(InstructionText)
+                .insert_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font: asset_server.load("FiraSans-Bold.ttf"),
+                            font_size: 40.0,
+                            color: Color::WHITE,
+                        },
+                        TextAlignment::default(),
+                    ),
+                    ..TextBundle::default()
+                });
+        })
+        .id();
+    let highlight = commands
+        .spawn()
+        .insert(Overlay)
+        .insert(Position::default())
+        .insert_bundle(SpriteBundle {
+            material: materials.highlight.clone(),
+            sprite: Sprite::new(Vec2::new(180.0, 180.0)),
+            ..SpriteBundle::default()
+        })
+        .id();
+
+    commands.insert_resource(TutorialData {
+        node_wrapper,
+        highlight,
+    });
+    state.push(GameState::InGame).unwrap();
+}
+
+/// Starts the current step once its trigger is met.
+fn trigger_system(time: Res<Time>, mut tutorial: ResMut<Tutorial>) {
+    if !tutorial.trigger.finished() {
+        tutorial.trigger.tick(time.delta());
+    }
+}
+
+/// Completes the steps with the contacts and the actions of Didi. Once a
+/// step requesting an item is done, Baobei asks for it.
+#[allow(clippy::too_many_arguments)]
+fn progress_system(
+    game_data: Res<GameData>,
+    script: Res<TutorialScript>,
+    mut tutorial: ResMut<Tutorial>,
+    mut contact_events: EventReader<ContactEvent>,
+    mut action_events: EventReader<ActionEvent>,
+    mut delivery_events: EventReader<DeliveryEvent>,
+    producers: Query<&ItemProducer>,
+    mut baobei: Query<&mut ItemRequestQueue, With<Baobei>>,
+) {
+    let mut completions = Vec::new();
+    for event in contact_events.iter() {
+        if let ContactEvent::Started(Contact(didi, other), _) = event {
+            if *didi != game_data.didi_entity {
+                continue;
+            }
+            if let Ok(producer) = producers.get(*other) {
+                completions.push(Completion::Reach(producer.item));
+            }
+        }
+    }
+    for action in action_events.iter() {
+        if let ActionEvent::Take(_, item) = action {
+            completions.push(Completion::Take(*item));
+        }
+    }
+    for delivery in delivery_events.iter() {
+        if baobei.get_mut(delivery.asker).is_ok() {
+            completions.push(Completion::Deliver);
+        }
+    }
+
+    for completion in completions {
+        if let Some(item) = tutorial.complete(&script, completion) {
+            for mut requests in baobei.iter_mut() {
+                if requests.front() != Some(item) {
+                    requests.0.push_front(item);
+                }
+            }
+        }
+    }
+}
+
+/// Shows the instruction of the current step, in the language of the
+/// settings.
+fn instruction_system(
+    script: Res<TutorialScript>,
+    settings: Res<Settings>,
+    tutorial: Res<Tutorial>,
+    mut texts: Query<&mut Text, With<InstructionText>>,
+) {
+    let instruction = if tutorial.is_done(&script) {
+        script.text(&script.outro, settings.language)
+    } else {
+        tutorial
+            .current(&script)
+            .map(|step| script.text(&step.text, settings.language))
+            .unwrap_or_default()
+    };
+    for mut text in texts.iter_mut() {
+        if text.sections[0].value != instruction {
+            text.sections[0].value = instruction.clone();
+        }
+    }
+}
+
+/// Moves the highlight on the target of the current step, pulsing to catch
+/// the eye.
+fn highlight_system(
+    time: Res<Time>,
+    script: Res<TutorialScript>,
+    tutorial: Res<Tutorial>,
+    tutorial_data: Res<TutorialData>,
+    producers: Query<(&ItemProducer, &Position)>,
+    baobei: Query<&Position, With<Baobei>>,
+    mut highlights: Query<
+        (&mut Position, &mut Transform, &mut Visible),
+        (Without<ItemProducer>, Without<Baobei>),
+    >,
+) {
+    let (mut position, mut transform, mut visible) =
+        match highlights.get_mut(tutorial_data.highlight) {
+            Ok(highlight) => highlight,
+            Err(_) => return,
+        };
+    let highlight = tutorial
+        .current(&script)
+        .map_or(Highlight::None, |step| step.highlight);
+    let target = match highlight {
+        Highlight::Producer(item) => producers
+            .iter()
+            .find(|(producer, _)| producer.item == item)
+            .map(|(_, position)| position.0),
+        Highlight::Baobei => baobei.iter().next().map(|position| position.0),
+        Highlight::None => None,
+    };
+
+    visible.is_visible = target.is_some();
+    if let Some(target) = target {
+        if position.0 != target {
+            position.0 = target;
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        let pulse = 0.1f32.mul_add((time.seconds_since_startup() * 6.0).sin() as f32, 1.0);
+        transform.scale = Vec3::new(pulse, pulse, 1.0);
+    }
+}
+
+/// Goes back to the menu a while after the last step, through the game
+/// pushed on top of the tutorial.
+fn finish_tutorial_system(
+    time: Res<Time>,
+    script: Res<TutorialScript>,
+    mut tutorial: ResMut<Tutorial>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if !tutorial.is_done(&script) {
+        return;
+    }
+    if tutorial.outro.tick(time.delta()).just_finished() {
+        state.replace(GameState::Menu).unwrap();
+    }
+}
+
+/// Removes the instruction and the highlight.
+fn cleanup_tutorial(mut commands: Commands, tutorial_data: Res<TutorialData>) {
+    commands
+        .entity(tutorial_data.node_wrapper)
+        .despawn_recursive();
+    commands.entity(tutorial_data.highlight).despawn_recursive();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steps_are_done_in_order() {
+        let registry = ItemRegistry::default();
+        let script = TutorialScript::parse(&SaveData::parse(BUILT_IN_SCRIPT), &registry).unwrap();
+        let mut tutorial = Tutorial::start(&script);
+        tutorial.trigger.tick(std::time::Duration::from_secs(1));
+
+        assert_eq!(tutorial.complete(&script, Completion::Deliver), None);
+        assert_eq!(tutorial.step, 0);
+
+        tutorial.complete(&script, Completion::Reach(Item::IceCream));
+        assert_eq!(
+            tutorial.complete(&script, Completion::Take(Item::IceCream)),
+            Some(Item::IceCream)
+        );
+        tutorial.complete(&script, Completion::Deliver);
+        assert!(tutorial.is_done(&script));
+        assert_eq!(
+            script.text(&script.outro, Language::English),
+            "Well done! Baobei is happy. Back to the menu…"
+        );
+    }
+
+    #[test]
+    fn invalid_scripts_are_rejected() {
+        let registry = ItemRegistry::default();
+        let parse = |content: &str| TutorialScript::parse(&SaveData::parse(content), &registry);
+
+        assert!(parse("").is_err());
+        assert!(
+            parse("steps = a\na.highlight = producer unknown\na.text = t\na.done = deliver")
+                .is_err()
+        );
+        assert!(parse("steps = a\na.highlight = baobei\na.text = t\na.done = deliver").is_ok());
+        for trigger in &["wait -1", "wait NaN", "wait inf"] {
+            let script = format!(
+                "steps = a\na.trigger = {}\na.highlight = baobei\na.text = t\na.done = deliver",
+                trigger
+            );
+            assert!(parse(&script).is_err());
+        }
+    }
+}
@@ -0,0 +1,349 @@
+//! Visitors: from time to time someone knocks at the door and waits for Didi
+//! to greet them, then sits on the couch for a while, making the Baobeis ask
+//! for more items at the same time.
+
+use bevy::{math::const_vec3, prelude::*};
+use rand::Rng;
+
+use crate::{
+    collisions::{Contact, Position, TriggerArea},
+    constants::{
+        GameState, VISITOR_EXTRA_REQUESTS, VISITOR_INTERVAL, VISITOR_PATIENCE,
+        VISITOR_SNUB_PENALTY, VISIT_DURATION,
+    },
+    locale::Language,
+    rng::GameRng,
+    scheduler::{ScheduledEvent, Scheduler, SchedulerSystems},
+    settings::Settings,
+    time_scale::TimeScale,
+    widgets::{entity_timer_system, spawn_timer_bar, EntityTimer, Progress, WidgetMaterials},
+};
+
+use super::{
+    bubbles::SayEvent, entities::GameData, happiness::Happiness, items::PickAndDropCooldown,
+    materials::GameplayMaterials, phases::PhaseController, score::Score, Baobei, NewRunAppExt,
+};
+
+/// Plugin managing the visitors.
+pub struct VisitorPlugin;
+
+impl Plugin for VisitorPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<VisitorEvent>()
+            .init_resource::<Visit>()
+            .add_startup_system(schedule_visitor.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(
+                        spawn_visitor_system
+                            .system()
+                            .label("visitors")
+                            .after(SchedulerSystems),
+                    )
+                    .with_system(greet_system.system().before("item_actions"))
+                    .with_system(visitor_state_system.system().label("visitor_state"))
+                    .with_system(
+                        entity_timer_system::<Visitor>
+                            .system()
+                            .after("visitor_state"),
+                    )
+                    .with_system(announce_system.system().after("visitors")),
+            )
+            .add_new_run_system(reset_visitors_system);
+    }
+}
+
+/// Scheduled task bringing the next visitor.
+const VISITOR_TASK: &str = "visitor";
+/// Seconds added or removed at random to the interval between two visitors.
+const INTERVAL_JITTER: f32 = 15.0;
+/// Where the visitors knock, at the door on the right border.
+pub const DOOR_POSITION: Vec3 = const_vec3!([1200.0, 260.0, 0.0]);
+/// Where the visitors sit on the couch, next to the Baobei.
+const SEAT_POSITION: Vec3 = const_vec3!([930.0, 150.0, 85.0]);
+/// Pixels walked by a visitor per second.
+const VISITOR_SPEED: f32 = 250.0;
+
+/// Step of the visit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VisitorState {
+    /// Knocks at the door until greeted or out of patience
+    Knocking,
+    /// Walks from the door to the couch after being greeted
+    WalkingIn,
+    /// Sits on the couch until the end of the visit
+    Seated,
+    /// Walks back to the door and leaves
+    Leaving,
+}
+
+/// Component on a visitor.
+struct Visitor {
+    /// Current step of the visit
+    state: VisitorState,
+    /// Timer of the patience at the door, then of the visit on the couch
+    timer: Timer,
+}
+
+impl Progress for Visitor {
+    fn remaining(&self) -> f32 {
+        self.timer.remaining()
+    }
+}
+
+/// Event sent when the visit moves on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VisitorEvent {
+    /// A visitor knocks at the door
+    Knocked,
+    /// Didi greeted the visitor
+    Greeted,
+    /// The visitor left without being greeted
+    Snubbed,
+    /// The visitor left after the visit
+    Left,
+}
+
+/// Whether a visitor sits on the couch, read by the requests.
+#[derive(Debug, Default)]
+pub struct Visit {
+    /// True while a visitor is seated
+    seated: bool,
+}
+
+impl Visit {
+    /// Returns the items the Baobeis ask for at the same time in addition.
+    pub const fn extra_requests(&self) -> usize {
+        if self.seated {
+            VISITOR_EXTRA_REQUESTS
+        } else {
+            0
+        }
+    }
+}
+
+/// Returns what Baobei says when someone knocks.
+const fn knock_line(language: Language) -> &'static str {
+    match language {
+        Language::English => "Someone is knocking, can you let them in?",
+        Language::French => "On frappe, tu peux ouvrir ?",
+    }
+}
+
+/// Returns the position after walking the distance toward the destination,
+/// and whether the destination is reached.
+fn step_toward(from: Vec3, to: Vec3, distance: f32) -> (Vec3, bool) {
+    let remaining = to - from;
+    if remaining.length() <= distance {
+        (to, true)
+    } else {
+        (from + remaining.normalize() * distance, false)
+    }
+}
+
+/// Schedules the next visitor after a random interval.
+fn schedule_next(scheduler: &mut Scheduler, rng: &mut GameRng) {
+    let interval = VISITOR_INTERVAL + rng.rng.gen_range(-INTERVAL_JITTER..INTERVAL_JITTER);
+    scheduler.once(VISITOR_TASK, interval);
+}
+
+/// Schedules the first visitor.
+fn schedule_visitor(mut scheduler: ResMut<Scheduler>, mut rng: ResMut<GameRng>) {
+    schedule_next(&mut scheduler, &mut rng);
+}
+
+/// Makes a visitor knock at the door when due, except when Baobei naps.
+#[allow(clippy::too_many_arguments)]
+fn spawn_visitor_system(
+    mut commands: Commands,
+    phases: Res<PhaseController>,
+    materials: Res<GameplayMaterials>,
+    widget_materials: Res<WidgetMaterials>,
+    mut scheduler: ResMut<Scheduler>,
+    mut rng: ResMut<GameRng>,
+    mut scheduled_events: EventReader<ScheduledEvent>,
+    mut visitor_events: EventWriter<VisitorEvent>,
+) {
+    let due = scheduled_events
+        .iter()
+        .any(|ScheduledEvent(task)| *task == VISITOR_TASK);
+
+    if !due {
+        return;
+    }
+    if phases.is_breather() {
+        schedule_next(&mut scheduler, &mut rng);
+        return;
+    }
+
+    let visitor = commands
+        .spawn()
+        .insert(Visitor {
+            state: VisitorState::Knocking,
+            timer: Timer::from_seconds(VISITOR_PATIENCE, false),
+        })
+        .insert(Position(DOOR_POSITION))
+        .insert(TriggerArea::new(150.0, 150.0))
+        .insert_bundle(SpriteBundle {
+            material: materials.visitor_sprite.clone(),
+            transform: Transform::from_scale(Vec3::new(0.3, 0.3, 0.0)),
+            ..SpriteBundle::default()
+        })
+        .id();
+
+    let patience_bar = spawn_timer_bar(
+        &mut commands,
+        &widget_materials,
+        Vec3::new(0.0, 330.0, 0.0),
+        Vec2::new(300.0, 30.0),
+    );
+    commands
+        .entity(patience_bar)
+        .insert(EntityTimer::<Visitor>::new(visitor));
+    commands.entity(visitor).push_children(&[patience_bar]);
+
+    info!("A visitor knocks at the door");
+    visitor_events.send(VisitorEvent::Knocked);
+}
+
+/// Greets the knocking visitor Didi stands next to when pressing `Space`.
+fn greet_system(
+    keyboard: Res<Input<KeyCode>>,
+    game_data: Res<GameData>,
+    mut cooldown: ResMut<PickAndDropCooldown>,
+    mut score: ResMut<Score>,
+    mut visitor_events: EventWriter<VisitorEvent>,
+    contacts: Query<&Contact>,
+    mut visitors: Query<&mut Visitor>,
+) {
+    if !cooldown.0.available() || !keyboard.pressed(KeyCode::Space) {
+        return;
+    }
+    for contact in contacts
+        .iter()
+        .filter(|contact| contact.0 == game_data.didi_entity)
+    {
+        let mut visitor = match visitors.get_mut(contact.1) {
+            Ok(visitor) if visitor.state == VisitorState::Knocking => visitor,
+            _ => continue,
+        };
+        info!("Greet the visitor");
+        visitor.state = VisitorState::WalkingIn;
+        visitor.timer = Timer::from_seconds(VISIT_DURATION, false);
+        score.reward_chore();
+        cooldown.0.start();
+        visitor_events.send(VisitorEvent::Greeted);
+        return;
+    }
+}
+
+/// Moves the visitors through the steps of the visit, upsetting Baobei when
+/// one leaves without being greeted.
+#[allow(clippy::too_many_arguments)]
+fn visitor_state_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut visit: ResMut<Visit>,
+    mut scheduler: ResMut<Scheduler>,
+    mut rng: ResMut<GameRng>,
+    mut visitor_events: EventWriter<VisitorEvent>,
+    mut visitors: Query<(Entity, &mut Visitor, &mut Position)>,
+    mut baobei: Query<&mut Happiness, With<Baobei>>,
+) {
+    let delta = time_scale.scale(time.delta());
+    let walked = VISITOR_SPEED * delta.as_secs_f32();
+
+    for (entity, mut visitor, mut position) in visitors.iter_mut() {
+        match visitor.state {
+            VisitorState::Knocking => {
+                if visitor.timer.tick(delta).just_finished() {
+                    info!("The visitor leaves without being greeted");
+                    visitor.state = VisitorState::Leaving;
+                    for mut happiness in baobei.iter_mut() {
+                        happiness.sub(VISITOR_SNUB_PENALTY);
+                    }
+                    visitor_events.send(VisitorEvent::Snubbed);
+                }
+            }
+            VisitorState::WalkingIn => {
+                let (next, arrived) = step_toward(position.0, SEAT_POSITION, walked);
+                position.0 = next;
+                if arrived {
+                    visitor.state = VisitorState::Seated;
+                }
+            }
+            VisitorState::Seated => {
+                if visitor.timer.tick(delta).just_finished() {
+                    visitor.state = VisitorState::Leaving;
+                }
+            }
+            VisitorState::Leaving => {
+                let (next, arrived) = step_toward(position.0, DOOR_POSITION, walked);
+                position.0 = next;
+                if arrived {
+                    commands.entity(entity).despawn_recursive();
+                    schedule_next(&mut scheduler, &mut rng);
+                    visitor_events.send(VisitorEvent::Left);
+                }
+            }
+        }
+    }
+
+    let seated = visitors
+        .iter_mut()
+        .any(|(_, visitor, _)| visitor.state == VisitorState::Seated);
+    if visit.seated != seated {
+        visit.seated = seated;
+    }
+}
+
+/// Makes Baobei ask Didi to open the door.
+fn announce_system(
+    settings: Res<Settings>,
+    mut visitor_events: EventReader<VisitorEvent>,
+    mut say_events: EventWriter<SayEvent>,
+    baobei: Query<Entity, With<Baobei>>,
+) {
+    for event in visitor_events.iter() {
+        if *event == VisitorEvent::Knocked {
+            for speaker in baobei.iter() {
+                say_events.send(SayEvent {
+                    speaker,
+                    line: knock_line(settings.language),
+                });
+            }
+        }
+    }
+}
+
+/// Sends the visitors away and schedules the first one of the new game.
+fn reset_visitors_system(
+    mut commands: Commands,
+    mut visit: ResMut<Visit>,
+    mut scheduler: ResMut<Scheduler>,
+    mut rng: ResMut<GameRng>,
+    visitors: Query<Entity, With<Visitor>>,
+) {
+    for visitor in visitors.iter() {
+        commands.entity(visitor).despawn_recursive();
+    }
+    *visit = Visit::default();
+    schedule_next(&mut scheduler, &mut rng);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn visitor_walks_to_the_destination() {
+        let (position, arrived) = step_toward(DOOR_POSITION, SEAT_POSITION, 10.0);
+        assert!(!arrived);
+        assert!((position.distance(DOOR_POSITION) - 10.0).abs() < 1e-3);
+
+        let (position, arrived) = step_toward(position, SEAT_POSITION, 1000.0);
+        assert!(arrived);
+        assert_eq!(position, SEAT_POSITION);
+    }
+}
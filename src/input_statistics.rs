@@ -0,0 +1,170 @@
+//! Statistics on the inputs of the player, detecting turbo buttons and input
+//! macros pressing keys at inhumanly regular intervals.
+
+use std::collections::{HashMap, VecDeque};
+
+use bevy::prelude::*;
+
+use crate::constants::GameState;
+
+/// Plugin analyzing the inputs during runs.
+pub struct InputStatisticsPlugin;
+
+impl Plugin for InputStatisticsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<InputStatistics>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::InGame)
+                    .with_system(reset_statistics_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame).with_system(record_inputs_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::InGame)
+                    .with_system(report_flagged_run_system.system()),
+            );
+    }
+}
+
+/// Number of consecutive intervals analyzed to detect a turbo.
+const SAMPLE_SIZE: usize = 20;
+/// Intervals longer than this (in seconds) break a sequence of rapid presses.
+const RAPID_PRESS_INTERVAL: f64 = 0.5;
+/// Standard deviation (in seconds) under which intervals are too regular.
+///
+/// Timings are quantized by the frame rate, but a human still varies by a few
+/// frames while a turbo presses at a fixed number of frames.
+const REGULARITY_THRESHOLD: f64 = 0.004;
+
+/// An input recorded for the statistics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum RecordedInput {
+    /// A key of the keyboard
+    Key(KeyCode),
+    /// A button of a gamepad
+    Button(GamepadButton),
+}
+
+/// Statistics on the inputs of the current run.
+#[derive(Default)]
+pub struct InputStatistics {
+    /// Timings of the presses of each input.
+    timings: HashMap<RecordedInput, PressTimings>,
+    /// Whether a turbo or a macro has been detected during the run.
+    pub flagged: bool,
+}
+
+/// Timings of the last rapid presses of an input.
+#[derive(Default)]
+struct PressTimings {
+    /// Time of the last press, in seconds since startup.
+    last_press: Option<f64>,
+    /// Last intervals between rapid presses, in seconds.
+    intervals: VecDeque<f64>,
+}
+
+impl PressTimings {
+    /// Records a press at the given time.
+    fn record(&mut self, time: f64) {
+        if let Some(last_press) = self.last_press {
+            let interval = time - last_press;
+
+            if interval > RAPID_PRESS_INTERVAL {
+                self.intervals.clear();
+            } else {
+                if self.intervals.len() == SAMPLE_SIZE {
+                    self.intervals.pop_front();
+                }
+                self.intervals.push_back(interval);
+            }
+        }
+        self.last_press = Some(time);
+    }
+
+    /// Returns true if the last rapid presses are too regular to come from a human.
+    fn is_inhumanly_regular(&self) -> bool {
+        if self.intervals.len() < SAMPLE_SIZE {
+            return false;
+        }
+        let count = self.intervals.len() as f64;
+        let mean = self.intervals.iter().sum::<f64>() / count;
+        let variance = self
+            .intervals
+            .iter()
+            .map(|interval| (interval - mean).powi(2))
+            .sum::<f64>()
+            / count;
+
+        variance.sqrt() < REGULARITY_THRESHOLD
+    }
+}
+
+/// Starts new statistics when a run starts.
+fn reset_statistics_system(mut statistics: ResMut<InputStatistics>) {
+    *statistics = InputStatistics::default();
+}
+
+/// Records the presses of keys and gamepad buttons and flags the run when
+/// they are too regular.
+fn record_inputs_system(
+    time: Res<Time>,
+    keyboard: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    mut statistics: ResMut<InputStatistics>,
+) {
+    let now = time.seconds_since_startup();
+
+    let presses = keyboard
+        .get_just_pressed()
+        .map(|key| RecordedInput::Key(*key))
+        .chain(
+            gamepad_buttons
+                .get_just_pressed()
+                .map(|button| RecordedInput::Button(*button)),
+        );
+
+    for input in presses {
+        let timings = statistics.timings.entry(input).or_default();
+        timings.record(now);
+        let regular = timings.is_inhumanly_regular();
+
+        if regular && !statistics.flagged {
+            warn!(
+                "Inhumanly regular presses of {:?}, the run is flagged",
+                input
+            );
+            statistics.flagged = true;
+        }
+    }
+}
+
+/// Reports flagged runs, which must not be submitted to leaderboards.
+fn report_flagged_run_system(statistics: Res<InputStatistics>) {
+    if statistics.flagged {
+        warn!("The run was flagged for turbo inputs and will not be ranked");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PressTimings, SAMPLE_SIZE};
+
+    #[test]
+    fn test_turbo_detection() {
+        let mut turbo = PressTimings::default();
+        let mut human = PressTimings::default();
+        let jitters = [0.0, 0.017, 0.033, 0.017, 0.0, 0.05];
+
+        for press in 0..=SAMPLE_SIZE {
+            turbo.record(press as f64 * 0.1);
+            human.record(press as f64 * 0.1 + jitters[press % jitters.len()]);
+        }
+        assert!(turbo.is_inhumanly_regular());
+        assert!(!human.is_inhumanly_regular());
+
+        // A pause restarts the analysis
+        turbo.record(10.0);
+        assert!(!turbo.is_inhumanly_regular());
+    }
+}
@@ -0,0 +1,114 @@
+//! Baobei needs, a little game made with Bevy.
+//!
+//! The game is made of the plugins exported by this library: the
+//! `GamePlugin` adds all of them with the resources and the state of the
+//! game, while the subsystems such as the collisions, the controllers or the
+//! drawing can be added on their own, like in the examples.
+
+// Clippy configuration
+#![deny(
+    clippy::all,
+    clippy::pedantic,
+    clippy::nursery,
+    clippy::cargo,
+    missing_docs
+)]
+#![warn(clippy::clippy::missing_docs_in_private_items)]
+#![allow(
+    clippy::needless_pass_by_value,
+    clippy::cast_precision_loss,
+    clippy::module_name_repetitions
+)]
+
+mod assists;
+mod bindings;
+mod calendar;
+pub mod camera;
+pub mod collisions;
+pub mod constants;
+pub mod controllers;
+mod cooldown;
+mod difficulty;
+pub mod drawing;
+mod frame_limiter;
+pub mod gameplay;
+mod input_statistics;
+mod locale;
+mod menu;
+mod onboarding;
+mod pause;
+mod photo;
+mod png;
+mod pool;
+mod power;
+mod preferences;
+mod rng;
+pub mod save;
+mod scenes;
+mod scheduler;
+pub mod settings;
+mod time_scale;
+mod widgets;
+
+use assists::Assists;
+use bevy::prelude::*;
+use bindings::BindingsPlugin;
+use camera::CameraPlugin;
+use collisions::CollisionPlugin;
+use constants::{GameMode, GameState};
+use controllers::ControllerPlugin;
+use difficulty::DifficultyPlugin;
+use drawing::DrawingPlugin;
+use frame_limiter::FrameLimiterPlugin;
+use gameplay::GameplayPlugin;
+use input_statistics::InputStatisticsPlugin;
+use menu::MenuPlugin;
+use onboarding::OnboardingPlugin;
+use pause::PausePlugin;
+use photo::PhotoPlugin;
+use pool::PoolPlugin;
+use power::PowerPlugin;
+use preferences::StartPreferences;
+use save::Profile;
+use scenes::SceneLoaderPlugin;
+use scheduler::SchedulerPlugin;
+use settings::Settings;
+use time_scale::TimeScalePlugin;
+use widgets::WidgetsPlugin;
+
+/// Version of the plugins API, following the version of the crate.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Plugin adding the whole game, with its resources and its state. Added
+/// after the default plugins of Bevy.
+pub struct GamePlugin;
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_state(GameState::Menu)
+            .init_resource::<Profile>()
+            .init_resource::<GameMode>()
+            .init_resource::<Settings>()
+            .init_resource::<StartPreferences>()
+            .init_resource::<Assists>()
+            .add_plugin(PowerPlugin)
+            .add_plugin(FrameLimiterPlugin)
+            .add_plugin(ControllerPlugin)
+            .add_plugin(InputStatisticsPlugin)
+            .add_plugin(CollisionPlugin)
+            .add_plugin(SceneLoaderPlugin)
+            .add_plugin(SchedulerPlugin)
+            .add_plugin(TimeScalePlugin)
+            .add_plugin(MenuPlugin)
+            .add_plugin(PausePlugin)
+            .add_plugin(PhotoPlugin)
+            .add_plugin(BindingsPlugin)
+            .add_plugin(DifficultyPlugin)
+            .add_plugin(OnboardingPlugin)
+            .add_plugin(GameplayPlugin)
+            .add_plugin(DrawingPlugin)
+            .add_plugin(CameraPlugin)
+            .add_plugin(WidgetsPlugin)
+            .add_plugin(PoolPlugin);
+    }
+}
@@ -0,0 +1,82 @@
+//! Language and verbosity of the texts of the game.
+
+use std::{fmt, str::FromStr};
+
+/// Language of the texts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    /// English
+    English,
+    /// French
+    French,
+}
+
+impl Language {
+    /// All the supported languages.
+    pub const ALL: [Self; 2] = [Self::English, Self::French];
+
+    /// Returns the name of the language, written in the language itself.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::English => "English",
+            Self::French => "Français",
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let code = match self {
+            Self::English => "en",
+            Self::French => "fr",
+        };
+        write!(f, "{}", code)
+    }
+}
+
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|language| language.to_string() == code)
+            .ok_or_else(|| format!("Unknown language: {}", code))
+    }
+}
+
+/// How much the characters talk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verbosity {
+    /// No speech bubbles
+    Silent,
+    /// A few words
+    Short,
+    /// Whole sentences
+    Full,
+}
+
+impl fmt::Display for Verbosity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Silent => "silent",
+            Self::Short => "short",
+            Self::Full => "full",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Verbosity {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "silent" => Ok(Self::Silent),
+            "short" => Ok(Self::Short),
+            "full" => Ok(Self::Full),
+            _ => Err(format!("Unknown verbosity: {}", name)),
+        }
+    }
+}
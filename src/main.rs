@@ -1,4 +1,4 @@
-//! A little game made with Bevy.
+//! A little game made with Bevy, the binary of the plugins of the library.
 
 // Clippy configuration
 #![deny(
@@ -8,29 +8,12 @@
     clippy::cargo,
     missing_docs
 )]
-#![warn(clippy::clippy::missing_docs_in_private_items)]
-#![allow(
-    clippy::needless_pass_by_value,
-    clippy::cast_precision_loss,
-    clippy::module_name_repetitions
-)]
-
-mod collisions;
-mod constants;
-mod controllers;
-mod cooldown;
-mod drawing;
-mod gameplay;
-mod menu;
-mod scenes;
 
-use collisions::CollisionPlugin;
-use constants::{GameState, WINDOW_HEIGHT, WINDOW_WIDTH};
-use controllers::ControllerPlugin;
-use drawing::DrawingPlugin;
-use gameplay::GameplayPlugin;
-use menu::MenuPlugin;
-use scenes::SceneLoaderPlugin;
+use baobei_needs::{
+    constants::{WINDOW_HEIGHT, WINDOW_WIDTH},
+    GamePlugin,
+};
+use bevy::prelude::*;
 
 fn main() {
     App::build()
@@ -45,13 +28,7 @@ fn main() {
             resizable: false,
             ..WindowDescriptor::default()
         })
-        .add_state(GameState::Menu)
         .add_plugins(DefaultPlugins)
-        .add_plugin(ControllerPlugin)
-        .add_plugin(CollisionPlugin)
-        .add_plugin(SceneLoaderPlugin)
-        .add_plugin(MenuPlugin)
-        .add_plugin(GameplayPlugin)
-        .add_plugin(DrawingPlugin)
+        .add_plugin(GamePlugin)
         .run();
 }
@@ -2,7 +2,15 @@
 
 use bevy::{input::system::exit_on_esc_system, prelude::*};
 
-use crate::constants::GameState;
+use crate::{
+    assists::Assists,
+    constants::{GameMode, GameState},
+    controllers::{BindingText, InputAction, InputMap},
+    onboarding::Onboarding,
+    preferences::StartPreferences,
+    save::Profile,
+    settings::Settings,
+};
 
 /// Plugin managing contact collisions
 pub struct MenuPlugin;
@@ -13,8 +21,11 @@ impl Plugin for MenuPlugin {
             .add_system_set(SystemSet::on_enter(GameState::Menu).with_system(setup_menu.system()))
             .add_system_set(
                 SystemSet::on_update(GameState::Menu)
+                    .with_system(boot_system.system())
                     .with_system(button_system.system())
                     .with_system(play_on_space_system.system())
+                    .with_system(switch_mode_system.system())
+                    .with_system(switch_kid_mode_system.system())
                     .with_system(exit_on_esc_system.system()),
             )
             .add_system_set(SystemSet::on_exit(GameState::Menu).with_system(cleanup_menu.system()));
@@ -49,29 +60,101 @@ impl FromWorld for MenuMaterials {
     }
 }
 
+/// Buttons of the main menu.
+#[derive(Clone, Copy)]
+enum MenuButton {
+    /// Starts a game at the first level
+    Play,
+    /// Starts a game in the last mode, at the last level reached
+    QuickStart,
+}
+
+/// Tag the text displaying the selected game mode.
+struct ModeText;
+/// Tag the text displaying whether the kid mode is enabled.
+struct KidModeText;
+
+/// Returns the text describing the selected game mode.
+fn mode_label(mode: GameMode) -> String {
+    format!("Mode: {} (press M to change)", mode.name())
+}
+
+/// Returns the text describing whether the kid mode is enabled.
+fn kid_mode_label(kid_mode: bool) -> String {
+    let toggle = if kid_mode { "on" } else { "off" };
+    format!("Kid mode: {} (press K to change)", toggle)
+}
+
 /// A button interacted by the player.
 type UpdatedButton = (Changed<Interaction>, With<Button>);
 
-/// Handles clicks on the `Play` button.
+/// Handles clicks on the `Play` and `Quick start` buttons.
 fn button_system(
     materials: Res<MenuMaterials>,
-    mut interaction_query: Query<(&Interaction, &mut Handle<ColorMaterial>), UpdatedButton>,
+    mut interaction_query: Query<
+        (&Interaction, &MenuButton, &mut Handle<ColorMaterial>),
+        UpdatedButton,
+    >,
+    mut mode: ResMut<GameMode>,
+    mut preferences: ResMut<StartPreferences>,
     mut state: ResMut<State<GameState>>,
 ) {
-    for (interaction, mut material) in interaction_query.iter_mut() {
-        match *interaction {
-            Interaction::Clicked => state.set(GameState::InGame).unwrap(),
-            Interaction::Hovered => *material = materials.hovered_button.clone(),
-            Interaction::None => *material = materials.normal_button.clone(),
+    for (interaction, button, mut material) in interaction_query.iter_mut() {
+        match (*interaction, *button) {
+            (Interaction::Clicked, MenuButton::Play) => state.set(GameState::InGame).unwrap(),
+            (Interaction::Clicked, MenuButton::QuickStart) => {
+                quick_start(&mut mode, &mut preferences, &mut state);
+            }
+            (Interaction::Hovered, _) => *material = materials.hovered_button.clone(),
+            (Interaction::None, _) => *material = materials.normal_button.clone(),
         }
     }
 }
 
+/// Starts a game in the last mode, at the last level reached.
+fn quick_start(
+    mode: &mut GameMode,
+    preferences: &mut StartPreferences,
+    state: &mut State<GameState>,
+) {
+    *mode = preferences.mode;
+    preferences.quick_start = true;
+    state.set(GameState::InGame).unwrap();
+}
+
+/// Selects the last mode at launch, starts the first-run flow if the profile
+/// never went through it, and otherwise skips the menu with a quick start if
+/// set in the settings.
+fn boot_system(
+    mut booted: Local<bool>,
+    settings: Res<Settings>,
+    onboarding: Res<Onboarding>,
+    mut mode: ResMut<GameMode>,
+    mut preferences: ResMut<StartPreferences>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if *booted {
+        return;
+    }
+    *booted = true;
+    *mode = preferences.mode;
+
+    if !onboarding.done {
+        state.set(GameState::Onboarding).unwrap();
+        return;
+    }
+    if settings.quick_start_on_boot {
+        quick_start(&mut mode, &mut preferences, &mut state);
+    }
+}
+
 /// Setup the title and `Play` button in the main menu.
 fn setup_menu(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     materials: Res<MenuMaterials>,
+    mode: Res<GameMode>,
+    preferences: Res<StartPreferences>,
 ) {
     commands.spawn().insert_bundle(UiCameraBundle::default());
 
@@ -103,33 +186,94 @@ fn setup_menu(
                 ),
                 ..TextBundle::default()
             });
+            let quick_start_label = format!("Quick start at level {}", preferences.level);
+            for (button, label, width) in [
+                (MenuButton::Play, "Play".to_string(), 150.0),
+                (MenuButton::QuickStart, quick_start_label, 420.0),
+            ] {
+                parent
+                    .spawn()
+                    .insert(button)
+                    .insert_bundle(ButtonBundle {
+                        style: Style {
+                            margin: Rect::all(Val::Px(15.0)),
+                            size: Size::new(Val::Px(width), Val::Px(65.0)),
+                            justify_content: JustifyContent::Center, // horizontally center child text
+                            align_items: AlignItems::Center,         // vertically center child text
+                            ..Style::default()
+                        },
+                        material: materials.normal_button.clone(),
+                        ..ButtonBundle::default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn().insert_bundle(TextBundle {
+                            text: Text::with_section(
+                                label,
+                                TextStyle {
+                                    font: font.clone(),
+                                    font_size: 40.0,
+                                    color: Color::WHITE,
+                                },
+                                TextAlignment::default(),
+                            ),
+                            ..TextBundle::default()
+                        });
+                    });
+            }
+            parent.spawn().insert(ModeText).insert_bundle(TextBundle {
+                text: Text::with_section(
+                    mode_label(*mode),
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 30.0,
+                        color: Color::WHITE,
+                    },
+                    TextAlignment::default(),
+                ),
+                ..TextBundle::default()
+            });
             parent
                 .spawn()
-                .insert_bundle(ButtonBundle {
-                    style: Style {
-                        margin: Rect::all(Val::Px(25.0)),
-                        size: Size::new(Val::Px(150.0), Val::Px(65.0)),
-                        justify_content: JustifyContent::Center, // horizontally center child text
-                        align_items: AlignItems::Center,         // vertically center child text
-                        ..Style::default()
-                    },
-                    material: materials.normal_button.clone(),
-                    ..ButtonBundle::default()
-                })
-                .with_children(|parent| {
-                    parent.spawn().insert_bundle(TextBundle {
-                        text: Text::with_section(
-                            "Play",
-                            TextStyle {
-                                font: font.clone(),
-                                font_size: 40.0,
-                                color: Color::WHITE,
-                            },
-                            TextAlignment::default(),
-                        ),
-                        ..TextBundle::default()
-                    });
+                .insert(KidModeText)
+                .insert_bundle(TextBundle {
+                    text: Text::with_section(
+                        kid_mode_label(preferences.kid_mode),
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 30.0,
+                            color: Color::WHITE,
+                        },
+                        TextAlignment::default(),
+                    ),
+                    ..TextBundle::default()
+                });
+            parent
+                .spawn()
+                .insert(BindingText("Press {confirm} to play".to_string()))
+                .insert_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 30.0,
+                            color: Color::WHITE,
+                        },
+                        TextAlignment::default(),
+                    ),
+                    ..TextBundle::default()
                 });
+            parent.spawn().insert_bundle(TextBundle {
+                text: Text::with_section(
+                    "Press T for the tutorial, S for the sandbox, R for a seed race, D to decorate, H for the difficulty, B to bind a device",
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 30.0,
+                        color: Color::WHITE,
+                    },
+                    TextAlignment::default(),
+                ),
+                ..TextBundle::default()
+            });
         })
         .id();
 
@@ -141,9 +285,55 @@ fn cleanup_menu(mut commands: Commands, menu_data: Res<MenuData>) {
     commands.entity(menu_data.node_wrapper).despawn_recursive();
 }
 
-/// Start the game play when the player press `Space`.
-fn play_on_space_system(keyboard_input: Res<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
-    if keyboard_input.just_pressed(KeyCode::Space) {
+/// Start the game play when the player press the confirm control, `Space`
+/// by default.
+fn play_on_space_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    input_map: Res<InputMap>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if input_map.just_pressed(InputAction::Confirm, &keyboard_input, &gamepad_buttons) {
         state.set(GameState::InGame).unwrap();
     }
 }
+
+/// Switches the game mode when the player press `M`.
+fn switch_mode_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    profile: Res<Profile>,
+    mut mode: ResMut<GameMode>,
+    mut preferences: ResMut<StartPreferences>,
+    mut texts: Query<&mut Text, With<ModeText>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::M) {
+        return;
+    }
+    *mode = mode.next();
+    preferences.mode = *mode;
+    preferences.store(&profile);
+
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = mode_label(*mode);
+    }
+}
+
+/// Toggles the assists of the kid mode when the player press `K`.
+fn switch_kid_mode_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    profile: Res<Profile>,
+    mut assists: ResMut<Assists>,
+    mut preferences: ResMut<StartPreferences>,
+    mut texts: Query<&mut Text, With<KidModeText>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::K) {
+        return;
+    }
+    preferences.kid_mode = !preferences.kid_mode;
+    preferences.store(&profile);
+    *assists = Assists::preset(preferences.kid_mode);
+
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = kid_mode_label(preferences.kid_mode);
+    }
+}
@@ -0,0 +1,345 @@
+//! First-run experience of a profile: the player chooses the language, the
+//! controls and the accessibility options, then plays the tutorial. The flow
+//! is skipped once done, or when the player presses `Escape`.
+
+use bevy::prelude::*;
+
+use crate::{
+    constants::GameState,
+    controllers::{ActiveDevice, BindingText, GamepadLobby, InputAction, InputDevice, InputMap},
+    locale::Language,
+    save::{Profile, SaveData},
+    settings::Settings,
+};
+
+/// Plugin managing the first-run experience.
+pub struct OnboardingPlugin;
+
+impl Plugin for OnboardingPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Onboarding>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Onboarding)
+                    .with_system(setup_onboarding_screen.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Onboarding)
+                    .with_system(select_system.system().label("onboarding_select"))
+                    .with_system(
+                        confirm_system
+                            .system()
+                            .label("onboarding_confirm")
+                            .after("onboarding_select"),
+                    )
+                    .with_system(onboarding_text_system.system().after("onboarding_confirm"))
+                    .with_system(skip_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Onboarding)
+                    .with_system(cleanup_onboarding_screen.system()),
+            );
+    }
+}
+
+/// Save file remembering that the profile went through the first run.
+const ONBOARDING_FILE: &str = "onboarding.sav";
+/// Devices offered on the controls step.
+const DEVICES: [InputDevice; 2] = [InputDevice::Keyboard, InputDevice::Gamepad];
+
+/// A step of the first run, done in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OnboardingStep {
+    /// The player chooses the language of the texts
+    Language,
+    /// The player chooses the keyboard or a gamepad
+    Controls,
+    /// The player toggles the assists and the camera motion
+    Accessibility,
+}
+
+impl OnboardingStep {
+    /// Returns the title shown during the step.
+    const fn title(self) -> &'static str {
+        match self {
+            Self::Language => "Choose your language",
+            Self::Controls => "Choose your controls",
+            Self::Accessibility => "Accessibility",
+        }
+    }
+}
+
+/// Progress of the first run of the profile.
+pub struct Onboarding {
+    /// Whether the profile already went through the first run
+    pub done: bool,
+    /// The current step
+    step: OnboardingStep,
+    /// Index of the selected choice of the step
+    selected: usize,
+}
+
+impl FromWorld for Onboarding {
+    fn from_world(world: &mut World) -> Self {
+        let profile = world.get_resource::<Profile>().unwrap();
+        let data = profile.load(ONBOARDING_FILE);
+
+        Self {
+            done: data.get("done").unwrap_or(false),
+            step: OnboardingStep::Language,
+            selected: 0,
+        }
+    }
+}
+
+impl Onboarding {
+    /// Goes to the given step, selecting its first choice.
+    fn go_to(&mut self, step: OnboardingStep) {
+        self.step = step;
+        self.selected = 0;
+    }
+
+    /// Remembers that the profile went through the first run.
+    fn finish(&mut self, profile: &Profile) {
+        self.done = true;
+        let mut data = SaveData::default();
+        data.set("done", true);
+        profile.store(ONBOARDING_FILE, &data);
+    }
+}
+
+/// Returns the choices of the step, reflecting the current settings.
+fn choices(step: OnboardingStep, settings: &Settings, lobby: &GamepadLobby) -> Vec<String> {
+    let toggle = |on: bool| if on { "on" } else { "off" };
+
+    match step {
+        OnboardingStep::Language => Language::ALL
+            .iter()
+            .map(|language| language.name().to_string())
+            .collect(),
+        OnboardingStep::Controls => vec![
+            "Keyboard".to_string(),
+            if lobby.has_gamepad() {
+                "Gamepad (connected)".to_string()
+            } else {
+                "Gamepad (none detected)".to_string()
+            },
+        ],
+        OnboardingStep::Accessibility => vec![
+            format!("Pickup assist: {}", toggle(settings.pickup_magnet)),
+            format!("Moving camera: {}", toggle(settings.dynamic_camera)),
+            "Continue to the tutorial".to_string(),
+        ],
+    }
+}
+
+/// Stores entities of the onboarding screen.
+struct OnboardingScreenData {
+    /// Entity wrapping all the entities of the screen
+    node_wrapper: Entity,
+}
+
+/// Tag the text showing the title of the step.
+struct StepTitleText;
+
+/// Tag the text listing the choices of the step.
+struct ChoicesText;
+
+/// Shows the first step of the first run.
+fn setup_onboarding_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut onboarding: ResMut<Onboarding>,
+) {
+    onboarding.go_to(OnboardingStep::Language);
+
+    let font = asset_server.load("FiraSans-Bold.ttf");
+    let text = |value: &str, font_size: f32| TextBundle {
+        text: Text::with_section(
+            value,
+            TextStyle {
+                font: font.clone(),
+                font_size,
+                color: Color::WHITE,
+            },
+            TextAlignment::default(),
+        ),
+        ..TextBundle::default()
+    };
+
+    let node_wrapper = commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                padding: Rect::all(Val::Px(50.0)),
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Style::default()
+            },
+            material: materials.add(Color::NONE.into()),
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            parent.spawn().insert_bundle(text("Welcome!", 80.0));
+            parent
+                .spawn()
+                .insert(StepTitleText)
+                .insert_bundle(text("", 50.0));
+            parent
+                .spawn()
+                .insert(ChoicesText)
+                .insert_bundle(text("", 40.0));
+            parent
+                .spawn()
+                .insert(BindingText(
+                    "Choose with the arrow keys, press {confirm} to confirm or Escape to skip"
+                        .to_string(),
+                ))
+                .insert_bundle(text("", 30.0));
+        })
+        .id();
+
+    commands.insert_resource(OnboardingScreenData { node_wrapper });
+}
+
+/// Selects a choice of the step with the arrow keys.
+fn select_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
+    lobby: Res<GamepadLobby>,
+    mut onboarding: ResMut<Onboarding>,
+) {
+    let count = choices(onboarding.step, &settings, &lobby).len();
+
+    if keyboard_input.just_pressed(KeyCode::Up) && onboarding.selected > 0 {
+        onboarding.selected -= 1;
+    }
+    if keyboard_input.just_pressed(KeyCode::Down) && onboarding.selected + 1 < count {
+        onboarding.selected += 1;
+    }
+}
+
+/// Applies the selected choice and goes to the next step, starting the
+/// tutorial after the last one. The gamepad is preselected if connected.
+#[allow(clippy::too_many_arguments)]
+fn confirm_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    input_map: Res<InputMap>,
+    lobby: Res<GamepadLobby>,
+    profile: Res<Profile>,
+    mut settings: ResMut<Settings>,
+    mut active_device: ResMut<ActiveDevice>,
+    mut onboarding: ResMut<Onboarding>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if !input_map.just_pressed(InputAction::Confirm, &keyboard_input, &gamepad_buttons) {
+        return;
+    }
+    let selected = onboarding.selected;
+
+    match onboarding.step {
+        OnboardingStep::Language => {
+            settings.language = Language::ALL[selected];
+            onboarding.go_to(OnboardingStep::Controls);
+            onboarding.selected = usize::from(lobby.has_gamepad());
+        }
+        OnboardingStep::Controls => {
+            active_device.0 = DEVICES[selected];
+            onboarding.go_to(OnboardingStep::Accessibility);
+        }
+        OnboardingStep::Accessibility => match selected {
+            0 => settings.pickup_magnet = !settings.pickup_magnet,
+            1 => settings.dynamic_camera = !settings.dynamic_camera,
+            _ => {
+                info!("First run done, starting the tutorial");
+                settings.store(&profile);
+                onboarding.finish(&profile);
+                state.set(GameState::Tutorial).unwrap();
+            }
+        },
+    }
+}
+
+/// Skips the rest of the first run when the player presses `Escape`.
+fn skip_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    profile: Res<Profile>,
+    settings: Res<Settings>,
+    mut onboarding: ResMut<Onboarding>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::Escape) {
+        return;
+    }
+    settings.store(&profile);
+    onboarding.finish(&profile);
+    state.set(GameState::Menu).unwrap();
+}
+
+/// Shows the title and the choices of the step, marking the selected one.
+fn onboarding_text_system(
+    onboarding: Res<Onboarding>,
+    settings: Res<Settings>,
+    lobby: Res<GamepadLobby>,
+    mut title_texts: Query<&mut Text, (With<StepTitleText>, Without<ChoicesText>)>,
+    mut choices_texts: Query<&mut Text, (With<ChoicesText>, Without<StepTitleText>)>,
+) {
+    let lines: Vec<String> = choices(onboarding.step, &settings, &lobby)
+        .into_iter()
+        .enumerate()
+        .map(|(index, choice)| {
+            let marker = if index == onboarding.selected {
+                "> "
+            } else {
+                ""
+            };
+            format!("{}{}", marker, choice)
+        })
+        .collect();
+    let choices = lines.join("\n");
+
+    for mut text in title_texts.iter_mut() {
+        if text.sections[0].value != onboarding.step.title() {
+            text.sections[0].value = onboarding.step.title().to_string();
+        }
+    }
+    for mut text in choices_texts.iter_mut() {
+        if text.sections[0].value != choices {
+            text.sections[0].value = choices.clone();
+        }
+    }
+}
+
+/// Removes all entities of the onboarding screen.
+fn cleanup_onboarding_screen(mut commands: Commands, screen_data: Res<OnboardingScreenData>) {
+    commands
+        .entity(screen_data.node_wrapper)
+        .despawn_recursive();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn controls_step_offers_every_device() {
+        let lobby = GamepadLobby::default();
+        let profile = Profile {
+            name: "onboarding_test".to_string(),
+        };
+        let mut world = World::default();
+        world.insert_resource(profile);
+        let settings = Settings::from_world(&mut world);
+
+        assert_eq!(
+            choices(OnboardingStep::Controls, &settings, &lobby).len(),
+            DEVICES.len()
+        );
+        assert_eq!(
+            choices(OnboardingStep::Language, &settings, &lobby).len(),
+            Language::ALL.len()
+        );
+    }
+}
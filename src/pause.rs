@@ -0,0 +1,225 @@
+//! Pause of the game with an overlay menu.
+//!
+//! The paused state is pushed on top of the game state, so the systems
+//! updated during the game are suspended until it is resumed. The game is
+//! also paused when the window loses the focus, the player resuming it from
+//! the pause menu when back.
+
+use bevy::{
+    prelude::*,
+    window::{WindowFocused, WindowResized},
+};
+
+use crate::{
+    constants::GameState,
+    controllers::{BindingText, InputAction, InputMap},
+    settings::Settings,
+};
+
+/// Plugin managing the pause.
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<PauseMaterials>()
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(pause_system.system())
+                    .with_system(focus_pause_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Paused).with_system(setup_pause_menu.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Paused)
+                    .with_system(resume_system.system())
+                    .with_system(pause_button_system.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Paused).with_system(cleanup_pause_menu.system()),
+            );
+    }
+}
+
+/// Stores entities of the pause menu
+struct PauseMenuData {
+    /// Entity wrapping all the entities of the pause menu
+    node_wrapper: Entity,
+}
+
+/// Colors of the pause menu.
+struct PauseMaterials {
+    /// Darkened game behind the menu
+    overlay: Handle<ColorMaterial>,
+    /// Default style of a button
+    normal_button: Handle<ColorMaterial>,
+    /// Hovered style of a button
+    hovered_button: Handle<ColorMaterial>,
+}
+
+impl FromWorld for PauseMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            overlay: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.6).into()),
+            normal_button: materials.add(Color::rgb(0.15, 0.15, 0.15).into()),
+            hovered_button: materials.add(Color::rgb(0.25, 0.25, 0.25).into()),
+        }
+    }
+}
+
+/// Buttons of the pause menu.
+#[derive(Clone, Copy)]
+enum PauseButton {
+    /// Goes back to the game
+    Resume,
+    /// Abandons the game and goes back to the main menu
+    Quit,
+}
+
+/// Pauses the game when the player presses the pause control, `P` or
+/// `Start` by default.
+fn pause_system(
+    keyboard: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    input_map: Res<InputMap>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if input_map.just_pressed(InputAction::Pause, &keyboard, &gamepad_buttons) {
+        state.push(GameState::Paused).unwrap();
+    }
+}
+
+/// Pauses the game when the window loses the focus or is minimized, if set in
+/// the settings.
+fn focus_pause_system(
+    settings: Res<Settings>,
+    windows: Res<Windows>,
+    mut focus_events: EventReader<WindowFocused>,
+    mut resize_events: EventReader<WindowResized>,
+    mut state: ResMut<State<GameState>>,
+) {
+    let primary = match windows.get_primary() {
+        Some(window) => window.id(),
+        None => return,
+    };
+    let unfocused = focus_events
+        .iter()
+        .any(|event| event.id == primary && !event.focused);
+    let minimized = resize_events
+        .iter()
+        .any(|event| event.id == primary && (event.width <= 0.0 || event.height <= 0.0));
+
+    if settings.pause_on_focus_loss && (unfocused || minimized) {
+        info!("Window lost the focus, pausing the game");
+        // The pause control may already have paused the game in this frame
+        state.push(GameState::Paused).ok();
+    }
+}
+
+/// Resumes the game when the player presses the pause control again.
+fn resume_system(
+    keyboard: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    input_map: Res<InputMap>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if input_map.just_pressed(InputAction::Pause, &keyboard, &gamepad_buttons) {
+        state.pop().unwrap();
+    }
+}
+
+/// A button of the pause menu interacted by the player.
+type UpdatedButton = (Changed<Interaction>, With<Button>);
+
+/// Handles clicks on the buttons of the pause menu.
+fn pause_button_system(
+    materials: Res<PauseMaterials>,
+    mut buttons: Query<(&Interaction, &PauseButton, &mut Handle<ColorMaterial>), UpdatedButton>,
+    mut state: ResMut<State<GameState>>,
+) {
+    for (interaction, button, mut material) in buttons.iter_mut() {
+        match (*interaction, *button) {
+            (Interaction::Clicked, PauseButton::Resume) => state.pop().unwrap(),
+            (Interaction::Clicked, PauseButton::Quit) => state.replace(GameState::Menu).unwrap(),
+            (Interaction::Hovered, _) => *material = materials.hovered_button.clone(),
+            (Interaction::None, _) => *material = materials.normal_button.clone(),
+        }
+    }
+}
+
+/// Shows the pause menu over the game.
+fn setup_pause_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    materials: Res<PauseMaterials>,
+) {
+    let font = asset_server.load("FiraSans-Bold.ttf");
+    let text = |value: &str, font_size: f32| TextBundle {
+        text: Text::with_section(
+            value,
+            TextStyle {
+                font: font.clone(),
+                font_size,
+                color: Color::WHITE,
+            },
+            TextAlignment::default(),
+        ),
+        ..TextBundle::default()
+    };
+
+    let node_wrapper = commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                padding: Rect::all(Val::Px(50.0)),
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Style::default()
+            },
+            material: materials.overlay.clone(),
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            parent.spawn().insert_bundle(text("Paused", 100.0));
+
+            for (button, label) in [
+                (PauseButton::Resume, "Resume"),
+                (PauseButton::Quit, "Quit to menu"),
+            ] {
+                parent
+                    .spawn()
+                    .insert(button)
+                    .insert_bundle(ButtonBundle {
+                        style: Style {
+                            margin: Rect::all(Val::Px(15.0)),
+                            size: Size::new(Val::Px(250.0), Val::Px(65.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..Style::default()
+                        },
+                        material: materials.normal_button.clone(),
+                        ..ButtonBundle::default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn().insert_bundle(text(label, 40.0));
+                    });
+            }
+            parent
+                .spawn()
+                .insert(BindingText("Press {pause} to resume".to_string()))
+                .insert_bundle(text("", 30.0));
+        })
+        .id();
+
+    commands.insert_resource(PauseMenuData { node_wrapper });
+}
+
+/// Removes all entities of the pause menu.
+fn cleanup_pause_menu(mut commands: Commands, pause_menu_data: Res<PauseMenuData>) {
+    commands
+        .entity(pause_menu_data.node_wrapper)
+        .despawn_recursive();
+}
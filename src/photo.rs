@@ -0,0 +1,416 @@
+//! Photo mode, toggled with `C` during the game.
+//!
+//! The photo state is pushed on top of the game state, so the game is frozen
+//! while the player frames the photo. The HUD is hidden and the camera moves
+//! freely with the arrow keys and zooms with `+` and `-`. The photo is
+//! painted in software from the sprites in view, and saved next to the save
+//! files of the profile.
+
+use std::{io, path::Path, time::SystemTime};
+
+use bevy::{prelude::*, render::texture::TextureFormat, ui::Node};
+
+use crate::{
+    camera::MainCamera,
+    constants::{GameState, WINDOW_HEIGHT, WINDOW_WIDTH},
+    drawing::UiObject,
+    png,
+    save::Profile,
+};
+
+/// Plugin managing the photo mode.
+pub struct PhotoPlugin;
+
+impl Plugin for PhotoPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::InGame).with_system(open_photo_mode_system.system()),
+        )
+        .add_system_set(
+            SystemSet::on_enter(GameState::Photo).with_system(setup_photo_mode.system()),
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::Photo)
+                .with_system(free_camera_system.system())
+                .with_system(take_photo_system.system())
+                .with_system(close_photo_mode_system.system()),
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::Photo).with_system(cleanup_photo_mode.system()),
+        );
+    }
+}
+
+/// Width and height of the photos in pixels, half of the window.
+const PHOTO_SIZE: (u32, u32) = (640, 360);
+/// Smallest zoom of the free camera, the fraction of the room shown.
+const MIN_ZOOM: f32 = 0.3;
+/// Biggest zoom of the free camera, showing the whole room.
+const MAX_ZOOM: f32 = 1.0;
+/// Distance in the room the free camera moves per second at full zoom.
+const PAN_SPEED: f32 = 600.0;
+/// Change of the zoom of the free camera per second.
+const ZOOM_SPEED: f32 = 0.8;
+/// Color of the photo behind the sprites.
+const BACKGROUND: [f32; 3] = [0.0, 0.0, 0.0];
+/// Controls shown during the photo mode.
+const CONTROLS: &str = "Arrows: move  +/-: zoom  Enter: take the photo  C: back to the game";
+
+/// Stores entities of the photo mode.
+struct PhotoData {
+    /// Entity wrapping the controls and the messages
+    node_wrapper: Entity,
+    /// Entities of the HUD hidden during the photo mode
+    hidden: Vec<Entity>,
+}
+
+/// Tag the text showing the controls and the path of the last photo.
+struct PhotoText;
+
+/// A sprite to paint on the photo.
+struct PhotoSprite<'a> {
+    /// Center in the room
+    center: Vec2,
+    /// Size in the room
+    size: Vec2,
+    /// Depth, the farthest sprites being painted first
+    z: f32,
+    /// Color of the material, tinting the texture
+    color: [f32; 4],
+    /// Texture of the material, if loaded
+    texture: Option<&'a Texture>,
+    /// Whether the texture is mirrored horizontally
+    flip_x: bool,
+}
+
+/// Returns the color of the texture at the coordinates, between 0 and 1 from
+/// the top left corner. White for the formats the game does not load.
+// The coordinates are clamped to the size of the texture
+#[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+fn sample(texture: &Texture, u: f32, v: f32) -> [f32; 4] {
+    let (width, height) = (texture.size.width, texture.size.height);
+    let rgba = matches!(
+        texture.format,
+        TextureFormat::Rgba8UnormSrgb | TextureFormat::Rgba8Unorm
+    );
+    if !rgba || width == 0 || height == 0 {
+        return [1.0; 4];
+    }
+    let x = ((u * width as f32) as u32).min(width - 1);
+    let y = ((v * height as f32) as u32).min(height - 1);
+    let index = ((y * width + x) * 4) as usize;
+
+    texture
+        .data
+        .get(index..index + 4)
+        .map_or([1.0; 4], |pixel| {
+            [
+                f32::from(pixel[0]) / 255.0,
+                f32::from(pixel[1]) / 255.0,
+                f32::from(pixel[2]) / 255.0,
+                f32::from(pixel[3]) / 255.0,
+            ]
+        })
+}
+
+/// Paints the sprites seen by the camera at the center with the zoom, from
+/// the farthest, returning the pixels row by row from the top.
+// The pixels are clamped to the size of the photo
+#[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+fn paint(view_center: Vec2, zoom: f32, sprites: &mut [PhotoSprite]) -> Vec<[u8; 3]> {
+    let (width, height) = PHOTO_SIZE;
+    let view_size = Vec2::new(WINDOW_WIDTH, WINDOW_HEIGHT) * zoom;
+    let view_min = view_center - view_size / 2.0;
+    let view_max = view_center + view_size / 2.0;
+    let scale = Vec2::new(width as f32, height as f32) / view_size;
+
+    let mut pixels = vec![BACKGROUND; (width * height) as usize];
+    sprites.sort_by(|a, b| a.z.partial_cmp(&b.z).unwrap_or(std::cmp::Ordering::Equal));
+
+    for sprite in sprites.iter() {
+        let min = sprite.center - sprite.size / 2.0;
+        let max = sprite.center + sprite.size / 2.0;
+        if sprite.size.x <= 0.0 || sprite.size.y <= 0.0 {
+            continue;
+        }
+        let columns = ((min.x - view_min.x) * scale.x).max(0.0) as u32
+            ..((max.x - view_min.x) * scale.x)
+                .clamp(0.0, width as f32)
+                .ceil() as u32;
+        let rows = ((view_max.y - max.y) * scale.y).max(0.0) as u32
+            ..((view_max.y - min.y) * scale.y)
+                .clamp(0.0, height as f32)
+                .ceil() as u32;
+
+        for row in rows {
+            let y = view_max.y - (row as f32 + 0.5) / scale.y;
+            let v = (max.y - y) / sprite.size.y;
+            for column in columns.clone() {
+                let x = view_min.x + (column as f32 + 0.5) / scale.x;
+                let mut u = (x - min.x) / sprite.size.x;
+                if !(0.0..1.0).contains(&u) || !(0.0..1.0).contains(&v) {
+                    continue;
+                }
+                if sprite.flip_x {
+                    u = 1.0 - u;
+                }
+                let texel = sprite
+                    .texture
+                    .map_or([1.0; 4], |texture| sample(texture, u, v));
+                let alpha = texel[3] * sprite.color[3];
+                let pixel = &mut pixels[(row * width + column) as usize];
+                let colors = texel.iter().zip(&sprite.color);
+                for (channel, (texel, tint)) in pixel.iter_mut().zip(colors) {
+                    *channel = texel.mul_add(*tint, -*channel).mul_add(alpha, *channel);
+                }
+            }
+        }
+    }
+
+    let to_byte = |channel: f32| (channel.clamp(0.0, 1.0) * 255.0).round() as u8;
+    pixels
+        .iter()
+        .map(|pixel| [to_byte(pixel[0]), to_byte(pixel[1]), to_byte(pixel[2])])
+        .collect()
+}
+
+/// Writes the photo as a PNG file, creating its directory.
+fn write_photo(path: &Path, pixels: &[[u8; 3]]) -> io::Result<()> {
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
+    let (width, height) = PHOTO_SIZE;
+    std::fs::write(path, png::encode_rgb(width, height, pixels))
+}
+
+/// Opens the photo mode when the player presses `C`.
+fn open_photo_mode_system(keyboard: Res<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
+    if keyboard.just_pressed(KeyCode::C) {
+        // The game may already be paused in this frame
+        state.push(GameState::Photo).ok();
+    }
+}
+
+/// Hides the HUD and shows the controls of the photo mode.
+fn setup_photo_mode(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+    children: Query<&Children>,
+    hud: Query<Entity, Or<(With<Node>, With<UiObject>)>>,
+    mut visibles: Query<&mut Visible>,
+) {
+    let mut hud_entities: Vec<Entity> = hud.iter().collect();
+    let mut index = 0;
+    while index < hud_entities.len() {
+        if let Ok(entity_children) = children.get(hud_entities[index]) {
+            hud_entities.extend(entity_children.iter().copied());
+        }
+        index += 1;
+    }
+
+    let mut hidden = Vec::new();
+    for entity in hud_entities {
+        if let Ok(mut visible) = visibles.get_mut(entity) {
+            if visible.is_visible {
+                visible.is_visible = false;
+                hidden.push(entity);
+            }
+        }
+    }
+
+    let node_wrapper = commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                padding: Rect::all(Val::Px(20.0)),
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Style::default()
+            },
+            material: color_materials.add(Color::NONE.into()),
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            parent.spawn().insert(PhotoText).insert_bundle(TextBundle {
+                text: Text::with_section(
+                    CONTROLS,
+                    TextStyle {
+                        font: asset_server.load("FiraSans-Bold.ttf"),
+                        font_size: 25.0,
+                        color: Color::WHITE,
+                    },
+                    TextAlignment::default(),
+                ),
+                ..TextBundle::default()
+            });
+        })
+        .id();
+
+    commands.insert_resource(PhotoData {
+        node_wrapper,
+        hidden,
+    });
+}
+
+/// Moves the camera with the arrow keys and zooms it with `+` and `-`,
+/// without showing anything outside of the room.
+fn free_camera_system(
+    time: Res<Time>,
+    keyboard: Res<Input<KeyCode>>,
+    mut cameras: Query<&mut Transform, With<MainCamera>>,
+) {
+    let seconds = time.delta_seconds();
+    let room_size = Vec2::new(WINDOW_WIDTH, WINDOW_HEIGHT);
+    let mut direction = Vec2::ZERO;
+    for (key, step) in [
+        (KeyCode::Left, -Vec2::X),
+        (KeyCode::Right, Vec2::X),
+        (KeyCode::Down, -Vec2::Y),
+        (KeyCode::Up, Vec2::Y),
+    ] {
+        if keyboard.pressed(key) {
+            direction += step;
+        }
+    }
+    let mut zoom_change = 0.0;
+    if keyboard.pressed(KeyCode::Equals) {
+        zoom_change -= ZOOM_SPEED * seconds;
+    }
+    if keyboard.pressed(KeyCode::Minus) {
+        zoom_change += ZOOM_SPEED * seconds;
+    }
+
+    for mut transform in cameras.iter_mut() {
+        let zoom = (transform.scale.x + zoom_change).clamp(MIN_ZOOM, MAX_ZOOM);
+        let half_view = room_size * zoom / 2.0;
+        let center = (transform.translation.truncate() + direction * PAN_SPEED * zoom * seconds)
+            .max(half_view)
+            .min(room_size - half_view);
+
+        transform.translation = center.extend(transform.translation.z);
+        transform.scale = Vec3::new(zoom, zoom, 1.0);
+    }
+}
+
+/// Paints the sprites in view and saves the photo when the player presses
+/// `Enter`, showing its path.
+fn take_photo_system(
+    keyboard: Res<Input<KeyCode>>,
+    profile: Res<Profile>,
+    materials: Res<Assets<ColorMaterial>>,
+    textures: Res<Assets<Texture>>,
+    cameras: Query<&Transform, With<MainCamera>>,
+    sprites: Query<(&Sprite, &Handle<ColorMaterial>, &GlobalTransform, &Visible)>,
+    mut texts: Query<&mut Text, With<PhotoText>>,
+) {
+    if !keyboard.just_pressed(KeyCode::Return) {
+        return;
+    }
+    let camera = match cameras.iter().next() {
+        Some(camera) => camera,
+        None => return,
+    };
+
+    let mut photo_sprites: Vec<PhotoSprite> = sprites
+        .iter()
+        .filter(|(_, _, _, visible)| visible.is_visible)
+        .filter_map(|(sprite, material, transform, _)| {
+            let material = materials.get(material)?;
+            Some(PhotoSprite {
+                center: transform.translation.truncate(),
+                size: sprite.size * transform.scale.truncate(),
+                z: transform.translation.z,
+                color: material.color.as_rgba_f32(),
+                texture: material
+                    .texture
+                    .as_ref()
+                    .and_then(|texture| textures.get(texture)),
+                flip_x: sprite.flip_x,
+            })
+        })
+        .collect();
+    let pixels = paint(
+        camera.translation.truncate(),
+        camera.scale.x,
+        &mut photo_sprites,
+    );
+
+    let seconds = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let path = profile.save_path(&format!("photo_{}.png", seconds));
+    let message = match write_photo(&path, &pixels) {
+        Ok(()) => {
+            info!("Photo saved to {:?}", path);
+            format!("Photo saved to {}", path.display())
+        }
+        Err(error) => {
+            warn!("Fail to save the photo {:?}: {}", path, error);
+            "The photo could not be saved".to_string()
+        }
+    };
+
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = format!("{}\n{}", message, CONTROLS);
+    }
+}
+
+/// Goes back to the game when the player presses `C` again.
+fn close_photo_mode_system(keyboard: Res<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
+    if keyboard.just_pressed(KeyCode::C) {
+        state.pop().unwrap();
+    }
+}
+
+/// Removes the controls and shows the HUD again, the camera going back to
+/// the characters with the game.
+fn cleanup_photo_mode(
+    mut commands: Commands,
+    photo_data: Res<PhotoData>,
+    mut visibles: Query<&mut Visible>,
+) {
+    commands.entity(photo_data.node_wrapper).despawn_recursive();
+    for entity in &photo_data.hidden {
+        if let Ok(mut visible) = visibles.get_mut(*entity) {
+            visible.is_visible = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sprites_are_painted_from_the_farthest() {
+        let room_center = Vec2::new(WINDOW_WIDTH / 2.0, WINDOW_HEIGHT / 2.0);
+        let sprite = |center: Vec2, z: f32, color: [f32; 4]| PhotoSprite {
+            center,
+            size: Vec2::new(WINDOW_WIDTH / 2.0, WINDOW_HEIGHT),
+            z,
+            color,
+            texture: None,
+            flip_x: false,
+        };
+        // A red half of the room, half covered by a transparent blue sprite
+        let mut sprites = [
+            sprite(room_center, 1.0, [0.0, 0.0, 1.0, 0.5]),
+            sprite(
+                Vec2::new(WINDOW_WIDTH / 4.0, room_center.y),
+                0.0,
+                [1.0, 0.0, 0.0, 1.0],
+            ),
+        ];
+        let pixels = paint(room_center, 1.0, &mut sprites);
+        let (width, height) = PHOTO_SIZE;
+        let pixel = |x: u32, y: u32| pixels[(y * width + x) as usize];
+
+        assert_eq!(pixels.len(), (width * height) as usize);
+        assert_eq!(pixel(10, 10), [255, 0, 0]);
+        assert_eq!(pixel(width / 2 - 10, 10), [128, 0, 128]);
+        assert_eq!(pixel(width / 2 + 10, 10), [0, 0, 128]);
+        assert_eq!(pixel(width - 10, height - 10), [0, 0, 0]);
+    }
+}
@@ -0,0 +1,107 @@
+//! Minimal PNG encoder for the images the game writes itself, storing the
+//! pixels without compression.
+
+/// Signature starting every PNG file.
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+/// Largest number of bytes in a stored deflate block.
+const MAX_STORED_BLOCK: usize = 0xFFFF;
+
+/// Encodes the RGB pixels, given row by row from the top, as a PNG file.
+pub fn encode_rgb(width: u32, height: u32, pixels: &[[u8; 3]]) -> Vec<u8> {
+    debug_assert_eq!(pixels.len(), (width * height) as usize);
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // 8 bits per channel, RGB, deflate, default filters, not interlaced
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    // Each row starts with its filter type, none here
+    let mut raw = Vec::with_capacity(pixels.len() * 3 + height as usize);
+    for row in pixels.chunks(width.max(1) as usize) {
+        raw.push(0);
+        raw.extend(row.iter().flatten());
+    }
+
+    let mut png = SIGNATURE.to_vec();
+    write_chunk(&mut png, *b"IHDR", &header);
+    write_chunk(&mut png, *b"IDAT", &zlib_stored(&raw));
+    write_chunk(&mut png, *b"IEND", &[]);
+    png
+}
+
+/// Appends the chunk with its length and checksum.
+// The chunks of the game are far smaller than 4 GiB
+#[allow(clippy::cast_possible_truncation)]
+fn write_chunk(png: &mut Vec<u8>, kind: [u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(&kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Wraps the data in a zlib stream of stored deflate blocks.
+// The blocks are cut to fit their 16 bits length
+#[allow(clippy::cast_possible_truncation)]
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut stream = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        stream.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let length = block.len() as u16;
+        stream.push(u8::from(last));
+        stream.extend_from_slice(&length.to_le_bytes());
+        stream.extend_from_slice(&(!length).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+/// Returns the CRC-32 checksum of the bytes, as used by the PNG chunks.
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, byte| {
+        (0..8).fold(crc ^ u32::from(*byte), |crc, _| {
+            if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+/// Returns the Adler-32 checksum of the bytes, ending the zlib streams.
+fn adler32(bytes: &[u8]) -> u32 {
+    const MODULO: u32 = 65521;
+    let (a, b) = bytes.iter().fold((1, 0), |(a, b), byte| {
+        let a = (a + u32::from(*byte)) % MODULO;
+        (a, (b + a) % MODULO)
+    });
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_a_valid_png() {
+        let png = encode_rgb(2, 1, &[[255, 0, 0], [0, 0, 255]]);
+
+        assert_eq!(png[..8], SIGNATURE);
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..24], &[0, 0, 0, 2, 0, 0, 0, 1]);
+        // The empty end chunk always has the same checksum
+        assert_eq!(
+            png[png.len() - 8..],
+            [b'I', b'E', b'N', b'D', 0xAE, 0x42, 0x60, 0x82]
+        );
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+}
@@ -0,0 +1,140 @@
+//! Pools of cosmetic entities spawned and removed all the time, like speech
+//! bubbles or contacts.
+//!
+//! Instead of being despawned, a released entity loses the component of its
+//! pool and is hidden with its children until it is acquired again, which
+//! avoids allocating new entities all the time.
+
+use std::marker::PhantomData;
+
+use bevy::{ecs::component::Component, prelude::*};
+
+/// Plugin hiding and showing the pooled entities.
+pub struct PoolPlugin;
+
+impl Plugin for PoolPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_to_stage(CoreStage::PostUpdate, hide_pooled_system.system())
+            .add_system_to_stage(CoreStage::PostUpdate, show_acquired_system.system());
+    }
+}
+
+/// Component on the released entities waiting in a pool.
+pub struct Pooled;
+
+/// Released entities tagged with the component `T` while in use.
+pub struct Pool<T> {
+    /// Entities ready to be acquired.
+    free: Vec<Entity>,
+    /// Component of the entities in use.
+    marker: PhantomData<T>,
+}
+
+impl<T> Default for Pool<T> {
+    fn default() -> Self {
+        Self {
+            free: Vec::new(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Component> Pool<T> {
+    /// Takes a released entity, or spawns one if the pool is empty, and
+    /// returns it with true if it is recycled. A recycled entity keeps its
+    /// children and its other components, which the caller resets.
+    pub fn acquire(&mut self, commands: &mut Commands) -> (Entity, bool) {
+        match self.free.pop() {
+            Some(entity) => {
+                commands.entity(entity).remove::<Pooled>();
+                (entity, true)
+            }
+            None => (commands.spawn().id(), false),
+        }
+    }
+
+    /// Gives the entity back to the pool, removing its component `T` and
+    /// hiding it.
+    pub fn release(&mut self, commands: &mut Commands, entity: Entity) {
+        if self.free.contains(&entity) {
+            return; // Already released this frame
+        }
+        commands.entity(entity).remove::<T>().insert(Pooled);
+        self.free.push(entity);
+    }
+}
+
+/// Sets the visibility of the entity and of its children.
+fn set_visible(
+    entity: Entity,
+    is_visible: bool,
+    children: &Query<&Children>,
+    visibles: &mut Query<&mut Visible>,
+) {
+    if let Ok(mut visible) = visibles.get_mut(entity) {
+        visible.is_visible = is_visible;
+    }
+    if let Ok(entity_children) = children.get(entity) {
+        for child in entity_children.iter() {
+            set_visible(*child, is_visible, children, visibles);
+        }
+    }
+}
+
+/// Hides the released entities with their children.
+fn hide_pooled_system(
+    pooled: Query<Entity, Added<Pooled>>,
+    children: Query<&Children>,
+    mut visibles: Query<&mut Visible>,
+) {
+    for entity in pooled.iter() {
+        set_visible(entity, false, &children, &mut visibles);
+    }
+}
+
+/// Shows the acquired entities with their children.
+fn show_acquired_system(
+    acquired: RemovedComponents<Pooled>,
+    children: Query<&Children>,
+    mut visibles: Query<&mut Visible>,
+) {
+    for entity in acquired.iter() {
+        set_visible(entity, true, &children, &mut visibles);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::CommandQueue;
+
+    use super::*;
+
+    /// Component of the pooled entities in the test.
+    struct Marker;
+
+    #[test]
+    fn released_entities_are_recycled() {
+        let mut world = World::default();
+        let mut queue = CommandQueue::default();
+        let mut pool = Pool::<Marker>::default();
+
+        let mut commands = Commands::new(&mut queue, &world);
+        let (entity, recycled) = pool.acquire(&mut commands);
+        commands.entity(entity).insert(Marker);
+        assert!(!recycled);
+        queue.apply(&mut world);
+
+        let mut commands = Commands::new(&mut queue, &world);
+        pool.release(&mut commands, entity);
+        pool.release(&mut commands, entity);
+        queue.apply(&mut world);
+        assert!(world.get::<Marker>(entity).is_none());
+        assert!(world.get::<Pooled>(entity).is_some());
+
+        let mut commands = Commands::new(&mut queue, &world);
+        assert_eq!(pool.acquire(&mut commands), (entity, true));
+        let (other, recycled) = pool.acquire(&mut commands);
+        assert_ne!(other, entity);
+        assert!(!recycled);
+    }
+}
@@ -0,0 +1,109 @@
+//! Battery saver lowering the frame rate when the player leaves the game
+//! idle on a device running on battery.
+//!
+//! The frame limiter renders fewer frames while saving, which also runs the
+//! cosmetic systems less often. The gameplay timers tick with the real frame
+//! time so they stay accurate, and the first input restores the frame rate.
+
+use bevy::{
+    input::{keyboard::KeyboardInput, mouse::MouseButtonInput},
+    prelude::*,
+    window::CursorMoved,
+};
+
+use crate::settings::Settings;
+
+/// Plugin managing the battery saver.
+pub struct PowerPlugin;
+
+impl Plugin for PowerPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<PowerSaver>()
+            .add_system_to_stage(CoreStage::PreUpdate, battery_status_system.system())
+            .add_system_to_stage(CoreStage::PreUpdate, idle_system.system());
+    }
+}
+
+/// Seconds without input before saving the battery.
+const IDLE_DELAY: f32 = 5.0;
+/// Seconds between two reads of the battery status.
+const BATTERY_POLL_INTERVAL: f32 = 30.0;
+/// Frames rendered per second while saving the battery.
+pub const SAVER_FPS: f64 = 10.0;
+
+/// Power status of the device and idleness of the player.
+pub struct PowerSaver {
+    /// Whether the device runs on battery
+    on_battery: bool,
+    /// Real time since the last input of the player
+    idle_timer: Timer,
+    /// Real time until the next read of the battery status
+    poll_timer: Timer,
+}
+
+impl Default for PowerSaver {
+    fn default() -> Self {
+        Self {
+            on_battery: on_battery(),
+            idle_timer: Timer::from_seconds(IDLE_DELAY, false),
+            poll_timer: Timer::from_seconds(BATTERY_POLL_INTERVAL, true),
+        }
+    }
+}
+
+impl PowerSaver {
+    /// Returns true if the frame rate must be lowered to save the battery.
+    pub fn is_saving(&self, settings: &Settings) -> bool {
+        settings.battery_saver && self.on_battery && self.idle_timer.finished()
+    }
+}
+
+/// Returns true if a battery of the device is discharging.
+#[cfg(target_os = "linux")]
+fn on_battery() -> bool {
+    use std::fs;
+
+    let read = |path: std::path::PathBuf| fs::read_to_string(path).unwrap_or_default();
+    fs::read_dir("/sys/class/power_supply").map_or(false, |supplies| {
+        supplies.filter_map(Result::ok).any(|supply| {
+            read(supply.path().join("type")).trim() == "Battery"
+                && read(supply.path().join("status")).trim() == "Discharging"
+        })
+    })
+}
+
+/// Returns true if a battery of the device is discharging, never known on
+/// this platform.
+#[cfg(not(target_os = "linux"))]
+const fn on_battery() -> bool {
+    false
+}
+
+/// Reads the battery status from time to time.
+fn battery_status_system(time: Res<Time>, mut saver: ResMut<PowerSaver>) {
+    if saver.poll_timer.tick(time.delta()).just_finished() {
+        saver.on_battery = on_battery();
+    }
+}
+
+/// Restarts the idle timer on any input of the player.
+fn idle_system(
+    time: Res<Time>,
+    mut saver: ResMut<PowerSaver>,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut mouse_events: EventReader<MouseButtonInput>,
+    mut cursor_events: EventReader<CursorMoved>,
+    mut gamepad_events: EventReader<GamepadEvent>,
+) {
+    let input = keyboard_events.iter().count()
+        + mouse_events.iter().count()
+        + cursor_events.iter().count()
+        + gamepad_events.iter().count()
+        > 0;
+
+    if input {
+        saver.idle_timer.reset();
+    } else {
+        saver.idle_timer.tick(time.delta());
+    }
+}
@@ -0,0 +1,104 @@
+//! Preferences remembered between two games to start the next one quickly,
+//! stored in the profile.
+
+use bevy::prelude::*;
+
+use crate::{
+    constants::GameMode,
+    difficulty::Difficulty,
+    save::{Profile, SaveData},
+};
+
+/// Save file storing the preferences of the profile.
+const PREFERENCES_FILE: &str = "preferences.sav";
+
+/// How the last game was started, offered again by the quick start.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StartPreferences {
+    /// Last selected mode.
+    pub mode: GameMode,
+    /// Chosen difficulty.
+    pub difficulty: Difficulty,
+    /// Whether the assists of the kid mode are enabled.
+    pub kid_mode: bool,
+    /// Last level reached.
+    pub level: u32,
+    /// Whether the next game starts at the last level reached.
+    pub quick_start: bool,
+}
+
+impl Default for StartPreferences {
+    fn default() -> Self {
+        Self {
+            mode: GameMode::default(),
+            difficulty: Difficulty::default(),
+            kid_mode: false,
+            level: 1,
+            quick_start: false,
+        }
+    }
+}
+
+impl FromWorld for StartPreferences {
+    fn from_world(world: &mut World) -> Self {
+        let profile = world.get_resource::<Profile>().unwrap();
+        Self::from_save_data(&profile.load(PREFERENCES_FILE))
+    }
+}
+
+impl StartPreferences {
+    /// Writes the preferences in the save file of the profile.
+    pub fn store(&self, profile: &Profile) {
+        profile.store(PREFERENCES_FILE, &self.to_save_data());
+    }
+
+    /// Reads the preferences from the save file, with defaults for the
+    /// invalid ones.
+    fn from_save_data(data: &SaveData) -> Self {
+        let default = Self::default();
+
+        Self {
+            mode: data.get("mode").unwrap_or(default.mode),
+            difficulty: data.get("difficulty").unwrap_or(default.difficulty),
+            kid_mode: data.get("kid_mode").unwrap_or(default.kid_mode),
+            level: data.get("level").unwrap_or(default.level).max(1),
+            quick_start: false,
+        }
+    }
+
+    /// Writes the preferences as entries of the save file.
+    fn to_save_data(&self) -> SaveData {
+        let mut data = SaveData::default();
+        data.set("mode", self.mode);
+        data.set("difficulty", self.difficulty);
+        data.set("kid_mode", self.kid_mode);
+        data.set("level", self.level);
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preferences_are_saved() {
+        let preferences = StartPreferences {
+            mode: GameMode::Story,
+            difficulty: Difficulty::Hectic,
+            kid_mode: true,
+            level: 4,
+            quick_start: true,
+        };
+        let loaded = StartPreferences::from_save_data(&preferences.to_save_data());
+
+        assert_eq!(loaded.mode, GameMode::Story);
+        assert_eq!(loaded.difficulty, Difficulty::Hectic);
+        assert!(loaded.kid_mode);
+        assert_eq!(loaded.level, 4);
+        assert!(!loaded.quick_start);
+
+        let invalid = StartPreferences::from_save_data(&SaveData::parse("mode = boss\nlevel = 0"));
+        assert_eq!(invalid, StartPreferences::default());
+    }
+}
@@ -0,0 +1,33 @@
+//! Deterministic random number generator of the game.
+
+use rand::{prelude::StdRng, random, SeedableRng};
+
+/// Random number generator used by the gameplay, replaying the same game
+/// when seeded with the same seed.
+pub struct GameRng {
+    /// The seed of the generator.
+    pub seed: u64,
+    /// The generator itself.
+    pub rng: StdRng,
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::from_seed(random())
+    }
+}
+
+impl GameRng {
+    /// Creates a generator from the given seed.
+    pub fn from_seed(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Restarts the generator from its seed.
+    pub fn restart(&mut self) {
+        self.rng = StdRng::seed_from_u64(self.seed);
+    }
+}
@@ -0,0 +1,148 @@
+//! Persistence of small save files owned by the player profile, and parsing
+//! of the `<name> <argument>` values of the scripts written like them.
+
+use std::{collections::BTreeMap, fmt, fs, path::PathBuf, str::FromStr};
+
+use bevy::prelude::*;
+
+/// Directory containing the save files of every profile.
+const SAVE_DIRECTORY: &str = "saves";
+
+/// The profile of the player, owning its own save files.
+pub struct Profile {
+    /// Name of the profile, also used as directory name.
+    pub name: String,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            name: "default".to_string(),
+        }
+    }
+}
+
+impl Profile {
+    /// Returns the path of the given save file for this profile.
+    #[must_use]
+    pub fn save_path(&self, file_name: &str) -> PathBuf {
+        PathBuf::from(SAVE_DIRECTORY)
+            .join(&self.name)
+            .join(file_name)
+    }
+
+    /// Loads the entries of the given save file, empty if it does not exist.
+    #[must_use]
+    pub fn load(&self, file_name: &str) -> SaveData {
+        fs::read_to_string(self.save_path(file_name))
+            .map(|content| SaveData::parse(&content))
+            .unwrap_or_default()
+    }
+
+    /// Writes the entries in the given save file, logging any failure.
+    pub fn store(&self, file_name: &str, data: &SaveData) {
+        let path = self.save_path(file_name);
+
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, data.to_string()));
+
+        if let Err(error) = result {
+            warn!("Fail to write the save file {:?}: {}", path, error);
+        }
+    }
+}
+
+/// Entries of a save file, written as `key = value` lines.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SaveData(BTreeMap<String, String>);
+
+impl SaveData {
+    /// Parses the content of a save file, ignoring malformed lines.
+    #[must_use]
+    pub fn parse(content: &str) -> Self {
+        let entries = content
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .collect();
+
+        Self(entries)
+    }
+
+    /// Returns the value of the given key, if present and valid.
+    #[must_use]
+    pub fn get<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.0.get(key).and_then(|value| value.parse().ok())
+    }
+
+    /// Returns the keys of the entries, in alphabetical order.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+
+    /// Sets the value of the given key.
+    pub fn set<T: ToString>(&mut self, key: &str, value: T) {
+        self.0.insert(key.to_string(), value.to_string());
+    }
+}
+
+impl fmt::Display for SaveData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (key, value) in &self.0 {
+            writeln!(f, "{} = {}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Splits the value of a field into its name and its argument.
+#[must_use]
+pub fn split_argument(value: &str) -> (&str, &str) {
+    value.split_once(' ').unwrap_or((value, ""))
+}
+
+/// Parses the argument of a field.
+///
+/// # Errors
+///
+/// Returns an error if the argument is not a valid `T`.
+pub fn parse_argument<T: FromStr>(argument: &str) -> Result<T, String> {
+    argument
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid argument: {}", argument))
+}
+
+/// Parses a duration in seconds.
+///
+/// # Errors
+///
+/// Returns an error if the argument is not a number, or is negative or not
+/// finite.
+pub fn parse_seconds(argument: &str) -> Result<f32, String> {
+    let seconds: f32 = parse_argument(argument)?;
+    if seconds.is_finite() && seconds >= 0.0 {
+        Ok(seconds)
+    } else {
+        Err(format!("Invalid duration: {}", argument))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SaveData;
+
+    #[test]
+    fn test_save_data() {
+        let mut data = SaveData::parse("affection = 12\nmalformed line\n name=didi ");
+        assert_eq!(data.get::<u32>("affection"), Some(12));
+        assert_eq!(data.get::<String>("name"), Some("didi".to_string()));
+        assert_eq!(data.get::<u32>("name"), None);
+        assert_eq!(data.get::<u32>("missing"), None);
+
+        data.set("affection", 13);
+        assert_eq!(SaveData::parse(&data.to_string()), data);
+    }
+}
@@ -0,0 +1,169 @@
+//! Scheduling of one-shot and repeating tasks in game time.
+//!
+//! Systems register named tasks in the `Scheduler` and react to the
+//! `ScheduledEvent` sent each time a task is due. The scheduler only ticks
+//! during the game, so the tasks are suspended with the pause.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::constants::GameState;
+
+/// Label of the system sending the scheduled events.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub struct SchedulerSystems;
+
+/// Plugin managing the scheduled tasks.
+pub struct SchedulerPlugin;
+
+impl Plugin for SchedulerPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<ScheduledEvent>()
+            .init_resource::<Scheduler>()
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(scheduler_system.system().label(SchedulerSystems)),
+            );
+    }
+}
+
+/// Event sent when the task with the given name is due.
+pub struct ScheduledEvent(pub &'static str);
+
+/// A task registered in the scheduler.
+struct Task {
+    /// Name of the task, sent in the events.
+    name: &'static str,
+    /// Timer until the task is due, repeating or not.
+    timer: Timer,
+}
+
+/// Tasks due after some game time, once or repeatedly.
+pub struct Scheduler {
+    /// The registered tasks.
+    tasks: Vec<Task>,
+    /// Speed of the game time, 1 being the real time, following the
+    /// `TimeScale` during the game.
+    pub time_scale: f32,
+    /// True while the tasks are suspended outside of the pause, like in the
+    /// sandbox.
+    suspended: bool,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self {
+            tasks: Vec::new(),
+            time_scale: 1.0,
+            suspended: false,
+        }
+    }
+}
+
+impl Scheduler {
+    /// Schedules the task once in `seconds`, replacing a task with the
+    /// same name.
+    pub fn once(&mut self, name: &'static str, seconds: f32) {
+        self.schedule(name, Timer::from_seconds(seconds, false));
+    }
+
+    /// Schedules the task every `seconds`, replacing a task with the same
+    /// name.
+    pub fn every(&mut self, name: &'static str, seconds: f32) {
+        self.schedule(name, Timer::from_seconds(seconds, true));
+    }
+
+    /// Removes the task with the given name.
+    pub fn cancel(&mut self, name: &'static str) {
+        self.tasks.retain(|task| task.name != name);
+    }
+
+    /// Returns true if the task with the given name is scheduled.
+    pub fn is_scheduled(&self, name: &'static str) -> bool {
+        self.tasks.iter().any(|task| task.name == name)
+    }
+
+    /// Suspends or resumes all the tasks, keeping their progress.
+    pub fn set_suspended(&mut self, suspended: bool) {
+        self.suspended = suspended;
+    }
+
+    /// Returns the elapsed fraction of the current period of the task, from
+    /// 0 to 1, if it is scheduled.
+    pub fn progress(&self, name: &'static str) -> Option<f32> {
+        self.tasks
+            .iter()
+            .find(|task| task.name == name)
+            .map(|task| task.timer.percent())
+    }
+
+    /// Registers the task with its timer.
+    fn schedule(&mut self, name: &'static str, timer: Timer) {
+        self.cancel(name);
+        self.tasks.push(Task { name, timer });
+    }
+
+    /// Advances the game time by `delta` and returns the names of the due
+    /// tasks, once per period elapsed. One-shot tasks are removed when due.
+    fn tick(&mut self, delta: Duration) -> Vec<&'static str> {
+        if self.suspended {
+            return Vec::new();
+        }
+        let delta = delta.mul_f32(self.time_scale.max(0.0));
+        let mut due = Vec::new();
+
+        for task in &mut self.tasks {
+            task.timer.tick(delta);
+            for _ in 0..task.timer.times_finished() {
+                due.push(task.name);
+            }
+        }
+        self.tasks
+            .retain(|task| task.timer.repeating() || !task.timer.finished());
+        due
+    }
+}
+
+/// Sends the events of the due tasks.
+fn scheduler_system(
+    time: Res<Time>,
+    mut scheduler: ResMut<Scheduler>,
+    mut scheduled_events: EventWriter<ScheduledEvent>,
+) {
+    for name in scheduler.tick(time.delta()) {
+        scheduled_events.send(ScheduledEvent(name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scheduler() {
+        let mut scheduler = Scheduler::default();
+        scheduler.once("once", 1.5);
+        scheduler.every("every", 1.0);
+
+        assert_eq!(scheduler.tick(Duration::from_secs_f32(1.0)), vec!["every"]);
+        assert_eq!(
+            scheduler.tick(Duration::from_secs_f32(1.0)),
+            vec!["once", "every"]
+        );
+        assert!(!scheduler.is_scheduled("once"));
+
+        scheduler.time_scale = 2.0;
+        assert_eq!(
+            scheduler.tick(Duration::from_secs_f32(1.0)),
+            vec!["every", "every"]
+        );
+
+        scheduler.set_suspended(true);
+        assert!(scheduler.tick(Duration::from_secs_f32(10.0)).is_empty());
+        scheduler.set_suspended(false);
+
+        scheduler.cancel("every");
+        assert!(scheduler.tick(Duration::from_secs_f32(1.0)).is_empty());
+    }
+}
@@ -0,0 +1,107 @@
+//! Settings of the player, stored in the profile.
+
+use bevy::prelude::*;
+
+use crate::{
+    calendar::SeasonSetting,
+    frame_limiter::FpsCap,
+    locale::{Language, Verbosity},
+    save::Profile,
+};
+
+/// Save file storing the settings of the profile.
+const SETTINGS_FILE: &str = "settings.sav";
+
+/// Settings of the player.
+pub struct Settings {
+    /// How the seasonal event is chosen, `season = christmas` forces one for testing.
+    pub season: SeasonSetting,
+    /// Hard mode mutator darkening the nights of the clock except around Didi.
+    pub night_mutator: bool,
+    /// Assist pulling the dropped items toward Didi and picking them up.
+    pub pickup_magnet: bool,
+    /// Language of the texts.
+    pub language: Language,
+    /// How much Baobei talks in speech bubbles.
+    pub bubble_verbosity: Verbosity,
+    /// Minimum seconds between two speech bubbles.
+    pub bubble_interval: f32,
+    /// Camera zooming to frame the characters instead of showing the whole room.
+    pub dynamic_camera: bool,
+    /// Seconds Baobei must stay happy to win in the survival mode.
+    pub survival_duration: f32,
+    /// Number of Baobeis asking for items at the same time.
+    pub baobei_count: usize,
+    /// Skips the menu at launch with a quick start of the last game.
+    pub quick_start_on_boot: bool,
+    /// Pauses the game when the window loses the focus or is minimized.
+    pub pause_on_focus_loss: bool,
+    /// Waits for the screen before presenting a frame.
+    pub vsync: bool,
+    /// Largest number of frames rendered per second.
+    pub fps_cap: FpsCap,
+    /// Lowers the frame rate when idle on battery.
+    pub battery_saver: bool,
+    /// Flashes the element of the key moments for the players who cannot
+    /// hear their sound.
+    pub visual_cues: bool,
+    /// Adds a helper controlled by a second player on `WASD` or a second
+    /// gamepad.
+    pub coop: bool,
+    /// Shows a precise timer of the game with a split at each delivery, and
+    /// exports the times at the end of the game.
+    pub speedrun_timer: bool,
+}
+
+impl FromWorld for Settings {
+    fn from_world(world: &mut World) -> Self {
+        let profile = world.get_resource::<Profile>().unwrap();
+        let data = profile.load(SETTINGS_FILE);
+
+        Self {
+            season: data.get("season").unwrap_or(SeasonSetting::Auto),
+            night_mutator: data.get("night_mutator").unwrap_or(false),
+            pickup_magnet: data.get("pickup_magnet").unwrap_or(false),
+            language: data.get("language").unwrap_or(Language::English),
+            bubble_verbosity: data.get("bubble_verbosity").unwrap_or(Verbosity::Full),
+            bubble_interval: data.get("bubble_interval").unwrap_or(10.0),
+            dynamic_camera: data.get("dynamic_camera").unwrap_or(true),
+            survival_duration: data.get("survival_duration").unwrap_or(180.0),
+            baobei_count: data.get("baobei_count").unwrap_or(1),
+            quick_start_on_boot: data.get("quick_start_on_boot").unwrap_or(false),
+            pause_on_focus_loss: data.get("pause_on_focus_loss").unwrap_or(true),
+            vsync: data.get("vsync").unwrap_or(true),
+            fps_cap: data.get("fps_cap").unwrap_or(FpsCap::Sixty),
+            battery_saver: data.get("battery_saver").unwrap_or(true),
+            visual_cues: data.get("visual_cues").unwrap_or(false),
+            coop: data.get("coop").unwrap_or(false),
+            speedrun_timer: data.get("speedrun_timer").unwrap_or(false),
+        }
+    }
+}
+
+impl Settings {
+    /// Writes the settings in the save file of the profile, keeping the
+    /// other entries of the file.
+    pub fn store(&self, profile: &Profile) {
+        let mut data = profile.load(SETTINGS_FILE);
+        data.set("season", self.season);
+        data.set("night_mutator", self.night_mutator);
+        data.set("pickup_magnet", self.pickup_magnet);
+        data.set("language", self.language);
+        data.set("bubble_verbosity", self.bubble_verbosity);
+        data.set("bubble_interval", self.bubble_interval);
+        data.set("dynamic_camera", self.dynamic_camera);
+        data.set("survival_duration", self.survival_duration);
+        data.set("baobei_count", self.baobei_count);
+        data.set("quick_start_on_boot", self.quick_start_on_boot);
+        data.set("pause_on_focus_loss", self.pause_on_focus_loss);
+        data.set("vsync", self.vsync);
+        data.set("fps_cap", self.fps_cap);
+        data.set("battery_saver", self.battery_saver);
+        data.set("visual_cues", self.visual_cues);
+        data.set("coop", self.coop);
+        data.set("speedrun_timer", self.speedrun_timer);
+        profile.store(SETTINGS_FILE, &data);
+    }
+}
@@ -0,0 +1,135 @@
+//! Speed of the game time, briefly frozen by hit-stops on key moments.
+//!
+//! The gameplay systems scale their delta time with the `TimeScale`, while
+//! the widgets, texts and bubbles keep using the real time.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    constants::{GameState, HIT_STOP_DURATION},
+    scheduler::{Scheduler, SchedulerSystems},
+};
+
+/// Label of the system updating the time scale.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub struct TimeScaleSystems;
+
+/// Plugin managing the time scale.
+pub struct TimeScalePlugin;
+
+impl Plugin for TimeScalePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<HitStopEvent>()
+            .init_resource::<TimeScale>()
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame).with_system(
+                    time_scale_system
+                        .system()
+                        .label(TimeScaleSystems)
+                        .before(SchedulerSystems),
+                ),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::InGame).with_system(reset_time_scale_system.system()),
+            );
+    }
+}
+
+/// Fraction of the hit-stop where the game is fully frozen, the rest easing
+/// back to the normal speed.
+const FREEZE_FRACTION: f32 = 0.6;
+
+/// Event freezing the game for a short moment.
+pub struct HitStopEvent;
+
+/// Speed of the game time, 1 being the real time.
+pub struct TimeScale {
+    /// Current speed.
+    value: f32,
+    /// Envelope of the current hit-stop, finished without hit-stop.
+    hit_stop: Timer,
+}
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        let mut hit_stop = Timer::from_seconds(HIT_STOP_DURATION, false);
+        hit_stop.tick(hit_stop.duration());
+
+        Self {
+            value: 1.0,
+            hit_stop,
+        }
+    }
+}
+
+impl TimeScale {
+    /// Returns the current speed of the game time.
+    pub const fn value(&self) -> f32 {
+        self.value
+    }
+
+    /// Returns the real delta time scaled to the game time.
+    pub fn scale(&self, delta: Duration) -> Duration {
+        delta.mul_f32(self.value)
+    }
+
+    /// Starts a hit-stop, freezing the game.
+    fn hit_stop(&mut self) {
+        self.hit_stop.reset();
+        self.value = 0.0;
+    }
+
+    /// Advances the hit-stop by the real `delta` and updates the speed.
+    fn tick(&mut self, delta: Duration) {
+        self.value = envelope(self.hit_stop.tick(delta).percent());
+    }
+}
+
+/// Returns the speed at the elapsed fraction of a hit-stop: frozen at first,
+/// then easing back to the normal speed.
+fn envelope(elapsed: f32) -> f32 {
+    ((elapsed - FREEZE_FRACTION) / (1.0 - FREEZE_FRACTION)).clamp(0.0, 1.0)
+}
+
+/// Starts the hit-stops and slows the scheduled tasks down with the game.
+fn time_scale_system(
+    time: Res<Time>,
+    mut time_scale: ResMut<TimeScale>,
+    mut scheduler: ResMut<Scheduler>,
+    mut hit_stop_events: EventReader<HitStopEvent>,
+) {
+    time_scale.tick(time.delta());
+    if hit_stop_events.iter().count() > 0 {
+        time_scale.hit_stop();
+    }
+    scheduler.time_scale = time_scale.value();
+}
+
+/// Gives the normal speed back when leaving the game.
+fn reset_time_scale_system(mut time_scale: ResMut<TimeScale>, mut scheduler: ResMut<Scheduler>) {
+    *time_scale = TimeScale::default();
+    scheduler.time_scale = 1.0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hit_stop_freezes_then_eases_back() {
+        let mut time_scale = TimeScale::default();
+        assert!((time_scale.value() - 1.0).abs() < f32::EPSILON);
+
+        time_scale.hit_stop();
+        time_scale.tick(Duration::from_secs_f32(HIT_STOP_DURATION * 0.3));
+        assert!(time_scale.value().abs() < f32::EPSILON);
+
+        time_scale.tick(Duration::from_secs_f32(HIT_STOP_DURATION * 0.5));
+        assert!(time_scale.value() > 0.0 && time_scale.value() < 1.0);
+
+        time_scale.tick(Duration::from_secs_f32(HIT_STOP_DURATION));
+        assert!((time_scale.value() - 1.0).abs() < f32::EPSILON);
+    }
+}
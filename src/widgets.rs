@@ -0,0 +1,272 @@
+//! Reusable widgets displaying the progress of timers and cooldowns.
+//!
+//! A widget is bound to a timer with [`EntityTimer`] (a component of another
+//! entity) or [`ResourceTimer`] (a resource), whose systems must be added by
+//! the plugin owning the timer:
+//!
+//! ```ignore
+//! .with_system(entity_timer_system::<InLaw>.system())
+//! ```
+
+use std::{
+    f32::consts::{FRAC_PI_2, TAU},
+    marker::PhantomData,
+};
+
+use bevy::prelude::*;
+
+use crate::{constants::GameState, cooldown::Cooldown};
+
+/// Plugin drawing the timer widgets.
+pub struct WidgetsPlugin;
+
+impl Plugin for WidgetsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<WidgetMaterials>().add_system_set(
+            SystemSet::on_update(GameState::InGame)
+                .with_system(draw_timer_bars_system.system())
+                .with_system(draw_timer_rings_system.system()),
+        );
+    }
+}
+
+/// Something progressing over time that can be displayed by a widget.
+pub trait Progress {
+    /// Returns the fraction of the time remaining, from 1 (just started) to
+    /// 0 (finished).
+    fn remaining(&self) -> f32;
+}
+
+impl Progress for Timer {
+    fn remaining(&self) -> f32 {
+        1.0 - self.percent()
+    }
+}
+
+impl Progress for Cooldown {
+    fn remaining(&self) -> f32 {
+        self.remaining_fraction()
+    }
+}
+
+/// Component on a widget displaying the remaining time of a timer.
+pub struct TimerWidget {
+    /// Fraction of the time remaining, updated from the bound timer.
+    pub remaining: f32,
+}
+
+impl Default for TimerWidget {
+    fn default() -> Self {
+        Self { remaining: 1.0 }
+    }
+}
+
+/// Component binding a widget to the `T` timer of an entity.
+pub struct EntityTimer<T> {
+    /// Entity having the timer.
+    pub entity: Entity,
+    /// Type of the timer component.
+    marker: PhantomData<T>,
+}
+
+impl<T> EntityTimer<T> {
+    /// Binds a widget to the `T` timer of the given entity.
+    pub const fn new(entity: Entity) -> Self {
+        Self {
+            entity,
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Component binding a widget to the `T` timer resource.
+pub struct ResourceTimer<T>(PhantomData<T>);
+
+impl<T> Default for ResourceTimer<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+/// Updates the widgets bound to the `T` timer of an entity.
+pub fn entity_timer_system<T: Progress + Component>(
+    timers: Query<&T>,
+    mut widgets: Query<(&EntityTimer<T>, &mut TimerWidget)>,
+) {
+    for (binding, mut widget) in widgets.iter_mut() {
+        if let Ok(timer) = timers.get(binding.entity) {
+            widget.remaining = timer.remaining();
+        }
+    }
+}
+
+/// Updates the widgets bound to the `T` timer resource.
+pub fn resource_timer_system<T: Progress + Component>(
+    timer: Res<T>,
+    mut widgets: Query<&mut TimerWidget, With<ResourceTimer<T>>>,
+) {
+    for mut widget in widgets.iter_mut() {
+        widget.remaining = timer.remaining();
+    }
+}
+
+/// Colors of the widgets.
+pub struct WidgetMaterials {
+    /// Background of the bars
+    background: Handle<ColorMaterial>,
+    /// Remaining time
+    fill: Handle<ColorMaterial>,
+    /// Remaining time when it is almost over
+    urgent: Handle<ColorMaterial>,
+}
+
+impl FromWorld for WidgetMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            background: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.5).into()),
+            fill: materials.add(Color::rgb(0.3, 0.8, 0.4).into()),
+            urgent: materials.add(Color::rgb(0.9, 0.25, 0.2).into()),
+        }
+    }
+}
+
+impl WidgetMaterials {
+    /// Returns the material of the remaining time.
+    fn fill_for(&self, remaining: f32) -> Handle<ColorMaterial> {
+        /// Fraction of the time under which the widget warns the player
+        const URGENT: f32 = 0.25;
+
+        if remaining < URGENT {
+            self.urgent.clone()
+        } else {
+            self.fill.clone()
+        }
+    }
+}
+
+/// Component on a horizontal bar emptying from right to left.
+pub struct TimerBar {
+    /// Size of the full bar.
+    size: Vec2,
+}
+
+/// Component on the part of the bar showing the remaining time.
+struct TimerBarFill;
+
+/// Spawns a timer bar at the given translation, the returned entity must be
+/// bound to a timer and can be the child of an entity to follow it in the
+/// world.
+pub fn spawn_timer_bar(
+    commands: &mut Commands,
+    materials: &WidgetMaterials,
+    translation: Vec3,
+    size: Vec2,
+) -> Entity {
+    commands
+        .spawn()
+        .insert(TimerBar { size })
+        .insert(TimerWidget::default())
+        .insert_bundle(SpriteBundle {
+            material: materials.background.clone(),
+            sprite: Sprite::new(size),
+            transform: Transform::from_translation(translation),
+            ..SpriteBundle::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn()
+                .insert(TimerBarFill)
+                .insert_bundle(SpriteBundle {
+                    material: materials.fill.clone(),
+                    sprite: Sprite::new(size),
+                    transform: Transform::from_xyz(0.0, 0.0, 0.1),
+                    ..SpriteBundle::default()
+                });
+        })
+        .id()
+}
+
+/// Shrinks the filling of the bars with the remaining time.
+fn draw_timer_bars_system(
+    materials: Res<WidgetMaterials>,
+    bars: Query<(&TimerBar, &TimerWidget, &Children), Changed<TimerWidget>>,
+    mut fills: Query<(&mut Sprite, &mut Transform, &mut Handle<ColorMaterial>), With<TimerBarFill>>,
+) {
+    for (bar, widget, children) in bars.iter() {
+        for &child in children.iter() {
+            if let Ok((mut sprite, mut transform, mut material)) = fills.get_mut(child) {
+                let width = bar.size.x * widget.remaining;
+
+                sprite.size.x = width;
+                transform.translation.x = (width - bar.size.x) / 2.0;
+                *material = materials.fill_for(widget.remaining);
+            }
+        }
+    }
+}
+
+/// Component on a ring of dots disappearing clockwise.
+pub struct TimerRing;
+
+/// Component on a dot of a ring, with its index from the top.
+struct RingSegment(usize);
+
+/// Spawns a timer ring at the given translation, the returned entity must be
+/// bound to a timer and can be the child of an entity to follow it in the
+/// world.
+pub fn spawn_timer_ring(
+    commands: &mut Commands,
+    materials: &WidgetMaterials,
+    translation: Vec3,
+    radius: f32,
+    segments: usize,
+) -> Entity {
+    let dot_size = Vec2::splat(TAU * radius / segments as f32 * 0.6);
+
+    commands
+        .spawn()
+        .insert(TimerRing)
+        .insert(TimerWidget::default())
+        .insert(Transform::from_translation(translation))
+        .insert(GlobalTransform::default())
+        .with_children(|parent| {
+            for index in 0..segments {
+                let angle = (index as f32).mul_add(-TAU / segments as f32, FRAC_PI_2);
+
+                parent
+                    .spawn()
+                    .insert(RingSegment(index))
+                    .insert_bundle(SpriteBundle {
+                        material: materials.fill.clone(),
+                        sprite: Sprite::new(dot_size),
+                        transform: Transform::from_xyz(
+                            angle.cos() * radius,
+                            angle.sin() * radius,
+                            0.0,
+                        ),
+                        ..SpriteBundle::default()
+                    });
+            }
+        })
+        .id()
+}
+
+/// Hides the dots of the rings with the elapsed time.
+fn draw_timer_rings_system(
+    materials: Res<WidgetMaterials>,
+    rings: Query<(&TimerWidget, &Children), (With<TimerRing>, Changed<TimerWidget>)>,
+    mut segments: Query<(&RingSegment, &mut Visible, &mut Handle<ColorMaterial>)>,
+) {
+    for (widget, children) in rings.iter() {
+        let segment_count = children.len() as f32;
+
+        for &child in children.iter() {
+            if let Ok((segment, mut visible, mut material)) = segments.get_mut(child) {
+                visible.is_visible = (segment.0 as f32) < widget.remaining * segment_count;
+                *material = materials.fill_for(widget.remaining);
+            }
+        }
+    }
+}